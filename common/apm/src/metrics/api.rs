@@ -44,6 +44,14 @@ make_auto_flush_static_metric! {
         eth_getUncleCountByBlockHash,
         eth_getUncleCountByBlockNumber,
         eth_getProof,
+        debug_getRawTransaction,
+        debug_getRawReceipts,
+        debug_getTransactionAccess,
+        axon_callWithGas,
+        axon_getBalances,
+        axon_getTransactionCounts,
+        debug_traceBlockByNumber,
+        debug_traceBlockByHash,
     }
 
     pub label_enum Request_Result {
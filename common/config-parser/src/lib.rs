@@ -78,11 +78,39 @@ impl From<reqwest::Error> for ParseError {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse_file, types::Config};
+    use protocol::types::{HardforkInfoInner, H256};
+
+    use super::{
+        parse_file,
+        types::{
+            spec::{HardforkInput, HardforkName},
+            Config,
+        },
+    };
 
     #[test]
     fn test_parse_config() {
         let file_path = "../../devtools/chain/config.toml";
         let _config: Config = parse_file(file_path, false).unwrap();
     }
+
+    #[test]
+    fn test_implicit_hardforks() {
+        let input = |hardforks| HardforkInput {
+            block_number: 0,
+            timestamp: 0,
+            hardforks,
+        };
+
+        // An empty list keeps the flags before the later hardforks were added.
+        let info: HardforkInfoInner = input(vec![]).into();
+        assert_eq!(info.flags, HardforkName::Andromeda.flag());
+
+        let info: HardforkInfoInner =
+            input(vec![HardforkName::Andromeda, HardforkName::Apus]).into();
+        assert_eq!(info.flags, H256::from_low_u64_be(0b101u64.to_be()));
+
+        let info: HardforkInfoInner = input(vec![HardforkName::None]).into();
+        assert_eq!(info.flags, H256::zero());
+    }
 }
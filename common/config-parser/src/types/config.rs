@@ -16,9 +16,16 @@ use protocol::types::{Key256Bits, H160};
 use crate::parse_file;
 
 pub const DEFAULT_BROADCAST_TXS_SIZE: usize = 200;
+pub const DEFAULT_BROADCAST_TXS_BYTES: usize = 1024 * 1024;
 pub const DEFAULT_BROADCAST_TXS_INTERVAL: u64 = 200; // milliseconds
+pub const DEFAULT_PACKAGE_TIME_RATIO: u64 = 30; // percentage of consensus interval
+pub const DEFAULT_MAX_ACCESS_LIST_ENTRIES: usize = 1024;
+pub const DEFAULT_PRICE_BUMP: u64 = 10; // percentage
+pub const DEFAULT_MAX_PENDING_PER_SENDER: usize = 64;
+pub const DEFAULT_MAX_QUEUED_PER_SENDER: usize = 64;
 pub const DEFAULT_SYNC_TXS_CHUNK_SIZE: usize = 5000;
 pub const DEFAULT_CACHE_SIZE: usize = 100;
+pub const DEFAULT_CODE_CACHE_SIZE: usize = 16 * 1024 * 1024; // bytes
 
 /// The configuration for Axon clients.
 ///
@@ -177,19 +184,89 @@ fn load_privkey_from_file(privkey_path: &Path) -> Result<Key256Bits, clap::Error
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigApi {
-    pub http_listening_address: Option<SocketAddr>,
-    pub ws_listening_address:   Option<SocketAddr>,
-    pub maxconn:                u32,
-    pub max_payload_size:       u32,
-    pub enable_dump_profile:    Option<bool>,
+    pub http_listening_address:           Option<SocketAddr>,
+    pub ws_listening_address:             Option<SocketAddr>,
+    pub maxconn:                          u32,
+    pub max_payload_size:                 u32,
+    pub enable_dump_profile:              Option<bool>,
+    /// The default timeout of a HTTP request in milliseconds, there is no
+    /// timeout if it is unset.
+    #[serde(default)]
+    pub request_timeout:                  Option<u64>,
+    /// The timeouts of the specified methods in milliseconds, which override
+    /// the default one, e.g. `eth_getLogs = 30000`.
+    #[serde(default)]
+    pub method_timeouts:                  HashMap<String, u64>,
+    /// The max number of the subscriptions and filters of a WebSocket
+    /// connection, they are all removed once the connection is closed.
+    #[serde(default = "default_max_subscriptions_per_connection")]
+    pub max_subscriptions_per_connection: u32,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigWeb3 {
     #[serde(default = "default_log_filter_max_block_range")]
-    pub log_filter_max_block_range: u64,
+    pub log_filter_max_block_range:  u64,
     #[serde(default = "default_max_gas_cap")]
-    pub max_gas_cap:                u64,
+    pub max_gas_cap:                 u64,
+    #[serde(default = "default_filter_read_retry_times")]
+    pub filter_read_retry_times:     u64,
+    /// The initial backoff of a failed read in milliseconds, it is doubled
+    /// after each retry.
+    #[serde(default = "default_filter_read_retry_interval")]
+    pub filter_read_retry_interval:  u64,
+    /// The timeout of a filter query in milliseconds, a read is not retried
+    /// beyond it.
+    #[serde(default = "default_filter_query_timeout")]
+    pub filter_query_timeout:        u64,
+    /// The max timeout of waiting for the filter changes in milliseconds.
+    #[serde(default = "default_filter_wait_max_timeout")]
+    pub filter_wait_max_timeout:     u64,
+    /// The timeout of a filter in milliseconds, a filter not polled within it
+    /// is removed.
+    #[serde(default = "default_filter_timeout")]
+    pub filter_timeout:              u64,
+    /// The interval of removing the timeout filters in milliseconds.
+    #[serde(default = "default_filter_gc_interval")]
+    pub filter_gc_interval:          u64,
+    #[serde(default = "default_max_pending_transactions")]
+    pub max_pending_transactions:    usize,
+    /// The max serialized size of the logs in a response in bytes.
+    #[serde(default = "default_max_logs_response_bytes")]
+    pub max_logs_response_bytes:     usize,
+    /// The max number of the installed log and block filters, the least
+    /// recently used one is evicted when a new filter is installed beyond it.
+    #[serde(default = "default_max_filters")]
+    pub max_filters:                 usize,
+    /// The max rounds of the binary search in `eth_estimateGas`, the lowest
+    /// successful gas limit found is returned once it is reached.
+    #[serde(default = "default_estimate_gas_max_iterations")]
+    pub estimate_gas_max_iterations: u64,
+    /// The max number of the log queries of `eth_getLogs` and the log filters
+    /// scanning at the same time, a query beyond it is rejected.
+    #[serde(default = "default_max_concurrent_logs_queries")]
+    pub max_concurrent_logs_queries: usize,
+    /// The max serialized size of the traces of a block in a response in
+    /// bytes, the tracing is aborted once it is exceeded.
+    #[serde(default = "default_max_trace_response_bytes")]
+    pub max_trace_response_bytes:    usize,
+    /// The max steps of the struct logs of a block in a trace, the traces
+    /// beyond it are truncated.
+    #[serde(default = "default_max_trace_steps")]
+    pub max_trace_steps:             usize,
+    /// The max number of the `debug_traceBlockByNumber` and
+    /// `debug_traceBlockByHash` calls running at the same time, a call beyond
+    /// it is rejected.
+    #[serde(default = "default_max_concurrent_traces")]
+    pub max_concurrent_traces:       usize,
+    /// The max number of the addresses of `axon_getBalances` and
+    /// `axon_getTransactionCounts`.
+    #[serde(default = "default_max_batch_addresses")]
+    pub max_batch_addresses:         usize,
+    /// The max number of the logs returned by a log filter query, a query
+    /// beyond it is rejected.
+    #[serde(default = "default_max_logs_returned")]
+    pub max_logs_returned:           usize,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -226,48 +303,156 @@ fn default_broadcast_txs_size() -> usize {
     DEFAULT_BROADCAST_TXS_SIZE
 }
 
+fn default_broadcast_txs_bytes() -> usize {
+    DEFAULT_BROADCAST_TXS_BYTES
+}
+
 fn default_broadcast_txs_interval() -> u64 {
     DEFAULT_BROADCAST_TXS_INTERVAL
 }
 
+fn default_package_time_ratio() -> u64 {
+    DEFAULT_PACKAGE_TIME_RATIO
+}
+
+fn default_check_fee_cap() -> bool {
+    true
+}
+
+fn default_max_access_list_entries() -> usize {
+    DEFAULT_MAX_ACCESS_LIST_ENTRIES
+}
+
+fn default_price_bump() -> u64 {
+    DEFAULT_PRICE_BUMP
+}
+
+fn default_max_pending_per_sender() -> usize {
+    DEFAULT_MAX_PENDING_PER_SENDER
+}
+
+fn default_max_queued_per_sender() -> usize {
+    DEFAULT_MAX_QUEUED_PER_SENDER
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigMempool {
     pub pool_size:   u64,
+    /// The number of blocks a transaction stays in the pool since it is
+    /// admitted, an unpackaged transaction is dropped after that.
+    #[serde(alias = "tx_block_lifetime")]
     pub timeout_gap: u64,
 
     #[serde(default = "default_broadcast_txs_size")]
-    pub broadcast_txs_size:     usize,
+    pub broadcast_txs_size:      usize,
+    /// The max encoded size of the transactions gossiped in a message in
+    /// bytes, a message may exceed it by the last transaction.
+    #[serde(default = "default_broadcast_txs_bytes")]
+    pub broadcast_txs_bytes:     usize,
     #[serde(default = "default_broadcast_txs_interval")]
-    pub broadcast_txs_interval: u64,
+    pub broadcast_txs_interval:  u64,
+    /// The percentage of the consensus interval which can be spent on
+    /// packaging the transactions of a block.
+    #[serde(default = "default_package_time_ratio")]
+    pub package_time_ratio:      u64,
+    /// Reject the EIP-1559 transactions whose max priority fee per gas is
+    /// above the max fee per gas.
+    #[serde(default = "default_check_fee_cap")]
+    pub check_fee_cap:           bool,
+    /// The max number of the pending transactions re-validated in the
+    /// background after a block is committed. The ones which become invalid,
+    /// such as the ones whose nonce is too low or whose sender can no longer
+    /// afford them, are removed. 0 means no re-validation.
+    #[serde(default)]
+    pub revalidate_after_commit: usize,
+    /// The max number of the addresses and storage keys in the access list of
+    /// a transaction.
+    #[serde(default = "default_max_access_list_entries")]
+    pub max_access_list_entries: usize,
+    /// The max number of the pending transactions pulled from a peer after
+    /// startup, 0 means no sync.
+    #[serde(default)]
+    pub startup_sync_txs:        usize,
+    /// The min percentage by which a transaction should raise the fees of the
+    /// pending one with the same sender and nonce to replace it.
+    #[serde(default = "default_price_bump")]
+    pub price_bump:              u64,
+    /// The max number of the transactions of a sender whose nonces are
+    /// consecutive from the account nonce.
+    #[serde(default = "default_max_pending_per_sender")]
+    pub max_pending_per_sender:  usize,
+    /// The max number of the transactions of a sender after a nonce gap.
+    #[serde(default = "default_max_queued_per_sender")]
+    pub max_queued_per_sender:   usize,
+    /// The seconds a transaction stays in the pool since it is inserted, an
+    /// unpackaged transaction is evicted after that. 0 means no limit.
+    #[serde(default)]
+    pub tx_ttl:                  u64,
+    /// Make a pending read, such as the pending nonce, observe the
+    /// transactions sent right before it.
+    #[serde(default)]
+    pub read_your_writes:        bool,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigExecutor {
-    pub triedb_cache_size: usize,
+    pub triedb_cache_size:    usize,
+    /// Commit the storage tries of the modified accounts in parallel before
+    /// updating the account trie.
+    #[serde(default)]
+    pub parallel_trie_commit: bool,
 }
 
 fn default_cache_size() -> usize {
     DEFAULT_CACHE_SIZE
 }
 
+fn default_code_cache_size() -> usize {
+    DEFAULT_CODE_CACHE_SIZE
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigRocksDB {
-    pub max_open_files: i32,
+    pub max_open_files:   i32,
     #[serde(default = "default_cache_size")]
-    pub cache_size:     usize,
-    pub options_file:   Option<PathBuf>,
+    pub cache_size:       usize,
+    /// The total bytes of the cached contract codes.
+    #[serde(default = "default_code_cache_size")]
+    pub code_cache_size:  usize,
+    /// Compress the data of the transactions in the block store.
+    #[serde(default)]
+    pub compress_tx_data: bool,
+    pub options_file:     Option<PathBuf>,
+    /// The compression of the column families keyed by the storage
+    /// categories, such as `Block`, `Receipt` and `EvmState`. The column
+    /// families absent from it keep the compression of the options file or
+    /// the default one of RocksDB.
+    #[serde(default)]
+    pub compression:      HashMap<String, DBCompression>,
 }
 
 impl Default for ConfigRocksDB {
     fn default() -> Self {
         Self {
-            max_open_files: 64,
-            cache_size:     default_cache_size(),
-            options_file:   None,
+            max_open_files:   64,
+            cache_size:       default_cache_size(),
+            code_cache_size:  default_code_cache_size(),
+            compress_tx_data: false,
+            options_file:     None,
+            compression:      HashMap::new(),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DBCompression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigLogger {
     pub filter:                     String,
@@ -315,3 +500,72 @@ fn default_max_gas_cap() -> u64 {
 fn default_log_filter_max_block_range() -> u64 {
     10_000
 }
+
+fn default_filter_read_retry_times() -> u64 {
+    3
+}
+
+fn default_filter_read_retry_interval() -> u64 {
+    20
+}
+
+fn default_max_subscriptions_per_connection() -> u32 {
+    1024
+}
+
+fn default_filter_query_timeout() -> u64 {
+    5_000
+}
+
+fn default_filter_wait_max_timeout() -> u64 {
+    30_000
+}
+
+fn default_filter_timeout() -> u64 {
+    40_000
+}
+
+fn default_filter_gc_interval() -> u64 {
+    20_000
+}
+
+fn default_max_pending_transactions() -> usize {
+    10_000
+}
+
+fn default_max_logs_response_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_max_filters() -> usize {
+    10_000
+}
+
+fn default_estimate_gas_max_iterations() -> u64 {
+    32
+}
+
+fn default_max_concurrent_logs_queries() -> usize {
+    16
+}
+
+fn default_max_concurrent_traces() -> usize {
+    2
+}
+
+fn default_max_logs_returned() -> usize {
+    10000
+}
+
+fn default_max_trace_response_bytes() -> usize {
+    // 64 MB
+    64 * 1024 * 1024
+}
+
+fn default_max_trace_steps() -> usize {
+    1_000_000
+}
+
+fn default_max_batch_addresses() -> usize {
+    1_000
+}
@@ -12,7 +12,9 @@ use common_crypto::Secp256k1RecoverablePrivateKey;
 use protocol::{
     codec::{decode_256bits_key, deserialize_address},
     types::{
-        HardforkInfoInner, Header, Key256Bits, Metadata, H160, H256, RLP_EMPTY_LIST, RLP_NULL, U256,
+        DisableableOpcode, DisabledOpcodes, ExecutorLimits, ExecutorRules, HardforkInfoInner,
+        Header, Key256Bits, Metadata, ModExpPricing, PrecompileGasOverride, H160, H256,
+        RLP_EMPTY_LIST, RLP_NULL, U256,
     },
 };
 
@@ -30,14 +32,57 @@ pub struct ChainSpec {
     /// All parameters are not allowed to be modified after the chain
     /// initialized.
     pub params:   Metadata,
+    /// The rules of the executor, which are not allowed to be modified after
+    /// the chain initialized either.
+    #[serde(default)]
+    pub executor: ExecutorSpec,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Genesis {
-    pub timestamp:        u64,
-    pub hardforks:        Vec<HardforkName>,
-    pub base_fee_per_gas: U256,
-    pub chain_id:         u64,
+    pub timestamp:          u64,
+    pub hardforks:          Vec<HardforkName>,
+    pub base_fee_per_gas:   U256,
+    pub chain_id:           u64,
+    /// Sort the verifier list by address before constructing the genesis
+    /// state, so that the genesis doesn't depend on the order of the
+    /// verifiers in the spec. It changes the genesis state root, so it is
+    /// off for the existing chains.
+    #[serde(default)]
+    pub sort_verifier_list: bool,
+}
+
+/// The rules of the executor in the chain spec, they are converted into the
+/// `ExecutorRules` stored in the genesis state.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExecutorSpec {
+    pub limits:             ExecutorLimits,
+    pub disabled_opcodes:   Vec<DisabledOpcodesInput>,
+    pub precompile_gas:     Vec<PrecompileGasInput>,
+    pub header_retention:   u64,
+    pub max_cell_data_size: u64,
+    pub slashing_threshold: Option<u64>,
+}
+
+/// The opcodes disabled since a hardfork is activated.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DisabledOpcodesInput {
+    pub hardfork: HardforkName,
+    pub opcodes:  Vec<DisableableOpcode>,
+}
+
+/// The gas costs of the precompiles since a hardfork is activated, the ones of
+/// `None` apply since the genesis.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PrecompileGasInput {
+    pub hardfork:         HardforkName,
+    pub modexp_pricing:   Option<ModExpPricing>,
+    pub modexp_min:       Option<u64>,
+    pub ec_add:           Option<u64>,
+    pub ec_mul:           Option<u64>,
+    pub ec_pairing_base:  Option<u64>,
+    pub ec_pairing_point: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -202,6 +247,50 @@ impl TypedValueParser for PrivateKeyFileValueParser {
     }
 }
 
+impl ChainSpec {
+    /// The metadata of the first epoch which is written into the genesis
+    /// state.
+    pub fn genesis_metadata(&self) -> Metadata {
+        let mut metadata = self.params.clone();
+        if self.genesis.sort_verifier_list {
+            metadata.verifier_list.sort_by_key(|v| v.address);
+        }
+        metadata
+    }
+}
+
+impl From<ExecutorSpec> for ExecutorRules {
+    fn from(value: ExecutorSpec) -> Self {
+        ExecutorRules {
+            limits:             value.limits,
+            disabled_opcodes:   value
+                .disabled_opcodes
+                .into_iter()
+                .map(|d| DisabledOpcodes {
+                    hardfork: d.hardfork.flag(),
+                    opcodes:  d.opcodes,
+                })
+                .collect(),
+            precompile_gas:     value
+                .precompile_gas
+                .into_iter()
+                .map(|p| PrecompileGasOverride {
+                    hardfork:         p.hardfork.flag(),
+                    modexp_pricing:   p.modexp_pricing,
+                    modexp_min:       p.modexp_min,
+                    ec_add:           p.ec_add,
+                    ec_mul:           p.ec_mul,
+                    ec_pairing_base:  p.ec_pairing_base,
+                    ec_pairing_point: p.ec_pairing_point,
+                })
+                .collect(),
+            header_retention:   value.header_retention,
+            max_cell_data_size: value.max_cell_data_size,
+            slashing_threshold: value.slashing_threshold,
+        }
+    }
+}
+
 impl Genesis {
     /// Build a `Header` of the genesis block from the user provided parameters.
     pub fn build_header(&self) -> Header {
@@ -219,6 +308,7 @@ impl Genesis {
         Into::<HardforkInfoInner>::into(HardforkInput {
             hardforks:    self.hardforks.clone(),
             block_number: 0,
+            timestamp:    0,
         })
     }
 }
@@ -231,6 +321,15 @@ pub struct HardforkInput {
         requires = "hardforks"
     )]
     pub block_number: u64,
+    /// Activate the hardforks since the block timestamp instead of the number
+    /// if it is not zero.
+    #[arg(
+        long = "hardfork-start-timestamp",
+        default_value_t = 0,
+        requires = "hardforks"
+    )]
+    #[serde(default)]
+    pub timestamp:    u64,
     #[arg(long = "feature", requires = "block_number")]
     pub hardforks:    Vec<HardforkName>,
 }
@@ -244,7 +343,7 @@ impl From<HardforkInput> for HardforkInfoInner {
         };
 
         let flags = if value.hardforks.is_empty() {
-            H256::from_low_u64_be(HardforkName::all().to_be())
+            H256::from_low_u64_be(HardforkName::implicit().to_be())
         } else if value.hardforks.len() == 1 {
             if value.hardforks[0] == HardforkName::None {
                 H256::zero()
@@ -258,6 +357,7 @@ impl From<HardforkInput> for HardforkInfoInner {
         HardforkInfoInner {
             block_number: value.block_number,
             flags,
+            timestamp: value.timestamp,
         }
     }
 }
@@ -269,6 +369,12 @@ pub enum HardforkName {
     /// If this hardfork is activated, chain validators can modify the EVM
     /// contract size limit.
     Andromeda = 0b1,
+    /// If this hardfork is activated, the system contract calls of a block
+    /// are executed ahead of the user transactions.
+    Antlia = 0b10,
+    /// If this hardfork is activated, an image cell update whose input points
+    /// at a missing cell is rejected instead of skipping the input.
+    Apus = 0b100,
 }
 
 impl HardforkName {
@@ -279,4 +385,16 @@ impl HardforkName {
         }
         res
     }
+
+    /// The hardforks activated by an empty list. It is frozen at the ones
+    /// before Antlia to keep the genesis of the existing chain specs, so the
+    /// later hardforks must be listed explicitly.
+    pub fn implicit() -> u64 {
+        HardforkName::Andromeda as u64
+    }
+
+    /// The flag of the hardfork as the one in `HardforkInfoInner`.
+    pub fn flag(self) -> H256 {
+        H256::from_low_u64_be((self as u64).to_be())
+    }
 }
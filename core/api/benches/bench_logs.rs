@@ -0,0 +1,55 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use core_api::jsonrpc::from_receipt_to_web3_log;
+use protocol::types::{Hash, Log, Receipt, H160};
+
+fn mock_receipt(address: H160, topic0: Hash, len: usize) -> Receipt {
+    let logs = (0..len)
+        .map(|i| Log {
+            address: if i % 4 == 0 { address } else { H160::random() },
+            topics:  vec![
+                if i % 2 == 0 { topic0 } else { Hash::random() },
+                Hash::random(),
+                Hash::random(),
+            ],
+            data:    Vec::new(),
+        })
+        .collect();
+
+    Receipt {
+        logs,
+        ..Default::default()
+    }
+}
+
+fn criterion_filter_single_event(c: &mut Criterion) {
+    let (address, topic0) = (H160::random(), Hash::random());
+    let receipts = (0..100)
+        .map(|_| mock_receipt(address, topic0, 100))
+        .collect::<Vec<_>>();
+    let topics = vec![Some(vec![Some(topic0)])];
+
+    c.bench_function("filter single event", |b| {
+        b.iter(|| {
+            let mut logs = Vec::new();
+            for (index, receipt) in receipts.iter().enumerate() {
+                from_receipt_to_web3_log(index, &topics, &[address], receipt, &mut logs);
+            }
+            logs
+        })
+    });
+
+    // The duplicated address makes the filter matched in the general way.
+    c.bench_function("filter single event in general way", |b| {
+        b.iter(|| {
+            let mut logs = Vec::new();
+            for (index, receipt) in receipts.iter().enumerate() {
+                from_receipt_to_web3_log(index, &topics, &[address, address], receipt, &mut logs);
+            }
+            logs
+        })
+    });
+}
+
+criterion_group!(benches, criterion_filter_single_event);
+criterion_main!(benches);
@@ -2,18 +2,20 @@ use std::sync::Arc;
 
 use protocol::traits::{
     APIAdapter, Context, Executor, ExecutorReadOnlyAdapter, MemPool, Network, ReadOnlyStorage,
+    Storage,
 };
 use protocol::trie::Trie as _;
 use protocol::types::{
-    Account, BigEndianHash, Block, BlockNumber, Bytes, CkbRelatedInfo, EthAccountProof,
-    EthStorageProof, ExecutorContext, HardforkInfo, HardforkInfoInner, Hash, Header, Hex, Metadata,
-    Proposal, Receipt, SignedTransaction, TxResp, H160, H256, MAX_BLOCK_GAS_LIMIT, NIL_DATA,
-    RLP_NULL, U256,
+    AccessedState, Account, BigEndianHash, Block, BlockNumber, Bytes, CkbRelatedInfo,
+    EthAccountProof, EthStorageProof, ExecutorContext, ExecutorLimits, HardforkInfo,
+    HardforkInfoInner, Hash, Header, Hex, Metadata, Proposal, Receipt, SignedTransaction, Tracer,
+    TxResp, TxTrace, H160, H256, MAX_BLOCK_GAS_LIMIT, NIL_DATA, RLP_NULL, U256,
 };
-use protocol::{async_trait, codec::ProtocolCodec, trie, ProtocolResult};
+use protocol::{async_trait, codec::ProtocolCodec, tokio, trie, ProtocolResult};
 
 use core_executor::{
-    system_contract::metadata::MetadataHandle, AxonExecutor, AxonExecutorReadOnlyAdapter, MPTTrie,
+    system_contract::metadata::MetadataHandle, AxonExecutor, AxonExecutorApplyAdapter,
+    AxonExecutorReadOnlyAdapter, MPTTrie, TraceBudget,
 };
 
 use crate::APIError;
@@ -24,6 +26,9 @@ pub struct DefaultAPIAdapter<M, S, DB, Net> {
     storage: Arc<S>,
     trie_db: Arc<DB>,
     net:     Arc<Net>,
+
+    executor_limits: ExecutorLimits,
+    trace_budget:    TraceBudget,
 }
 
 impl<M, S, DB, Net> DefaultAPIAdapter<M, S, DB, Net>
@@ -33,15 +38,76 @@ where
     DB: trie::DB + Send + Sync + 'static,
     Net: Network + 'static,
 {
-    pub fn new(mempool: Arc<M>, storage: Arc<S>, trie_db: Arc<DB>, net: Arc<Net>) -> Self {
+    pub fn new(
+        mempool: Arc<M>,
+        storage: Arc<S>,
+        trie_db: Arc<DB>,
+        net: Arc<Net>,
+        executor_limits: ExecutorLimits,
+    ) -> Self {
         Self {
             mempool,
             storage,
             trie_db,
             net,
+            executor_limits,
+            trace_budget: TraceBudget {
+                steps: usize::MAX,
+                bytes: usize::MAX,
+            },
         }
     }
 
+    /// The max steps of the struct logs of a block in a trace.
+    pub fn max_trace_steps(mut self, max_steps: usize) -> Self {
+        self.trace_budget.steps = max_steps;
+        self
+    }
+
+    /// The max approximate size of the traces of a block in bytes.
+    pub fn max_trace_bytes(mut self, max_bytes: usize) -> Self {
+        self.trace_budget.bytes = max_bytes;
+        self
+    }
+
+    /// The backend to replay the given transactions of a block on, which is at
+    /// the state of its parent.
+    async fn replay_backend(
+        &self,
+        ctx: Context,
+        block: &Block,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<(AxonExecutorApplyAdapter<S, DB>, Vec<SignedTransaction>)> {
+        let number = block.header.number;
+        let state_root = if number == 0 {
+            RLP_NULL
+        } else {
+            self.storage
+                .get_block_header(ctx.clone(), number - 1)
+                .await?
+                .ok_or_else(|| APIError::Adapter(format!("Cannot get {} block", number - 1)))?
+                .state_root
+        };
+
+        let txs = self
+            .storage
+            .get_transactions(ctx, number, tx_hashes)
+            .await?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| APIError::Adapter(format!("Cannot get txs of {} block", number)))?;
+
+        // The replayed state is never committed.
+        let backend = AxonExecutorApplyAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Proposal::new_without_state_root(&block.header).into(),
+        )?;
+
+        Ok((backend, txs))
+    }
+
     pub async fn evm_backend(
         &self,
         number: Option<BlockNumber>,
@@ -64,7 +130,7 @@ where
 impl<M, S, DB, Net> APIAdapter for DefaultAPIAdapter<M, S, DB, Net>
 where
     M: MemPool + 'static,
-    S: ReadOnlyStorage + 'static,
+    S: Storage + 'static,
     DB: trie::DB + Send + Sync + 'static,
     Net: Network + 'static,
 {
@@ -80,6 +146,14 @@ where
         self.mempool.contains(ctx, tx_hash).await
     }
 
+    async fn get_pending_txs(
+        &self,
+        ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.mempool.get_pending_txs(ctx, limit).await
+    }
+
     async fn get_block_by_number(
         &self,
         ctx: Context,
@@ -214,13 +288,24 @@ where
             .map(|gas| gas.as_u64())
             .unwrap_or(MAX_BLOCK_GAS_LIMIT);
 
-        Ok(AxonExecutor.call(&backend, gas_limit, from, to, value, data))
+        Ok(
+            AxonExecutor::new(self.executor_limits)
+                .call(&backend, gas_limit, from, to, value, data),
+        )
     }
 
     async fn get_code_by_hash(&self, ctx: Context, hash: &Hash) -> ProtocolResult<Option<Bytes>> {
         self.storage.get_code_by_hash(ctx, hash).await
     }
 
+    async fn get_contracts_by_deployer(
+        &self,
+        ctx: Context,
+        deployer: H160,
+    ) -> ProtocolResult<Vec<H160>> {
+        self.storage.get_contracts_by_deployer(ctx, &deployer).await
+    }
+
     async fn peer_count(&self, ctx: Context) -> ProtocolResult<U256> {
         self.net.peer_count(ctx).map(Into::into)
     }
@@ -326,23 +411,78 @@ where
         }
     }
 
+    async fn get_accessed_state(
+        &self,
+        ctx: Context,
+        receipt: &Receipt,
+    ) -> ProtocolResult<Option<AccessedState>> {
+        let number = receipt.block_number;
+        let block = self
+            .storage
+            .get_block(ctx.clone(), number)
+            .await?
+            .ok_or_else(|| APIError::Adapter(format!("Cannot get {} block", number)))?;
+        let hashes = block
+            .tx_hashes
+            .get(..=receipt.tx_index as usize)
+            .ok_or_else(|| APIError::Adapter(format!("Invalid tx index {}", receipt.tx_index)))?;
+
+        let (mut backend, txs) = self.replay_backend(ctx, &block, hashes).await?;
+        let mut traced = tokio::task::block_in_place(|| {
+            AxonExecutor::new(self.executor_limits).trace_access(&mut backend, &txs)
+        });
+
+        Ok(traced.pop().flatten().map(|(_, accessed)| accessed))
+    }
+
+    async fn trace_block(
+        &self,
+        ctx: Context,
+        block: &Block,
+        tracer: Tracer,
+    ) -> ProtocolResult<Option<Vec<Option<TxTrace>>>> {
+        let (mut backend, txs) = self.replay_backend(ctx, block, &block.tx_hashes).await?;
+        Ok(tokio::task::block_in_place(|| {
+            AxonExecutor::new(self.executor_limits).trace_txs(
+                &mut backend,
+                &txs,
+                tracer,
+                self.trace_budget,
+            )
+        }))
+    }
+
     async fn get_metadata_by_number(
         &self,
         ctx: Context,
         block_number: Option<u64>,
     ) -> ProtocolResult<Metadata> {
-        if let Some(num) = block_number {
-            return MetadataHandle::new(self.get_metadata_root(ctx, Some(num)).await?)
-                .get_metadata_by_block_number(num);
-        }
-
-        let num = self
+        let latest = self
             .storage
             .get_latest_block_header(ctx.clone())
             .await?
             .number;
-        MetadataHandle::new(self.get_metadata_root(ctx, None).await?)
-            .get_metadata_by_block_number(num)
+
+        match block_number {
+            Some(num) if num <= latest => {
+                MetadataHandle::new(self.get_metadata_root(ctx, Some(num)).await?)
+                    .get_metadata_by_block_number(num)
+            }
+            // A block to come is in an epoch recorded by the latest metadata, if any.
+            Some(num) => {
+                let handle = MetadataHandle::new(self.get_metadata_root(ctx, None).await?);
+                match handle.epoch_of_block(num)? {
+                    Some(epoch) => handle.get_metadata_by_epoch(epoch),
+                    None => Err(APIError::RequestPayload(format!(
+                        "The epoch of block {} is not recorded yet",
+                        num
+                    ))
+                    .into()),
+                }
+            }
+            None => MetadataHandle::new(self.get_metadata_root(ctx, None).await?)
+                .get_metadata_by_block_number(latest),
+        }
     }
 
     async fn get_ckb_related_info(&self, ctx: Context) -> ProtocolResult<CkbRelatedInfo> {
@@ -1,6 +1,6 @@
 use jsonrpsee::types::{error::ErrorObject, ErrorObjectOwned};
 
-use protocol::types::{ExitReason, TxResp};
+use protocol::types::{ExitReason, TxResp, U256};
 use protocol::{codec::hex_encode, Display};
 
 use core_executor::decode_revert_msg;
@@ -15,6 +15,34 @@ pub enum RpcError {
     DecodeInteroperationSigS(String),
     #[display(fmt = "Invalid address source")]
     InvalidAddressSource,
+    #[display(fmt = "Invalid address {}", _0)]
+    InvalidAddress(String),
+    #[display(fmt = "Logs response is larger than {} bytes", _0)]
+    LogsResponseTooLarge(usize),
+    #[display(fmt = "Invalid block range from {} is greater than to {}", _0, _1)]
+    InvalidBlockRangeOrder(u64, u64),
+    #[display(fmt = "Request timeout after {} ms", _0)]
+    RequestTimeout(u64),
+    #[display(
+        fmt = "Insufficient funds for gas * price + value: have {} want {}",
+        _0,
+        _1
+    )]
+    InsufficientFunds(U256, U256),
+    #[display(fmt = "Too many concurrent queries, the limit is {}", _0)]
+    TooManyConcurrentQueries(usize),
+    #[display(fmt = "Trace response is larger than {} bytes", _0)]
+    TraceResponseTooLarge(usize),
+    #[display(fmt = "Too many topics {}, the limit is 4", _0)]
+    TooManyTopics(usize),
+    #[display(fmt = "Too many addresses {}, the limit is {}", _0, _1)]
+    TooManyAddresses(usize, usize),
+    #[display(fmt = "Tracer busy, the limit of concurrent traces is {}", _0)]
+    TracerBusy(usize),
+    #[display(fmt = "The block hash can't be used with the from block or the to block")]
+    InvalidFilterFields,
+    #[display(fmt = "query returned more than {} results", _0)]
+    QueryReturnedTooManyResults(usize),
     #[display(fmt = "Missing dummy input cell")]
     MissingDummyInputCell,
     #[display(fmt = "Cannot find image cell")]
@@ -91,6 +119,18 @@ impl RpcError {
             RpcError::InvalidFromBlockAndToBlockUnion => -40021,
             RpcError::CannotFindFilterId(_) => -40022,
             RpcError::CallSystemContract => -40023,
+            RpcError::InvalidAddress(_) => -40024,
+            RpcError::LogsResponseTooLarge(_) => -40025,
+            RpcError::InvalidBlockRangeOrder(_, _) => -40026,
+            RpcError::RequestTimeout(_) => -40027,
+            RpcError::InsufficientFunds(_, _) => -40028,
+            RpcError::TooManyConcurrentQueries(_) => -40029,
+            RpcError::TraceResponseTooLarge(_) => -40030,
+            RpcError::TooManyTopics(_) => -40031,
+            RpcError::TooManyAddresses(_, _) => -40032,
+            RpcError::TracerBusy(_) => -40033,
+            RpcError::InvalidFilterFields => -40034,
+            RpcError::QueryReturnedTooManyResults(_) => -40035,
 
             RpcError::Evm(_) => -49998,
             RpcError::Internal(_) => -49999,
@@ -133,6 +173,20 @@ impl From<RpcError> for ErrorObjectOwned {
             }
             RpcError::CannotFindFilterId(_) => ErrorObject::owned(err_code, err, none_data),
             RpcError::CallSystemContract => ErrorObject::owned(err_code, err, none_data),
+            RpcError::InvalidAddress(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::LogsResponseTooLarge(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::InvalidBlockRangeOrder(_, _) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::RequestTimeout(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::InsufficientFunds(_, _) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::TooManyConcurrentQueries(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::TraceResponseTooLarge(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::TooManyTopics(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::TooManyAddresses(_, _) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::TracerBusy(_) => ErrorObject::owned(err_code, err, none_data),
+            RpcError::InvalidFilterFields => ErrorObject::owned(err_code, err, none_data),
+            RpcError::QueryReturnedTooManyResults(_) => {
+                ErrorObject::owned(err_code, err, none_data)
+            }
 
             RpcError::Evm(resp) => {
                 ErrorObject::owned(err_code, err.clone(), Some(vm_err(resp.clone())))
@@ -1,25 +1,34 @@
 use std::{collections::HashMap, sync::Arc};
 
-use jsonrpsee::{core::RpcResult, types::error::ErrorCode};
+use jsonrpsee::core::RpcResult;
 use strum::IntoEnumIterator;
 
+use common_apm::metrics_rpc;
 use common_config_parser::types::spec::HardforkName;
 use protocol::async_trait;
 use protocol::traits::{APIAdapter, Context};
 use protocol::types::{
-    Block, CkbRelatedInfo, HardforkInfoInner, Metadata, Proof, Proposal, H256, U256,
+    Block, CkbRelatedInfo, HardforkInfoInner, Hex, Metadata, Proof, Proposal, SignedTransaction,
+    H256, MAX_BLOCK_GAS_LIMIT, MIN_TRANSACTION_GAS_LIMIT, U256,
 };
 
-use crate::jsonrpc::web3_types::{BlockId, HardforkStatus};
+use crate::jsonrpc::r#impl::web3::checked_call;
+use crate::jsonrpc::web3_types::{
+    BlockId, HardforkStatus, InclusionEstimate, Web3CallRequest, Web3CallWithGas, Web3Transaction,
+};
 use crate::jsonrpc::{error::RpcError, AxonRpcServer};
 
 pub struct AxonRpcImpl<Adapter> {
-    adapter: Arc<Adapter>,
+    adapter:                  Arc<Adapter>,
+    max_pending_transactions: usize,
 }
 
 impl<Adapter: APIAdapter> AxonRpcImpl<Adapter> {
-    pub fn new(adapter: Arc<Adapter>) -> Self {
-        AxonRpcImpl { adapter }
+    pub fn new(adapter: Arc<Adapter>, max_pending_transactions: usize) -> Self {
+        AxonRpcImpl {
+            adapter,
+            max_pending_transactions,
+        }
     }
 }
 
@@ -33,8 +42,14 @@ impl<Adapter: APIAdapter + 'static> AxonRpcServer for AxonRpcImpl<Adapter> {
                     .get_block_by_number(Context::new(), Some(num.as_u64()))
                     .await
             }
-            BlockId::Latest => self.adapter.get_block_by_number(Context::new(), None).await,
-            _ => return Err(ErrorCode::InvalidRequest.into()),
+            BlockId::Earliest => {
+                self.adapter
+                    .get_block_by_number(Context::new(), Some(0))
+                    .await
+            }
+            BlockId::Latest | BlockId::Pending | BlockId::Safe | BlockId::Finalized => {
+                self.adapter.get_block_by_number(Context::new(), None).await
+            }
         }
         .map_err(|e| RpcError::Internal(e.to_string()))?;
 
@@ -121,16 +136,18 @@ impl<Adapter: APIAdapter + 'static> AxonRpcServer for AxonRpcImpl<Adapter> {
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        let current_number = self
+        let current_header = self
             .adapter
             .get_block_header_by_number(Default::default(), None)
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?
-            .unwrap()
-            .number;
+            .unwrap();
 
-        let (enabled_latest, determined_latest) =
-            enabled_and_determined(&all_determined.inner, current_number);
+        let (enabled_latest, determined_latest) = enabled_and_determined(
+            &all_determined.inner,
+            current_header.number,
+            current_header.timestamp,
+        );
 
         let mut hardfork_infos = HashMap::new();
         for hardfork_name in HardforkName::iter() {
@@ -155,66 +172,232 @@ impl<Adapter: APIAdapter + 'static> AxonRpcServer for AxonRpcImpl<Adapter> {
 
         Ok(hardfork_infos)
     }
+
+    async fn pending_transactions(&self) -> RpcResult<Vec<Web3Transaction>> {
+        let txs = self
+            .adapter
+            .get_pending_txs(Context::new(), self.max_pending_transactions)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
+
+        Ok(txs.into_iter().map(Into::into).collect())
+    }
+
+    async fn estimate_inclusion(
+        &self,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        gas: Option<U256>,
+    ) -> RpcResult<InclusionEstimate> {
+        let header = self
+            .adapter
+            .get_block_header_by_number(Context::new(), None)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or_else(|| RpcError::Internal("Cannot get the latest block".to_string()))?;
+        let pending = self
+            .adapter
+            .get_pending_txs(Context::new(), self.max_pending_transactions)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
+
+        Ok(estimate_inclusion(
+            &pending,
+            header.base_fee_per_gas,
+            header.gas_limit,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            gas.unwrap_or_else(|| MIN_TRANSACTION_GAS_LIMIT.into()),
+        ))
+    }
+
+    #[metrics_rpc("axon_callWithGas")]
+    async fn call_with_gas(
+        &self,
+        req: Web3CallRequest,
+        block_id: Option<BlockId>,
+    ) -> RpcResult<Web3CallWithGas> {
+        let gas = req.gas;
+        let resp = checked_call(&*self.adapter, req, block_id).await?;
+        // The gas is checked against the cap of the call, so it fits in u64.
+        let gas_limit = gas.map_or(MAX_BLOCK_GAS_LIMIT, |gas| gas.as_u64());
+
+        Ok(Web3CallWithGas {
+            gas_used:   resp.gas_used.into(),
+            gas_refund: resp.gas_refund(gas_limit).into(),
+            output:     Hex::encode(resp.ret),
+        })
+    }
 }
 
-/// Returns (enabled_flags, determined_flags) in target block height
-fn enabled_and_determined(iter: &[HardforkInfoInner], current_number: u64) -> (H256, H256) {
-    if iter.len() < 2 {
-        match iter.last() {
-            Some(latest) => {
-                if latest.block_number > current_number {
-                    (H256::zero(), latest.flags)
-                } else {
-                    (latest.flags, H256::zero())
-                }
-            }
-            None => (H256::zero(), H256::zero()),
+/// The tip paid to the proposer per gas after the base fee is burnt.
+fn effective_tip(max_fee_per_gas: U256, max_priority_fee_per_gas: U256, base_fee: U256) -> U256 {
+    max_priority_fee_per_gas.min(max_fee_per_gas.saturating_sub(base_fee))
+}
+
+/// The transactions of the higher effective tip are packaged first, so a
+/// transaction is expected in the next block if the ones offering no lower
+/// tip leave room in the block for its gas.
+fn estimate_inclusion(
+    pending: &[SignedTransaction],
+    base_fee_per_gas: U256,
+    block_gas_limit: U256,
+    max_fee_per_gas: U256,
+    max_priority_fee_per_gas: U256,
+    gas: U256,
+) -> InclusionEstimate {
+    if max_fee_per_gas < base_fee_per_gas || max_priority_fee_per_gas > max_fee_per_gas {
+        return InclusionEstimate {
+            will_include_next_block: false,
+            percentile:              0.0,
+        };
+    }
+
+    let tip = effective_tip(max_fee_per_gas, max_priority_fee_per_gas, base_fee_per_gas);
+    let (mut lower, mut gas_ahead) = (0usize, U256::zero());
+    for tx in pending.iter() {
+        let unsigned = &tx.transaction.unsigned;
+        let pending_tip = effective_tip(
+            unsigned.gas_price(),
+            *unsigned.max_priority_fee_per_gas(),
+            base_fee_per_gas,
+        );
+        if pending_tip < tip {
+            lower += 1;
+        } else {
+            gas_ahead = gas_ahead.saturating_add(*unsigned.gas_limit());
         }
+    }
+
+    let percentile = if pending.is_empty() {
+        100.0
     } else {
-        let (determined, enabled) = {
-            let mut determined_latest = None;
-            let mut enabled_latest = None;
-            for hf in iter.iter().rev() {
-                if hf.block_number > current_number {
-                    if determined_latest.is_none() {
-                        determined_latest = Some(hf.clone());
-                    }
+        lower as f64 * 100.0 / pending.len() as f64
+    };
+    InclusionEstimate {
+        will_include_next_block: gas_ahead.saturating_add(gas) <= block_gas_limit,
+        percentile,
+    }
+}
+
+/// Returns (enabled_flags, determined_flags) in target block height
+fn enabled_and_determined(
+    iter: &[HardforkInfoInner],
+    current_number: u64,
+    current_timestamp: u64,
+) -> (H256, H256) {
+    let (enabled, scheduled) =
+        iter.iter()
+            .fold((H256::zero(), H256::zero()), |(enabled, scheduled), hf| {
+                if hf.is_activated(current_number, current_timestamp) {
+                    (enabled | hf.flags, scheduled)
                 } else {
-                    enabled_latest = Some(hf.clone())
+                    (enabled, scheduled | hf.flags)
                 }
-                if enabled_latest.is_some() {
-                    break;
-                }
-            }
-            (
-                determined_latest.map(|i| i.flags).unwrap_or_default(),
-                enabled_latest.map(|i| i.flags).unwrap_or_default(),
-            )
-        };
+            });
 
-        (
-            enabled,
-            if determined != H256::zero() {
-                determined ^ enabled
-            } else {
-                determined
-            },
-        )
-    }
+    (enabled, scheduled ^ (scheduled & enabled))
 }
 
 #[cfg(test)]
 mod test {
-    use super::{enabled_and_determined, HardforkInfoInner, H256};
+    use protocol::types::{
+        Account, BlockNumber, Bytes, Eip1559Transaction, Hash, Header, TransactionAction, TxResp,
+        UnsignedTransaction, UnverifiedTransaction, H160,
+    };
+    use protocol::{tokio, ProtocolResult};
+
+    use crate::jsonrpc::r#impl::mock::{Mock, MockAdapter};
+    use crate::jsonrpc::r#impl::Web3RpcImpl;
+    use crate::jsonrpc::Web3RpcServer;
+
+    use super::{
+        async_trait, enabled_and_determined, estimate_inclusion, Arc, AxonRpcImpl, AxonRpcServer,
+        HardforkInfoInner, SignedTransaction, Web3CallRequest, H256, U256,
+    };
+
+    fn mock_tx(max_fee: u64, max_priority_fee: u64, gas_limit: u64) -> SignedTransaction {
+        SignedTransaction {
+            transaction: UnverifiedTransaction {
+                unsigned:  UnsignedTransaction::Eip1559(Eip1559Transaction {
+                    nonce:                    U256::zero(),
+                    max_priority_fee_per_gas: U256::from(max_priority_fee),
+                    gas_price:                U256::from(max_fee),
+                    gas_limit:                U256::from(gas_limit),
+                    action:                   TransactionAction::Call(H160::random()),
+                    value:                    U256::zero(),
+                    data:                     Bytes::new(),
+                    access_list:              Vec::new(),
+                }),
+                signature: None,
+                chain_id:  Some(0),
+                hash:      H256::random(),
+            },
+            sender:      H160::random(),
+            public:      None,
+        }
+    }
+
+    #[test]
+    fn test_estimate_inclusion() {
+        // The pending transactions tipping 5 to 95 take 10 blocks.
+        let mut pending = (1..=10u64)
+            .map(|i| mock_tx(i * 10, i * 10, 50_000))
+            .collect::<Vec<_>>();
+        // A high max fee with a low priority fee only tips 1.
+        pending.push(mock_tx(1000, 1, 50_000));
+        let (base_fee, gas_limit) = (U256::from(5), U256::from(100_000));
+        let estimate = |max_fee: u64, max_priority_fee: u64, gas: u64| {
+            estimate_inclusion(
+                &pending,
+                base_fee,
+                gas_limit,
+                max_fee.into(),
+                max_priority_fee.into(),
+                gas.into(),
+            )
+        };
+
+        let high = estimate(200, 200, 21_000);
+        assert!(high.will_include_next_block);
+        assert_eq!(high.percentile, 100.0);
+
+        // The tip is capped by the max fee minus the base fee.
+        let capped = estimate(95, 200, 21_000);
+        assert!(capped.will_include_next_block);
+        assert_eq!(capped.percentile, 10.0 * 100.0 / 11.0);
+
+        // The tip is capped by the priority fee.
+        let low = estimate(200, 2, 21_000);
+        assert!(!low.will_include_next_block);
+        assert_eq!(low.percentile, 100.0 / 11.0);
+
+        // The gas of the transaction counts against the block gas limit.
+        let room = estimate(100, 91, 50_000);
+        assert!(room.will_include_next_block);
+        assert!(!estimate(100, 91, 50_001).will_include_next_block);
+
+        // The one offering the same tip as the highest pending one is behind it.
+        let tie = estimate(100, 95, 60_000);
+        assert!(!tie.will_include_next_block);
+        assert_eq!(tie.percentile, 10.0 * 100.0 / 11.0);
+        assert!(estimate(200, 96, 60_000).will_include_next_block);
+
+        // The one below the base fee is never packaged.
+        let below = estimate(1, 1, 21_000);
+        assert!(!below.will_include_next_block);
+        assert_eq!(below.percentile, 0.0);
+    }
 
     #[test]
     fn test_select() {
         let v1 = vec![HardforkInfoInner {
             block_number: 0,
             flags:        H256::zero(),
+            timestamp:    0,
         }];
 
-        let (ve1, vd1) = enabled_and_determined(&v1, 0);
+        let (ve1, vd1) = enabled_and_determined(&v1, 0, 0);
 
         assert_eq!(ve1, H256::zero());
         assert_eq!(vd1, H256::zero());
@@ -226,9 +409,10 @@ mod test {
                 a[0] = 0b10;
                 H256::from(a)
             },
+            timestamp:    0,
         }];
 
-        let (ve2, vd2) = enabled_and_determined(&v2, 0);
+        let (ve2, vd2) = enabled_and_determined(&v2, 0, 0);
 
         assert_eq!(ve2, {
             let mut a = [0; 32];
@@ -245,6 +429,7 @@ mod test {
                     a[0] = 0b10;
                     H256::from(a)
                 },
+                timestamp:    0,
             },
             HardforkInfoInner {
                 block_number: 5,
@@ -253,10 +438,11 @@ mod test {
                     a[0] = 0b11;
                     H256::from(a)
                 },
+                timestamp:    0,
             },
         ];
 
-        let (ve3, vd3) = enabled_and_determined(&v3, 0);
+        let (ve3, vd3) = enabled_and_determined(&v3, 0, 0);
 
         assert_eq!(ve3, {
             let mut a = [0; 32];
@@ -269,7 +455,7 @@ mod test {
             H256::from(a)
         });
 
-        let (ve31, vd31) = enabled_and_determined(&v3, 5);
+        let (ve31, vd31) = enabled_and_determined(&v3, 5, 0);
 
         assert_eq!(ve31, {
             let mut a = [0; 32];
@@ -286,6 +472,7 @@ mod test {
                     a[0] = 0b10;
                     H256::from(a)
                 },
+                timestamp:    0,
             },
             HardforkInfoInner {
                 block_number: 5,
@@ -294,6 +481,7 @@ mod test {
                     a[0] = 0b11;
                     H256::from(a)
                 },
+                timestamp:    0,
             },
             HardforkInfoInner {
                 block_number: 10,
@@ -302,10 +490,11 @@ mod test {
                     a[0] = 0b111;
                     H256::from(a)
                 },
+                timestamp:    0,
             },
         ];
 
-        let (ve4, vd4) = enabled_and_determined(&v4, 0);
+        let (ve4, vd4) = enabled_and_determined(&v4, 0, 0);
 
         assert_eq!(ve4, {
             let mut a = [0; 32];
@@ -318,7 +507,7 @@ mod test {
             H256::from(a)
         });
 
-        let (ve41, vd41) = enabled_and_determined(&v4, 2);
+        let (ve41, vd41) = enabled_and_determined(&v4, 2, 0);
 
         assert_eq!(ve41, {
             let mut a = [0; 32];
@@ -331,7 +520,7 @@ mod test {
             H256::from(a)
         });
 
-        let (ve42, vd42) = enabled_and_determined(&v4, 5);
+        let (ve42, vd42) = enabled_and_determined(&v4, 5, 0);
 
         assert_eq!(ve42, {
             let mut a = [0; 32];
@@ -344,7 +533,7 @@ mod test {
             H256::from(a)
         });
 
-        let (ve43, vd43) = enabled_and_determined(&v4, 8);
+        let (ve43, vd43) = enabled_and_determined(&v4, 8, 0);
 
         assert_eq!(ve43, {
             let mut a = [0; 32];
@@ -357,7 +546,7 @@ mod test {
             H256::from(a)
         });
 
-        let (ve44, vd44) = enabled_and_determined(&v4, 10);
+        let (ve44, vd44) = enabled_and_determined(&v4, 10, 0);
 
         assert_eq!(ve44, {
             let mut a = [0; 32];
@@ -366,7 +555,7 @@ mod test {
         });
         assert_eq!(vd44, H256::zero());
 
-        let (ve45, vd45) = enabled_and_determined(&v4, 12);
+        let (ve45, vd45) = enabled_and_determined(&v4, 12, 0);
 
         assert_eq!(ve45, {
             let mut a = [0; 32];
@@ -375,4 +564,61 @@ mod test {
         });
         assert_eq!(vd45, H256::zero());
     }
+
+    #[tokio::test]
+    async fn test_call_with_gas() {
+        /// The calls succeed with the response.
+        struct EvmCall(TxResp);
+
+        #[async_trait]
+        impl MockAdapter for EvmCall {
+            async fn get_block_header_by_number(
+                &self,
+                _: Option<u64>,
+            ) -> ProtocolResult<Option<Header>> {
+                Ok(Some(Header::default()))
+            }
+
+            async fn get_account(
+                &self,
+                _: H160,
+                _: Option<BlockNumber>,
+            ) -> ProtocolResult<Account> {
+                Ok(Account {
+                    nonce:        U256::zero(),
+                    balance:      U256::zero(),
+                    storage_root: Hash::default(),
+                    code_hash:    Hash::default(),
+                })
+            }
+
+            async fn evm_call(&self) -> ProtocolResult<TxResp> {
+                Ok(self.0.clone())
+            }
+        }
+
+        let adapter = Arc::new(Mock(EvmCall(TxResp {
+            ret: vec![0x2a],
+            gas_used: 26_000,
+            remain_gas: 70_000,
+            ..Default::default()
+        })));
+        let web3 = Web3RpcImpl::new(Arc::clone(&adapter), u64::MAX, 100, usize::MAX, 32);
+        let axon = AxonRpcImpl::new(adapter, usize::MAX);
+        let mut req: Web3CallRequest = serde_json::from_value(serde_json::json!({
+            "to": H160::repeat_byte(2),
+            "gas": "0x186a0",
+        }))
+        .unwrap();
+
+        let resp = axon.call_with_gas(req.clone(), None).await.unwrap();
+        assert_eq!(resp.output, web3.call(req.clone(), None).await.unwrap());
+        assert_eq!(resp.gas_used, U256::from(26_000u64));
+        assert_eq!(resp.gas_refund, U256::from(4_000u64));
+
+        // A gas beyond u64 is rejected as eth_call does.
+        req.gas = Some(U256::MAX);
+        let err = axon.call_with_gas(req.clone(), None).await.unwrap_err();
+        assert_eq!(err.code(), web3.call(req, None).await.unwrap_err().code());
+    }
 }
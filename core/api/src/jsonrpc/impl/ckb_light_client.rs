@@ -8,7 +8,7 @@ use jsonrpsee::core::RpcResult;
 
 use core_executor::DataProvider;
 use protocol::traits::{APIAdapter, Context};
-use protocol::{async_trait, ckb_blake2b_256, types::H256};
+use protocol::{async_trait, hash::ckb_blake2b, types::H256};
 
 use crate::jsonrpc::{error::RpcError, CkbLightClientRpcServer};
 
@@ -48,7 +48,7 @@ impl<Adapter: APIAdapter + 'static> CkbLightClientRpcServer for CkbLightClientRp
                 Ok(Some(CellInfo {
                     output: c.cell_output.into(),
                     data:   data.map(|r| CellData {
-                        hash:    ckb_types::H256(ckb_blake2b_256(&r)),
+                        hash:    ckb_types::H256(ckb_blake2b(&r)),
                         content: JsonBytes::from_bytes(r),
                     }),
                 }))
@@ -1,48 +1,145 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use jsonrpsee::core::RpcResult;
+use jsonrpsee::server::{
+    ConnectionId, IntoResponse, MethodCallback, MethodResponse, MethodSink, Methods,
+    SubscriptionPermit,
+};
+use jsonrpsee::types::Params;
 use serde::{Deserialize, Serialize};
 
 use protocol::tokio::sync::mpsc::{channel, Receiver, Sender};
-use protocol::tokio::{self, select, sync::oneshot, time::interval};
+use protocol::tokio::{self, select, sync::oneshot, task::JoinSet, time::interval, time::sleep};
 use protocol::traits::{APIAdapter, Context};
 use protocol::types::{BlockNumber, Hash, Receipt, H160, H256, U256, U64};
-use protocol::{async_trait, rand::prelude::*};
+use protocol::{async_trait, rand::prelude::*, ProtocolResult};
 
+use common_config_parser::types::ConfigWeb3;
+
+use crate::jsonrpc::r#impl::{
+    from_receipt_to_web3_log,
+    web3::{LogsQueryLimit, LogsSizeLimit},
+};
 use crate::jsonrpc::web3_types::{BlockId, FilterChanges, RawLoggerFilter, Web3Log};
-use crate::jsonrpc::{error::RpcError, r#impl::from_receipt_to_web3_log, Web3FilterServer};
+use crate::jsonrpc::{error::RpcError, Web3FilterServer};
+
+const WAIT_FILTER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_FILTER_TIMEOUT: Duration = Duration::from_secs(40);
+const DEFAULT_GC_INTERVAL: Duration = Duration::from_secs(20);
+const DEFAULT_FILTER_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// The number of the latest returned blocks of a log filter whose hashes are
+/// tracked to detect a reorg.
+const MAX_TRACKED_BLOCKS: usize = 64;
 
 pub fn filter_module<Adapter>(
     adapter: Arc<Adapter>,
-    log_filter_max_block_range: u64,
+    config: &ConfigWeb3,
+    logs_query_limit: LogsQueryLimit,
 ) -> AxonWeb3RpcFilter
 where
     Adapter: APIAdapter + 'static,
 {
     let (tx, rx) = channel(128);
+    let retry = ReadRetry {
+        times:    config.filter_read_retry_times,
+        interval: Duration::from_millis(config.filter_read_retry_interval),
+        timeout:  Duration::from_millis(config.filter_query_timeout),
+    };
+
+    tokio::spawn(
+        FilterHub::new(adapter, rx, config.log_filter_max_block_range)
+            .max_logs_response_bytes(config.max_logs_response_bytes)
+            .max_filters(config.max_filters)
+            .read_retry(retry)
+            .logs_query_limit(logs_query_limit)
+            .filter_timeout(Duration::from_millis(config.filter_timeout))
+            .gc_interval(Duration::from_millis(config.filter_gc_interval))
+            .max_logs_returned(config.max_logs_returned)
+            .run(),
+    );
+
+    AxonWeb3RpcFilter {
+        sender:   tx,
+        max_wait: Duration::from_millis(config.filter_wait_max_timeout),
+    }
+}
 
-    tokio::spawn(FilterHub::new(adapter, rx, log_filter_max_block_range).run());
+/// The bounded retry with backoff of the adapter reads in a filter query, so
+/// that a transient storage error does not fail the query immediately.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadRetry {
+    pub times:    u64,
+    pub interval: Duration,
+    pub timeout:  Duration,
+}
 
-    AxonWeb3RpcFilter { sender: tx }
+/// A read is not retried by default.
+impl Default for ReadRetry {
+    fn default() -> Self {
+        ReadRetry {
+            times:    0,
+            interval: Duration::ZERO,
+            timeout:  DEFAULT_FILTER_QUERY_TIMEOUT,
+        }
+    }
+}
+
+impl ReadRetry {
+    fn deadline(&self) -> Instant {
+        Instant::now() + self.timeout
+    }
+
+    async fn read<T, F, Fut>(&self, deadline: Instant, mut f: F) -> ProtocolResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ProtocolResult<T>>,
+    {
+        let mut backoff = self.interval;
+        let mut retried = 0;
+
+        loop {
+            match f().await {
+                Ok(res) => return Ok(res),
+                Err(e) if retried >= self.times || Instant::now() + backoff >= deadline => {
+                    return Err(e)
+                }
+                Err(e) => {
+                    log::warn!("[filter] read failed {:?}, retry after {:?}", e, backoff);
+                    sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    retried += 1;
+                }
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct LoggerFilter {
-    pub from_block: Option<BlockId>,
-    pub to_block:   Option<BlockId>,
-    pub address:    Option<Vec<H160>>,
-    pub topics:     Vec<Option<Vec<Option<Hash>>>>,
+    pub from_block:          Option<BlockId>,
+    pub to_block:            Option<BlockId>,
+    pub address:             Option<Vec<H160>>,
+    pub topics:              Vec<Option<Vec<Option<Hash>>>>,
+    /// The only block scanned by the filter, whose logs are returned once in
+    /// the changes.
+    pub block_hash:          Option<H256>,
+    /// The from block given on installing, the `from_block` is the cursor of
+    /// the changes which moves forward on each poll.
+    pub original_from_block: Option<BlockId>,
 }
 
 impl From<RawLoggerFilter> for LoggerFilter {
     fn from(src: RawLoggerFilter) -> Self {
         LoggerFilter {
-            from_block: src.from_block,
-            to_block:   src.to_block,
-            address:    src.address.into(),
-            topics:     src
+            original_from_block: src.from_block.clone(),
+            from_block:          src.from_block,
+            to_block:            src.to_block,
+            block_hash:          src.block_hash,
+            address:             src.address.into(),
+            topics:              src
                 .topics
                 .unwrap_or_default()
                 .into_iter()
@@ -53,16 +150,89 @@ impl From<RawLoggerFilter> for LoggerFilter {
     }
 }
 
+#[derive(Clone)]
 pub struct AxonWeb3RpcFilter {
-    sender: Sender<Command>,
+    sender:   Sender<Command>,
+    max_wait: Duration,
+}
+
+impl AxonWeb3RpcFilter {
+    /// The filter methods served over WebSocket. A filter installed over a
+    /// connection takes one of its subscription slots until the filter is
+    /// removed, and all the filters of a connection are removed once it's
+    /// closed.
+    pub fn into_ws_rpc(self) -> Methods {
+        let rpc = self.clone().into_rpc();
+        let mut methods = Methods::new();
+
+        for name in rpc.method_names() {
+            let callback = match name {
+                "eth_newFilter" => self.ws_install(|filter, params| async move {
+                    filter.new_filter(params.sequence().next()?).await
+                }),
+                "eth_newBlockFilter" => {
+                    self.ws_install(|filter, _| async move { filter.block_filter().await })
+                }
+                _ => rpc.method(name).unwrap().clone(),
+            };
+            methods.verify_and_insert(name, callback).unwrap();
+        }
+        methods
+    }
+
+    /// Install a filter as a subscription, so that the transport takes a slot
+    /// of the connection for it, which is held by the hub along with the
+    /// filter.
+    fn ws_install<F, Fut>(&self, install: F) -> MethodCallback
+    where
+        F: Fn(AxonWeb3RpcFilter, Params<'static>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = RpcResult<U256>> + Send + 'static,
+    {
+        let filter = self.clone();
+
+        MethodCallback::Subscription(Arc::new(move |id, params, sink, conn| {
+            let (filter, install) = (filter.clone(), install.clone());
+            let (id, params) = (id.into_owned(), params.into_owned());
+            let (conn_id, permit) = (conn.conn_id, conn.subscription_permit);
+
+            Box::pin(async move {
+                let res = install(filter.clone(), params).await;
+                if let Ok(filter_id) = res {
+                    let _ = filter
+                        .sender
+                        .send(Command::Own((filter_id, conn_id, permit, sink.clone())))
+                        .await;
+                }
+
+                // The transport doesn't reply to a subscription method.
+                let resp = MethodResponse::response(
+                    id,
+                    res.into_response(),
+                    sink.max_response_size() as usize,
+                );
+                let _ = sink.send(resp.result.clone()).await;
+                Ok(resp)
+            })
+        }))
+    }
 }
 
 #[async_trait]
 impl Web3FilterServer for AxonWeb3RpcFilter {
     async fn new_filter(&self, filter: RawLoggerFilter) -> RpcResult<U256> {
+        if filter.block_hash.is_some() && (filter.from_block.is_some() || filter.to_block.is_some())
+        {
+            return Err(RpcError::InvalidFilterFields.into());
+        }
         if let Some(BlockId::Pending) = filter.from_block {
             return Err(RpcError::InvalidFromBlockAndToBlockUnion.into());
         }
+        // A position of the topics may be null as the wildcard, but there are at
+        // most 4 positions.
+        let topics_len = filter.topics.as_ref().map(Vec::len).unwrap_or_default();
+        if topics_len > 4 {
+            return Err(RpcError::TooManyTopics(topics_len).into());
+        }
         match filter.to_block {
             Some(BlockId::Earliest) | Some(BlockId::Num(U64([0]))) => {
                 return Err(RpcError::Internal("Invalid to_block".to_string()).into())
@@ -76,7 +246,7 @@ impl Web3FilterServer for AxonWeb3RpcFilter {
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        Ok(rx.await.unwrap())
+        rx.await.map_err(|e| RpcError::Internal(e.to_string()))?
     }
 
     async fn block_filter(&self) -> RpcResult<U256> {
@@ -87,18 +257,18 @@ impl Web3FilterServer for AxonWeb3RpcFilter {
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        Ok(rx.await.unwrap())
+        rx.await.map_err(|e| RpcError::Internal(e.to_string()))?
     }
 
     async fn get_filter_logs(&self, id: U256) -> RpcResult<FilterChanges> {
         let (tx, rx) = oneshot::channel();
 
         self.sender
-            .send(Command::FilterRequest((id, tx)))
+            .send(Command::FilterLogs((id, tx)))
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        rx.await.unwrap()
+        rx.await.map_err(|e| RpcError::Internal(e.to_string()))?
     }
 
     async fn get_filter_changes(&self, id: U256) -> RpcResult<FilterChanges> {
@@ -109,7 +279,7 @@ impl Web3FilterServer for AxonWeb3RpcFilter {
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        rx.await.unwrap()
+        rx.await.map_err(|e| RpcError::Internal(e.to_string()))?
     }
 
     async fn uninstall_filter(&self, id: U256) -> RpcResult<bool> {
@@ -120,23 +290,67 @@ impl Web3FilterServer for AxonWeb3RpcFilter {
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        Ok(rx.await.unwrap())
+        Ok(rx.await.map_err(|e| RpcError::Internal(e.to_string()))?)
+    }
+
+    async fn wait_filter_changes(
+        &self,
+        id: U256,
+        timeout: Option<u64>,
+    ) -> RpcResult<FilterChanges> {
+        let timeout = timeout
+            .map(|t| Duration::from_millis(t).min(self.max_wait))
+            .unwrap_or(self.max_wait);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let changes = self.get_filter_changes(id).await?;
+            match changes {
+                FilterChanges::Blocks(ref hashes)
+                    if hashes.is_empty() && Instant::now() < deadline => {}
+                _ => return Ok(changes),
+            }
+
+            sleep(
+                WAIT_FILTER_POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now())),
+            )
+            .await;
+        }
     }
 }
 
 pub enum Command {
-    NewLogs((LoggerFilter, oneshot::Sender<U256>)),
-    NewBlocks(oneshot::Sender<U256>),
+    NewLogs((LoggerFilter, oneshot::Sender<RpcResult<U256>>)),
+    NewBlocks(oneshot::Sender<RpcResult<U256>>),
     FilterRequest((U256, oneshot::Sender<RpcResult<FilterChanges>>)),
+    FilterLogs((U256, oneshot::Sender<RpcResult<FilterChanges>>)),
     Uninstall((U256, oneshot::Sender<bool>)),
+    /// The filter is installed over the WebSocket connection, which is closed
+    /// once the sink is closed.
+    Own((U256, ConnectionId, SubscriptionPermit, MethodSink)),
 }
 
+/// The hash and the logs of each block returned by a log filter.
+type ReturnedBlocks = BTreeMap<BlockNumber, (H256, Vec<Web3Log>)>;
+
 pub struct FilterHub<Adapter> {
-    logs_hub:                   HashMap<U256, (LoggerFilter, Instant)>,
+    logs_hub:                   HashMap<U256, (LoggerFilter, Instant, ReturnedBlocks)>,
     blocks_hub:                 HashMap<U256, (BlockNumber, Instant)>,
+    /// The WebSocket connections of the filters and the slots they take.
+    owners:                     HashMap<U256, (ConnectionId, SubscriptionPermit)>,
+    /// Each one resolves to the connection once it's closed.
+    connections:                JoinSet<ConnectionId>,
+    watched:                    HashSet<ConnectionId>,
     recv:                       Receiver<Command>,
     adapter:                    Arc<Adapter>,
     log_filter_max_block_range: u64,
+    max_logs_response_bytes:    usize,
+    max_logs_returned:          usize,
+    max_filters:                usize,
+    retry:                      ReadRetry,
+    logs_query_limit:           LogsQueryLimit,
+    filter_timeout:             Duration,
+    gc_interval:                Duration,
 }
 
 impl<Adapter> FilterHub<Adapter>
@@ -151,26 +365,85 @@ where
         Self {
             logs_hub: HashMap::new(),
             blocks_hub: HashMap::new(),
+            owners: HashMap::new(),
+            connections: JoinSet::new(),
+            watched: HashSet::new(),
             recv,
             adapter,
             log_filter_max_block_range,
+            max_logs_response_bytes: usize::MAX,
+            max_logs_returned: usize::MAX,
+            max_filters: usize::MAX,
+            retry: ReadRetry::default(),
+            logs_query_limit: LogsQueryLimit::unlimited(),
+            filter_timeout: DEFAULT_FILTER_TIMEOUT,
+            gc_interval: DEFAULT_GC_INTERVAL,
         }
     }
 
+    /// A log query whose response exceeds the max bytes is rejected.
+    pub fn max_logs_response_bytes(mut self, max: usize) -> Self {
+        self.max_logs_response_bytes = max;
+        self
+    }
+
+    /// The least recently used filter is evicted once the number of the
+    /// filters reaches the max.
+    pub fn max_filters(mut self, max: usize) -> Self {
+        self.max_filters = max;
+        self
+    }
+
+    /// Retry the failed adapter reads of a filter query.
+    pub fn read_retry(mut self, retry: ReadRetry) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Share the limit of the concurrent log queries with `eth_getLogs`.
+    pub fn logs_query_limit(mut self, limit: LogsQueryLimit) -> Self {
+        self.logs_query_limit = limit;
+        self
+    }
+
+    /// A filter not polled within the timeout is removed.
+    pub fn filter_timeout(mut self, timeout: Duration) -> Self {
+        self.filter_timeout = timeout;
+        self
+    }
+
+    /// The interval of removing the timeout filters.
+    pub fn gc_interval(mut self, interval: Duration) -> Self {
+        self.gc_interval = interval;
+        self
+    }
+
+    /// A log query returning more logs than the max is rejected.
+    pub fn max_logs_returned(mut self, max: usize) -> Self {
+        self.max_logs_returned = max;
+        self
+    }
+
     async fn run(mut self) {
-        let mut time_internal = interval(Duration::from_secs(20));
+        // A zero period panics the interval.
+        let mut time_internal = interval(self.gc_interval.max(Duration::from_millis(1)));
         loop {
             select! {
                 event = self.recv.recv() => {
                     match event {
                         Some(cmd) => {
-                            self.handle(cmd).await
+                            if let Err(e) = self.handle(cmd).await {
+                                log::warn!("[filter] handle command error {:?}", e);
+                            }
                         },
                         None => {
                             break
                         }
                     }
                 }
+                Some(Ok(conn_id)) = self.connections.join_next(), if !self.connections.is_empty() => {
+                    self.close_connection(conn_id);
+                }
                 _ = time_internal.tick() => {
                     self.check_hubs();
                 }
@@ -183,95 +456,190 @@ where
 
     fn check_hubs(&mut self) {
         let now = Instant::now();
+        let timeout = self.filter_timeout;
         self.blocks_hub
-            .retain(|_, (_, time)| now.saturating_duration_since(*time) < Duration::from_secs(40));
+            .retain(|_, (_, time)| now.saturating_duration_since(*time) < timeout);
         self.logs_hub
-            .retain(|_, (_, time)| now.saturating_duration_since(*time) < Duration::from_secs(40))
+            .retain(|_, (_, time, _)| now.saturating_duration_since(*time) < timeout);
+        self.release_owners();
     }
 
-    async fn handle(&mut self, cmd: Command) {
-        match cmd {
-            Command::NewLogs((mut filter, sender)) => {
-                let id = random_id();
+    /// Release the slots of the connections taken by the removed filters.
+    fn release_owners(&mut self) {
+        let (logs_hub, blocks_hub) = (&self.logs_hub, &self.blocks_hub);
+        self.owners
+            .retain(|id, _| logs_hub.contains_key(id) || blocks_hub.contains_key(id));
+    }
 
-                let header = self
-                    .adapter
-                    .get_block_header_by_number(Context::new(), None)
-                    .await
-                    .unwrap()
-                    .unwrap();
-                let from = filter.from_block.as_ref().unwrap_or(&BlockId::Latest);
+    /// Remove all the filters installed over a closed connection.
+    fn close_connection(&mut self, conn_id: ConnectionId) {
+        let (logs_hub, blocks_hub) = (&mut self.logs_hub, &mut self.blocks_hub);
+        self.owners.retain(|id, (owner, _)| {
+            if *owner != conn_id {
+                return true;
+            }
+            logs_hub.remove(id);
+            blocks_hub.remove(id);
+            false
+        });
+        self.watched.remove(&conn_id);
+    }
 
-                match from {
-                    BlockId::Num(n) => {
-                        if n.as_u64() < header.number {
-                            filter.from_block = Some(BlockId::Num(U64::from(header.number + 1)));
-                        }
-                    }
-                    _ => filter.from_block = Some(BlockId::Num(U64::from(header.number + 1))),
+    /// Evict the least recently used filters until there is room for a new
+    /// one, the polls of an evicted filter get `CannotFindFilterId`.
+    fn evict_filters(&mut self) {
+        while self.logs_hub.len() + self.blocks_hub.len() >= self.max_filters.max(1) {
+            let oldest = self
+                .logs_hub
+                .iter()
+                .map(|(id, (_, time, _))| (*time, *id, true))
+                .chain(
+                    self.blocks_hub
+                        .iter()
+                        .map(|(id, (_, time))| (*time, *id, false)),
+                )
+                .min();
+
+            match oldest {
+                Some((_, id, true)) => {
+                    self.logs_hub.remove(&id);
                 }
+                Some((_, id, false)) => {
+                    self.blocks_hub.remove(&id);
+                }
+                None => break,
+            }
+        }
+        self.release_owners();
+    }
 
-                self.logs_hub.insert(id, (filter, Instant::now()));
-                sender.send(id).unwrap()
+    /// The errors of the commands are sent back to the callers, so an error
+    /// returned here only means that a caller has gone.
+    async fn handle(&mut self, cmd: Command) -> RpcResult<()> {
+        match cmd {
+            Command::NewLogs((filter, sender)) => {
+                let res = self.new_logs_filter(filter).await;
+                reply(sender, res)
             }
             Command::NewBlocks(sender) => {
-                let id = random_id();
-                let header = self
-                    .adapter
-                    .get_block_header_by_number(Context::new(), None)
-                    .await
-                    .unwrap()
-                    .unwrap();
-                self.blocks_hub.insert(id, (header.number, Instant::now()));
-                sender.send(id).unwrap()
+                let res = self.new_blocks_filter().await;
+                reply(sender, res)
+            }
+            Command::FilterRequest((id, sender)) => {
+                let res = self.impl_filter(id).await;
+                reply(sender, res)
+            }
+            Command::FilterLogs((id, sender)) => {
+                let res = self.filter_logs(&id, true).await.map(FilterChanges::Logs);
+                reply(sender, res)
             }
-            Command::FilterRequest((id, sender)) => self.impl_filter(id, sender).await,
             Command::Uninstall((id, sender)) => {
                 let removed =
                     self.blocks_hub.remove(&id).is_some() || self.logs_hub.remove(&id).is_some();
-                sender.send(removed).unwrap()
+                self.release_owners();
+                reply(sender, removed)
+            }
+            Command::Own((id, conn_id, permit, sink)) => {
+                // The filter may be removed before it's owned.
+                if self.logs_hub.contains_key(&id) || self.blocks_hub.contains_key(&id) {
+                    self.owners.insert(id, (conn_id, permit));
+                    if self.watched.insert(conn_id) {
+                        self.connections.spawn(async move {
+                            sink.closed().await;
+                            conn_id
+                        });
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn new_logs_filter(&mut self, mut filter: LoggerFilter) -> RpcResult<U256> {
+        let id = random_id();
+        let header = self
+            .adapter
+            .get_block_header_by_number(Context::new(), None)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or_else(|| RpcError::Internal("Cannot get the latest block".to_string()))?;
+        let from = filter.from_block.as_ref().unwrap_or(&BlockId::Latest);
+
+        match from {
+            // The filter pinned to a block has no range.
+            _ if filter.block_hash.is_some() => (),
+            BlockId::Num(n) => {
+                if n.as_u64() < header.number {
+                    filter.from_block = Some(BlockId::Num(U64::from(header.number + 1)));
+                }
             }
+            _ => filter.from_block = Some(BlockId::Num(U64::from(header.number + 1))),
         }
+
+        self.evict_filters();
+        self.logs_hub
+            .insert(id, (filter, Instant::now(), BTreeMap::new()));
+        Ok(id)
     }
 
-    async fn impl_filter(&mut self, id: U256, sender: oneshot::Sender<RpcResult<FilterChanges>>) {
+    async fn new_blocks_filter(&mut self) -> RpcResult<U256> {
+        let id = random_id();
+        let header = self
+            .adapter
+            .get_block_header_by_number(Context::new(), None)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or_else(|| RpcError::Internal("Cannot get the latest block".to_string()))?;
+
+        self.evict_filters();
+        self.blocks_hub.insert(id, (header.number, Instant::now()));
+        Ok(id)
+    }
+
+    async fn impl_filter(&mut self, id: U256) -> RpcResult<FilterChanges> {
         if self.blocks_hub.contains_key(&id) {
-            let res = Ok(FilterChanges::Blocks(self.filter_block(&id).await));
-            sender.send(res).unwrap()
+            self.filter_block(&id).await.map(FilterChanges::Blocks)
         } else if self.logs_hub.contains_key(&id) {
-            let res = self.filter_logs(&id).await.map(FilterChanges::Logs);
+            let res = self.filter_logs(&id, false).await.map(FilterChanges::Logs);
             if res.is_err() {
                 self.logs_hub.remove(&id);
             }
-            sender.send(res).unwrap()
+            res
         } else {
-            sender
-                .send(Err(RpcError::CannotFindFilterId(id.as_u64()).into()))
-                .unwrap()
+            Err(RpcError::CannotFindFilterId(id.low_u64()).into())
         }
     }
 
-    async fn filter_block(&mut self, id: &U256) -> Vec<H256> {
-        let (start, time) = self.blocks_hub.get_mut(id).unwrap();
+    async fn filter_block(&mut self, id: &U256) -> RpcResult<Vec<H256>> {
+        let deadline = self.retry.deadline();
+        let (start, time) = self
+            .blocks_hub
+            .get_mut(id)
+            .ok_or(RpcError::CannotFindFilterId(id.low_u64()))?;
         let latest = self
-            .adapter
-            .get_block_by_number(Context::new(), None)
+            .retry
+            .read(deadline, || {
+                self.adapter.get_block_by_number(Context::new(), None)
+            })
             .await
-            .unwrap()
-            .unwrap();
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or_else(|| RpcError::Internal("Cannot get the latest block".to_string()))?;
         if *start >= latest.header.number {
-            return Vec::new();
+            return Ok(Vec::new());
         }
 
         let mut block_hashes = Vec::with_capacity((latest.header.number - *start) as usize);
 
         for number in *start + 1..latest.header.number {
             let block = self
-                .adapter
-                .get_block_by_number(Context::new(), Some(number))
+                .retry
+                .read(deadline, || {
+                    self.adapter
+                        .get_block_by_number(Context::new(), Some(number))
+                })
                 .await
-                .unwrap()
-                .unwrap();
+                .map_err(|e| RpcError::Internal(e.to_string()))?
+                .ok_or_else(|| RpcError::Internal(format!("Cannot get block {}", number)))?;
 
             block_hashes.push(block.hash());
         }
@@ -281,25 +649,103 @@ where
         *start = latest.header.number;
         *time = Instant::now();
 
-        block_hashes
+        Ok(block_hashes)
     }
 
-    async fn filter_logs(&mut self, id: &U256) -> RpcResult<Vec<Web3Log>> {
-        let (filter, time) = self.logs_hub.get_mut(id).unwrap();
-
-        let topics = filter.topics.as_slice();
+    /// Return the changes since the last poll, or all the logs matching the
+    /// filter from its original from block if `full` is true. The full query
+    /// leaves the cursor of the changes untouched.
+    async fn filter_logs(&mut self, id: &U256, full: bool) -> RpcResult<Vec<Web3Log>> {
+        let _permit = self.logs_query_limit.acquire()?;
+        let deadline = self.retry.deadline();
+        let (filter, time, returned) = self
+            .logs_hub
+            .get_mut(id)
+            .ok_or(RpcError::CannotFindFilterId(id.low_u64()))?;
+        let (mut full_filter, mut full_returned);
+        let (filter, returned) = if full {
+            full_filter = LoggerFilter {
+                from_block: filter.original_from_block.clone(),
+                ..filter.clone()
+            };
+            full_returned = ReturnedBlocks::new();
+            (&mut full_filter, &mut full_returned)
+        } else {
+            (filter, returned)
+        };
 
         let mut all_logs = Vec::new();
 
+        // The block pinned by the hash is scanned once in the changes, it may be
+        // out of the canonical chain.
+        let mut pinned = match filter.block_hash {
+            Some(_) if !returned.is_empty() => {
+                *time = Instant::now();
+                return Ok(all_logs);
+            }
+            Some(hash) => Some(
+                self.retry
+                    .read(deadline, || {
+                        self.adapter.get_block_by_hash(Context::new(), hash)
+                    })
+                    .await
+                    .map_err(|e| RpcError::Internal(e.to_string()))?
+                    .ok_or(RpcError::InvalidBlockHash)?,
+            ),
+            None => None,
+        };
+
         let latest_block = self
-            .adapter
-            .get_block_by_number(Context::new(), None)
+            .retry
+            .read(deadline, || {
+                self.adapter.get_block_by_number(Context::new(), None)
+            })
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?
-            .unwrap();
+            .ok_or_else(|| RpcError::Internal("Cannot get the latest block".to_string()))?;
 
         let latest_number = latest_block.header.number;
-        let (start, end) = {
+
+        // Walk back from the last returned block to the one still in the canonical
+        // chain, the logs of the blocks reorged out are returned again as removed
+        // and the new blocks at their numbers are scanned.
+        let mut fork = None;
+        for (number, (hash, _)) in returned.iter().rev() {
+            let canonical = if *number > latest_number {
+                None
+            } else if *number == latest_number {
+                Some(latest_block.hash())
+            } else {
+                self.retry
+                    .read(deadline, || {
+                        self.adapter
+                            .get_block_by_number(Context::new(), Some(*number))
+                    })
+                    .await
+                    .map_err(|e| RpcError::Internal(e.to_string()))?
+                    .map(|block| block.hash())
+            };
+            if canonical == Some(*hash) {
+                break;
+            }
+            fork = Some(*number);
+        }
+        if let Some(fork) = fork {
+            for (_, (_, logs)) in returned.split_off(&fork) {
+                all_logs.extend(logs.into_iter().map(|mut log| {
+                    log.removed = true;
+                    log
+                }));
+            }
+            if let Some(BlockId::Num(ref mut n)) = filter.from_block {
+                *n = U64::from(fork.min(n.as_u64()));
+            }
+        }
+
+        let topics = filter.topics.as_slice();
+        let (start, end) = if let Some(block) = pinned.as_ref() {
+            (block.header.number, block.header.number)
+        } else {
             let convert = |id: &BlockId| -> BlockNumber {
                 match id {
                     BlockId::Num(n) => n.as_u64(),
@@ -325,59 +771,80 @@ where
             )
         };
 
-        if start > latest_number {
-            return Ok(Vec::new());
+        // The from block of a polled filter moves forward past its to block once
+        // all the blocks in range have been returned, so nothing is left to scan.
+        if start > latest_number || start > end {
+            return Ok(all_logs);
         }
         if end.saturating_sub(start) > self.log_filter_max_block_range {
             return Err(
                 RpcError::InvalidBlockRange(start, end, self.log_filter_max_block_range).into(),
             );
         }
-        let extend_logs = |logs: &mut Vec<Web3Log>, receipts: Vec<Option<Receipt>>| {
-            for (index, receipt) in receipts.into_iter().flatten().enumerate() {
-                from_receipt_to_web3_log(
-                    index,
-                    topics,
-                    filter.address.as_ref().unwrap_or(&Vec::new()),
-                    &receipt,
-                    logs,
-                )
-            }
-        };
+        let mut size_limit = LogsSizeLimit::new(self.max_logs_response_bytes);
+        let max_logs_returned = self.max_logs_returned;
+        let mut extend_logs =
+            |logs: &mut Vec<Web3Log>, receipts: Vec<Option<Receipt>>| -> Result<(), RpcError> {
+                for (index, receipt) in receipts.into_iter().flatten().enumerate() {
+                    from_receipt_to_web3_log(
+                        index,
+                        topics,
+                        filter.address.as_ref().unwrap_or(&Vec::new()),
+                        &receipt,
+                        logs,
+                    );
+                    if logs.len() > max_logs_returned {
+                        return Err(RpcError::QueryReturnedTooManyResults(max_logs_returned));
+                    }
+                    size_limit.check(logs)?;
+                }
+                Ok(())
+            };
 
-        let mut visiter_last_block = false;
+        // The latest block is pinned at the query start, so a block committed during
+        // the query is never scanned. The log bloom of a header can't tell whether
+        // the block contains a matching log, so the receipts of every block in range
+        // are read.
         for n in start..=end {
-            if n == latest_number {
-                visiter_last_block = true;
+            let block = if pinned.is_some() {
+                pinned.take()
+            } else if n == latest_number {
+                None
             } else {
                 let block = self
-                    .adapter
-                    .get_block_by_number(Context::new(), Some(n))
+                    .retry
+                    .read(deadline, || {
+                        self.adapter.get_block_by_number(Context::new(), Some(n))
+                    })
                     .await
                     .map_err(|e| RpcError::Internal(e.to_string()))?
-                    .unwrap();
-                let receipts = self
-                    .adapter
-                    .get_receipts_by_hashes(Context::new(), block.header.number, &block.tx_hashes)
+                    .ok_or_else(|| RpcError::Internal(format!("Cannot get block {}", n)))?;
+                Some(block)
+            };
+            let block = block.as_ref().unwrap_or(&latest_block);
+            // A block without transactions, such as the genesis, has no receipt.
+            let receipts = if block.tx_hashes.is_empty() {
+                Vec::new()
+            } else {
+                self.retry
+                    .read(deadline, || {
+                        self.adapter.get_receipts_by_hashes(
+                            Context::new(),
+                            block.header.number,
+                            &block.tx_hashes,
+                        )
+                    })
                     .await
-                    .map_err(|e| RpcError::Internal(e.to_string()))?;
+                    .map_err(|e| RpcError::Internal(e.to_string()))?
+            };
 
-                extend_logs(&mut all_logs, receipts);
-            }
+            let scanned = all_logs.len();
+            extend_logs(&mut all_logs, receipts)?;
+            returned.insert(n, (block.hash(), all_logs[scanned..].to_vec()));
         }
 
-        if visiter_last_block {
-            let receipts = self
-                .adapter
-                .get_receipts_by_hashes(
-                    Context::new(),
-                    latest_block.header.number,
-                    &latest_block.tx_hashes,
-                )
-                .await
-                .map_err(|e| RpcError::Internal(e.to_string()))?;
-
-            extend_logs(&mut all_logs, receipts);
+        while returned.len() > MAX_TRACKED_BLOCKS {
+            returned.pop_first();
         }
 
         if let Some(BlockId::Num(ref mut n)) = filter.from_block {
@@ -388,7 +855,611 @@ where
     }
 }
 
+/// The caller of a command may have gone, e.g. the request is cancelled by the
+/// timeout, which must not stop the hub.
+fn reply<T>(sender: oneshot::Sender<T>, res: T) -> RpcResult<()> {
+    sender
+        .send(res)
+        .map_err(|_| RpcError::Internal("The receiver of filter command is dropped".to_string()))?;
+    Ok(())
+}
+
 fn random_id() -> U256 {
     let bytes: [u8; 32] = thread_rng().gen();
     U256::from_big_endian(&bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use jsonrpsee::server::{BoundedSubscriptions, RandomIntegerIdProvider, SubscriptionState};
+    use jsonrpsee::types::Id;
+    use protocol::types::{Block, Log};
+
+    use crate::jsonrpc::r#impl::mock::{mock_log, Blocks, Mock, MockAdapter, Reorg};
+    use crate::jsonrpc::r#impl::Web3RpcImpl;
+    use crate::jsonrpc::web3_types::{MultiType, Web3Filter};
+    use crate::jsonrpc::Web3RpcServer;
+    use crate::APIError;
+
+    use super::*;
+
+    fn mock_retry(retry_times: u64) -> ReadRetry {
+        ReadRetry {
+            times:    retry_times,
+            interval: Duration::from_millis(1),
+            timeout:  Duration::from_secs(1),
+        }
+    }
+
+    fn mock_hub<T: MockAdapter + 'static>(adapter: Arc<Mock<T>>) -> FilterHub<Mock<T>> {
+        let (_tx, rx) = channel(1);
+        FilterHub::new(adapter, rx, 100)
+    }
+
+    /// A hub with a log filter installed, the id of the filter is returned as
+    /// well.
+    fn hub_with_log_filter<T: MockAdapter + 'static>(
+        adapter: Arc<Mock<T>>,
+        filter: LoggerFilter,
+    ) -> (FilterHub<Mock<T>>, U256) {
+        let mut hub = mock_hub(adapter);
+        let id = random_id();
+        hub.logs_hub
+            .insert(id, (filter, Instant::now(), BTreeMap::new()));
+        (hub, id)
+    }
+
+    /// Run a hub over the adapter and return the filter RPCs served by it.
+    fn spawn_hub<T: MockAdapter + 'static>(adapter: Arc<Mock<T>>) -> AxonWeb3RpcFilter {
+        let (tx, rx) = channel(128);
+        tokio::spawn(FilterHub::new(adapter, rx, 100).run());
+        AxonWeb3RpcFilter {
+            sender:   tx,
+            max_wait: Duration::from_secs(5),
+        }
+    }
+
+    fn log_filter() -> LoggerFilter {
+        LoggerFilter {
+            from_block:          Some(BlockId::Num(U64::one())),
+            to_block:            None,
+            block_hash:          None,
+            address:             None,
+            topics:              Vec::new(),
+            original_from_block: Some(BlockId::Num(U64::one())),
+        }
+    }
+
+    fn block_numbers(logs: &[Web3Log]) -> Vec<u64> {
+        logs.iter()
+            .map(|log| log.block_number.unwrap().as_u64())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_filter_retry_transient_read_error() {
+        /// The block reads fail for the given times before succeeding.
+        struct Flaky {
+            failures: AtomicU64,
+            blocks:   Blocks,
+        }
+
+        #[async_trait]
+        impl MockAdapter for Flaky {
+            async fn get_block_by_number(
+                &self,
+                height: Option<u64>,
+            ) -> ProtocolResult<Option<Block>> {
+                let failed = self
+                    .failures
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok();
+                if failed {
+                    return Err(APIError::Storage("transient read error".to_string()).into());
+                }
+                self.blocks.get_block_by_number(height).await
+            }
+
+            async fn get_receipts_by_hashes(
+                &self,
+                block_number: u64,
+                tx_hashes: &[Hash],
+            ) -> ProtocolResult<Vec<Option<Receipt>>> {
+                self.blocks
+                    .get_receipts_by_hashes(block_number, tx_hashes)
+                    .await
+            }
+        }
+
+        let hub = |retry_times| {
+            let adapter = Arc::new(Mock(Flaky {
+                failures: AtomicU64::new(1),
+                blocks:   Blocks::new(1),
+            }));
+            mock_hub(adapter).read_retry(mock_retry(retry_times))
+        };
+        let id = random_id();
+
+        let mut logs_hub = hub(3);
+        logs_hub
+            .logs_hub
+            .insert(id, (log_filter(), Instant::now(), BTreeMap::new()));
+        assert!(logs_hub.filter_logs(&id, false).await.unwrap().is_empty());
+
+        let mut blocks_hub = hub(3);
+        blocks_hub.blocks_hub.insert(id, (0, Instant::now()));
+        assert_eq!(blocks_hub.filter_block(&id).await.unwrap().len(), 1);
+
+        // The error is returned once the retries are used up.
+        let mut logs_hub = hub(0);
+        logs_hub
+            .logs_hub
+            .insert(id, (log_filter(), Instant::now(), BTreeMap::new()));
+        assert!(logs_hub.filter_logs(&id, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_response_bytes_limit() {
+        let log = Log {
+            data: vec![0xff; 1024],
+            ..mock_log(H160::random())
+        };
+        let adapter = Arc::new(Mock(Blocks::new(3).logs(vec![log])));
+
+        let (hub, id) = hub_with_log_filter(Arc::clone(&adapter), log_filter());
+        let mut hub = hub.max_logs_response_bytes(1 << 20);
+        assert_eq!(hub.filter_logs(&id, false).await.unwrap().len(), 3);
+
+        // Each log takes more than 2048 bytes since the data is hex encoded.
+        let (hub, id) = hub_with_log_filter(adapter, log_filter());
+        let mut hub = hub.max_logs_response_bytes(5000);
+        let err = hub.filter_logs(&id, false).await.unwrap_err();
+        assert_eq!(err.code(), -40025);
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_max_results() {
+        let logs = (0..3).map(|_| mock_log(H160::random())).collect();
+        let adapter = Arc::new(Mock(Blocks::new(1).logs(logs)));
+
+        let (hub, id) = hub_with_log_filter(Arc::clone(&adapter), log_filter());
+        let mut hub = hub.max_logs_returned(3);
+        assert_eq!(hub.filter_logs(&id, false).await.unwrap().len(), 3);
+
+        let (hub, id) = hub_with_log_filter(adapter, log_filter());
+        let mut hub = hub.max_logs_returned(2);
+        let err = hub.filter_logs(&id, false).await.unwrap_err();
+        assert_eq!(err.code(), -40035);
+        assert_eq!(err.message(), "query returned more than 2 results");
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_pin_latest_block() {
+        /// A new block is committed right after each read of the latest block.
+        struct Committing {
+            blocks: Blocks,
+        }
+
+        #[async_trait]
+        impl MockAdapter for Committing {
+            async fn get_block_by_number(
+                &self,
+                height: Option<u64>,
+            ) -> ProtocolResult<Option<Block>> {
+                let number =
+                    height.unwrap_or_else(|| self.blocks.latest.fetch_add(1, Ordering::SeqCst));
+                self.blocks.get_block_by_number(Some(number)).await
+            }
+
+            async fn get_receipts_by_hashes(
+                &self,
+                block_number: u64,
+                tx_hashes: &[Hash],
+            ) -> ProtocolResult<Vec<Option<Receipt>>> {
+                self.blocks
+                    .get_receipts_by_hashes(block_number, tx_hashes)
+                    .await
+            }
+        }
+
+        let blocks = Blocks::new(2).logs(vec![mock_log(H160::random())]);
+        let adapter = Arc::new(Mock(Committing { blocks }));
+        let filter = LoggerFilter {
+            to_block: Some(BlockId::Latest),
+            ..log_filter()
+        };
+        let (mut hub, id) = hub_with_log_filter(Arc::clone(&adapter), filter);
+
+        // Block 3 is committed during the query and is excluded from the result.
+        let logs = hub.filter_logs(&id, false).await.unwrap();
+        assert_eq!(adapter.blocks.latest.load(Ordering::SeqCst), 3);
+        assert_eq!(block_numbers(&logs), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_from_block_greater_than_to_block() {
+        // A polled filter returns nothing instead of the error of
+        // `eth_getLogs` since its from block moves past the to block.
+        let filter = LoggerFilter {
+            from_block: Some(BlockId::Num(10u64.into())),
+            to_block: Some(BlockId::Num(5u64.into())),
+            ..log_filter()
+        };
+        let (mut hub, id) = hub_with_log_filter(Arc::new(Mock(Blocks::new(20))), filter);
+        assert!(hub.filter_logs(&id, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filter_block_without_duplicates_or_gaps() {
+        let adapter = Arc::new(Mock(Blocks::new(0)));
+        let mut hub = mock_hub(Arc::clone(&adapter));
+        let id = random_id();
+        hub.blocks_hub.insert(id, (0, Instant::now()));
+
+        // The chain grows one block at a time, then several blocks at once.
+        let mut hashes = Vec::new();
+        for latest in (1..=5).chain([8, 9]) {
+            adapter.latest.store(latest, Ordering::SeqCst);
+            hashes.extend(hub.filter_block(&id).await.unwrap());
+            assert!(hub.filter_block(&id).await.unwrap().is_empty());
+        }
+
+        let expected = adapter.chain[1..=9]
+            .iter()
+            .map(Block::hash)
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, expected);
+    }
+
+    #[tokio::test]
+    async fn test_evict_least_recently_used_filter() {
+        let max_filters = 3;
+        let mut hub = mock_hub(Arc::new(Mock(Blocks::new(1)))).max_filters(max_filters);
+
+        let mut ids = Vec::new();
+        for i in 0..=max_filters {
+            let (tx, rx) = oneshot::channel();
+            if i % 2 == 0 {
+                hub.handle(Command::NewBlocks(tx)).await.unwrap();
+            } else {
+                hub.handle(Command::NewLogs((log_filter(), tx)))
+                    .await
+                    .unwrap();
+            }
+            ids.push(rx.await.unwrap().unwrap());
+        }
+
+        assert_eq!(hub.logs_hub.len() + hub.blocks_hub.len(), max_filters);
+        let (tx, rx) = oneshot::channel();
+        hub.handle(Command::FilterRequest((ids[0], tx)))
+            .await
+            .unwrap();
+        let err = rx.await.unwrap().unwrap_err();
+        assert_eq!(err.code(), -40022);
+        for id in ids.iter().skip(1) {
+            assert!(hub.blocks_hub.contains_key(id) || hub.logs_hub.contains_key(id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_block_filter_changes() {
+        let adapter = Arc::new(Mock(Blocks::new(1)));
+        let filter = spawn_hub(Arc::clone(&adapter));
+        let id = filter.block_filter().await.unwrap();
+
+        // Return empty at the timeout if there is no new block.
+        let now = Instant::now();
+        let changes = filter.wait_filter_changes(id, Some(200)).await.unwrap();
+        assert_eq!(changes, FilterChanges::Blocks(Vec::new()));
+        assert!(now.elapsed() >= Duration::from_millis(200));
+
+        // Return promptly once a new block arrives during the wait.
+        let new_block = Arc::clone(&adapter);
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(100)).await;
+            new_block.latest.store(2, Ordering::SeqCst);
+        });
+        let now = Instant::now();
+        let changes = filter.wait_filter_changes(id, Some(5000)).await.unwrap();
+        assert!(matches!(changes, FilterChanges::Blocks(hashes) if hashes.len() == 1));
+        assert!(now.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_new_filter_too_many_topics() {
+        let filter = spawn_hub(Arc::new(Mock(Blocks::new(1))));
+        let raw_filter = |topics: usize| {
+            let mut topics = vec![serde_json::Value::Null; topics];
+            if let Some(first) = topics.first_mut() {
+                *first = serde_json::json!(H256::random());
+            }
+            serde_json::from_value::<RawLoggerFilter>(serde_json::json!({ "topics": topics }))
+                .unwrap()
+        };
+
+        assert!(filter.new_filter(raw_filter(4)).await.is_ok());
+        let err = filter.new_filter(raw_filter(5)).await.unwrap_err();
+        assert_eq!(err.code(), -40031);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_logs_queries_limit() {
+        let adapter = Arc::new(Mock(Blocks::new(1)));
+        let limit = LogsQueryLimit::new(1);
+        let web3 = Web3RpcImpl::new(Arc::clone(&adapter), u64::MAX, 100, usize::MAX, 32)
+            .logs_query_limit(limit.clone());
+        let (hub, id) = hub_with_log_filter(adapter, log_filter());
+        let mut hub = hub.logs_query_limit(limit.clone());
+        let filter = || Web3Filter {
+            from_block: Some(BlockId::Num(U64::one())),
+            to_block:   None,
+            block_hash: None,
+            address:    MultiType::Null,
+            topics:     None,
+            created_by: None,
+        };
+
+        // Both queries are rejected while a query is running.
+        let permit = limit.acquire().unwrap();
+        assert_eq!(web3.get_logs(filter()).await.unwrap_err().code(), -40029);
+        assert_eq!(
+            hub.filter_logs(&id, false).await.unwrap_err().code(),
+            -40029
+        );
+
+        drop(permit);
+        assert!(web3.get_logs(filter()).await.is_ok());
+        assert!(hub.filter_logs(&id, false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_from_earliest_block() {
+        let mut blocks = Blocks::new(3).logs(vec![mock_log(H160::random())]);
+        // The genesis block contains no transaction.
+        blocks.chain[0].tx_hashes.clear();
+        let filter = LoggerFilter {
+            from_block: Some(BlockId::Earliest),
+            to_block: Some(BlockId::Num(U64::from(2))),
+            original_from_block: Some(BlockId::Earliest),
+            ..log_filter()
+        };
+        let (mut hub, id) = hub_with_log_filter(Arc::new(Mock(blocks)), filter);
+
+        let logs = hub.filter_logs(&id, false).await.unwrap();
+        assert_eq!(block_numbers(&logs), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_by_block_hash() {
+        // The blocks by number are always read from the first chain.
+        let adapter = Reorg::new(4, u64::MAX);
+        // The pinned block is out of the canonical chain.
+        let pinned = adapter.chains[1][2].hash();
+        let filter = LoggerFilter {
+            from_block: None,
+            block_hash: Some(pinned),
+            original_from_block: None,
+            ..log_filter()
+        };
+        let (mut hub, id) = hub_with_log_filter(Arc::new(Mock(adapter)), filter);
+
+        let logs = hub.filter_logs(&id, false).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].block_number, Some(U256::from(2)));
+        assert_eq!(logs[0].transaction_hash, Some(H256::repeat_byte(2)));
+
+        // The logs are returned once in the changes but always in the full query.
+        assert!(hub.filter_logs(&id, false).await.unwrap().is_empty());
+        assert_eq!(hub.filter_logs(&id, true).await.unwrap(), logs);
+
+        let id = random_id();
+        let filter = LoggerFilter {
+            block_hash: Some(H256::random()),
+            ..log_filter()
+        };
+        hub.logs_hub
+            .insert(id, (filter, Instant::now(), BTreeMap::new()));
+        let err = hub.filter_logs(&id, false).await.unwrap_err();
+        assert_eq!(err.code(), -40014);
+    }
+
+    #[tokio::test]
+    async fn test_new_filter_block_hash_with_block_range() {
+        let filter = spawn_hub(Arc::new(Mock(Blocks::new(1))));
+        let hash = H256::random();
+
+        let raw = serde_json::json!({ "blockHash": hash });
+        let raw = serde_json::from_value::<RawLoggerFilter>(raw).unwrap();
+        assert!(filter.new_filter(raw).await.is_ok());
+        let raw = serde_json::json!({ "blockHash": hash, "fromBlock": "0x1" });
+        let raw = serde_json::from_value::<RawLoggerFilter>(raw).unwrap();
+        let err = filter.new_filter(raw).await.unwrap_err();
+        assert_eq!(err.code(), -40034);
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_removed_by_reorg() {
+        // The chain reorgs after the latest block and the blocks 1 and 2 are read
+        // in the first poll.
+        let (mut hub, id) = hub_with_log_filter(Arc::new(Mock(Reorg::new(4, 3))), log_filter());
+
+        let logs = hub.filter_logs(&id, false).await.unwrap();
+        assert_eq!(logs.len(), 3);
+        assert!(logs
+            .iter()
+            .all(|log| !log.removed && log.transaction_hash == Some(H256::repeat_byte(1))));
+
+        // The logs of the old chain are removed before the ones of the new chain.
+        let logs = hub.filter_logs(&id, false).await.unwrap();
+        let changes = logs
+            .iter()
+            .map(|log| {
+                (
+                    log.block_number.unwrap().as_u64(),
+                    log.transaction_hash.unwrap(),
+                    log.removed,
+                )
+            })
+            .collect::<Vec<_>>();
+        let (old, new) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        assert_eq!(changes, vec![
+            (1, old, true),
+            (2, old, true),
+            (3, old, true),
+            (1, new, false),
+            (2, new, false),
+            (3, new, false),
+        ]);
+
+        // Nothing changes without a reorg.
+        assert!(hub.filter_logs(&id, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filter_logs_from_original_block() {
+        let adapter = Arc::new(Mock(Blocks::new(3).logs(vec![mock_log(H160::random())])));
+        let mut hub = mock_hub(Arc::clone(&adapter));
+
+        let (tx, rx) = oneshot::channel();
+        hub.handle(Command::NewLogs((log_filter(), tx)))
+            .await
+            .unwrap();
+        let id = rx.await.unwrap().unwrap();
+
+        let request = |cmd: fn(_) -> Command| {
+            let (tx, rx) = oneshot::channel();
+            (cmd((id, tx)), rx)
+        };
+        let numbers = |changes: FilterChanges| match changes {
+            FilterChanges::Logs(logs) => block_numbers(&logs),
+            _ => unreachable!(),
+        };
+
+        // The changes start after the latest block on installing.
+        adapter.latest.store(4, Ordering::SeqCst);
+        let (cmd, rx) = request(Command::FilterRequest);
+        hub.handle(cmd).await.unwrap();
+        assert_eq!(numbers(rx.await.unwrap().unwrap()), vec![4]);
+
+        // The full query returns all the logs from the original from block.
+        let (cmd, rx) = request(Command::FilterLogs);
+        hub.handle(cmd).await.unwrap();
+        assert_eq!(numbers(rx.await.unwrap().unwrap()), vec![1, 2, 3, 4]);
+
+        // And it doesn't move the cursor of the changes.
+        adapter.latest.store(5, Ordering::SeqCst);
+        let (cmd, rx) = request(Command::FilterRequest);
+        hub.handle(cmd).await.unwrap();
+        assert_eq!(numbers(rx.await.unwrap().unwrap()), vec![5]);
+    }
+
+    #[tokio::test]
+    async fn test_filter_timeout() {
+        let mut hub =
+            mock_hub(Arc::new(Mock(Blocks::new(1)))).filter_timeout(Duration::from_millis(100));
+        let (stale, fresh) = (random_id(), random_id());
+        hub.blocks_hub.insert(stale, (0, Instant::now()));
+        hub.logs_hub
+            .insert(stale, (log_filter(), Instant::now(), BTreeMap::new()));
+        sleep(Duration::from_millis(150)).await;
+        hub.blocks_hub.insert(fresh, (0, Instant::now()));
+
+        hub.check_hubs();
+        assert!(hub.logs_hub.is_empty());
+        assert_eq!(hub.blocks_hub.keys().collect::<Vec<_>>(), vec![&fresh]);
+    }
+
+    #[tokio::test]
+    async fn test_hub_survives_dropped_receiver() {
+        let (tx, rx) = channel(128);
+        let hub = tokio::spawn(FilterHub::new(Arc::new(Mock(Blocks::new(1))), rx, 100).run());
+        let filter = AxonWeb3RpcFilter {
+            sender:   tx.clone(),
+            max_wait: Duration::from_secs(5),
+        };
+        let id = filter.block_filter().await.unwrap();
+
+        // The callers go away before the hub replies.
+        for cmd in [
+            Command::NewBlocks(oneshot::channel().0),
+            Command::NewLogs((log_filter(), oneshot::channel().0)),
+            Command::FilterRequest((id, oneshot::channel().0)),
+            Command::Uninstall((random_id(), oneshot::channel().0)),
+        ] {
+            tx.send(cmd).await.unwrap();
+        }
+
+        assert!(filter.get_filter_changes(id).await.is_ok());
+        assert!(filter.uninstall_filter(id).await.unwrap());
+        assert!(!hub.is_finished());
+    }
+
+    /// Install a filter over a WebSocket connection as the transport does,
+    /// which rejects it if the connection has no free slot.
+    async fn ws_install(
+        methods: &Methods,
+        name: &str,
+        params: Option<&str>,
+        sink: &MethodSink,
+        slots: &BoundedSubscriptions,
+    ) -> Option<U256> {
+        let callback = match methods.method(name) {
+            Some(MethodCallback::Subscription(callback)) => Arc::clone(callback),
+            _ => unreachable!(),
+        };
+        let state = SubscriptionState {
+            conn_id:             1,
+            id_provider:         &RandomIntegerIdProvider,
+            subscription_permit: slots.acquire()?,
+        };
+
+        let resp = callback(Id::Number(1), Params::new(params), sink.clone(), state)
+            .await
+            .unwrap();
+        let resp: serde_json::Value = serde_json::from_str(&resp.result).unwrap();
+        Some(serde_json::from_value(resp["result"].clone()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_ws_filters_per_connection() {
+        let filter = spawn_hub(Arc::new(Mock(Blocks::new(1))));
+        let methods = filter.clone().into_ws_rpc();
+        let slots = BoundedSubscriptions::new(2);
+        let (sink_tx, sink_rx) = channel(16);
+        let sink = MethodSink::new(sink_tx);
+
+        let logs_id = ws_install(&methods, "eth_newFilter", Some("[{}]"), &sink, &slots)
+            .await
+            .unwrap();
+        let blocks_id = ws_install(&methods, "eth_newBlockFilter", None, &sink, &slots)
+            .await
+            .unwrap();
+        assert!(
+            ws_install(&methods, "eth_newBlockFilter", None, &sink, &slots)
+                .await
+                .is_none()
+        );
+
+        // Uninstalling a filter frees its slot.
+        assert!(filter.uninstall_filter(blocks_id).await.unwrap());
+        let blocks_id = ws_install(&methods, "eth_newBlockFilter", None, &sink, &slots)
+            .await
+            .unwrap();
+
+        // Closing the connection removes all its filters and frees the slots.
+        drop(sink_rx);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let permits = [slots.acquire(), slots.acquire()];
+            if permits.iter().all(Option::is_some) {
+                break;
+            }
+            assert!(Instant::now() < deadline);
+            sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!filter.uninstall_filter(logs_id).await.unwrap());
+        assert!(!filter.uninstall_filter(blocks_id).await.unwrap());
+    }
+}
@@ -0,0 +1,410 @@
+//! The adapters of the RPC tests. A test mock implements only the reads its
+//! test makes with `MockAdapter`, and is wrapped in `Mock` to serve the RPCs.
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use protocol::traits::{APIAdapter, Context};
+use protocol::types::{
+    AccessedState, Account, Block, BlockNumber, Bytes, CkbRelatedInfo, EthAccountProof,
+    HardforkInfo, HardforkInfoInner, Hash, Header, Log, Metadata, Proposal, Receipt,
+    SignedTransaction, Tracer, TxResp, TxTrace, H160, H256, U256,
+};
+use protocol::{async_trait, ProtocolResult};
+
+/// The reads of `APIAdapter` served by a test mock, without the context.
+#[async_trait]
+pub trait MockAdapter: Send + Sync {
+    async fn get_block_by_number(&self, _height: Option<u64>) -> ProtocolResult<Option<Block>> {
+        unimplemented!()
+    }
+
+    async fn get_block_by_hash(&self, _hash: Hash) -> ProtocolResult<Option<Block>> {
+        unimplemented!()
+    }
+
+    async fn get_block_header_by_number(
+        &self,
+        _height: Option<u64>,
+    ) -> ProtocolResult<Option<Header>> {
+        unimplemented!()
+    }
+
+    async fn get_receipt_by_tx_hash(&self, _tx_hash: Hash) -> ProtocolResult<Option<Receipt>> {
+        unimplemented!()
+    }
+
+    async fn get_receipts_by_hashes(
+        &self,
+        _block_number: u64,
+        _tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<Receipt>>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _tx_hash: Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn get_account(
+        &self,
+        _address: H160,
+        _number: Option<BlockNumber>,
+    ) -> ProtocolResult<Account> {
+        unimplemented!()
+    }
+
+    async fn evm_call(&self) -> ProtocolResult<TxResp> {
+        unimplemented!()
+    }
+
+    async fn get_contracts_by_deployer(&self, _deployer: H160) -> ProtocolResult<Vec<H160>> {
+        unimplemented!()
+    }
+
+    async fn trace_block(&self, _block: &Block) -> ProtocolResult<Option<Vec<Option<TxTrace>>>> {
+        unimplemented!()
+    }
+}
+
+/// The adapter serving the reads of the test mock.
+pub struct Mock<T>(pub T);
+
+impl<T> Deref for Mock<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[async_trait]
+impl<T: MockAdapter> APIAdapter for Mock<T> {
+    async fn insert_signed_txs(&self, _: Context, _: SignedTransaction) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn mempool_contains_tx(&self, _: Context, _: &Hash) -> bool {
+        unimplemented!()
+    }
+
+    async fn get_pending_txs(
+        &self,
+        _: Context,
+        _: usize,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn get_block_by_number(
+        &self,
+        _: Context,
+        height: Option<u64>,
+    ) -> ProtocolResult<Option<Block>> {
+        self.0.get_block_by_number(height).await
+    }
+
+    async fn get_block_by_hash(&self, _: Context, hash: Hash) -> ProtocolResult<Option<Block>> {
+        self.0.get_block_by_hash(hash).await
+    }
+
+    async fn get_block_header_by_number(
+        &self,
+        _: Context,
+        height: Option<u64>,
+    ) -> ProtocolResult<Option<Header>> {
+        self.0.get_block_header_by_number(height).await
+    }
+
+    async fn get_block_number_by_hash(
+        &self,
+        _: Context,
+        _: Hash,
+    ) -> ProtocolResult<Option<BlockNumber>> {
+        unimplemented!()
+    }
+
+    async fn get_receipt_by_tx_hash(
+        &self,
+        _: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<Receipt>> {
+        self.0.get_receipt_by_tx_hash(tx_hash).await
+    }
+
+    async fn get_receipts_by_hashes(
+        &self,
+        _: Context,
+        block_number: u64,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<Receipt>>> {
+        self.0.get_receipts_by_hashes(block_number, tx_hashes).await
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        self.0.get_transaction_by_hash(tx_hash).await
+    }
+
+    async fn get_transactions_by_hashes(
+        &self,
+        _: Context,
+        _: u64,
+        _: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        unimplemented!()
+    }
+
+    async fn get_account(
+        &self,
+        _: Context,
+        address: H160,
+        number: Option<BlockNumber>,
+    ) -> ProtocolResult<Account> {
+        self.0.get_account(address, number).await
+    }
+
+    async fn get_pending_tx_count(
+        &self,
+        _: Context,
+        _: H160,
+    ) -> ProtocolResult<(U256, Option<BlockNumber>)> {
+        unimplemented!()
+    }
+
+    async fn evm_call(
+        &self,
+        _: Context,
+        _: Option<H160>,
+        _: Option<H160>,
+        _: Option<U256>,
+        _: Option<U256>,
+        _: U256,
+        _: Vec<u8>,
+        _: Hash,
+        _: Proposal,
+    ) -> ProtocolResult<TxResp> {
+        self.0.evm_call().await
+    }
+
+    async fn get_code_by_hash(&self, _: Context, _: &Hash) -> ProtocolResult<Option<Bytes>> {
+        unimplemented!()
+    }
+
+    async fn get_contracts_by_deployer(
+        &self,
+        _: Context,
+        deployer: H160,
+    ) -> ProtocolResult<Vec<H160>> {
+        self.0.get_contracts_by_deployer(deployer).await
+    }
+
+    async fn peer_count(&self, _: Context) -> ProtocolResult<U256> {
+        unimplemented!()
+    }
+
+    async fn get_storage_at(&self, _: Context, _: H160, _: U256, _: Hash) -> ProtocolResult<Bytes> {
+        unimplemented!()
+    }
+
+    async fn get_metadata_by_number(&self, _: Context, _: Option<u64>) -> ProtocolResult<Metadata> {
+        unimplemented!()
+    }
+
+    async fn get_ckb_related_info(&self, _: Context) -> ProtocolResult<CkbRelatedInfo> {
+        unimplemented!()
+    }
+
+    async fn get_image_cell_root(&self, _: Context) -> ProtocolResult<H256> {
+        unimplemented!()
+    }
+
+    async fn get_metadata_root(&self, _: Context, _: Option<u64>) -> ProtocolResult<H256> {
+        unimplemented!()
+    }
+
+    async fn hardfork_info(&self, _: Context) -> ProtocolResult<HardforkInfo> {
+        unimplemented!()
+    }
+
+    async fn hardfork_proposal(&self, _: Context) -> ProtocolResult<Option<HardforkInfoInner>> {
+        unimplemented!()
+    }
+
+    async fn get_proof(
+        &self,
+        _: Context,
+        _: H160,
+        _: Vec<U256>,
+        _: Hash,
+    ) -> ProtocolResult<EthAccountProof> {
+        unimplemented!()
+    }
+
+    async fn get_accessed_state(
+        &self,
+        _: Context,
+        _: &Receipt,
+    ) -> ProtocolResult<Option<AccessedState>> {
+        unimplemented!()
+    }
+
+    async fn trace_block(
+        &self,
+        _: Context,
+        block: &Block,
+        _: Tracer,
+    ) -> ProtocolResult<Option<Vec<Option<TxTrace>>>> {
+        self.0.trace_block(block).await
+    }
+}
+
+/// A chain whose blocks all contain a tx hashed `repeat_byte(fork)`.
+pub fn mock_chain(fork: u8, len: u64) -> Vec<Block> {
+    let mut chain: Vec<Block> = Vec::new();
+    for number in 0..len {
+        let mut block = Block::default();
+        block.header.number = number;
+        block.header.timestamp = fork as u64;
+        block.header.prev_hash = chain.last().map(Block::hash).unwrap_or_default();
+        block.tx_hashes = vec![H256::repeat_byte(fork)];
+        chain.push(block);
+    }
+    chain
+}
+
+/// A log of the address without topics or data.
+pub fn mock_log(address: H160) -> Log {
+    Log {
+        address,
+        topics: Vec::new(),
+        data: Vec::new(),
+    }
+}
+
+/// The receipts of the txs of a block, each of them has all the logs.
+pub fn mock_receipts(logs: &[Log], number: u64, tx_hashes: &[Hash]) -> Vec<Option<Receipt>> {
+    tx_hashes
+        .iter()
+        .map(|hash| {
+            Some(Receipt {
+                tx_hash: *hash,
+                block_number: number,
+                logs: logs.to_vec(),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// A chain up to the latest block, the receipt of each tx has the logs.
+pub struct Blocks {
+    pub chain:  Vec<Block>,
+    pub latest: AtomicU64,
+    pub logs:   Vec<Log>,
+}
+
+impl Blocks {
+    pub fn new(latest: u64) -> Self {
+        Blocks {
+            chain:  mock_chain(1, 32),
+            latest: AtomicU64::new(latest),
+            logs:   Vec::new(),
+        }
+    }
+
+    pub fn logs(mut self, logs: Vec<Log>) -> Self {
+        self.logs = logs;
+        self
+    }
+}
+
+#[async_trait]
+impl MockAdapter for Blocks {
+    async fn get_block_by_number(&self, height: Option<u64>) -> ProtocolResult<Option<Block>> {
+        let number = height.unwrap_or_else(|| self.latest.load(Ordering::SeqCst));
+        Ok(self.chain.get(number as usize).cloned())
+    }
+
+    async fn get_block_by_hash(&self, hash: Hash) -> ProtocolResult<Option<Block>> {
+        Ok(self
+            .chain
+            .iter()
+            .find(|block| block.hash() == hash)
+            .cloned())
+    }
+
+    async fn get_block_header_by_number(
+        &self,
+        height: Option<u64>,
+    ) -> ProtocolResult<Option<Header>> {
+        Ok(self
+            .get_block_by_number(height)
+            .await?
+            .map(|block| block.header))
+    }
+
+    async fn get_receipts_by_hashes(
+        &self,
+        block_number: u64,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<Receipt>>> {
+        Ok(mock_receipts(&self.logs, block_number, tx_hashes))
+    }
+}
+
+/// Two chains of the same length, the blocks by number are read from the
+/// first one and from the second one after `reorg_after` reads. The receipt of
+/// each tx has the logs.
+pub struct Reorg {
+    pub chains:      [Vec<Block>; 2],
+    pub reorg_after: AtomicU64,
+    pub logs:        Vec<Log>,
+}
+
+impl Reorg {
+    pub fn new(len: u64, reorg_after: u64) -> Self {
+        Reorg {
+            chains:      [mock_chain(1, len), mock_chain(2, len)],
+            reorg_after: AtomicU64::new(reorg_after),
+            logs:        vec![mock_log(H160::random())],
+        }
+    }
+}
+
+#[async_trait]
+impl MockAdapter for Reorg {
+    async fn get_block_by_number(&self, height: Option<u64>) -> ProtocolResult<Option<Block>> {
+        let reorged = self
+            .reorg_after
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_err();
+        let chain = &self.chains[reorged as usize];
+        Ok(height
+            .map_or(chain.last(), |n| chain.get(n as usize))
+            .cloned())
+    }
+
+    async fn get_block_by_hash(&self, hash: Hash) -> ProtocolResult<Option<Block>> {
+        Ok(self
+            .chains
+            .iter()
+            .flatten()
+            .find(|block| block.hash() == hash)
+            .cloned())
+    }
+
+    async fn get_receipts_by_hashes(
+        &self,
+        block_number: u64,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<Receipt>>> {
+        Ok(mock_receipts(&self.logs, block_number, tx_hashes))
+    }
+}
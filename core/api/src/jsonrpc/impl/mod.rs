@@ -1,6 +1,8 @@
 mod axon;
 mod ckb_light_client;
 mod filter;
+#[cfg(test)]
+pub(crate) mod mock;
 mod node;
 mod web3;
 
@@ -8,4 +10,4 @@ pub use axon::AxonRpcImpl;
 pub use ckb_light_client::CkbLightClientRpcImpl;
 pub use filter::filter_module;
 pub use node::NodeRpcImpl;
-pub use web3::{from_receipt_to_web3_log, Web3RpcImpl};
+pub use web3::{from_receipt_to_web3_log, LogsQueryLimit, Web3RpcImpl};
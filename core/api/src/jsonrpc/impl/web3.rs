@@ -1,24 +1,28 @@
-use std::{sync::Arc, time::Duration};
+use std::{future::Future, sync::Arc, time::Duration};
 
 use jsonrpsee::core::RpcResult;
 
 use common_apm::metrics_rpc;
-use core_executor::is_system_contract_address_format;
+use core_executor::{encode_receipts, is_system_contract_address_format};
 use protocol::traits::{APIAdapter, Context};
 use protocol::types::{
-    Block, BlockNumber, Bytes, EthAccountProof, Hash, Header, Hex, Proposal, Receipt,
+    Block, BlockNumber, Bytes, EthAccountProof, Hash, Header, Hex, Log, Proposal, Receipt,
     SignedTransaction, TxResp, UnverifiedTransaction, BASE_FEE_PER_GAS, H160, H256,
-    MAX_FEE_HISTORY, MAX_RPC_GAS_CAP, MIN_TRANSACTION_GAS_LIMIT, U256, U64,
+    MAX_BLOCK_GAS_LIMIT, MAX_FEE_HISTORY, MAX_RPC_GAS_CAP, MIN_TRANSACTION_GAS_LIMIT, U256, U64,
 };
 use protocol::{
-    async_trait, codec::ProtocolCodec, lazy::PROTOCOL_VERSION, tokio::time::sleep, ProtocolResult,
-    MEMPOOL_REFRESH_TIMEOUT,
+    async_trait,
+    codec::ProtocolCodec,
+    lazy::PROTOCOL_VERSION,
+    tokio::sync::{OwnedSemaphorePermit, Semaphore},
+    tokio::time::sleep,
+    ProtocolResult, MEMPOOL_REFRESH_TIMEOUT,
 };
 
 use crate::jsonrpc::web3_types::{
     BlockCount, BlockId, FeeHistoryEmpty, FeeHistoryWithReward, FeeHistoryWithoutReward,
     RichTransactionOrHash, Web3Block, Web3CallRequest, Web3FeeHistory, Web3Filter, Web3Log,
-    Web3Receipt, Web3Transaction,
+    Web3Receipt, Web3TraceOptions, Web3Transaction, Web3TransactionAccess, Web3TxTrace,
 };
 use crate::jsonrpc::{error::RpcError, Web3RpcServer};
 use crate::APIError;
@@ -26,65 +30,142 @@ use crate::APIError;
 pub(crate) const MAX_LOG_NUM: usize = 10000;
 
 pub struct Web3RpcImpl<Adapter> {
-    adapter:                    Arc<Adapter>,
-    max_gas_cap:                U256,
-    log_filter_max_block_range: u64,
+    adapter:                     Arc<Adapter>,
+    max_gas_cap:                 U256,
+    log_filter_max_block_range:  u64,
+    max_logs_response_bytes:     usize,
+    estimate_gas_max_iterations: u64,
+    logs_query_limit:            LogsQueryLimit,
+    max_trace_response_bytes:    usize,
+    max_batch_addresses:         usize,
+    read_your_writes:            bool,
+    trace_permits:               Arc<Semaphore>,
+    max_concurrent_traces:       usize,
 }
 
 impl<Adapter: APIAdapter> Web3RpcImpl<Adapter> {
-    pub fn new(adapter: Arc<Adapter>, max_gas_cap: u64, log_filter_max_block_range: u64) -> Self {
+    pub fn new(
+        adapter: Arc<Adapter>,
+        max_gas_cap: u64,
+        log_filter_max_block_range: u64,
+        max_logs_response_bytes: usize,
+        estimate_gas_max_iterations: u64,
+    ) -> Self {
         Self {
             adapter,
             max_gas_cap: max_gas_cap.into(),
             log_filter_max_block_range,
+            max_logs_response_bytes,
+            estimate_gas_max_iterations,
+            logs_query_limit: LogsQueryLimit::unlimited(),
+            max_trace_response_bytes: usize::MAX,
+            max_batch_addresses: usize::MAX,
+            read_your_writes: false,
+            trace_permits: Arc::new(Semaphore::new(Semaphore::MAX_PERMITS)),
+            max_concurrent_traces: Semaphore::MAX_PERMITS,
         }
     }
 
-    async fn get_block_number_by_id(
+    /// Share the limit of the concurrent log queries with the log filters.
+    pub fn logs_query_limit(mut self, limit: LogsQueryLimit) -> Self {
+        self.logs_query_limit = limit;
+        self
+    }
+
+    /// Limit the serialized size of the traces of a block in a response.
+    pub fn max_trace_response_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_trace_response_bytes = max_bytes;
+        self
+    }
+
+    /// Limit the number of the traces running at the same time, a trace beyond
+    /// it is rejected.
+    pub fn max_concurrent_traces(mut self, max: usize) -> Self {
+        self.trace_permits = Arc::new(Semaphore::new(max));
+        self.max_concurrent_traces = max;
+        self
+    }
+
+    /// Limit the number of the addresses of a batch account query.
+    pub fn max_batch_addresses(mut self, max: usize) -> Self {
+        self.max_batch_addresses = max;
+        self
+    }
+
+    /// Skip waiting for the mempool after sending a transaction, the mempool
+    /// makes the pending reads observe it instead.
+    pub fn read_your_writes(mut self, enable: bool) -> Self {
+        self.read_your_writes = enable;
+        self
+    }
+
+    /// Resolve the block of a batch account query to a number, so that all
+    /// the accounts are read from the same state.
+    async fn pin_block_number(
         &self,
+        addresses: &[H160],
         block_id: Option<BlockId>,
-    ) -> Result<Option<BlockNumber>, RpcError> {
-        match block_id {
-            Some(BlockId::Hash(ref hash)) => Ok(self
+    ) -> Result<BlockNumber, RpcError> {
+        if addresses.len() > self.max_batch_addresses {
+            return Err(RpcError::TooManyAddresses(
+                addresses.len(),
+                self.max_batch_addresses,
+            ));
+        }
+
+        let number = match get_block_number_by_id(&*self.adapter, block_id).await? {
+            Some(number) => Some(number),
+            None => self
                 .adapter
-                .get_block_number_by_hash(Context::new(), *hash)
+                .get_block_header_by_number(Context::new(), None)
                 .await
-                .map_err(|e| RpcError::Internal(e.to_string()))?),
-            _ => Ok(block_id.unwrap_or_default().into()),
-        }
+                .map_err(|e| RpcError::Internal(e.to_string()))?
+                .map(|header| header.number),
+        };
+        number.ok_or(RpcError::CannotGetLatestBlock)
     }
 
-    async fn call_evm(
+    async fn trace_block(
         &self,
-        req: Web3CallRequest,
-        data: Bytes,
-        number: Option<u64>,
-    ) -> ProtocolResult<TxResp> {
-        if req.from.is_none() && req.to.is_none() {
-            return Err(APIError::RequestPayload("from and to are both None".to_string()).into());
-        }
-
-        let header = self
+        block: Block,
+        options: Option<Web3TraceOptions>,
+    ) -> Result<Vec<Web3TxTrace>, RpcError> {
+        let _permit = Arc::clone(&self.trace_permits)
+            .try_acquire_owned()
+            .map_err(|_| RpcError::TracerBusy(self.max_concurrent_traces))?;
+        let tracer = options.and_then(|o| o.tracer).unwrap_or_default();
+        let traces = self
             .adapter
-            .get_block_header_by_number(Context::new(), number)
-            .await?
-            .ok_or_else(|| APIError::Storage(format!("Cannot get {:?} header", number)))?;
-
-        let mock_header = mock_header_by_call_req(header, &req);
-
-        self.adapter
-            .evm_call(
-                Context::new(),
-                req.from,
-                req.to,
-                req.gas_price,
-                req.gas,
-                req.value.unwrap_or_default(),
-                data.to_vec(),
-                mock_header.state_root,
-                Proposal::new_without_state_root(&mock_header),
-            )
+            .trace_block(Context::new(), &block, tracer)
             .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or(RpcError::TraceResponseTooLarge(
+                self.max_trace_response_bytes,
+            ))?;
+
+        let mut bytes = 0;
+        block
+            .tx_hashes
+            .iter()
+            .zip(traces)
+            .map(|(hash, result)| {
+                let trace = Web3TxTrace {
+                    tx_hash: *hash,
+                    result,
+                };
+                // Add one byte for the separator in the array.
+                bytes += serde_json::to_vec(&trace)
+                    .map_err(|e| RpcError::Internal(e.to_string()))?
+                    .len()
+                    + 1;
+                if bytes > self.max_trace_response_bytes {
+                    return Err(RpcError::TraceResponseTooLarge(
+                        self.max_trace_response_bytes,
+                    ));
+                }
+                Ok(trace)
+            })
+            .collect()
     }
 
     async fn calculate_rewards(
@@ -191,6 +272,8 @@ impl<Adapter: APIAdapter> Web3RpcImpl<Adapter> {
 impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
     #[metrics_rpc("eth_sendRawTransaction")]
     async fn send_raw_transaction(&self, tx: Hex) -> RpcResult<H256> {
+        UnverifiedTransaction::check_type(&tx.as_bytes())
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
         let utx = UnverifiedTransaction::decode(&tx.as_bytes())
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
@@ -228,7 +311,9 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
 
         // TODO `eth_getTransactionCount(..., "pending")` should be synchronous with
         // `eth_sendRawTransaction`. Temporary solution for axonweb3/axon#1544.
-        sleep(Duration::from_millis(MEMPOOL_REFRESH_TIMEOUT)).await;
+        if !self.read_your_writes {
+            sleep(Duration::from_millis(MEMPOOL_REFRESH_TIMEOUT)).await;
+        }
 
         Ok(hash)
     }
@@ -401,7 +486,7 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
 
     #[metrics_rpc("eth_getBalance")]
     async fn get_balance(&self, address: H160, block_id: Option<BlockId>) -> RpcResult<U256> {
-        let number = self.get_block_number_by_id(block_id).await?;
+        let number = get_block_number_by_id(&*self.adapter, block_id).await?;
 
         Ok(self
             .adapter
@@ -410,40 +495,59 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
             .map_or(U256::zero(), |account| account.balance))
     }
 
-    #[metrics_rpc("eth_call")]
-    async fn call(&self, req: Web3CallRequest, block_id: Option<BlockId>) -> RpcResult<Hex> {
-        if req.gas_price.unwrap_or_default() > U256::from(u64::MAX) {
-            return Err(RpcError::GasLimitIsTooLarge.into());
-        }
-
-        if req.gas.unwrap_or_default() > U256::from(MAX_RPC_GAS_CAP) {
-            return Err(RpcError::GasLimitIsTooLarge.into());
-        }
+    #[metrics_rpc("axon_getBalances")]
+    async fn get_balances(
+        &self,
+        addresses: Vec<H160>,
+        block_id: Option<BlockId>,
+    ) -> RpcResult<Vec<U256>> {
+        let number = self.pin_block_number(&addresses, block_id).await?;
 
-        if let Some(call_addr) = req.to {
-            if is_system_contract_address_format(&call_addr) {
-                return Err(RpcError::CallSystemContract.into());
-            }
+        let mut balances = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let balance = self
+                .adapter
+                .get_account(Context::new(), address, Some(number))
+                .await
+                .map_or(U256::zero(), |account| account.balance);
+            balances.push(balance);
         }
+        Ok(balances)
+    }
 
-        let number = self.get_block_number_by_id(block_id).await?;
-
-        let data_bytes = req
-            .data
-            .as_ref()
-            .map(|hex| hex.as_bytes())
-            .unwrap_or_default();
-        let resp = self
-            .call_evm(req, data_bytes, number)
-            .await
-            .map_err(|e| RpcError::Internal(e.to_string()))?;
+    #[metrics_rpc("axon_getTransactionCounts")]
+    async fn get_transaction_counts(
+        &self,
+        addresses: Vec<H160>,
+        block_id: Option<BlockId>,
+    ) -> RpcResult<Vec<U256>> {
+        let pending = matches!(block_id, Some(BlockId::Pending));
+        let number = self.pin_block_number(&addresses, block_id).await?;
 
-        if resp.exit_reason.is_succeed() {
-            let call_hex_result = Hex::encode(resp.ret);
-            return Ok(call_hex_result);
+        let mut counts = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let mut count = self
+                .adapter
+                .get_account(Context::new(), address, Some(number))
+                .await
+                .map_or(U256::zero(), |account| account.nonce);
+            if pending {
+                let (pending_tx_count, _) = self
+                    .adapter
+                    .get_pending_tx_count(Context::new(), address)
+                    .await
+                    .map_err(|e| RpcError::Internal(e.to_string()))?;
+                count += pending_tx_count;
+            }
+            counts.push(count);
         }
+        Ok(counts)
+    }
 
-        Err(RpcError::Evm(resp).into())
+    #[metrics_rpc("eth_call")]
+    async fn call(&self, req: Web3CallRequest, block_id: Option<BlockId>) -> RpcResult<Hex> {
+        let resp = checked_call(&*self.adapter, req, block_id).await?;
+        Ok(Hex::encode(resp.ret))
     }
 
     #[metrics_rpc("eth_estimateGas")]
@@ -475,21 +579,43 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
             .as_ref()
             .map(|hex| hex.as_bytes())
             .unwrap_or_default();
-        let resp = self
-            .call_evm(req, data_bytes, num)
+        let resp = call_evm(&*self.adapter, req.clone(), data_bytes.clone(), num)
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        if resp.exit_reason.is_succeed() {
-            return Ok(resp.gas_used.into());
+        if !resp.exit_reason.is_succeed() {
+            return Err(RpcError::Evm(resp).into());
         }
 
-        Err(RpcError::Evm(resp).into())
+        let gas_limit = req
+            .gas
+            .map(|gas| gas.as_u64())
+            .unwrap_or(MAX_BLOCK_GAS_LIMIT);
+        let estimate = search_gas_limit(
+            resp.gas_used,
+            gas_limit,
+            self.estimate_gas_max_iterations,
+            |gas| {
+                let mut req = req.clone();
+                req.gas = Some(gas.into());
+                let data = data_bytes.clone();
+
+                async move {
+                    call_evm(&*self.adapter, req, data, num)
+                        .await
+                        .map(|resp| resp.exit_reason.is_succeed())
+                        .map_err(|e| RpcError::Internal(e.to_string()))
+                }
+            },
+        )
+        .await?;
+
+        Ok(estimate.into())
     }
 
     #[metrics_rpc("eth_getCode")]
     async fn get_code(&self, address: H160, block_id: Option<BlockId>) -> RpcResult<Hex> {
-        let number = self.get_block_number_by_id(block_id).await?;
+        let number = get_block_number_by_id(&*self.adapter, block_id).await?;
 
         let account = self
             .adapter
@@ -526,24 +652,26 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
     #[metrics_rpc("eth_getTransactionReceipt")]
     async fn get_transaction_receipt(&self, hash: H256) -> RpcResult<Option<Web3Receipt>> {
         let ctx = Context::new();
+
+        // Only a mined transaction is in the transaction location index and has a
+        // receipt, so an unknown or pending one gets `null`.
+        let receipt = match self
+            .adapter
+            .get_receipt_by_tx_hash(ctx.clone(), hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+        {
+            Some(receipt) => receipt,
+            None => return Ok(None),
+        };
+
         let res = self
             .adapter
-            .get_transaction_by_hash(ctx.clone(), hash)
+            .get_transaction_by_hash(ctx, hash)
             .await
             .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-        if let Some(stx) = res {
-            if let Some(receipt) = self
-                .adapter
-                .get_receipt_by_tx_hash(ctx, hash)
-                .await
-                .map_err(|e| RpcError::Internal(e.to_string()))?
-            {
-                return Ok(Some(Web3Receipt::new(receipt, stx)));
-            }
-        }
-
-        Ok(None)
+        Ok(res.map(|stx| Web3Receipt::new(receipt, stx)))
     }
 
     #[metrics_rpc("net_peerCount")]
@@ -561,11 +689,21 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
 
     #[metrics_rpc("eth_getLogs")]
     async fn get_logs(&self, filter: Web3Filter) -> RpcResult<Vec<Web3Log>> {
+        if filter.block_hash.is_some() && (filter.from_block.is_some() || filter.to_block.is_some())
+        {
+            return Err(RpcError::InvalidFilterFields.into());
+        }
+        // Reject the topics as `eth_newFilter` does rather than dropping the
+        // positions after the 4th.
+        let topics_len = filter.topics.as_ref().map(Vec::len).unwrap_or_default();
+        if topics_len > 4 {
+            return Err(RpcError::TooManyTopics(topics_len).into());
+        }
+        let _permit = self.logs_query_limit.acquire()?;
         let topics: Vec<Option<Vec<Option<H256>>>> = filter
             .topics
             .map(|s| {
                 s.into_iter()
-                    .take(4)
                     .map(Into::<Option<Vec<Option<H256>>>>::into)
                     .collect()
             })
@@ -573,8 +711,7 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
 
         enum BlockPosition {
             Hash(H256),
-            Num(BlockNumber),
-            Block(Block),
+            Block(BlockNumber, Vec<Hash>),
         }
 
         async fn get_logs<T: APIAdapter>(
@@ -584,10 +721,13 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
             logs: &mut Vec<Web3Log>,
             address: Option<&Vec<H160>>,
             early_return: &mut bool,
+            size_limit: &mut LogsSizeLimit,
         ) -> RpcResult<()> {
             let extend_logs = |logs: &mut Vec<Web3Log>,
                                receipts: Vec<Option<Receipt>>,
-                               early_return: &mut bool| {
+                               early_return: &mut bool,
+                               size_limit: &mut LogsSizeLimit|
+             -> Result<(), RpcError> {
                 for (index, receipt) in receipts.into_iter().flatten().enumerate() {
                     from_receipt_to_web3_log(
                         index,
@@ -596,12 +736,14 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
                         &receipt,
                         logs,
                     );
+                    size_limit.check(logs)?;
 
                     if logs.len() > MAX_LOG_NUM {
                         *early_return = true;
-                        return;
+                        return Ok(());
                     }
                 }
+                Ok(())
             };
 
             match position {
@@ -620,49 +762,56 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
                                 )
                                 .await
                                 .map_err(|e| RpcError::Internal(e.to_string()))?;
-                            extend_logs(logs, receipts, early_return);
+                            extend_logs(logs, receipts, early_return, size_limit)?;
                             Ok(())
                         }
                         None => Err(RpcError::InvalidBlockHash.into()),
                     }
                 }
-                BlockPosition::Num(n) => {
-                    let block = adapter
-                        .get_block_by_number(Context::new(), Some(n))
-                        .await
-                        .map_err(|e| RpcError::Internal(e.to_string()))?
-                        .unwrap();
-                    let receipts = adapter
-                        .get_receipts_by_hashes(
-                            Context::new(),
-                            block.header.number,
-                            &block.tx_hashes,
-                        )
-                        .await
-                        .map_err(|e| RpcError::Internal(e.to_string()))?;
+                BlockPosition::Block(number, tx_hashes) => {
+                    // A block without transactions, such as the genesis, has no receipt.
+                    if tx_hashes.is_empty() {
+                        return Ok(());
+                    }
 
-                    extend_logs(logs, receipts, early_return);
-                    Ok(())
-                }
-                BlockPosition::Block(block) => {
                     let receipts = adapter
-                        .get_receipts_by_hashes(
-                            Context::new(),
-                            block.header.number,
-                            &block.tx_hashes,
-                        )
+                        .get_receipts_by_hashes(Context::new(), number, &tx_hashes)
                         .await
                         .map_err(|e| RpcError::Internal(e.to_string()))?;
 
-                    extend_logs(logs, receipts, early_return);
+                    extend_logs(logs, receipts, early_return, size_limit)?;
                     Ok(())
                 }
             }
         }
 
         let address_filter: Option<Vec<H160>> = filter.address.into();
+        let address_filter = match filter.created_by {
+            Some(deployer) => {
+                let contracts = self
+                    .adapter
+                    .get_contracts_by_deployer(Context::new(), deployer)
+                    .await
+                    .map_err(|e| RpcError::Internal(e.to_string()))?;
+                let contracts = match address_filter {
+                    Some(addresses) if !addresses.is_empty() => contracts
+                        .into_iter()
+                        .filter(|contract| addresses.contains(contract))
+                        .collect(),
+                    _ => contracts,
+                };
+
+                // An empty address filter matches all the logs.
+                if contracts.is_empty() {
+                    return Ok(Vec::new());
+                }
+                Some(contracts)
+            }
+            None => address_filter,
+        };
         let mut all_logs = Vec::new();
         let mut early_return = false;
+        let mut size_limit = LogsSizeLimit::new(self.max_logs_response_bytes);
         match filter.block_hash {
             Some(hash) => {
                 get_logs(
@@ -672,6 +821,7 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
                     &mut all_logs,
                     address_filter.as_ref(),
                     &mut early_return,
+                    &mut size_limit,
                 )
                 .await?;
             }
@@ -705,6 +855,10 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
                     return Err(RpcError::InvalidFromBlockNumber(start).into());
                 }
 
+                if start > end {
+                    return Err(RpcError::InvalidBlockRangeOrder(start, end).into());
+                }
+
                 if end.saturating_sub(start) > self.log_filter_max_block_range {
                     return Err(RpcError::InvalidBlockRange(
                         start,
@@ -714,37 +868,36 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
                     .into());
                 }
 
-                let mut visiter_last_block = false;
-                for n in start..=end {
-                    if n == latest_number {
-                        visiter_last_block = true;
-                    } else {
-                        get_logs(
-                            &*self.adapter,
-                            BlockPosition::Num(n),
-                            &topics,
-                            &mut all_logs,
-                            address_filter.as_ref(),
-                            &mut early_return,
-                        )
-                        .await?;
-
-                        if early_return {
-                            return Ok(all_logs);
-                        }
-                    }
-                }
+                // The latest block is pinned at the query start, so a block committed
+                // during the query is never scanned.
+                let end_block = if end == latest_number {
+                    latest_block
+                } else {
+                    self.adapter
+                        .get_block_by_number(Context::new(), Some(end))
+                        .await
+                        .map_err(|e| RpcError::Internal(e.to_string()))?
+                        .unwrap()
+                };
+                // All the blocks in range are resolved before reading any receipt, so a
+                // reorg during the scan can't mix the logs of two chains.
+                let blocks = resolve_blocks(&*self.adapter, start, end_block).await?;
 
-                if visiter_last_block {
+                for (number, tx_hashes) in blocks {
                     get_logs(
                         &*self.adapter,
-                        BlockPosition::Block(latest_block),
+                        BlockPosition::Block(number, tx_hashes),
                         &topics,
                         &mut all_logs,
                         address_filter.as_ref(),
                         &mut early_return,
+                        &mut size_limit,
                     )
                     .await?;
+
+                    if early_return {
+                        return Ok(all_logs);
+                    }
                 }
             }
         }
@@ -804,7 +957,7 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
                     }
                 }
             }
-            BlockId::Latest | BlockId::Pending => {
+            BlockId::Latest | BlockId::Pending | BlockId::Safe | BlockId::Finalized => {
                 let (oldest_block_number, bash_fee_per_gases, gas_used_ratios, reward) = self
                     .inner_fee_history(None, blocks_count, &reward_percentiles)
                     .await?;
@@ -967,7 +1120,7 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
         position: U256,
         block_id: Option<BlockId>,
     ) -> RpcResult<Hex> {
-        let number = self.get_block_number_by_id(block_id).await?;
+        let number = get_block_number_by_id(&*self.adapter, block_id).await?;
 
         let header = self
             .adapter
@@ -1028,7 +1181,7 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
             return Err(RpcError::CallSystemContract.into());
         }
 
-        let number = self.get_block_number_by_id(Some(number)).await?;
+        let number = get_block_number_by_id(&*self.adapter, Some(number)).await?;
 
         let header = self
             .adapter
@@ -1042,6 +1195,133 @@ impl<Adapter: APIAdapter + 'static> Web3RpcServer for Web3RpcImpl<Adapter> {
             .await
             .map_err(|e| RpcError::Internal(e.to_string()).into())
     }
+
+    #[metrics_rpc("debug_getRawTransaction")]
+    async fn get_raw_transaction(&self, hash: H256) -> RpcResult<Option<Hex>> {
+        let res = self
+            .adapter
+            .get_transaction_by_hash(Context::new(), hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
+
+        res.map(|stx| raw_transaction(&stx))
+            .transpose()
+            .map_err(|e| RpcError::Internal(e.to_string()).into())
+    }
+
+    #[metrics_rpc("debug_getRawReceipts")]
+    async fn get_raw_receipts(&self, block_id: BlockId) -> RpcResult<Vec<Hex>> {
+        let block = match block_id {
+            BlockId::Hash(hash) => self.adapter.get_block_by_hash(Context::new(), hash).await,
+            _ => {
+                self.adapter
+                    .get_block_by_number(Context::new(), block_id.into())
+                    .await
+            }
+        }
+        .map_err(|e| RpcError::Internal(e.to_string()))?
+        .ok_or(RpcError::CannotFindBlock)?;
+
+        let txs = self
+            .adapter
+            .get_transactions_by_hashes(Context::new(), block.header.number, &block.tx_hashes)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
+        let receipts = self
+            .adapter
+            .get_receipts_by_hashes(Context::new(), block.header.number, &block.tx_hashes)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
+
+        Ok(raw_receipts(txs, receipts))
+    }
+
+    #[metrics_rpc("debug_getTransactionAccess")]
+    async fn get_transaction_access(&self, hash: H256) -> RpcResult<Option<Web3TransactionAccess>> {
+        let ctx = Context::new();
+        let stx = match self
+            .adapter
+            .get_transaction_by_hash(ctx.clone(), hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+        {
+            Some(stx) => stx,
+            None => return Ok(None),
+        };
+        let receipt = match self
+            .adapter
+            .get_receipt_by_tx_hash(ctx.clone(), hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+        {
+            Some(receipt) => receipt,
+            None => return Ok(None),
+        };
+
+        let accessed_state = self
+            .adapter
+            .get_accessed_state(ctx, &receipt)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?;
+
+        Ok(Some(Web3TransactionAccess {
+            receipt: Web3Receipt::new(receipt, stx),
+            accessed_state,
+        }))
+    }
+
+    #[metrics_rpc("debug_traceBlockByNumber")]
+    async fn trace_block_by_number(
+        &self,
+        number: BlockId,
+        options: Option<Web3TraceOptions>,
+    ) -> RpcResult<Vec<Web3TxTrace>> {
+        let block = self
+            .adapter
+            .get_block_by_number(Context::new(), number.into())
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or(RpcError::CannotFindBlock)?;
+
+        Ok(self.trace_block(block, options).await?)
+    }
+
+    #[metrics_rpc("debug_traceBlockByHash")]
+    async fn trace_block_by_hash(
+        &self,
+        hash: H256,
+        options: Option<Web3TraceOptions>,
+    ) -> RpcResult<Vec<Web3TxTrace>> {
+        let block = self
+            .adapter
+            .get_block_by_hash(Context::new(), hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or(RpcError::CannotFindBlock)?;
+
+        Ok(self.trace_block(block, options).await?)
+    }
+}
+
+/// The typed transaction envelope, which is the same as the payload of
+/// `eth_sendRawTransaction`.
+fn raw_transaction(stx: &SignedTransaction) -> ProtocolResult<Hex> {
+    stx.transaction.encode().map(Hex::encode)
+}
+
+/// The receipts encoded in the same way as the leaves of the receipts trie, so
+/// that they can be checked against the `receipts_root` of the block.
+fn raw_receipts(txs: Vec<Option<SignedTransaction>>, receipts: Vec<Option<Receipt>>) -> Vec<Hex> {
+    let (txs, receipts): (Vec<_>, Vec<_>) = txs
+        .into_iter()
+        .zip(receipts)
+        .filter_map(|(tx, receipt)| Some((tx?, receipt?)))
+        .unzip();
+
+    encode_receipts(&txs, &receipts)
+        .into_iter()
+        .map(Hex::encode)
+        .collect()
 }
 
 // 1. checks for rewardPercentile's sorted-ness
@@ -1118,6 +1398,152 @@ fn mock_header_by_call_req(latest_header: Header, call_req: &Web3CallRequest) ->
     }
 }
 
+/// Resolve the numbers and transaction hashes of the blocks from `start` up
+/// to `end_block` by walking back along the parent hashes, so all of them are
+/// on the chain of `end_block` even if the chain reorgs during the resolution.
+/// Only one block is held at a time during the walk.
+async fn resolve_blocks<T: APIAdapter>(
+    adapter: &T,
+    start: BlockNumber,
+    end_block: Block,
+) -> Result<Vec<(BlockNumber, Vec<Hash>)>, RpcError> {
+    let mut blocks = Vec::with_capacity((end_block.header.number - start + 1) as usize);
+    let mut block = end_block;
+
+    while block.header.number > start {
+        let prev_hash = block.header.prev_hash;
+        blocks.push((block.header.number, block.tx_hashes));
+        block = adapter
+            .get_block_by_hash(Context::new(), prev_hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?
+            .ok_or_else(|| {
+                RpcError::Internal(format!("cannot find the parent block {:?}", prev_hash))
+            })?;
+    }
+
+    blocks.push((block.header.number, block.tx_hashes));
+    blocks.reverse();
+    Ok(blocks)
+}
+
+async fn get_block_number_by_id<T: APIAdapter>(
+    adapter: &T,
+    block_id: Option<BlockId>,
+) -> Result<Option<BlockNumber>, RpcError> {
+    match block_id {
+        Some(BlockId::Hash(ref hash)) => Ok(adapter
+            .get_block_number_by_hash(Context::new(), *hash)
+            .await
+            .map_err(|e| RpcError::Internal(e.to_string()))?),
+        _ => Ok(block_id.unwrap_or_default().into()),
+    }
+}
+
+async fn call_evm<T: APIAdapter>(
+    adapter: &T,
+    req: Web3CallRequest,
+    data: Bytes,
+    number: Option<u64>,
+) -> ProtocolResult<TxResp> {
+    if req.from.is_none() && req.to.is_none() {
+        return Err(APIError::RequestPayload("from and to are both None".to_string()).into());
+    }
+
+    let header = adapter
+        .get_block_header_by_number(Context::new(), number)
+        .await?
+        .ok_or_else(|| APIError::Storage(format!("Cannot get {:?} header", number)))?;
+
+    let mock_header = mock_header_by_call_req(header, &req);
+
+    adapter
+        .evm_call(
+            Context::new(),
+            req.from,
+            req.to,
+            req.gas_price,
+            req.gas,
+            req.value.unwrap_or_default(),
+            data.to_vec(),
+            mock_header.state_root,
+            Proposal::new_without_state_root(&mock_header),
+        )
+        .await
+}
+
+/// Execute the call of `eth_call` and `axon_callWithGas`, the execution is
+/// required to succeed.
+pub(crate) async fn checked_call<T: APIAdapter>(
+    adapter: &T,
+    req: Web3CallRequest,
+    block_id: Option<BlockId>,
+) -> Result<TxResp, RpcError> {
+    if req.gas_price.unwrap_or_default() > U256::from(u64::MAX) {
+        return Err(RpcError::GasLimitIsTooLarge);
+    }
+
+    if req.gas.unwrap_or_default() > U256::from(MAX_RPC_GAS_CAP) {
+        return Err(RpcError::GasLimitIsTooLarge);
+    }
+
+    if let Some(call_addr) = req.to {
+        if is_system_contract_address_format(&call_addr) {
+            return Err(RpcError::CallSystemContract);
+        }
+    }
+
+    let number = get_block_number_by_id(adapter, block_id).await?;
+
+    if req.strict.unwrap_or_default() {
+        if let Some(from) = req.from {
+            check_call_funds(adapter, from, &req, number).await?;
+        }
+    }
+
+    let data_bytes = req
+        .data
+        .as_ref()
+        .map(|hex| hex.as_bytes())
+        .unwrap_or_default();
+    let resp = call_evm(adapter, req, data_bytes, number)
+        .await
+        .map_err(|e| RpcError::Internal(e.to_string()))?;
+
+    if resp.exit_reason.is_succeed() {
+        return Ok(resp);
+    }
+
+    Err(RpcError::Evm(resp))
+}
+
+/// Check whether the balance of the caller covers the gas fee and value of
+/// a strict call.
+async fn check_call_funds<T: APIAdapter>(
+    adapter: &T,
+    from: H160,
+    req: &Web3CallRequest,
+    number: Option<u64>,
+) -> Result<(), RpcError> {
+    let balance = adapter
+        .get_account(Context::new(), from, number)
+        .await
+        .map_err(|e| RpcError::Internal(e.to_string()))?
+        .balance;
+    let cost = req
+        .gas
+        .unwrap_or_default()
+        .checked_mul(req.gas_price.unwrap_or_default())
+        .and_then(|fee| fee.checked_add(req.value.unwrap_or_default()))
+        .unwrap_or(U256::MAX);
+
+    if balance < cost {
+        return Err(RpcError::InsufficientFunds(balance, cost));
+    }
+
+    Ok(())
+}
+
 pub fn from_receipt_to_web3_log(
     index: usize,
     topics: &[Option<Vec<Option<Hash>>>],
@@ -1125,27 +1551,27 @@ pub fn from_receipt_to_web3_log(
     receipt: &Receipt,
     logs: &mut Vec<Web3Log>,
 ) {
-    macro_rules! contains_topic {
-        ($topics: expr, $log: expr) => {{
-            $topics.is_empty()
-                || contains_topic!($topics, 1, $log, 0)
-                || contains_topic!($topics, 2, $log, 0, 1)
-                || contains_topic!($topics, 3, $log, 0, 1, 2)
-                || contains_topic!($topics, 4, $log, 0, 1, 2, 3)
-        }};
-
-        ($topics: expr, $min_len: expr, $log: expr$ (, $offset: expr)*) => {{
-            $topics.len() == $min_len && $log.topics.len() >= $min_len
-            $( && $topics[$offset]
-                .as_ref()
-                .map(|i| i.contains(&None) || i.contains(&Some($log.topics[$offset])))
-                .unwrap_or(true)
-            )*
-        }};
-    }
+    // A filter of one event on one contract is the most common, so it's matched
+    // by comparing the address and the first topic directly.
+    let single_event = match (address, topics) {
+        ([address], [Some(topic0)]) => match topic0.as_slice() {
+            [Some(topic0)] => Some((*address, *topic0)),
+            _ => None,
+        },
+        _ => None,
+    };
 
     for (log_idex, log) in receipt.logs.iter().enumerate() {
-        if (address.is_empty() || address.contains(&log.address)) && contains_topic!(topics, log) {
+        let matched = match single_event {
+            Some((address, topic0)) => {
+                log.address == address && log.topics.first() == Some(&topic0)
+            }
+            None => {
+                (address.is_empty() || address.contains(&log.address))
+                    && contains_topic(topics, log)
+            }
+        };
+        if matched {
             let web3_log = Web3Log {
                 address:           log.address,
                 topics:            log.topics.clone(),
@@ -1161,3 +1587,762 @@ pub fn from_receipt_to_web3_log(
         }
     }
 }
+
+/// Search the lowest gas limit in `[gas_used, hi]` which the call succeeds
+/// with, where `hi` is known to succeed. The gas usage of some contracts
+/// depends on the gas limit, so the search may not converge. The lowest
+/// successful limit found is returned once `max_iterations` rounds are run.
+async fn search_gas_limit<F, Fut>(
+    gas_used: u64,
+    mut hi: u64,
+    max_iterations: u64,
+    mut call: F,
+) -> Result<u64, RpcError>
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<bool, RpcError>>,
+{
+    if gas_used >= hi || call(gas_used).await? {
+        return Ok(gas_used.min(hi));
+    }
+
+    let mut lo = gas_used;
+    let mut iterations = 0;
+    while lo + 1 < hi {
+        if iterations >= max_iterations {
+            log::warn!(
+                "[estimate gas] not converged in {} rounds, use gas limit {}",
+                max_iterations,
+                hi
+            );
+            break;
+        }
+
+        let mid = lo + (hi - lo) / 2;
+        if call(mid).await? {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+        iterations += 1;
+    }
+
+    Ok(hi)
+}
+
+/// The limit of the log queries scanning at the same time, which is shared by
+/// `eth_getLogs` and the log filters.
+#[derive(Clone, Debug)]
+pub struct LogsQueryLimit {
+    permits: Arc<Semaphore>,
+    max:     usize,
+}
+
+impl LogsQueryLimit {
+    pub fn new(max: usize) -> Self {
+        LogsQueryLimit {
+            permits: Arc::new(Semaphore::new(max)),
+            max,
+        }
+    }
+
+    pub(crate) fn unlimited() -> Self {
+        Self::new(Semaphore::MAX_PERMITS)
+    }
+
+    /// Take a permit for a query, which is released when it is dropped.
+    pub(crate) fn acquire(&self) -> Result<OwnedSemaphorePermit, RpcError> {
+        Arc::clone(&self.permits)
+            .try_acquire_owned()
+            .map_err(|_| RpcError::TooManyConcurrentQueries(self.max))
+    }
+}
+
+/// The serialized size of the logs in a response. The logs pushed since the
+/// last check are counted on each check, so that a response is rejected as
+/// soon as it grows beyond the limit.
+pub(crate) struct LogsSizeLimit {
+    max_bytes: usize,
+    bytes:     usize,
+    counted:   usize,
+}
+
+impl LogsSizeLimit {
+    pub(crate) fn new(max_bytes: usize) -> Self {
+        LogsSizeLimit {
+            max_bytes,
+            bytes: 0,
+            counted: 0,
+        }
+    }
+
+    pub(crate) fn check(&mut self, logs: &[Web3Log]) -> Result<(), RpcError> {
+        for log in logs.iter().skip(self.counted) {
+            let size = serde_json::to_vec(log)
+                .map_err(|e| RpcError::Internal(e.to_string()))?
+                .len();
+            // Add one byte for the separator in the array.
+            self.bytes += size + 1;
+            if self.bytes > self.max_bytes {
+                return Err(RpcError::LogsResponseTooLarge(self.max_bytes));
+            }
+        }
+
+        self.counted = logs.len();
+        Ok(())
+    }
+}
+
+/// A filter matches at most 4 topic positions. An empty filter `[]` matches
+/// any log, including the ones without topics. A null at a position matches
+/// any topic there, but the log must still have a topic at that position, so
+/// `[null]` doesn't match the logs without topics.
+fn contains_topic(topics: &[Option<Vec<Option<Hash>>>], log: &Log) -> bool {
+    if topics.len() > 4 || topics.len() > log.topics.len() {
+        return false;
+    }
+
+    topics
+        .iter()
+        .zip(log.topics.iter())
+        .all(|(topic, log_topic)| match topic {
+            Some(candidates) if !candidates.contains(&None) => {
+                candidates.contains(&Some(*log_topic))
+            }
+            _ => true,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    use common_merkle::TrieMerkle;
+    use core_executor::compute_receipts_root;
+    use protocol::tokio;
+    use protocol::types::{
+        Account, Eip1559Transaction, ExitReason, ExitRevert, Public, SignatureComponents,
+        StructTrace, TransactionAction, TxTrace, UnsignedTransaction,
+    };
+
+    use crate::jsonrpc::r#impl::mock::{mock_chain, mock_log, Blocks, Mock, MockAdapter, Reorg};
+    use crate::jsonrpc::web3_types::MultiType;
+
+    use super::*;
+
+    fn mock_receipt(topics: Vec<H256>) -> Receipt {
+        Receipt {
+            logs: vec![Log {
+                address: H160::random(),
+                topics,
+                data: Vec::new(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn filter_logs(topics: &[Option<Vec<Option<Hash>>>], receipt: &Receipt) -> Vec<Web3Log> {
+        let mut logs = Vec::new();
+        from_receipt_to_web3_log(0, topics, &[], receipt, &mut logs);
+        logs
+    }
+
+    #[test]
+    fn test_filter_topic_count_greater_than_log_topics() {
+        let (t0, t1, t2) = (H256::random(), H256::random(), H256::random());
+        let filter = vec![
+            Some(vec![Some(t0)]),
+            Some(vec![Some(t1)]),
+            Some(vec![Some(t2)]),
+        ];
+
+        let receipt = mock_receipt(vec![t0, t1]);
+        assert!(filter_logs(&filter, &receipt).is_empty());
+
+        let receipt = mock_receipt(vec![t0, t1, t2]);
+        assert_eq!(filter_logs(&filter, &receipt).len(), 1);
+
+        // A wildcard position still requires the log to have a topic there.
+        let filter = vec![Some(vec![Some(t0)]), None, None];
+        assert!(filter_logs(&filter, &mock_receipt(vec![t0])).is_empty());
+        assert_eq!(
+            filter_logs(&filter, &mock_receipt(vec![t0, t1, t2])).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_filter_empty_topics() {
+        let t0 = H256::random();
+        let without_topics = mock_receipt(vec![]);
+        let with_topic = mock_receipt(vec![t0]);
+
+        // `topics: []` matches any log.
+        assert_eq!(filter_logs(&[], &without_topics).len(), 1);
+        assert_eq!(filter_logs(&[], &with_topic).len(), 1);
+
+        // `topics: [null]` matches any topic0, but requires topic0 to exist.
+        assert!(filter_logs(&[None], &without_topics).is_empty());
+        assert_eq!(filter_logs(&[None], &with_topic).len(), 1);
+        assert!(filter_logs(&[Some(vec![None])], &without_topics).is_empty());
+        assert_eq!(filter_logs(&[Some(vec![None])], &with_topic).len(), 1);
+    }
+
+    #[test]
+    fn test_filter_single_event() {
+        let (address, t0, t1) = (H160::random(), H256::random(), H256::random());
+        let mut receipt = Receipt::default();
+        for (addr, topics) in [
+            (address, vec![t0]),
+            (address, vec![t0, t1]),
+            (address, vec![t1, t0]),
+            (address, vec![]),
+            (H160::random(), vec![t0]),
+        ] {
+            receipt.logs.push(Log {
+                address: addr,
+                topics,
+                data: Vec::new(),
+            });
+        }
+
+        // The duplicated address makes the filter matched in the general way.
+        let topics = vec![Some(vec![Some(t0)])];
+        let (mut fast, mut general) = (Vec::new(), Vec::new());
+        from_receipt_to_web3_log(0, &topics, &[address], &receipt, &mut fast);
+        from_receipt_to_web3_log(0, &topics, &[address, address], &receipt, &mut general);
+        assert_eq!(fast, general);
+        let indexes = fast
+            .iter()
+            .map(|log| log.log_index.unwrap().as_u64())
+            .collect::<Vec<_>>();
+        assert_eq!(indexes, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_raw_transaction_and_receipts() {
+        // The signed transaction example of EIP-155.
+        let raw = Hex::from_str("0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let stx = SignedTransaction::from_unverified(
+            UnverifiedTransaction::decode(raw.as_bytes()).unwrap(),
+        )
+        .unwrap();
+
+        let raw_tx = raw_transaction(&stx).unwrap();
+        assert_eq!(raw_tx, raw);
+        let decoded = SignedTransaction::from_unverified(
+            UnverifiedTransaction::decode(raw_tx.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decoded, stx);
+
+        let mut failed = mock_receipt(vec![]);
+        failed.ret = ExitReason::Revert(ExitRevert::Reverted);
+        let succeed = Receipt {
+            tx_hash: stx.transaction.hash,
+            ..mock_receipt(vec![H256::random()])
+        };
+        let encoded = raw_receipts(
+            vec![Some(stx.clone()), Some(stx.clone()), Some(stx.clone())],
+            vec![Some(succeed.clone()), None, Some(failed.clone())],
+        );
+        assert_eq!(encoded.len(), 2);
+
+        // The trie of the raw receipts has the receipts root of the block.
+        let receipts_root = compute_receipts_root(&[stx.clone(), stx], &[succeed, failed]);
+        let leaves = encoded
+            .iter()
+            .map(|r| Bytes::copy_from_slice(r.as_bytes()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            TrieMerkle::from_receipts(&leaves).root_hash().unwrap(),
+            receipts_root
+        );
+    }
+
+    #[test]
+    fn test_filter_checksummed_address() {
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let filter: Web3Filter =
+            serde_json::from_value(serde_json::json!({ "address": checksummed })).unwrap();
+        let address: Option<Vec<H160>> = filter.address.into();
+        let address = address.unwrap();
+
+        let mut receipt = mock_receipt(vec![]);
+        receipt.logs[0].address =
+            H160::from_str("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+        let mut logs = Vec::new();
+        from_receipt_to_web3_log(0, &[], &address, &receipt, &mut logs);
+        assert_eq!(logs.len(), 1);
+
+        for malformed in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAe",
+            "5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg",
+        ] {
+            let err = serde_json::from_value::<Web3Filter>(
+                serde_json::json!({ "address": [checksummed, malformed] }),
+            )
+            .unwrap_err();
+            assert!(err
+                .to_string()
+                .contains(&RpcError::InvalidAddress(malformed.to_string()).to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_search_terminates() {
+        let calls = &Cell::new(0);
+        let search = move |max_iterations, succeed: fn(u64) -> bool| {
+            calls.set(0);
+            search_gas_limit(21_000, 30_000_001, max_iterations, move |gas| {
+                calls.set(calls.get() + 1);
+                async move { Ok(succeed(gas)) }
+            })
+        };
+
+        assert_eq!(search(64, |gas| gas >= 50_000).await.unwrap(), 50_000);
+        assert_eq!(search(64, |_| true).await.unwrap(), 21_000);
+        assert_eq!(calls.get(), 1);
+
+        // The call fails with the gas limits of multiples of 3, which makes the
+        // search oscillate. The lowest successful limit found is returned.
+        let estimate = search(10, |gas| gas >= 50_000 && gas % 3 != 0)
+            .await
+            .unwrap();
+        assert!(estimate >= 50_000 && estimate % 3 != 0);
+        assert_eq!(calls.get(), 11);
+    }
+
+    fn mock_web3<T: MockAdapter>(adapter: T) -> Web3RpcImpl<Mock<T>> {
+        Web3RpcImpl::new(Arc::new(Mock(adapter)), u64::MAX, 100, usize::MAX, 32)
+    }
+
+    fn logs_filter(from_block: Option<BlockId>, to_block: Option<BlockId>) -> Web3Filter {
+        Web3Filter {
+            from_block,
+            to_block,
+            block_hash: None,
+            address: MultiType::Null,
+            topics: None,
+            created_by: None,
+        }
+    }
+
+    fn block_numbers(logs: &[Web3Log]) -> Vec<u64> {
+        logs.iter()
+            .map(|log| log.block_number.unwrap().as_u64())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_logs_from_block_greater_than_to_block() {
+        let web3 = mock_web3(Blocks::new(20));
+        let filter = logs_filter(
+            Some(BlockId::Num(10u64.into())),
+            Some(BlockId::Num(5u64.into())),
+        );
+        let err = web3.get_logs(filter).await.unwrap_err();
+        assert_eq!(err.code(), -40026);
+    }
+
+    #[tokio::test]
+    async fn test_get_logs_too_many_topics() {
+        let web3 = mock_web3(Blocks::new(1));
+        let filter = |topics: usize| {
+            let mut topics = vec![serde_json::Value::Null; topics];
+            if let Some(first) = topics.first_mut() {
+                *first = serde_json::json!(H256::random());
+            }
+            serde_json::from_value::<Web3Filter>(serde_json::json!({ "topics": topics })).unwrap()
+        };
+
+        assert!(web3.get_logs(filter(4)).await.is_ok());
+        let err = web3.get_logs(filter(5)).await.unwrap_err();
+        assert_eq!(err.code(), -40031);
+    }
+
+    #[tokio::test]
+    async fn test_logs_block_hash_with_block_range() {
+        let web3 = mock_web3(Blocks::new(1));
+        let filter = Web3Filter {
+            block_hash: Some(H256::random()),
+            ..logs_filter(None, Some(BlockId::Latest))
+        };
+        let err = web3.get_logs(filter).await.unwrap_err();
+        assert_eq!(err.code(), -40034);
+    }
+
+    #[tokio::test]
+    async fn test_logs_consistent_during_reorg() {
+        // The chain reorgs right after the latest block is pinned.
+        let web3 = mock_web3(Reorg::new(6, 1));
+
+        let logs = web3
+            .get_logs(logs_filter(Some(BlockId::Num(U64::one())), None))
+            .await
+            .unwrap();
+        assert_eq!(block_numbers(&logs), vec![1, 2, 3, 4, 5]);
+        assert!(logs
+            .iter()
+            .all(|log| log.transaction_hash == Some(H256::repeat_byte(1))));
+    }
+
+    #[tokio::test]
+    async fn test_logs_from_earliest_block() {
+        let mut blocks = Blocks::new(3).logs(vec![mock_log(H160::random())]);
+        // The genesis block contains no transaction.
+        blocks.chain[0].tx_hashes.clear();
+        let web3 = mock_web3(blocks);
+
+        let logs = web3
+            .get_logs(logs_filter(
+                Some(BlockId::Earliest),
+                Some(BlockId::Num(U64::from(2))),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(block_numbers(&logs), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_logs_created_by() {
+        struct Deployed {
+            blocks:   Blocks,
+            deployed: HashMap<H160, Vec<H160>>,
+        }
+
+        #[async_trait]
+        impl MockAdapter for Deployed {
+            async fn get_block_by_number(
+                &self,
+                height: Option<u64>,
+            ) -> ProtocolResult<Option<Block>> {
+                self.blocks.get_block_by_number(height).await
+            }
+
+            async fn get_receipts_by_hashes(
+                &self,
+                block_number: u64,
+                tx_hashes: &[Hash],
+            ) -> ProtocolResult<Vec<Option<Receipt>>> {
+                self.blocks
+                    .get_receipts_by_hashes(block_number, tx_hashes)
+                    .await
+            }
+
+            async fn get_contracts_by_deployer(&self, deployer: H160) -> ProtocolResult<Vec<H160>> {
+                Ok(self.deployed.get(&deployer).cloned().unwrap_or_default())
+            }
+        }
+
+        let (alice, bob) = (H160::repeat_byte(0xa), H160::repeat_byte(0xb));
+        let (contract_a, contract_b) = (H160::repeat_byte(1), H160::repeat_byte(2));
+        let web3 = mock_web3(Deployed {
+            blocks:   Blocks::new(1).logs(vec![mock_log(contract_a), mock_log(contract_b)]),
+            deployed: HashMap::from([(alice, vec![contract_a]), (bob, vec![contract_b])]),
+        });
+        let filter = |address, created_by| Web3Filter {
+            address,
+            created_by,
+            ..logs_filter(Some(BlockId::Num(U64::one())), None)
+        };
+
+        let logs = web3.get_logs(filter(MultiType::Null, None)).await.unwrap();
+        assert_eq!(logs.len(), 2);
+
+        let logs = web3
+            .get_logs(filter(MultiType::Null, Some(alice)))
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].address, contract_a);
+
+        // Combined with an address filter out of the contracts of the deployer.
+        let logs = web3
+            .get_logs(filter(MultiType::Single(contract_b), Some(alice)))
+            .await
+            .unwrap();
+        assert!(logs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_call_from_poor_account() {
+        /// The calls succeed with the response, and all the accounts are empty.
+        struct EvmCall(TxResp);
+
+        #[async_trait]
+        impl MockAdapter for EvmCall {
+            async fn get_block_header_by_number(
+                &self,
+                _: Option<u64>,
+            ) -> ProtocolResult<Option<Header>> {
+                Ok(Some(Header::default()))
+            }
+
+            async fn get_account(
+                &self,
+                _: H160,
+                _: Option<BlockNumber>,
+            ) -> ProtocolResult<Account> {
+                Ok(Account {
+                    nonce:        U256::zero(),
+                    balance:      U256::zero(),
+                    storage_root: Hash::default(),
+                    code_hash:    Hash::default(),
+                })
+            }
+
+            async fn evm_call(&self) -> ProtocolResult<TxResp> {
+                Ok(self.0.clone())
+            }
+        }
+
+        let web3 = mock_web3(EvmCall(TxResp::default()));
+        let req = |strict: Option<bool>| {
+            let mut req: Web3CallRequest = serde_json::from_value(serde_json::json!({
+                "from": H160::repeat_byte(1),
+                "to": H160::repeat_byte(2),
+                "gas": "0x5208",
+                "gasPrice": "0x3b9aca00",
+            }))
+            .unwrap();
+            req.strict = strict;
+            req
+        };
+
+        // The gas fee is not required by default.
+        assert!(web3.call(req(None), None).await.is_ok());
+        assert!(web3.call(req(Some(false)), None).await.is_ok());
+
+        let err = web3.call(req(Some(true)), None).await.unwrap_err();
+        assert_eq!(err.code(), -40028);
+        assert!(err.message().contains("Insufficient funds"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_account_queries() {
+        struct Accounts(HashMap<H160, Account>);
+
+        #[async_trait]
+        impl MockAdapter for Accounts {
+            async fn get_block_header_by_number(
+                &self,
+                _: Option<u64>,
+            ) -> ProtocolResult<Option<Header>> {
+                Ok(Some(Header::default()))
+            }
+
+            async fn get_account(
+                &self,
+                address: H160,
+                _: Option<BlockNumber>,
+            ) -> ProtocolResult<Account> {
+                self.0
+                    .get(&address)
+                    .cloned()
+                    .ok_or_else(|| APIError::Storage("absent account".to_string()).into())
+            }
+        }
+
+        let accounts = (1..=2u8)
+            .map(|i| {
+                (H160::repeat_byte(i), Account {
+                    nonce:        U256::from(i),
+                    balance:      U256::from(i as u64 * 100),
+                    storage_root: Hash::default(),
+                    code_hash:    Hash::default(),
+                })
+            })
+            .collect();
+        let web3 = mock_web3(Accounts(accounts)).max_batch_addresses(3);
+        let addresses = vec![
+            H160::repeat_byte(2),
+            H160::repeat_byte(9),
+            H160::repeat_byte(1),
+        ];
+
+        // The absent account is zero.
+        let balances = web3.get_balances(addresses.clone(), None).await.unwrap();
+        assert_eq!(balances, vec![200u64.into(), U256::zero(), 100u64.into()]);
+        let nonces = web3
+            .get_transaction_counts(addresses.clone(), Some(BlockId::Num(U64::one())))
+            .await
+            .unwrap();
+        assert_eq!(nonces, vec![2u64.into(), U256::zero(), 1u64.into()]);
+
+        let mut too_many = addresses;
+        too_many.push(H160::repeat_byte(3));
+        let err = web3.get_balances(too_many, None).await.unwrap_err();
+        assert_eq!(err.code(), -40032);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_receipt_of_pending_tx() {
+        /// Only the mined txs have receipts.
+        struct Txs {
+            txs:      HashMap<Hash, SignedTransaction>,
+            receipts: HashMap<Hash, Receipt>,
+        }
+
+        #[async_trait]
+        impl MockAdapter for Txs {
+            async fn get_receipt_by_tx_hash(&self, hash: Hash) -> ProtocolResult<Option<Receipt>> {
+                Ok(self.receipts.get(&hash).cloned())
+            }
+
+            async fn get_transaction_by_hash(
+                &self,
+                hash: Hash,
+            ) -> ProtocolResult<Option<SignedTransaction>> {
+                Ok(self.txs.get(&hash).cloned())
+            }
+        }
+
+        let mock_tx = |hash| SignedTransaction {
+            transaction: UnverifiedTransaction {
+                unsigned: UnsignedTransaction::Eip1559(Eip1559Transaction {
+                    nonce:                    U256::zero(),
+                    max_priority_fee_per_gas: U256::one(),
+                    gas_price:                U256::one(),
+                    gas_limit:                U256::from(21_000u64),
+                    action:                   TransactionAction::Call(H160::repeat_byte(2)),
+                    value:                    U256::zero(),
+                    data:                     Bytes::new(),
+                    access_list:              Vec::new(),
+                }),
+                signature: Some(SignatureComponents::default()),
+                chain_id: Some(0),
+                hash,
+            },
+            sender:      H160::repeat_byte(1),
+            public:      Some(Public::default()),
+        };
+        let (pending, mined) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        let web3 = mock_web3(Txs {
+            txs:      HashMap::from([(pending, mock_tx(pending)), (mined, mock_tx(mined))]),
+            receipts: HashMap::from([(mined, Receipt {
+                tx_hash: mined,
+                block_number: 1,
+                used_gas: U256::from(21_000u64),
+                ..Default::default()
+            })]),
+        });
+
+        let unknown = H256::repeat_byte(3);
+        assert!(web3
+            .get_transaction_receipt(unknown)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(web3
+            .get_transaction_receipt(pending)
+            .await
+            .unwrap()
+            .is_none());
+
+        let receipt = web3.get_transaction_receipt(mined).await.unwrap().unwrap();
+        assert_eq!(receipt.transaction_hash, mined);
+        assert_eq!(receipt.block_number, U256::one());
+
+        let json = serde_json::to_value(web3.get_transaction_receipt(pending).await.unwrap());
+        assert_eq!(json.unwrap(), serde_json::Value::Null);
+    }
+
+    /// Each tx of the blocks is traced with its hash as the return value,
+    /// which takes the delay.
+    struct Traces {
+        chain: Vec<Block>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl MockAdapter for Traces {
+        async fn get_block_by_number(&self, height: Option<u64>) -> ProtocolResult<Option<Block>> {
+            Ok(height.and_then(|n| self.chain.get(n as usize)).cloned())
+        }
+
+        async fn get_block_by_hash(&self, hash: Hash) -> ProtocolResult<Option<Block>> {
+            Ok(self
+                .chain
+                .iter()
+                .find(|block| block.hash() == hash)
+                .cloned())
+        }
+
+        async fn trace_block(&self, block: &Block) -> ProtocolResult<Option<Vec<Option<TxTrace>>>> {
+            sleep(self.delay).await;
+            Ok(Some(
+                block
+                    .tx_hashes
+                    .iter()
+                    .map(|hash| {
+                        Some(TxTrace::Struct(StructTrace {
+                            gas:          21_000,
+                            failed:       false,
+                            return_value: Hex::encode(hash),
+                            struct_logs:  Vec::new(),
+                            truncated:    false,
+                        }))
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_block() {
+        let (tx_a, tx_b) = (H256::repeat_byte(1), H256::repeat_byte(2));
+        let mut chain = mock_chain(1, 2);
+        chain[1].tx_hashes = vec![tx_a, tx_b];
+        let adapter = Arc::new(Mock(Traces {
+            chain: chain.clone(),
+            delay: Duration::ZERO,
+        }));
+
+        let web3 = Web3RpcImpl::new(Arc::clone(&adapter), u64::MAX, 100, usize::MAX, 32);
+        let traces = web3
+            .trace_block_by_number(BlockId::Num(U64::one()), None)
+            .await
+            .unwrap();
+        let hashes = traces.iter().map(|t| t.tx_hash).collect::<Vec<_>>();
+        assert_eq!(hashes, vec![tx_a, tx_b]);
+        assert!(traces.iter().all(|t| t.result.is_some()));
+        let by_hash = web3
+            .trace_block_by_hash(chain[1].hash(), None)
+            .await
+            .unwrap();
+        assert_eq!(by_hash, traces);
+
+        // The traces of the whole block are bounded.
+        let web3 =
+            Web3RpcImpl::new(adapter, u64::MAX, 100, usize::MAX, 32).max_trace_response_bytes(100);
+        let err = web3
+            .trace_block_by_number(BlockId::Num(U64::one()), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), -40030);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_traces_limit() {
+        let web3 = mock_web3(Traces {
+            chain: mock_chain(1, 2),
+            delay: Duration::from_millis(100),
+        })
+        .max_concurrent_traces(2);
+
+        let trace = || web3.trace_block_by_number(BlockId::Num(U64::one()), None);
+        let (a, b, c) = tokio::join!(trace(), trace(), trace());
+        let codes = [a, b, c]
+            .iter()
+            .map(|res| res.as_ref().err().map(|e| e.code()))
+            .collect::<Vec<_>>();
+        assert_eq!(codes, vec![None, None, Some(-40033)]);
+
+        // The permits are released once the traces finish.
+        assert!(trace().await.is_ok());
+    }
+}
@@ -1,8 +1,11 @@
 mod error;
 mod r#impl;
+mod timeout;
 pub mod web3_types;
 mod ws_subscription;
 
+pub use r#impl::from_receipt_to_web3_log;
+
 use std::{collections::HashMap, sync::Arc};
 
 use ckb_jsonrpc_types::{CellInfo, HeaderView as CkbHeaderView, OutPoint};
@@ -18,10 +21,11 @@ use protocol::types::{
 };
 use protocol::ProtocolResult;
 
+use crate::jsonrpc::timeout::{MethodTimeoutLayer, MethodTimeouts};
 use crate::jsonrpc::web3_types::{
-    BlockCount, BlockId, FilterChanges, HardforkStatus, RawLoggerFilter, Web3Block,
-    Web3CallRequest, Web3FeeHistory, Web3Filter, Web3Log, Web3Receipt, Web3SyncStatus,
-    Web3Transaction,
+    BlockCount, BlockId, FilterChanges, HardforkStatus, InclusionEstimate, RawLoggerFilter,
+    Web3Block, Web3CallRequest, Web3CallWithGas, Web3FeeHistory, Web3Filter, Web3Log, Web3Receipt,
+    Web3SyncStatus, Web3TraceOptions, Web3Transaction, Web3TransactionAccess, Web3TxTrace,
 };
 use crate::jsonrpc::ws_subscription::{ws_subscription_module, HexIdProvider};
 use crate::APIError;
@@ -67,6 +71,24 @@ pub trait Web3Rpc {
     #[method(name = "eth_getBalance")]
     async fn get_balance(&self, address: H160, number: Option<BlockId>) -> RpcResult<U256>;
 
+    /// Returns the balances of the accounts at the same block, in the order of
+    /// the addresses.
+    #[method(name = "axon_getBalances")]
+    async fn get_balances(
+        &self,
+        addresses: Vec<H160>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Vec<U256>>;
+
+    /// Returns the nonces of the accounts at the same block, in the order of
+    /// the addresses.
+    #[method(name = "axon_getTransactionCounts")]
+    async fn get_transaction_counts(
+        &self,
+        addresses: Vec<H160>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Vec<U256>>;
+
     #[method(name = "eth_call")]
     async fn call(&self, req: Web3CallRequest, number: Option<BlockId>) -> RpcResult<Hex>;
 
@@ -154,6 +176,35 @@ pub trait Web3Rpc {
         storage_position: Vec<U256>,
         number: BlockId,
     ) -> RpcResult<EthAccountProof>;
+
+    /// Returns the typed RLP encoding of a stored transaction.
+    #[method(name = "debug_getRawTransaction")]
+    async fn get_raw_transaction(&self, hash: H256) -> RpcResult<Option<Hex>>;
+
+    /// Returns the RLP encoded receipts of a block.
+    #[method(name = "debug_getRawReceipts")]
+    async fn get_raw_receipts(&self, block_id: BlockId) -> RpcResult<Vec<Hex>>;
+
+    /// Returns the receipt of a transaction along with the accounts and
+    /// storage slots read or written by it.
+    #[method(name = "debug_getTransactionAccess")]
+    async fn get_transaction_access(&self, hash: H256) -> RpcResult<Option<Web3TransactionAccess>>;
+
+    /// Replays a block and returns the traces of all its transactions in
+    /// order.
+    #[method(name = "debug_traceBlockByNumber")]
+    async fn trace_block_by_number(
+        &self,
+        number: BlockId,
+        options: Option<Web3TraceOptions>,
+    ) -> RpcResult<Vec<Web3TxTrace>>;
+
+    #[method(name = "debug_traceBlockByHash")]
+    async fn trace_block_by_hash(
+        &self,
+        hash: H256,
+        options: Option<Web3TraceOptions>,
+    ) -> RpcResult<Vec<Web3TxTrace>>;
 }
 
 #[rpc(server)]
@@ -172,6 +223,12 @@ pub trait Web3Filter {
 
     #[method(name = "eth_uninstallFilter")]
     async fn uninstall_filter(&self, id: U256) -> RpcResult<bool>;
+
+    /// The long-poll variant of `eth_getFilterChanges`. On a block filter it
+    /// waits for at least one new block or the timeout in milliseconds.
+    #[method(name = "axon_waitFilterChanges")]
+    async fn wait_filter_changes(&self, id: U256, timeout: Option<u64>)
+        -> RpcResult<FilterChanges>;
 }
 
 #[rpc(server)]
@@ -221,6 +278,8 @@ pub trait AxonRpc {
     #[method(name = "axon_getProofById")]
     async fn get_proof_by_id(&self, block_id: BlockId) -> RpcResult<Option<Proof>>;
 
+    /// Returns the metadata active at the given block. A block to come is
+    /// served if its epoch has been recorded.
     #[method(name = "axon_getMetadataByNumber")]
     async fn get_metadata_by_number(&self, block_number: U256) -> RpcResult<Metadata>;
 
@@ -235,6 +294,30 @@ pub trait AxonRpc {
 
     #[method(name = "axon_getHardforkInfo")]
     async fn hardfork_infos(&self) -> RpcResult<HashMap<HardforkName, HardforkStatus>>;
+
+    /// Returns the pending transactions in the order they would be packaged.
+    #[method(name = "axon_pendingTransactions")]
+    async fn pending_transactions(&self) -> RpcResult<Vec<Web3Transaction>>;
+
+    /// Estimates whether a transaction offering the given fees is packaged in
+    /// the next block, against the pending transactions and the latest base
+    /// fee. The gas of the transaction is 21000 by default.
+    #[method(name = "axon_estimateInclusion")]
+    async fn estimate_inclusion(
+        &self,
+        max_fee_per_gas: U256,
+        max_priority_fee_per_gas: U256,
+        gas: Option<U256>,
+    ) -> RpcResult<InclusionEstimate>;
+
+    /// Executes a call as `eth_call` does, returning its output along with
+    /// the gas used and refunded.
+    #[method(name = "axon_callWithGas")]
+    async fn call_with_gas(
+        &self,
+        req: Web3CallRequest,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3CallWithGas>;
 }
 
 #[rpc(server)]
@@ -257,23 +340,30 @@ pub async fn run_jsonrpc_server<Adapter: APIAdapter + 'static>(
 ) -> ProtocolResult<(Option<ServerHandle>, Option<ServerHandle>)> {
     let mut ret = (None, None);
 
+    let logs_query_limit = r#impl::LogsQueryLimit::new(config.web3.max_concurrent_logs_queries);
     let mut rpc = r#impl::Web3RpcImpl::new(
         Arc::clone(&adapter),
         config.web3.max_gas_cap,
         config.web3.log_filter_max_block_range,
+        config.web3.max_logs_response_bytes,
+        config.web3.estimate_gas_max_iterations,
     )
+    .logs_query_limit(logs_query_limit.clone())
+    .max_trace_response_bytes(config.web3.max_trace_response_bytes)
+    .max_batch_addresses(config.web3.max_batch_addresses)
+    .max_concurrent_traces(config.web3.max_concurrent_traces)
+    .read_your_writes(config.mempool.read_your_writes)
     .into_rpc();
 
     let node_rpc = r#impl::NodeRpcImpl::new(version, config.data_path).into_rpc();
-    let axon_rpc = r#impl::AxonRpcImpl::new(Arc::clone(&adapter)).into_rpc();
-    let filter =
-        r#impl::filter_module(Arc::clone(&adapter), config.web3.log_filter_max_block_range)
+    let axon_rpc =
+        r#impl::AxonRpcImpl::new(Arc::clone(&adapter), config.web3.max_pending_transactions)
             .into_rpc();
+    let filter = r#impl::filter_module(Arc::clone(&adapter), &config.web3, logs_query_limit);
     let ckb_light_client_rpc = r#impl::CkbLightClientRpcImpl::new(Arc::clone(&adapter)).into_rpc();
 
     rpc.merge(node_rpc).unwrap();
     rpc.merge(axon_rpc).unwrap();
-    rpc.merge(filter).unwrap();
     rpc.merge(ckb_light_client_rpc).unwrap();
 
     if let Some(addr) = config.rpc.http_listening_address {
@@ -281,7 +371,9 @@ pub async fn run_jsonrpc_server<Adapter: APIAdapter + 'static>(
             .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
             .allow_origin(CorsAny)
             .allow_headers([CONTENT_TYPE]);
-        let middleware = tower::ServiceBuilder::new().layer(cors);
+        let middleware = tower::ServiceBuilder::new()
+            .layer(cors)
+            .layer(MethodTimeoutLayer::new(MethodTimeouts::new(&config.rpc)));
 
         let server = ServerBuilder::new()
             .http_only()
@@ -293,7 +385,9 @@ pub async fn run_jsonrpc_server<Adapter: APIAdapter + 'static>(
             .await
             .map_err(|e| APIError::HttpServer(e.to_string()))?;
 
-        ret.0 = Some(server.start(rpc.clone()));
+        let mut http_rpc = rpc.clone();
+        http_rpc.merge(filter.clone().into_rpc()).unwrap();
+        ret.0 = Some(server.start(http_rpc));
     }
 
     if let Some(addr) = config.rpc.ws_listening_address {
@@ -302,11 +396,13 @@ pub async fn run_jsonrpc_server<Adapter: APIAdapter + 'static>(
             .max_request_body_size(config.rpc.max_payload_size)
             .max_request_body_size(config.rpc.max_payload_size)
             .max_connections(config.rpc.maxconn)
+            .max_subscriptions_per_connection(config.rpc.max_subscriptions_per_connection)
             .set_id_provider(HexIdProvider::default())
             .build(addr)
             .await
             .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
 
+        rpc.merge(filter.into_ws_rpc()).unwrap();
         rpc.merge(ws_subscription_module(adapter).await).unwrap();
 
         ret.1 = Some(server.start(rpc))
@@ -0,0 +1,269 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{body::HttpBody, header::CONTENT_TYPE, Body, Request, Response};
+use jsonrpsee::types::ErrorObjectOwned;
+use serde_json::{json, Value};
+use tower::{Layer, Service};
+
+use common_config_parser::types::ConfigApi;
+use protocol::tokio::time::timeout;
+
+use crate::jsonrpc::error::RpcError;
+
+/// The methods which wait for the changes by themselves up to the timeout in
+/// their params, they are only limited by the timeouts set for them.
+const LONG_POLLING_METHODS: [&str; 1] = ["axon_waitFilterChanges"];
+
+/// The timeouts of the methods in milliseconds.
+#[derive(Clone, Debug)]
+pub struct MethodTimeouts {
+    default:          Option<u64>,
+    methods:          HashMap<String, u64>,
+    max_request_size: usize,
+}
+
+impl MethodTimeouts {
+    pub fn new(config: &ConfigApi) -> Self {
+        MethodTimeouts {
+            default:          config.request_timeout,
+            methods:          config.method_timeouts.clone(),
+            max_request_size: config.max_payload_size as usize,
+        }
+    }
+
+    /// The timeout of a batch request is the max one of its calls, a batch
+    /// with any call of no timeout has no timeout.
+    fn timeout_of(&self, request: &Value) -> Option<u64> {
+        let method_timeout = |call: &Value| match call.get("method").and_then(Value::as_str) {
+            Some(method) => match self.methods.get(method) {
+                Some(ms) => Some(*ms),
+                None if LONG_POLLING_METHODS.contains(&method) => None,
+                None => self.default,
+            },
+            None => self.default,
+        };
+
+        match request {
+            Value::Array(calls) => calls
+                .iter()
+                .map(method_timeout)
+                .collect::<Option<Vec<_>>>()
+                .and_then(|timeouts| timeouts.into_iter().max().or(self.default)),
+            call => method_timeout(call),
+        }
+    }
+}
+
+/// A HTTP middleware which cancels a request exceeding the timeout of its
+/// method and responds a `RpcError::RequestTimeout` instead.
+#[derive(Clone, Debug)]
+pub struct MethodTimeoutLayer {
+    timeouts: Arc<MethodTimeouts>,
+}
+
+impl MethodTimeoutLayer {
+    pub fn new(timeouts: MethodTimeouts) -> Self {
+        MethodTimeoutLayer {
+            timeouts: Arc::new(timeouts),
+        }
+    }
+}
+
+impl<S> Layer<S> for MethodTimeoutLayer {
+    type Service = MethodTimeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodTimeout {
+            inner,
+            timeouts: Arc::clone(&self.timeouts),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MethodTimeout<S> {
+    inner:    S,
+    timeouts: Arc<MethodTimeouts>,
+}
+
+impl<S> Service<Request<Body>> for MethodTimeout<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Error: From<hyper::Error> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Response = Response<Body>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // The inner service has been driven to readiness, so take it and leave a
+        // clone behind.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let timeouts = Arc::clone(&self.timeouts);
+
+        Box::pin(async move {
+            let (parts, mut body) = req.into_parts();
+
+            // Stop reading an oversized body, it is rejected by the server anyway.
+            let mut raw = Vec::new();
+            while let Some(chunk) = body.data().await {
+                raw.extend_from_slice(&chunk?);
+                if raw.len() > timeouts.max_request_size {
+                    break;
+                }
+            }
+
+            let request = serde_json::from_slice::<Value>(&raw).unwrap_or(Value::Null);
+            let fut = inner.call(Request::from_parts(parts, Body::from(raw)));
+            let ms = match timeouts.timeout_of(&request) {
+                Some(ms) => ms,
+                None => return fut.await,
+            };
+
+            // Dropping the future on timeout cancels the underlying work.
+            match timeout(Duration::from_millis(ms), fut).await {
+                Ok(res) => res,
+                Err(_) => {
+                    log::warn!("[jsonrpc] request timeout after {} ms", ms);
+                    Ok(timeout_response(&request, ms))
+                }
+            }
+        })
+    }
+}
+
+fn timeout_response(request: &Value, ms: u64) -> Response<Body> {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let body = json!({
+        "jsonrpc": "2.0",
+        "error": ErrorObjectOwned::from(RpcError::RequestTimeout(ms)),
+        "id": id,
+    });
+
+    let mut resp = Response::new(Body::from(body.to_string()));
+    resp.headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::tokio::{self, time::sleep};
+
+    use super::*;
+
+    /// A service whose `slow` method takes one second and whose
+    /// `axon_waitFilterChanges` method takes 11 seconds.
+    #[derive(Clone)]
+    struct MockService;
+
+    impl Service<Request<Body>> for MockService {
+        type Error = Box<dyn std::error::Error + Send + Sync>;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+        type Response = Response<Body>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<Body>) -> Self::Future {
+            Box::pin(async move {
+                let raw = hyper::body::to_bytes(req.into_body()).await?;
+                let raw = String::from_utf8_lossy(&raw);
+                if raw.contains("slow") {
+                    sleep(Duration::from_secs(1)).await;
+                } else if raw.contains("axon_waitFilterChanges") {
+                    sleep(Duration::from_secs(11)).await;
+                }
+                Ok::<_, Self::Error>(Response::new(Body::from("ok")))
+            })
+        }
+    }
+
+    async fn call(
+        service: &mut MethodTimeout<MockService>,
+        request: Value,
+    ) -> (Option<i64>, String) {
+        let req = Request::new(Body::from(request.to_string()));
+        let resp = service.call(req).await.unwrap();
+        let raw = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body = String::from_utf8(raw.to_vec()).unwrap();
+        let code = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v["error"]["code"].as_i64());
+        (code, body)
+    }
+
+    #[tokio::test]
+    async fn test_method_timeout() {
+        let timeouts = MethodTimeouts {
+            default:          Some(10_000),
+            methods:          HashMap::from([("slow".to_string(), 50)]),
+            max_request_size: usize::MAX,
+        };
+        let mut service = MethodTimeoutLayer::new(timeouts).layer(MockService);
+
+        let (code, body) = call(
+            &mut service,
+            json!({"jsonrpc": "2.0", "method": "slow", "id": 1}),
+        )
+        .await;
+        assert_eq!(code, Some(-40027));
+        assert!(body.contains("\"id\":1"));
+
+        let (code, body) = call(
+            &mut service,
+            json!({"jsonrpc": "2.0", "method": "fast", "id": 2}),
+        )
+        .await;
+        assert_eq!(code, None);
+        assert_eq!(body, "ok");
+
+        // A batch takes the max timeout of its calls.
+        let batch = json!([
+            {"jsonrpc": "2.0", "method": "slow", "id": 3},
+            {"jsonrpc": "2.0", "method": "other", "id": 4},
+        ]);
+        let (code, _) = call(&mut service, batch).await;
+        assert_eq!(code, None);
+    }
+
+    #[tokio::test]
+    async fn test_long_polling_method_timeout() {
+        let request = json!({"jsonrpc": "2.0", "method": "axon_waitFilterChanges", "id": 1});
+
+        // The long polling method is not limited by the default timeout.
+        let timeouts = MethodTimeouts {
+            default:          Some(10_000),
+            methods:          HashMap::new(),
+            max_request_size: usize::MAX,
+        };
+        let mut service = MethodTimeoutLayer::new(timeouts).layer(MockService);
+        let (code, body) = call(&mut service, request.clone()).await;
+        assert_eq!(code, None);
+        assert_eq!(body, "ok");
+
+        // It is limited by the timeout set for it.
+        let timeouts = MethodTimeouts {
+            default:          None,
+            methods:          HashMap::from([("axon_waitFilterChanges".to_string(), 50)]),
+            max_request_size: usize::MAX,
+        };
+        let mut service = MethodTimeoutLayer::new(timeouts).layer(MockService);
+        let (code, _) = call(&mut service, request).await;
+        assert_eq!(code, Some(-40027));
+    }
+}
@@ -6,8 +6,8 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use protocol::codec::ProtocolCodec;
 use protocol::types::{
-    AccessList, Block, Bloom, Bytes, Hash, Header, Hex, Public, Receipt, SignedTransaction, H160,
-    H256, H64, MAX_PRIORITY_FEE_PER_GAS, U256, U64,
+    AccessList, AccessedState, Block, Bloom, Bytes, Hash, Header, Hex, Public, Receipt,
+    SignedTransaction, Tracer, TxTrace, H160, H256, H64, MAX_PRIORITY_FEE_PER_GAS, U256, U64,
 };
 
 pub const EMPTY_UNCLE_HASH: H256 = H256([
@@ -17,6 +17,8 @@ pub const EMPTY_UNCLE_HASH: H256 = H256([
 
 use core_consensus::SyncStatus as InnerSyncStatus;
 
+use crate::jsonrpc::error::RpcError;
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum RichTransactionOrHash {
     Hash(Hash),
@@ -176,6 +178,10 @@ pub struct Web3Receipt {
     pub transaction_index:   Option<U256>,
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub transaction_type:    Option<U64>,
+    /// Axon reaches the BFT finality immediately, so the receipt of a
+    /// committed transaction is always finalized.
+    #[serde(default)]
+    pub finalized:           bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -192,6 +198,40 @@ pub struct Web3ReceiptLog {
     pub removed:           bool,
 }
 
+/// The output of a call along with the gas it consumes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3CallWithGas {
+    pub output:     Hex,
+    pub gas_used:   U256,
+    pub gas_refund: U256,
+}
+
+/// The options of tracing a block, the struct logger is used by default.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3TraceOptions {
+    pub tracer: Option<Tracer>,
+}
+
+/// The trace of a transaction in a block, the result is `None` for a system
+/// contract call.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3TxTrace {
+    pub tx_hash: H256,
+    pub result:  Option<TxTrace>,
+}
+
+/// The receipt of a transaction along with the state it accesses, the
+/// accessed state is `None` for a system contract call.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3TransactionAccess {
+    pub receipt:        Web3Receipt,
+    pub accessed_state: Option<AccessedState>,
+}
+
 impl Web3Receipt {
     pub fn new(receipt: Receipt, stx: SignedTransaction) -> Web3Receipt {
         let logs_list = receipt
@@ -227,6 +267,7 @@ impl Web3Receipt {
             transaction_hash:    receipt.tx_hash,
             transaction_index:   Some(receipt.tx_index.into()),
             transaction_type:    Some(stx.type_().into()),
+            finalized:           true,
         }
     }
 }
@@ -320,6 +361,10 @@ pub struct Web3CallRequest {
     pub chain_id:                 Option<U64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_priority_fee_per_gas: Option<U256>,
+    /// Require the balance of `from` to cover `gas * gasPrice + value` as a
+    /// real transaction does. The call doesn't debit the gas fee by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict:                   Option<bool>,
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq, Hash)]
@@ -330,6 +375,10 @@ pub enum BlockId {
     Latest,
     Earliest,
     Pending,
+    /// A committed block is final in Axon, so the safe and finalized blocks
+    /// are the latest one.
+    Safe,
+    Finalized,
 }
 
 impl From<BlockId> for Option<u64> {
@@ -362,6 +411,8 @@ impl Serialize for BlockId {
             BlockId::Latest => serializer.serialize_str("latest"),
             BlockId::Earliest => serializer.serialize_str("earliest"),
             BlockId::Pending => serializer.serialize_str("pending"),
+            BlockId::Safe => serializer.serialize_str("safe"),
+            BlockId::Finalized => serializer.serialize_str("finalized"),
         }
     }
 }
@@ -441,6 +492,8 @@ impl<'a> Visitor<'a> for BlockIdVisitor {
             "latest" => Ok(BlockId::Latest),
             "earliest" => Ok(BlockId::Earliest),
             "pending" => Ok(BlockId::Pending),
+            "safe" => Ok(BlockId::Safe),
+            "finalized" => Ok(BlockId::Finalized),
             _ if value.starts_with("0x") => u64::from_str_radix(&value[2..], 16)
                 .map(|n| BlockId::Num(U64::from(n)))
                 .map_err(|e| Error::custom(format!("Invalid block number: {}", e))),
@@ -514,9 +567,44 @@ pub struct Web3Filter {
     pub from_block: Option<BlockId>,
     pub to_block:   Option<BlockId>,
     pub block_hash: Option<H256>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_filter_address")]
     pub address:    MultiType<H160>,
     pub topics:     Option<Vec<MultiNestType<Hash>>>,
+    /// An Axon extension, only the logs of the contracts created by the
+    /// address are returned.
+    #[serde(default)]
+    pub created_by: Option<H160>,
+}
+
+/// The filter addresses may be checksummed or not, they are all normalized to
+/// `H160` which is case insensitive. A malformed address is rejected with the
+/// message of `RpcError::InvalidAddress` naming it.
+fn deserialize_filter_address<'de, D>(deserializer: D) -> Result<MultiType<H160>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let parse = |v: &serde_json::Value| -> Result<H160, D::Error> {
+        v.as_str()
+            .and_then(|s| s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")))
+            .filter(|h| h.len() == 40)
+            .and_then(|h| H160::from_str(h).ok())
+            .ok_or_else(|| {
+                let addr = v.as_str().map(ToString::to_string);
+                D::Error::custom(RpcError::InvalidAddress(
+                    addr.unwrap_or_else(|| v.to_string()),
+                ))
+            })
+    };
+
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Null => Ok(MultiType::Null),
+        serde_json::Value::Array(addrs) => addrs
+            .iter()
+            .map(parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(MultiType::Multi),
+        v => parse(&v).map(MultiType::Single),
+    }
 }
 
 #[derive(Default, PartialEq, Eq, Debug, Clone)]
@@ -805,8 +893,10 @@ impl From<Header> for Web3Header {
     }
 }
 
+/// The changes of a filter are serialized as a bare array of the block hashes
+/// or the logs, without any tag of the variant.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-#[serde(deny_unknown_fields, rename_all = "camelCase", untagged)]
+#[serde(untagged)]
 pub enum FilterChanges {
     Blocks(Vec<H256>),
     Logs(Vec<Web3Log>),
@@ -817,11 +907,24 @@ pub enum FilterChanges {
 pub struct RawLoggerFilter {
     pub from_block: Option<BlockId>,
     pub to_block:   Option<BlockId>,
+    /// Pin the filter to a single block, it can't be used with the from block
+    /// or the to block.
+    pub block_hash: Option<H256>,
     #[serde(default)]
     pub address:    MultiType<H160>,
     pub topics:     Option<Vec<MultiNestType<Hash>>>,
 }
 
+/// Response type for `axon_estimateInclusion` RPC call.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionEstimate {
+    pub will_include_next_block: bool,
+    /// The percentage of the pending transactions offering a lower effective
+    /// tip.
+    pub percentile:              f64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub enum HardforkStatus {
@@ -835,6 +938,23 @@ mod tests {
     use super::*;
     use protocol::{rand::random, types::UnverifiedTransaction};
 
+    #[test]
+    fn test_block_id_tags() {
+        for (tag, id, number) in [
+            ("latest", BlockId::Latest, None),
+            ("earliest", BlockId::Earliest, Some(0)),
+            ("pending", BlockId::Pending, None),
+            ("safe", BlockId::Safe, None),
+            ("finalized", BlockId::Finalized, None),
+        ] {
+            let json = serde_json::to_string(tag).unwrap();
+            let decoded: BlockId = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, id);
+            assert_eq!(serde_json::to_string(&id).unwrap(), json);
+            assert_eq!(Option::<u64>::from(id), number);
+        }
+    }
+
     #[test]
     fn test_sync_status_json() {
         let status = Web3SyncStatus::False;
@@ -872,4 +992,71 @@ mod tests {
         );
         assert_eq!(tx_json["v"], "0x25");
     }
+
+    #[test]
+    fn test_web3_receipt_finalized() {
+        // The signed transaction example of EIP-155.
+        let tx = Hex::from_str("0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let tx = UnverifiedTransaction::decode(tx).unwrap();
+        let stx = SignedTransaction::from_unverified(tx).unwrap();
+        let receipt = Receipt {
+            tx_hash: stx.transaction.hash,
+            block_number: 1,
+            ..Default::default()
+        };
+
+        let receipt = Web3Receipt::new(receipt, stx);
+        assert!(receipt.finalized);
+        let mut json = serde_json::to_value(receipt).unwrap();
+        assert_eq!(json["finalized"], true);
+
+        // The receipts of the nodes without the field still deserialize.
+        json.as_object_mut().unwrap().remove("finalized");
+        let receipt: Web3Receipt = serde_json::from_value(json).unwrap();
+        assert!(!receipt.finalized);
+    }
+
+    #[test]
+    fn test_filter_changes_json() {
+        let hashes = vec![H256::random(), H256::random()];
+        let changes = FilterChanges::Blocks(hashes.clone());
+        let json = serde_json::to_value(&changes).unwrap();
+        let items = json.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.is_string()));
+        assert_eq!(items[0], serde_json::to_value(hashes[0]).unwrap());
+        assert_eq!(
+            serde_json::from_value::<FilterChanges>(json).unwrap(),
+            changes
+        );
+
+        let log = Web3Log {
+            address:           H160::random(),
+            topics:            vec![H256::random()],
+            data:              Hex::encode([1u8, 2, 3]),
+            block_hash:        Some(H256::random()),
+            block_number:      Some(U256::one()),
+            transaction_hash:  Some(H256::random()),
+            transaction_index: Some(U256::zero()),
+            log_index:         Some(U256::zero()),
+            removed:           false,
+        };
+        let changes = FilterChanges::Logs(vec![log.clone()]);
+        let json = serde_json::to_value(&changes).unwrap();
+        let items = json.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0], serde_json::to_value(&log).unwrap());
+        assert_eq!(
+            serde_json::from_value::<FilterChanges>(json).unwrap(),
+            changes
+        );
+
+        // No change of either variant is an empty array.
+        for changes in [
+            FilterChanges::Blocks(Vec::new()),
+            FilterChanges::Logs(Vec::new()),
+        ] {
+            assert_eq!(serde_json::to_string(&changes).unwrap(), "[]");
+        }
+    }
 }
@@ -69,6 +69,10 @@ async fn subscription_callback(
     }
 }
 
+/// The hubs of `eth_subscribe`. The logs are matched by
+/// `from_receipt_to_web3_log` as the polling filters do, and the new blocks are
+/// found by polling the latest one since the API isn't notified of the
+/// committed blocks.
 pub struct Subscription<Adapter> {
     log_hubs:       Vec<Hub<LoggerFilter>>,
     header_hubs:    Vec<Hub<()>>,
@@ -323,3 +327,46 @@ impl IdProvider for HexIdProvider {
         SubscriptionId::Str(beef::Cow::owned(Hex::encode(hash.as_bytes()).as_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use protocol::tokio::time::{sleep, timeout};
+    use protocol::types::Log;
+
+    use crate::jsonrpc::r#impl::mock::{mock_log, Blocks, Mock};
+    use crate::jsonrpc::web3_types::Web3Log;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribe_logs() {
+        let (address, topic) = (H160::random(), H256::random());
+        let matched = Log {
+            topics: vec![topic],
+            ..mock_log(address)
+        };
+        let blocks = Blocks::new(1).logs(vec![mock_log(H160::random()), matched]);
+        let adapter = Arc::new(Mock(blocks));
+        let rpc = ws_subscription_module(Arc::clone(&adapter)).await;
+        let filter = serde_json::json!({ "address": address, "topics": [topic] });
+        let mut sub = rpc
+            .subscribe_unbounded("eth_subscribe", ("logs", filter))
+            .await
+            .unwrap();
+
+        // Two blocks are mined after the subscription is registered.
+        sleep(Duration::from_millis(100)).await;
+        adapter.latest.store(3, Ordering::SeqCst);
+
+        for number in [2u64, 3] {
+            let (log, _) = timeout(Duration::from_secs(10), sub.next::<Web3Log>())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert_eq!(log.address, address);
+            assert_eq!(log.block_number, Some(U256::from(number)));
+            assert_eq!(log.log_index, Some(U256::one()));
+        }
+    }
+}
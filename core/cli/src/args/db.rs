@@ -0,0 +1,45 @@
+use clap::{Parser, Subcommand};
+
+use common_config_parser::types::Config;
+
+use crate::error::{Error, Result};
+
+#[derive(Parser, Debug)]
+#[command(about = "Maintain the databases of a stopped node")]
+pub struct DbArgs {
+    #[command(subcommand)]
+    command: DbCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    Compact(CompactArgs),
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Garbage-collect the trie nodes and compact the databases")]
+struct CompactArgs {
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "CONFIG_FILE",
+        help = "File path of client configurations."
+    )]
+    config:      Config,
+    #[arg(
+        long = "keep-states",
+        value_name = "NUMBER",
+        help = "Remove the trie nodes unreachable from the states of the latest NUMBER blocks, \
+                no trie node is removed if it is not given."
+    )]
+    keep_states: Option<u64>,
+}
+
+impl DbArgs {
+    pub fn execute(self) -> Result<()> {
+        match self.command {
+            DbCommand::Compact(args) => core_run::compact_db(args.config, args.keep_states),
+        }
+        .map_err(Error::Running)
+    }
+}
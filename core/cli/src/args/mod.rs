@@ -1,3 +1,5 @@
+pub(crate) mod db;
 pub(crate) mod hardfork;
 pub(crate) mod init;
 pub(crate) mod run;
+pub(crate) mod snapshot;
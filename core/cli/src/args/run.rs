@@ -23,6 +23,12 @@ pub struct RunArgs {
     pub mine_blocks:    Option<u64>,
     #[arg(long = "mine-to-height", help = "Exit when reach the height")]
     pub mine_to_height: Option<u64>,
+    #[arg(
+        long = "reindex",
+        help = "Rebuild the indexes of the blocks before start. They are rebuilt anyway if the \
+                ones of the latest block are missing."
+    )]
+    pub reindex:        bool,
 }
 
 impl RunArgs {
@@ -36,6 +42,7 @@ impl RunArgs {
             config,
             mine_blocks,
             mine_to_height,
+            reindex,
         } = self;
 
         let stop_opt = match (mine_blocks, mine_to_height) {
@@ -52,6 +59,6 @@ impl RunArgs {
         utils::register_log(&config);
 
         let version = application_version.to_string();
-        core_run::run(version, config, key_provider, stop_opt).map_err(Error::Running)
+        core_run::run(version, config, key_provider, stop_opt, reindex).map_err(Error::Running)
     }
 }
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use common_config_parser::types::Config;
+
+use crate::error::{Error, Result};
+
+#[derive(Parser, Debug)]
+#[command(about = "Export or import a snapshot of the state")]
+pub struct SnapshotArgs {
+    #[command(subcommand)]
+    command: SnapshotCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotCommand {
+    Export(ExportArgs),
+    Import(ImportArgs),
+    Genesis(GenesisArgs),
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Export the state at a block into a snapshot file")]
+struct ExportArgs {
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "CONFIG_FILE",
+        help = "File path of client configurations."
+    )]
+    config: Config,
+    #[arg(
+        long = "block",
+        value_name = "NUMBER",
+        help = "The block to export the state at, default to the latest block."
+    )]
+    block:  Option<u64>,
+    #[arg(
+        long = "out",
+        value_name = "SNAPSHOT_FILE",
+        help = "File path of the snapshot."
+    )]
+    out:    PathBuf,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Import a snapshot file into a new data directory")]
+struct ImportArgs {
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "CONFIG_FILE",
+        help = "File path of client configurations."
+    )]
+    config: Config,
+    #[arg(
+        long = "in",
+        value_name = "SNAPSHOT_FILE",
+        help = "File path of the snapshot."
+    )]
+    input:  PathBuf,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Dump the genesis state into a JSON file")]
+struct GenesisArgs {
+    #[arg(
+        short = 'c',
+        long = "config",
+        value_name = "CONFIG_FILE",
+        help = "File path of client configurations."
+    )]
+    config: Config,
+    #[arg(
+        long = "out",
+        value_name = "JSON_FILE",
+        help = "File path of the genesis state dump."
+    )]
+    out:    PathBuf,
+}
+
+impl SnapshotArgs {
+    pub fn execute(self) -> Result<()> {
+        match self.command {
+            SnapshotCommand::Export(args) => {
+                core_run::export_snapshot(args.config, args.block, args.out)
+            }
+            SnapshotCommand::Import(args) => core_run::import_snapshot(args.config, args.input),
+            SnapshotCommand::Genesis(args) => core_run::dump_genesis(args.config, args.out),
+        }
+        .map_err(Error::Running)
+    }
+}
@@ -2,7 +2,9 @@ mod args;
 mod error;
 pub(crate) mod utils;
 
-pub use args::{hardfork::HardforkArgs, init::InitArgs, run::RunArgs};
+pub use args::{
+    db::DbArgs, hardfork::HardforkArgs, init::InitArgs, run::RunArgs, snapshot::SnapshotArgs,
+};
 pub use error::{CheckingVersionError, Error, Result};
 
 use clap::{CommandFactory as _, FromArgMatches as _, Parser, Subcommand};
@@ -22,6 +24,8 @@ enum Commands {
     Init(InitArgs),
     Run(RunArgs),
     Hardfork(HardforkArgs),
+    Snapshot(SnapshotArgs),
+    Db(DbArgs),
 }
 
 pub struct AxonCli {
@@ -62,6 +66,8 @@ impl AxonCli {
             Commands::Init(args) => args.execute(kernel_version),
             Commands::Run(args) => args.execute(application_version, kernel_version, key_provider),
             Commands::Hardfork(args) => args.execute(),
+            Commands::Snapshot(args) => args.execute(),
+            Commands::Db(args) => args.execute(),
         }
     }
 }
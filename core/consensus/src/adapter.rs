@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use overlord::types::{Node, OverlordMsg, Vote, VoteType};
 use overlord::{extract_voters, Crypto, OverlordHandler};
@@ -18,9 +19,9 @@ use protocol::traits::{
     Network, PeerTrust, Priority, Rpc, Storage, SynchronizationAdapter,
 };
 use protocol::types::{
-    BatchSignedTxs, Block, BlockNumber, BlockVersion, Bytes, ExecResp, Hash, Header, Hex,
-    MerkleRoot, Metadata, PackedTxHashes, Proof, Proposal, Receipt, SignedTransaction, Validator,
-    U256,
+    BatchSignedTxs, Block, BlockNumber, BlockVersion, Bytes, ExecResp, ExecutorLimits, Hash,
+    Header, Hex, MerkleRoot, Metadata, PackedTxHashes, Proof, Proposal, Receipt, SignedTransaction,
+    Validator, U256,
 };
 use protocol::{async_trait, tokio::task, trie, ProtocolResult};
 
@@ -43,6 +44,13 @@ pub struct OverlordConsensusAdapter<
     trie_db:          Arc<DB>,
     overlord_handler: RwLock<Option<OverlordHandler<Proposal>>>,
     crypto:           Arc<OverlordCrypto>,
+
+    // The percentage of the consensus interval which can be spent on packaging
+    // the transactions.
+    package_time_ratio:   u64,
+    // Commit the storage tries in parallel when executing a block.
+    parallel_trie_commit: bool,
+    executor_limits:      ExecutorLimits,
 }
 
 #[async_trait]
@@ -57,11 +65,21 @@ where
     async fn get_txs_from_mempool(
         &self,
         ctx: Context,
-        _number: u64,
+        number: u64,
         gas_limit: U256,
         tx_num_limit: u64,
     ) -> ProtocolResult<PackedTxHashes> {
-        self.mempool.package(ctx, gas_limit, tx_num_limit).await
+        let interval = self
+            .get_metadata_by_block_number(number)
+            .await?
+            .consensus_config
+            .interval;
+        let time_budget =
+            Duration::from_millis(interval.saturating_mul(self.package_time_ratio) / 100);
+
+        self.mempool
+            .package(ctx, gas_limit, tx_num_limit, time_budget)
+            .await
     }
 
     #[trace_span(kind = "consensus.adapter", logs = "{txs_len: txs.len()}")]
@@ -338,6 +356,7 @@ where
             Arc::clone(&self.storage),
             proposal.clone().into(),
         )?;
+        backend.set_parallel_commit(self.parallel_trie_commit);
         let root = backend.get_metadata_root();
         let metadata_handle = MetadataHandle::new(root);
 
@@ -347,7 +366,11 @@ where
 
         Ok(task::block_in_place(|| {
             let time = Instant::now();
-            let res = AxonExecutor.exec(&mut backend, signed_txs, &verifier_list);
+            let res = AxonExecutor::new(self.executor_limits).exec(
+                &mut backend,
+                signed_txs,
+                &verifier_list,
+            );
             common_apm::metrics::consensus::CONSENSUS_TIME_HISTOGRAM_VEC_STATIC
                 .exec
                 .observe(common_apm::metrics::duration_to_sec(time.elapsed()));
@@ -657,6 +680,8 @@ where
         storage: Arc<S>,
         trie_db: Arc<DB>,
         crypto: Arc<OverlordCrypto>,
+        package_time_ratio: u64,
+        parallel_trie_commit: bool,
     ) -> ProtocolResult<Self> {
         Ok(OverlordConsensusAdapter {
             network,
@@ -665,9 +690,17 @@ where
             trie_db,
             overlord_handler: RwLock::new(None),
             crypto,
+            package_time_ratio,
+            parallel_trie_commit,
+            executor_limits: ExecutorLimits::default(),
         })
     }
 
+    pub fn executor_limits(mut self, limits: ExecutorLimits) -> Self {
+        self.executor_limits = limits;
+        self
+    }
+
     pub fn set_overlord_handler(&self, handler: OverlordHandler<Proposal>) {
         *self.overlord_handler.write() = Some(handler)
     }
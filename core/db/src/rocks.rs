@@ -1,6 +1,9 @@
 use std::{error::Error, fs, io, marker::PhantomData, path::Path, sync::Arc};
 
-use rocksdb::ops::{DeleteCF, GetCF, GetColumnFamilys, IterateCF, OpenCF, PutCF, WriteOps};
+use rocksdb::ops::{
+    CompactRangeCF, DeleteCF, GetCF, GetColumnFamilys, IterateCF, OpenCF, PutCF, SetOptions,
+    WriteOps,
+};
 use rocksdb::{
     ColumnFamily, ColumnFamilyDescriptor, DBIterator, FullOptions, Options, WriteBatch,
     WriteOptions, DB,
@@ -8,7 +11,7 @@ use rocksdb::{
 
 use common_apm::metrics::storage::on_storage_put_cf;
 use common_apm::Instant;
-use common_config_parser::types::ConfigRocksDB;
+use common_config_parser::types::{ConfigRocksDB, DBCompression};
 use protocol::codec::{hex_encode, ProtocolCodec};
 use protocol::traits::{
     IntoIteratorByRef, StorageAdapter, StorageBatchModify, StorageCategory, StorageIterator,
@@ -41,19 +44,7 @@ impl RocksAdapter {
             fs::create_dir_all(&path).map_err(RocksDBError::CreateDB)?;
         }
 
-        let categories = [
-            map_category(StorageCategory::Block),
-            map_category(StorageCategory::BlockHeader),
-            map_category(StorageCategory::Receipt),
-            map_category(StorageCategory::SignedTransaction),
-            map_category(StorageCategory::Wal),
-            map_category(StorageCategory::HashHeight),
-            map_category(StorageCategory::Code),
-            map_category(StorageCategory::EvmState),
-            map_category(StorageCategory::MetadataState),
-            map_category(StorageCategory::CkbLightClientState),
-            map_category(StorageCategory::Version),
-        ];
+        let categories = ALL_CATEGORIES.map(map_category);
 
         let (mut opts, cf_descriptors) = if let Some(ref file) = config.options_file {
             let cache_size = match config.cache_size {
@@ -89,6 +80,7 @@ impl RocksAdapter {
 
         let db =
             DB::open_cf_descriptors(&opts, path, cf_descriptors).map_err(RocksDBError::from)?;
+        set_compression(&db, &config)?;
 
         Ok(RocksAdapter { db: Arc::new(db) })
     }
@@ -96,6 +88,46 @@ impl RocksAdapter {
     pub fn inner_db(&self) -> Arc<DB> {
         Arc::clone(&self.db)
     }
+
+    /// Compact all the column families manually, so that the space of the
+    /// removed data is reclaimed.
+    pub fn compact(&self) -> ProtocolResult<()> {
+        for category in ALL_CATEGORIES.iter() {
+            let column = self
+                .db
+                .cf_handle(map_category(*category))
+                .ok_or(RocksDBError::CategoryNotFound(map_category(*category)))?;
+            self.db
+                .compact_range_cf(column, None::<&[u8]>, None::<&[u8]>)
+                .map_err(RocksDBError::from)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Set the compression of the column families in the config, which applies to
+/// the files written afterwards.
+fn set_compression(db: &DB, config: &ConfigRocksDB) -> Result<(), RocksDBError> {
+    for (name, compression) in config.compression.iter() {
+        let category = ALL_CATEGORIES
+            .iter()
+            .find(|c| c.to_string() == *name)
+            .ok_or_else(|| RocksDBError::UnknownCategory(name.clone()))?;
+        let column = db
+            .cf_handle(map_category(*category))
+            .ok_or(RocksDBError::CategoryNotFound(map_category(*category)))?;
+
+        let compression = match compression {
+            DBCompression::None => "kNoCompression",
+            DBCompression::Snappy => "kSnappyCompression",
+            DBCompression::Lz4 => "kLZ4Compression",
+            DBCompression::Zstd => "kZSTD",
+        };
+        db.set_options_cf(column, &[("compression", compression)])?;
+    }
+
+    Ok(())
 }
 
 macro_rules! db {
@@ -274,6 +306,9 @@ pub enum RocksDBError {
     #[display(fmt = "category {} not found", _0)]
     CategoryNotFound(&'static str),
 
+    #[display(fmt = "unknown category {} in the compression config", _0)]
+    UnknownCategory(String),
+
     #[display(fmt = "rocksdb {}", _0)]
     RocksDB(rocksdb::Error),
 
@@ -295,6 +330,21 @@ impl From<RocksDBError> for ProtocolError {
     }
 }
 
+const ALL_CATEGORIES: [StorageCategory; 12] = [
+    StorageCategory::Block,
+    StorageCategory::BlockHeader,
+    StorageCategory::Receipt,
+    StorageCategory::SignedTransaction,
+    StorageCategory::Wal,
+    StorageCategory::HashHeight,
+    StorageCategory::Code,
+    StorageCategory::EvmState,
+    StorageCategory::MetadataState,
+    StorageCategory::CkbLightClientState,
+    StorageCategory::Version,
+    StorageCategory::DeployedContracts,
+];
+
 const C_VERSION: &str = "c0";
 const C_BLOCKS: &str = "c1";
 const C_BLOCK_HEADER: &str = "c2";
@@ -306,6 +356,7 @@ const C_EVM_CODE_MAP: &str = "c7";
 const C_EVM_STATE: &str = "c8";
 const C_METADATA_STATE: &str = "c9";
 const C_CKB_LIGHT_CLIENT_STATE: &str = "c10";
+const C_DEPLOYED_CONTRACTS: &str = "c11";
 
 pub fn map_category(c: StorageCategory) -> &'static str {
     match c {
@@ -320,6 +371,7 @@ pub fn map_category(c: StorageCategory) -> &'static str {
         StorageCategory::MetadataState => C_METADATA_STATE,
         StorageCategory::CkbLightClientState => C_CKB_LIGHT_CLIENT_STATE,
         StorageCategory::Version => C_VERSION,
+        StorageCategory::DeployedContracts => C_DEPLOYED_CONTRACTS,
     }
 }
 
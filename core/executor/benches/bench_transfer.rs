@@ -136,7 +136,7 @@ fn criterion_100_txs(c: &mut Criterion) {
     let mut backend = BenchAdapter::new().init_backend();
 
     c.bench_function("transfer 100", |b| {
-        b.iter(|| AxonExecutor.exec(&mut backend, &txs, &[]))
+        b.iter(|| AxonExecutor::default().exec(&mut backend, &txs, &[]))
     });
 }
 
@@ -145,7 +145,7 @@ fn criterion_1000_txs(c: &mut Criterion) {
     let mut backend = BenchAdapter::new().init_backend();
 
     c.bench_function("transfer 1000", |b| {
-        b.iter(|| AxonExecutor.exec(&mut backend, &txs, &[]))
+        b.iter(|| AxonExecutor::default().exec(&mut backend, &txs, &[]))
     });
 }
 
@@ -154,7 +154,7 @@ fn criterion_10000_txs(c: &mut Criterion) {
     let mut backend = BenchAdapter::new().init_backend();
 
     c.bench_function("transfer 10000", |b| {
-        b.iter(|| AxonExecutor.exec(&mut backend, &txs, &[]))
+        b.iter(|| AxonExecutor::default().exec(&mut backend, &txs, &[]))
     });
 }
 
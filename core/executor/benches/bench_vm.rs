@@ -92,7 +92,7 @@ fn criterion_10000_txs(c: &mut Criterion) {
         let mut axon_adapter = AxonExecutorApplyAdapter::init(storage, db, exec_ctx, account, addr);
 
         b.iter(|| {
-            AxonExecutor.exec(&mut axon_adapter, &txs, &[]);
+            AxonExecutor::default().exec(&mut axon_adapter, &txs, &[]);
         })
     });
 }
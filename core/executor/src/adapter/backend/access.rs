@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+
+use evm::backend::{Apply, Basic};
+
+use protocol::traits::Backend;
+use protocol::types::{AccessedState, H160, H256, U256};
+
+/// A backend wrapper which records the accounts and storage slots read
+/// through it.
+pub struct AccessRecorder<'a, B> {
+    inner:    &'a B,
+    accessed: RefCell<AccessedState>,
+}
+
+impl<'a, B: Backend> AccessRecorder<'a, B> {
+    pub fn new(inner: &'a B) -> Self {
+        AccessRecorder {
+            inner,
+            accessed: RefCell::new(AccessedState::default()),
+        }
+    }
+
+    pub fn into_accessed(self) -> AccessedState {
+        self.accessed.into_inner()
+    }
+}
+
+impl<'a, B: Backend> Backend for AccessRecorder<'a, B> {
+    fn gas_price(&self) -> U256 {
+        self.inner.gas_price()
+    }
+
+    fn origin(&self) -> H160 {
+        self.inner.origin()
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        self.inner.block_hash(number)
+    }
+
+    fn block_number(&self) -> U256 {
+        self.inner.block_number()
+    }
+
+    fn block_coinbase(&self) -> H160 {
+        self.inner.block_coinbase()
+    }
+
+    fn block_timestamp(&self) -> U256 {
+        self.inner.block_timestamp()
+    }
+
+    fn block_difficulty(&self) -> U256 {
+        self.inner.block_difficulty()
+    }
+
+    fn block_gas_limit(&self) -> U256 {
+        self.inner.block_gas_limit()
+    }
+
+    fn block_base_fee_per_gas(&self) -> U256 {
+        self.inner.block_base_fee_per_gas()
+    }
+
+    fn chain_id(&self) -> U256 {
+        self.inner.chain_id()
+    }
+
+    fn exists(&self, address: H160) -> bool {
+        self.accessed.borrow_mut().read_account(address);
+        self.inner.exists(address)
+    }
+
+    fn basic(&self, address: H160) -> Basic {
+        self.accessed.borrow_mut().read_account(address);
+        self.inner.basic(address)
+    }
+
+    fn code(&self, address: H160) -> Vec<u8> {
+        self.accessed.borrow_mut().read_account(address);
+        self.inner.code(address)
+    }
+
+    fn storage(&self, address: H160, key: H256) -> H256 {
+        self.accessed.borrow_mut().read_slot(address, key);
+        self.inner.storage(address, key)
+    }
+
+    fn original_storage(&self, address: H160, key: H256) -> Option<H256> {
+        self.accessed.borrow_mut().read_slot(address, key);
+        self.inner.original_storage(address, key)
+    }
+}
+
+/// Record the accounts and storage slots written by the changes to apply.
+pub fn record_writes(accessed: &mut AccessedState, values: &[Apply<Vec<(H256, H256)>>]) {
+    for apply in values.iter() {
+        match apply {
+            Apply::Modify {
+                address, storage, ..
+            } => {
+                accessed.write_account(*address);
+                for (key, _) in storage.iter() {
+                    accessed.write_slot(*address, *key);
+                }
+            }
+            Apply::Delete { address } => accessed.write_account(*address),
+        }
+    }
+}
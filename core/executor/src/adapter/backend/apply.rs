@@ -1,4 +1,6 @@
+use std::num::NonZeroUsize;
 use std::sync::Arc;
+use std::thread;
 
 use evm::backend::{Apply, Basic};
 
@@ -19,6 +21,8 @@ use crate::{adapter::AxonExecutorReadOnlyAdapter, MPTTrie};
 pub struct AxonExecutorApplyAdapter<S, DB: trie::DB> {
     inner: AxonExecutorReadOnlyAdapter<S, DB>,
     logs:  Vec<Log>,
+
+    parallel_commit: bool,
 }
 
 impl<S, DB> ExecutorReadOnlyAdapter for AxonExecutorApplyAdapter<S, DB>
@@ -152,7 +156,98 @@ where
         storage: I,
         reset_storage: bool,
     ) -> bool {
-        let old_account = match self.inner.trie.get(address.as_bytes()) {
+        let old_account = self.get_raw_account(&address);
+        let storage_root = commit_storage_trie(
+            &self.inner.db,
+            if reset_storage {
+                RLP_NULL
+            } else {
+                old_account.storage_root
+            },
+            storage,
+        );
+
+        self.update_account(address, basic, code, old_account, storage_root)
+    }
+
+    /// Apply the changes with the storage tries of all the modified accounts
+    /// committed in parallel before the account trie is updated. The storage
+    /// tries are independent of each other, so the final state root is the
+    /// same as the one of the serial apply.
+    fn apply_parallel<A, I>(&mut self, values: A, delete_empty: bool)
+    where
+        A: IntoIterator<Item = Apply<I>>,
+        I: IntoIterator<Item = (H256, H256)>,
+    {
+        let values = values
+            .into_iter()
+            .map(|apply| match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage,
+                    reset_storage,
+                } => Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage: storage.into_iter().collect::<Vec<_>>(),
+                    reset_storage,
+                },
+                Apply::Delete { address } => Apply::Delete { address },
+            })
+            .collect::<Vec<_>>();
+
+        let mut old_accounts = Vec::new();
+        let mut tasks = Vec::new();
+        for apply in values.iter() {
+            if let Apply::Modify {
+                address,
+                storage,
+                reset_storage,
+                ..
+            } = apply
+            {
+                let old_account = self.get_raw_account(address);
+                let storage_root = if *reset_storage {
+                    RLP_NULL
+                } else {
+                    old_account.storage_root
+                };
+                old_accounts.push(old_account);
+                tasks.push((storage_root, storage.as_slice()));
+            }
+        }
+
+        let storage_roots = commit_storage_tries_parallel(&self.inner.db, &tasks);
+        let mut modified = old_accounts.into_iter().zip(storage_roots);
+
+        for apply in values.into_iter() {
+            match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    ..
+                } => {
+                    let (old_account, storage_root) =
+                        modified.next().expect("storage root of modified account");
+                    let is_empty =
+                        self.update_account(address, basic, code, old_account, storage_root);
+                    if is_empty && delete_empty {
+                        self.inner.trie.remove(address.as_bytes()).unwrap();
+                    }
+                }
+                Apply::Delete { address } => {
+                    let _ = self.inner.trie.remove(address.as_bytes());
+                }
+            }
+        }
+    }
+
+    fn get_raw_account(&self, address: &H160) -> Account {
+        match self.inner.trie.get(address.as_bytes()) {
             Ok(Some(raw)) => Account::decode(raw).unwrap(),
             _ => Account {
                 nonce:        U256::zero(),
@@ -160,46 +255,17 @@ where
                 storage_root: RLP_NULL,
                 code_hash:    NIL_DATA,
             },
-        };
-
-        let storage_root = if reset_storage {
-            RLP_NULL
-        } else {
-            old_account.storage_root
-        };
-
-        let mut storage_trie = if storage_root == RLP_NULL {
-            MPTTrie::new(Arc::clone(&self.inner.db))
-        } else {
-            MPTTrie::from_root(old_account.storage_root, Arc::clone(&self.inner.db)).unwrap()
-        };
-
-        storage.into_iter().for_each(|(k, v)| {
-            // https://github.com/ethereum/go-ethereum/blob/ad16f11f841ab3a5fdedc8ddfc602f0717a34dd0/core/state/state_object.go#L306-L311
-            // if value is zero, delete it's key
-            if v == H256::zero() {
-                storage_trie
-                    .remove(k.as_bytes())
-                    .expect("Failed to remove entry with zero value from storage trie");
-            } else {
-                storage_trie
-                    .insert(
-                        k.as_bytes().to_vec(),
-                        // https://github.com/ethereum/go-ethereum/blob/ad16f11f841ab3a5fdedc8ddfc602f0717a34dd0/core/state/state_object.go#L314
-                        // Trim left zeroes and then rlp
-                        U256::from_big_endian(v.as_bytes())
-                            .encode()
-                            .unwrap()
-                            .to_vec(),
-                    )
-                    .expect("trie tree insert fail");
-            }
-        });
-
-        let storage_root = storage_trie
-            .commit()
-            .unwrap_or_else(|err| panic!("failed to update the trie storage since {err}"));
+        }
+    }
 
+    fn update_account(
+        &mut self,
+        address: H160,
+        basic: Basic,
+        code: Option<Vec<u8>>,
+        old_account: Account,
+        storage_root: MerkleRoot,
+    ) -> bool {
         let mut new_account = Account {
             nonce: basic.nonce,
             balance: basic.balance,
@@ -250,6 +316,12 @@ where
         I: IntoIterator<Item = (H256, H256)>,
         L: IntoIterator<Item = Log>,
     {
+        if self.parallel_commit {
+            self.apply_parallel(values, delete_empty);
+            self.logs = logs.into_iter().collect::<Vec<_>>();
+            return;
+        }
+
         for apply in values.into_iter() {
             match apply {
                 Apply::Modify {
@@ -283,6 +355,8 @@ where
         Ok(AxonExecutorApplyAdapter {
             inner: AxonExecutorReadOnlyAdapter::new(db, storage, exec_ctx)?,
             logs:  Vec::new(),
+
+            parallel_commit: false,
         })
     }
 
@@ -295,9 +369,17 @@ where
         Ok(AxonExecutorApplyAdapter {
             inner: AxonExecutorReadOnlyAdapter::from_root(state_root, db, storage, exec_ctx)?,
             logs:  Vec::new(),
+
+            parallel_commit: false,
         })
     }
 
+    /// Commit the storage tries of the modified accounts in parallel when
+    /// applying the state changes.
+    pub fn set_parallel_commit(&mut self, enable: bool) {
+        self.parallel_commit = enable;
+    }
+
     pub fn get_metadata_root(&self) -> H256 {
         self.storage(METADATA_CONTRACT_ADDRESS, *METADATA_ROOT_KEY)
     }
@@ -306,3 +388,70 @@ where
         Arc::clone(&self.inner.storage)
     }
 }
+
+fn commit_storage_trie<DB, I>(db: &Arc<DB>, storage_root: MerkleRoot, storage: I) -> MerkleRoot
+where
+    DB: trie::DB + 'static,
+    I: IntoIterator<Item = (H256, H256)>,
+{
+    let mut storage_trie = if storage_root == RLP_NULL {
+        MPTTrie::new(Arc::clone(db))
+    } else {
+        MPTTrie::from_root(storage_root, Arc::clone(db)).unwrap()
+    };
+
+    storage.into_iter().for_each(|(k, v)| {
+        // https://github.com/ethereum/go-ethereum/blob/ad16f11f841ab3a5fdedc8ddfc602f0717a34dd0/core/state/state_object.go#L306-L311
+        // if value is zero, delete it's key
+        if v == H256::zero() {
+            storage_trie
+                .remove(k.as_bytes())
+                .expect("Failed to remove entry with zero value from storage trie");
+        } else {
+            storage_trie
+                .insert(
+                    k.as_bytes().to_vec(),
+                    // https://github.com/ethereum/go-ethereum/blob/ad16f11f841ab3a5fdedc8ddfc602f0717a34dd0/core/state/state_object.go#L314
+                    // Trim left zeroes and then rlp
+                    U256::from_big_endian(v.as_bytes())
+                        .encode()
+                        .unwrap()
+                        .to_vec(),
+                )
+                .expect("trie tree insert fail");
+        }
+    });
+
+    storage_trie
+        .commit()
+        .unwrap_or_else(|err| panic!("failed to update the trie storage since {err}"))
+}
+
+fn commit_storage_tries_parallel<DB: trie::DB + 'static>(
+    db: &Arc<DB>,
+    tasks: &[(MerkleRoot, &[(H256, H256)])],
+) -> Vec<MerkleRoot> {
+    let threads = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1);
+    let chunk_size = ((tasks.len() + threads - 1) / threads).max(1);
+
+    thread::scope(|s| {
+        tasks
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(root, storage)| {
+                            commit_storage_trie(db, *root, storage.iter().copied())
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("commit storage trie thread panicked"))
+            .collect()
+    })
+}
@@ -1,2 +1,3 @@
+pub mod access;
 pub mod apply;
 pub mod read_only;
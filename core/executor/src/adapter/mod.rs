@@ -1,7 +1,12 @@
 mod backend;
+mod state_diff;
 mod trie;
 
-pub use backend::{apply::AxonExecutorApplyAdapter, read_only::AxonExecutorReadOnlyAdapter};
+pub(crate) use backend::access::record_writes;
+pub use backend::{
+    access::AccessRecorder, apply::AxonExecutorApplyAdapter, read_only::AxonExecutorReadOnlyAdapter,
+};
+pub use state_diff::{AccountChange, AccountDiff, StateDiff};
 pub use trie::{db::RocksTrieDB, wrapped::MPTTrie};
 
 #[macro_export]
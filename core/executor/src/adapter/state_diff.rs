@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use protocol::codec::ProtocolCodec;
+use protocol::trie::{PatriciaTrie, Trie, DB as TrieDB};
+use protocol::types::{
+    Account, BigEndianHash, Hasher, HasherKeccak, MerkleRoot, H160, H256, RLP_NULL, U256,
+};
+use protocol::ProtocolResult;
+
+/// The new value of an account. The storage changes are keyed by the hashed
+/// slot key which is the key of the storage trie, and a zero value means the
+/// slot is removed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountChange {
+    pub nonce:     U256,
+    pub balance:   U256,
+    pub code_hash: H256,
+    pub storage:   BTreeMap<H256, H256>,
+}
+
+impl AccountChange {
+    pub fn set_storage(&mut self, slot: H256, value: H256) -> &mut Self {
+        self.storage.insert(StateDiff::slot_key(&slot), value);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AccountDiff {
+    Created(AccountChange),
+    Modified(AccountChange),
+    Deleted,
+}
+
+/// A typed difference between two world states.
+///
+/// The accounts are keyed by the hashed address which is the key of the state
+/// trie, so that a diff calculated from two state roots can be applied without
+/// knowing the preimages of the trie keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts: BTreeMap<H256, AccountDiff>,
+}
+
+impl StateDiff {
+    pub fn account_key(address: &H160) -> H256 {
+        Hasher::digest(address.as_bytes())
+    }
+
+    pub fn slot_key(slot: &H256) -> H256 {
+        Hasher::digest(slot.as_bytes())
+    }
+
+    pub fn insert(&mut self, address: &H160, diff: AccountDiff) -> &mut Self {
+        self.accounts.insert(Self::account_key(address), diff);
+        self
+    }
+
+    pub fn get(&self, address: &H160) -> Option<&AccountDiff> {
+        self.accounts.get(&Self::account_key(address))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Apply the diff on the state of the given root and return the new state
+    /// root. The modified nodes are written into the given trie database.
+    pub fn apply<DB: TrieDB>(&self, db: Arc<DB>, root: MerkleRoot) -> ProtocolResult<MerkleRoot> {
+        let mut state = open_trie(&db, root)?;
+
+        for (key, diff) in self.accounts.iter() {
+            let change = match diff {
+                AccountDiff::Deleted => {
+                    state.remove(key.as_bytes())?;
+                    continue;
+                }
+                AccountDiff::Created(change) | AccountDiff::Modified(change) => change,
+            };
+
+            let storage_root = match (diff, state.get(key.as_bytes())?) {
+                (AccountDiff::Modified(_), Some(raw)) => Account::decode(raw)?.storage_root,
+                _ => RLP_NULL,
+            };
+
+            let mut storage = open_trie(&db, storage_root)?;
+            for (slot, value) in change.storage.iter() {
+                if value.is_zero() {
+                    storage.remove(slot.as_bytes())?;
+                } else {
+                    storage.insert(
+                        slot.as_bytes().to_vec(),
+                        U256::from_big_endian(value.as_bytes()).encode()?.to_vec(),
+                    )?;
+                }
+            }
+
+            let account = Account {
+                nonce:        change.nonce,
+                balance:      change.balance,
+                storage_root: MerkleRoot::from_slice(&storage.root()?),
+                code_hash:    change.code_hash,
+            };
+            state.insert(key.as_bytes().to_vec(), account.encode()?.to_vec())?;
+        }
+
+        Ok(MerkleRoot::from_slice(&state.root()?))
+    }
+
+    /// Calculate the diff which turns the state of `origin` into the state of
+    /// `target`. Both states must be stored in the given trie database.
+    pub fn diff<DB: TrieDB>(
+        db: Arc<DB>,
+        origin: MerkleRoot,
+        target: MerkleRoot,
+    ) -> ProtocolResult<Self> {
+        let before = collect_trie(&db, origin)?;
+        let after = collect_trie(&db, target)?;
+        let mut accounts = BTreeMap::new();
+
+        for (key, raw) in after.iter() {
+            let old = before.get(key);
+            if old == Some(raw) {
+                continue;
+            }
+
+            let account = Account::decode(raw)?;
+            let old_storage_root = match old {
+                Some(old_raw) => Account::decode(old_raw)?.storage_root,
+                None => RLP_NULL,
+            };
+            let change = AccountChange {
+                nonce:     account.nonce,
+                balance:   account.balance,
+                code_hash: account.code_hash,
+                storage:   diff_storage(&db, old_storage_root, account.storage_root)?,
+            };
+
+            let diff = if old.is_some() {
+                AccountDiff::Modified(change)
+            } else {
+                AccountDiff::Created(change)
+            };
+            accounts.insert(H256::from_slice(key), diff);
+        }
+
+        for key in before.keys().filter(|k| !after.contains_key(*k)) {
+            accounts.insert(H256::from_slice(key), AccountDiff::Deleted);
+        }
+
+        Ok(StateDiff { accounts })
+    }
+}
+
+fn open_trie<DB: TrieDB>(
+    db: &Arc<DB>,
+    root: MerkleRoot,
+) -> ProtocolResult<PatriciaTrie<DB, HasherKeccak>> {
+    let hasher = Arc::new(HasherKeccak::new());
+
+    if root == RLP_NULL {
+        return Ok(PatriciaTrie::new(Arc::clone(db), hasher));
+    }
+
+    Ok(PatriciaTrie::from(Arc::clone(db), hasher, root.as_bytes())?)
+}
+
+fn collect_trie<DB: TrieDB>(
+    db: &Arc<DB>,
+    root: MerkleRoot,
+) -> ProtocolResult<BTreeMap<Vec<u8>, Vec<u8>>> {
+    if root == RLP_NULL {
+        return Ok(BTreeMap::new());
+    }
+
+    Ok(open_trie(db, root)?.iter().collect())
+}
+
+fn diff_storage<DB: TrieDB>(
+    db: &Arc<DB>,
+    origin: MerkleRoot,
+    target: MerkleRoot,
+) -> ProtocolResult<BTreeMap<H256, H256>> {
+    let mut ret = BTreeMap::new();
+    if origin == target {
+        return Ok(ret);
+    }
+
+    let before = collect_trie(db, origin)?;
+    let after = collect_trie(db, target)?;
+
+    for (slot, raw) in after.iter() {
+        if before.get(slot) != Some(raw) {
+            let value: H256 = BigEndianHash::from_uint(&U256::decode(raw)?);
+            ret.insert(H256::from_slice(slot), value);
+        }
+    }
+
+    for slot in before.keys().filter(|k| !after.contains_key(*k)) {
+        ret.insert(H256::from_slice(slot), H256::zero());
+    }
+
+    Ok(ret)
+}
@@ -1,19 +1,27 @@
-use std::{collections::HashMap, io, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    sync::Arc,
+};
 
 use parking_lot::RwLock;
-use rocksdb::ops::{GetCF, GetColumnFamilys, PutCF, WriteOps};
-use rocksdb::{ColumnFamily, WriteBatch, DB};
+use rlp::Rlp;
+use rocksdb::ops::{DeleteCF, GetCF, GetColumnFamilys, IterateCF, PutCF, WriteOps};
+use rocksdb::{ColumnFamily, IteratorMode, WriteBatch, DB};
 
 use common_apm::metrics::storage::{on_storage_get_state, on_storage_put_state};
 use common_apm::Instant;
+use protocol::codec::{hex_encode, ProtocolCodec};
 use protocol::rand::{rngs::SmallRng, Rng, SeedableRng};
 use protocol::traits::StateStorageCategory;
-use protocol::trie;
+use protocol::trie::{self, DB as _};
+use protocol::types::{Account, MerkleRoot, RLP_NULL};
 
 use core_db::map_category;
 
 // 49999 is the largest prime number within 50000.
 const RAND_SEED: u64 = 49999;
+const GC_BATCH_SIZE: usize = 10_000;
 
 macro_rules! db {
     ($db:expr, $op:ident, $column:expr$ (, $args: expr)*) => {
@@ -167,6 +175,113 @@ impl RocksTrieDB {
         }
     }
 
+    /// Remove the trie nodes which are not reachable from the given state
+    /// roots, including the nodes of the storage tries of the accounts. Returns
+    /// the number of the removed nodes.
+    ///
+    /// The nodes written during the collection may be removed, so it must run
+    /// while nothing else writes the database.
+    pub fn gc(&self, roots: &[MerkleRoot]) -> Result<usize, io::Error> {
+        let mut reachable = HashSet::new();
+        for root in roots.iter().filter(|root| **root != RLP_NULL) {
+            self.mark(root.as_bytes(), false, &mut reachable)?;
+        }
+
+        let column = self.get_column();
+        let mut batch = WriteBatch::default();
+        let mut removed = 0;
+        for (key, _) in db!(self.db, iterator_cf, column, IteratorMode::Start) {
+            if reachable.contains(&*key) {
+                continue;
+            }
+
+            db!(batch, delete_cf, column, &key);
+            removed += 1;
+            if removed % GC_BATCH_SIZE == 0 {
+                db!(self.db, write, &std::mem::take(&mut batch));
+            }
+        }
+        db!(self.db, write, &batch);
+
+        self.cache.write().clear();
+        Ok(removed)
+    }
+
+    /// Mark the node of the hash and its descendants as reachable. The leaves
+    /// of the state trie are accounts whose storage tries are marked as well.
+    fn mark(
+        &self,
+        hash: &[u8],
+        is_storage: bool,
+        reachable: &mut HashSet<Vec<u8>>,
+    ) -> Result<(), io::Error> {
+        if !reachable.insert(hash.to_vec()) {
+            return Ok(());
+        }
+
+        let raw = self.get(hash)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("trie node {} is missing", hex_encode(hash)),
+            )
+        })?;
+        self.mark_node(&Rlp::new(&raw), is_storage, reachable)
+    }
+
+    fn mark_node(
+        &self,
+        node: &Rlp,
+        is_storage: bool,
+        reachable: &mut HashSet<Vec<u8>>,
+    ) -> Result<(), io::Error> {
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e));
+
+        match node.item_count().map_err(invalid)? {
+            // A branch node, the value of which is always empty since the keys
+            // of the tries are fixed-length.
+            17 => {
+                for i in 0..16 {
+                    self.mark_child(&node.at(i).map_err(invalid)?, is_storage, reachable)?;
+                }
+            }
+            2 => {
+                let path = node.at(0).and_then(|p| p.data()).map_err(invalid)?;
+                let is_leaf = path.first().map(|b| b >> 4 >= 2).unwrap_or_default();
+                let value = node.at(1).map_err(invalid)?;
+
+                if !is_leaf {
+                    self.mark_child(&value, is_storage, reachable)?;
+                } else if !is_storage {
+                    let account = Account::decode(value.data().map_err(invalid)?)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                    if account.storage_root != RLP_NULL {
+                        self.mark(account.storage_root.as_bytes(), true, reachable)?;
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// A child is either an inline node or the hash of a stored node.
+    fn mark_child(
+        &self,
+        child: &Rlp,
+        is_storage: bool,
+        reachable: &mut HashSet<Vec<u8>>,
+    ) -> Result<(), io::Error> {
+        if child.is_list() {
+            return self.mark_node(child, is_storage, reachable);
+        }
+
+        match child.data() {
+            Ok(hash) if !hash.is_empty() => self.mark(hash, is_storage, reachable),
+            _ => Ok(()),
+        }
+    }
+
     fn get_column(&self) -> &ColumnFamily {
         let category = map_category(self.category.into());
         self.db
@@ -190,3 +305,59 @@ fn rand_remove_list<T: Clone>(keys: Vec<&T>, num: usize) -> impl Iterator<Item =
 
     ret.into_iter()
 }
+
+#[cfg(test)]
+mod tests {
+    use core_db::RocksAdapter;
+    use protocol::types::{H160, H256, NIL_DATA};
+
+    use crate::{AccountChange, AccountDiff, StateDiff};
+
+    use super::*;
+
+    fn state_diff(range: std::ops::Range<u64>, balance: u64, modified: bool) -> StateDiff {
+        let mut diff = StateDiff::default();
+        for i in range {
+            let mut change = AccountChange {
+                balance: (balance + i).into(),
+                code_hash: NIL_DATA,
+                ..Default::default()
+            };
+            change.set_storage(H256::from_low_u64_be(i), H256::from_low_u64_be(balance + i));
+            let account = if modified {
+                AccountDiff::Modified(change)
+            } else {
+                AccountDiff::Created(change)
+            };
+            diff.insert(&H160::from_low_u64_be(i), account);
+        }
+        diff
+    }
+
+    #[test]
+    fn test_gc() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner_db =
+            Arc::new(RocksAdapter::new(dir.path(), Default::default()).unwrap()).inner_db();
+        let db = Arc::new(RocksTrieDB::new_evm(inner_db, 100));
+
+        let old_root = state_diff(0..20, 100, false)
+            .apply(Arc::clone(&db), RLP_NULL)
+            .unwrap();
+        let new_root = state_diff(0..10, 1000, true)
+            .apply(Arc::clone(&db), old_root)
+            .unwrap();
+        let state = StateDiff::diff(Arc::clone(&db), RLP_NULL, new_root).unwrap();
+
+        // Only the state of the new root is retained.
+        assert!(db.gc(&[new_root]).unwrap() > 0);
+        assert!(!db.contains(old_root.as_bytes()).unwrap());
+        assert_eq!(
+            StateDiff::diff(Arc::clone(&db), RLP_NULL, new_root).unwrap(),
+            state
+        );
+        assert_eq!(db.gc(&[new_root]).unwrap(), 0);
+
+        dir.close().unwrap();
+    }
+}
@@ -57,6 +57,17 @@ impl<DB: TrieDB> MPTTrie<DB> {
         )?))
     }
 
+    /// Iterate over the pairs of the key hash and the value, in the order of
+    /// the key hashes.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + '_ {
+        self.0.iter()
+    }
+
+    /// Insert the value by the key hash, such as the one yielded by `iter`.
+    pub fn insert_hashed(&mut self, key_hash: Vec<u8>, value: Vec<u8>) -> ProtocolResult<()> {
+        self.0.insert(key_hash, value).map_err(Into::into)
+    }
+
     pub fn commit(&mut self) -> ProtocolResult<MerkleRoot> {
         self.0
             .root()
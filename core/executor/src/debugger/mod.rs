@@ -66,7 +66,7 @@ impl EvmDebugger {
 
     pub fn exec(&mut self, number: u64, txs: Vec<SignedTransaction>) -> ExecResp {
         let mut backend = self.backend(number);
-        let res = AxonExecutor.test_exec(&mut backend, &txs, &[]);
+        let res = AxonExecutor::default().test_exec(&mut backend, &txs, &[]);
         self.state_root = res.state_root;
         res
     }
@@ -81,7 +81,7 @@ impl EvmDebugger {
         data: Vec<u8>,
     ) -> TxResp {
         let backend = self.backend(number);
-        AxonExecutor.call(&backend, MAX_BLOCK_GAS_LIMIT, from, to, value, data)
+        AxonExecutor::default().call(&backend, MAX_BLOCK_GAS_LIMIT, from, to, value, data)
     }
 
     fn backend(
@@ -5,42 +5,54 @@ mod precompiles;
 pub mod system_contract;
 #[cfg(test)]
 mod tests;
+mod tracer;
 mod utils;
 
 pub use crate::adapter::{
-    AxonExecutorApplyAdapter, AxonExecutorReadOnlyAdapter, MPTTrie, RocksTrieDB,
+    AccountChange, AccountDiff, AxonExecutorApplyAdapter, AxonExecutorReadOnlyAdapter, MPTTrie,
+    RocksTrieDB, StateDiff,
 };
+pub use crate::precompiles::set_precompile_gas_overrides;
 pub use crate::system_contract::{
-    is_call_system_script, is_system_contract_address_format,
+    is_call_system_script, is_system_contract_address_format, is_system_contract_mutation,
     metadata::{MetadataHandle, HARDFORK_INFO},
     DataProvider,
 };
+pub use crate::tracer::TraceBudget;
 pub use crate::utils::{code_address, decode_revert_msg, DefaultFeeAllocator, FeeInlet};
 
 use std::cell::RefCell;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 use arc_swap::ArcSwap;
 use common_config_parser::types::spec::HardforkName;
+use evm::backend::Apply;
 use evm::executor::stack::{MemoryStackState, PrecompileFn, StackExecutor, StackSubstateMetadata};
 use evm::CreateScheme;
 
 use common_merkle::TrieMerkle;
 use protocol::traits::{Backend, Executor, ExecutorAdapter};
 use protocol::types::{
-    logs_bloom, Config, ExecResp, SignedTransaction, TransactionAction, TxResp, ValidatorExtend,
-    H160, H256, RLP_NULL, U256,
+    logs_bloom, AccessedState, Bytes, Config, DisableableOpcode, DisabledOpcodes, ExecResp,
+    ExecutorLimits, ExitReason, ExitRevert, Log, MerkleRoot, Receipt, SignedTransaction, Tracer,
+    TransactionAction, TxResp, TxTrace, ValidatorExtend, H160, H256, RLP_NULL, U256,
 };
 
+use crate::adapter::{record_writes, AccessRecorder};
 use crate::precompiles::build_precompile_set;
 use crate::system_contract::{
     after_block_hook, before_block_hook, system_contract_dispatch,
     CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, HEADER_CELL_ROOT_KEY, METADATA_CONTRACT_ADDRESS,
     METADATA_ROOT_KEY,
 };
+use crate::tracer::{CallTracer, StructLogger};
+
+const CALL_SYSTEM_CONTRACT_REVERT: &str = "system contract is not callable in a simulation";
 
 lazy_static::lazy_static! {
     pub static ref FEE_ALLOCATOR: ArcSwap<Box<dyn FeeAllocate>> = ArcSwap::from_pointee(Box::new(DefaultFeeAllocator));
+    static ref DISABLED_OPCODES: ArcSwap<Vec<DisabledOpcodes>> = ArcSwap::from_pointee(Vec::new());
 }
 
 thread_local! {
@@ -48,6 +60,9 @@ thread_local! {
     pub(crate) static CURRENT_METADATA_ROOT: RefCell<H256> = RefCell::new(H256::default());
 }
 
+/// The account changes and logs of a successful transaction.
+type StateChanges = (Vec<Apply<Vec<(H256, H256)>>>, Vec<Log>);
+
 pub trait FeeAllocate: Sync + Send {
     fn allocate(
         &self,
@@ -59,7 +74,11 @@ pub trait FeeAllocate: Sync + Send {
 }
 
 #[derive(Default)]
-pub struct AxonExecutor;
+pub struct AxonExecutor {
+    limits:    ExecutorLimits,
+    // The hardfork flags pinned instead of the ones of the block being executed.
+    hardforks: Option<H256>,
+}
 
 impl Executor for AxonExecutor {
     // Used for query data API, this function will not modify the global state.
@@ -73,6 +92,22 @@ impl Executor for AxonExecutor {
         data: Vec<u8>,
     ) -> TxResp {
         self.init_local_system_contract_roots(backend);
+
+        // The system contracts run natively in a block, so a simulated call to one
+        // of their state-mutating methods reverts instead of touching their stores.
+        if to.map_or(false, |addr| is_system_contract_mutation(&addr, &data)) {
+            return TxResp {
+                exit_reason:  ExitReason::Revert(ExitRevert::Reverted),
+                ret:          CALL_SYSTEM_CONTRACT_REVERT.as_bytes().to_vec(),
+                remain_gas:   gas_limit,
+                gas_used:     0,
+                fee_cost:     U256::zero(),
+                logs:         vec![],
+                code_address: None,
+                removed:      false,
+            };
+        }
+
         let config = {
             let mut config = self.config();
             // run the gasometer in estimate mode
@@ -85,6 +120,8 @@ impl Executor for AxonExecutor {
         let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
 
         let (exit, res) = if let Some(addr) = &to {
+            // A call to an account without code returns empty output as in Ethereum,
+            // and the value is transferred only if the sender affords it.
             executor.transact_call(
                 from.unwrap_or_default(),
                 *addr,
@@ -125,6 +162,13 @@ impl Executor for AxonExecutor {
     }
 
     // Function execute returns exit_reason, ret_data and remain_gas.
+    //
+    // The transactions are executed in the block order before the Antlia
+    // hardfork. Since it, the system contract calls are executed ahead of the
+    // user transactions, so that a user transaction sees the metadata and image
+    // cell updates of the same block. The relative order of the system contract
+    // calls and of the user transactions is kept, and the responses and
+    // receipts are always in the block order.
     fn exec<Adapter: ExecutorAdapter>(
         &self,
         adapter: &mut Adapter,
@@ -133,23 +177,23 @@ impl Executor for AxonExecutor {
     ) -> ExecResp {
         let txs_len = txs.len();
         let block_number = adapter.block_number();
-        let mut res = Vec::with_capacity(txs_len);
-        let mut encode_receipts = Vec::with_capacity(txs_len);
+        let mut executed = Vec::with_capacity(txs_len);
         let (mut gas, mut fee) = (0u64, U256::zero());
         let precompiles = build_precompile_set();
         self.init_local_system_contract_roots(adapter);
+        let hardforks = self.hardforks();
         let config = self.config();
 
         // Execute system contracts before block hook.
         before_block_hook(adapter);
 
-        for tx in txs.iter() {
+        for (idx, tx) in Self::execution_order(txs, &hardforks) {
             adapter.set_gas_price(tx.transaction.unsigned.gas_price());
             adapter.set_origin(tx.sender);
 
             // Execute a transaction, if system contract dispatch return None, means the
             // transaction called EVM
-            let mut r = system_contract_dispatch(adapter, tx)
+            let mut r = system_contract_dispatch(adapter, tx, &hardforks)
                 .unwrap_or_else(|| Self::evm_exec(adapter, &config, &precompiles, tx));
 
             r.logs = adapter.take_logs();
@@ -158,11 +202,15 @@ impl Executor for AxonExecutor {
 
             let logs_bloom = logs_bloom(r.logs.iter());
             let receipt = tx.encode_receipt(&r, logs_bloom);
-            encode_receipts.push(receipt);
-
-            res.push(r);
+            executed.push((idx, r, receipt));
         }
 
+        executed.sort_unstable_by_key(|(idx, _, _)| *idx);
+        let (res, encode_receipts): (Vec<_>, Vec<_>) = executed
+            .into_iter()
+            .map(|(_, r, receipt)| (r, receipt))
+            .unzip();
+
         // Allocate collected fee for validators
         if !block_number.is_zero() {
             let alloc =
@@ -187,21 +235,11 @@ impl Executor for AxonExecutor {
 
         // self.update_system_contract_roots_for_external_module();
 
-        let receipt_root = if encode_receipts.is_empty() {
-            RLP_NULL
-        } else {
-            TrieMerkle::from_receipts(&encode_receipts)
-                .root_hash()
-                .unwrap_or_else(|err| {
-                    panic!("failed to calculate trie root hash for receipts since {err}")
-                })
-        };
-
         ExecResp {
-            state_root: new_state_root,
-            receipt_root,
-            gas_used: gas,
-            tx_resp: res,
+            state_root:   new_state_root,
+            receipt_root: receipts_root(&encode_receipts),
+            gas_used:     gas,
+            tx_resp:      res,
         }
     }
 }
@@ -291,11 +329,139 @@ fn test_receipt() {
 }
 
 impl AxonExecutor {
+    pub fn new(limits: ExecutorLimits) -> Self {
+        AxonExecutor {
+            limits,
+            hardforks: None,
+        }
+    }
+
+    /// Execute with the hardforks enabled in the flags instead of the ones of
+    /// the block being executed.
+    pub fn with_hardforks(mut self, flags: H256) -> Self {
+        self.hardforks = Some(flags);
+        self
+    }
+
+    fn hardforks(&self) -> H256 {
+        self.hardforks.unwrap_or_else(|| **HARDFORK_INFO.load())
+    }
+
     pub fn evm_exec<Adapter: ExecutorAdapter>(
         adapter: &mut Adapter,
         config: &Config,
         precompiles: &BTreeMap<H160, PrecompileFn>,
         tx: &SignedTransaction,
+    ) -> TxResp {
+        Self::evm_exec_inner(adapter, config, precompiles, tx, None)
+    }
+
+    /// Execute a transaction as `evm_exec` does, and record the accounts and
+    /// storage slots accessed by it.
+    pub fn evm_exec_with_access<Adapter: ExecutorAdapter>(
+        adapter: &mut Adapter,
+        config: &Config,
+        precompiles: &BTreeMap<H160, PrecompileFn>,
+        tx: &SignedTransaction,
+    ) -> (TxResp, AccessedState) {
+        let mut accessed = AccessedState::default();
+        let resp = Self::evm_exec_inner(adapter, config, precompiles, tx, Some(&mut accessed));
+        (resp, accessed)
+    }
+
+    /// Execute the user transactions in order without committing the state,
+    /// and record the accounts and storage slots accessed by each of them.
+    ///
+    /// The system contract calls write the system contract databases directly,
+    /// so they are skipped with `None` returned, and so are the block hooks.
+    pub fn trace_access<Adapter: ExecutorAdapter>(
+        &self,
+        adapter: &mut Adapter,
+        txs: &[SignedTransaction],
+    ) -> Vec<Option<(TxResp, AccessedState)>> {
+        let precompiles = build_precompile_set();
+        self.init_local_system_contract_roots(adapter);
+        let config = self.config();
+
+        let mut ret = Vec::with_capacity(txs.len());
+        for tx in txs.iter() {
+            if is_call_system_script(tx.transaction.unsigned.action()).unwrap_or(true) {
+                ret.push(None);
+                continue;
+            }
+
+            adapter.set_gas_price(tx.transaction.unsigned.gas_price());
+            adapter.set_origin(tx.sender);
+
+            let (mut r, accessed) = Self::evm_exec_with_access(adapter, &config, &precompiles, tx);
+            r.logs = adapter.take_logs();
+            ret.push(Some((r, accessed)));
+        }
+
+        ret
+    }
+
+    /// Execute the user transactions in order without committing the state as
+    /// `trace_access` does, and trace each of them with the tracer. The budget
+    /// is shared by the whole block, the struct logs beyond its steps are
+    /// truncated and `None` is returned once its bytes are exceeded, without
+    /// executing the rest of the transactions.
+    pub fn trace_txs<Adapter: ExecutorAdapter>(
+        &self,
+        adapter: &mut Adapter,
+        txs: &[SignedTransaction],
+        tracer: Tracer,
+        budget: TraceBudget,
+    ) -> Option<Vec<Option<TxTrace>>> {
+        let precompiles = build_precompile_set();
+        self.init_local_system_contract_roots(adapter);
+        let config = self.config();
+        let logger = StructLogger::new(budget);
+        let mut call_tracer = CallTracer::new(budget.bytes);
+
+        let mut ret = Vec::with_capacity(txs.len());
+        for tx in txs.iter() {
+            if is_call_system_script(tx.transaction.unsigned.action()).unwrap_or(true) {
+                ret.push(None);
+                continue;
+            }
+
+            adapter.set_gas_price(tx.transaction.unsigned.gas_price());
+            adapter.set_origin(tx.sender);
+
+            let trace = match tracer {
+                Tracer::Struct => {
+                    let (mut calls, mut steps) = (logger.clone(), logger.clone());
+                    let resp = evm::tracing::using(&mut calls, || {
+                        evm_runtime::tracing::using(&mut steps, || {
+                            Self::evm_exec(adapter, &config, &precompiles, tx)
+                        })
+                    });
+                    TxTrace::Struct(logger.take_trace(&resp))
+                }
+                Tracer::Call => {
+                    let resp = evm::tracing::using(&mut call_tracer, || {
+                        Self::evm_exec(adapter, &config, &precompiles, tx)
+                    });
+                    TxTrace::Call(call_tracer.take_trace(tx, &resp))
+                }
+            };
+            if logger.is_exceeded() || call_tracer.is_exceeded() {
+                return None;
+            }
+            adapter.take_logs();
+            ret.push(Some(trace));
+        }
+
+        Some(ret)
+    }
+
+    fn evm_exec_inner<Adapter: ExecutorAdapter>(
+        adapter: &mut Adapter,
+        config: &Config,
+        precompiles: &BTreeMap<H160, PrecompileFn>,
+        tx: &SignedTransaction,
+        accessed: Option<&mut AccessedState>,
     ) -> TxResp {
         // Deduct pre-pay gas
         let sender = tx.sender;
@@ -309,52 +475,30 @@ impl AxonExecutor {
         account.balance = account.balance.saturating_sub(prepay_gas);
         adapter.save_account(&sender, &account);
 
-        let metadata = StackSubstateMetadata::new(gas_limit.as_u64(), config);
-        let mut executor = StackExecutor::new_with_precompiles(
-            MemoryStackState::new(metadata, adapter),
-            config,
-            precompiles,
-        );
-
-        let access_list = tx
-            .transaction
-            .unsigned
-            .access_list()
-            .into_iter()
-            .map(|x| (x.address, x.storage_keys))
-            .collect::<Vec<_>>();
-
-        let (exit, res) = match tx.transaction.unsigned.action() {
-            TransactionAction::Call(addr) => executor.transact_call(
-                tx.sender,
-                *addr,
-                *tx.transaction.unsigned.value(),
-                tx.transaction.unsigned.data().to_vec(),
-                gas_limit.as_u64(),
-                access_list,
-            ),
-            TransactionAction::Create => executor.transact_create(
-                tx.sender,
-                *tx.transaction.unsigned.value(),
-                tx.transaction.unsigned.data().to_vec(),
-                gas_limit.as_u64(),
-                access_list,
-            ),
+        let (exit, res, remained_gas, used_gas, changes) = match accessed {
+            Some(accessed) => {
+                let recorder = AccessRecorder::new(&*adapter);
+                let ret = Self::transact(&recorder, config, precompiles, tx);
+                *accessed = recorder.into_accessed();
+
+                // The gas of the sender is charged out of the EVM.
+                accessed.read_account(sender);
+                accessed.write_account(sender);
+                if let Some((values, _)) = &ret.4 {
+                    record_writes(accessed, values);
+                }
+                ret
+            }
+            None => Self::transact(&*adapter, config, precompiles, tx),
         };
 
-        let remained_gas = executor.gas();
-        let used_gas = executor.used_gas();
-
-        let code_addr = if tx.transaction.unsigned.action() == &TransactionAction::Create
-            && exit.is_succeed()
-        {
+        let code_addr = if tx.transaction.unsigned.is_create() && exit.is_succeed() {
             Some(code_address(&tx.sender, &old_nonce))
         } else {
             None
         };
 
-        if exit.is_succeed() {
-            let (values, logs) = executor.into_state().deconstruct();
+        if let Some((values, logs)) = changes {
             adapter.apply(values, logs, true);
         }
 
@@ -388,6 +532,79 @@ impl AxonExecutor {
         }
     }
 
+    /// Run a transaction in the EVM, and return the changes to apply if it
+    /// succeeds.
+    fn transact<B: Backend>(
+        backend: &B,
+        config: &Config,
+        precompiles: &BTreeMap<H160, PrecompileFn>,
+        tx: &SignedTransaction,
+    ) -> (ExitReason, Vec<u8>, u64, u64, Option<StateChanges>) {
+        let gas_limit = tx.transaction.unsigned.gas_limit().as_u64();
+        let metadata = StackSubstateMetadata::new(gas_limit, config);
+        let mut executor = StackExecutor::new_with_precompiles(
+            MemoryStackState::new(metadata, backend),
+            config,
+            precompiles,
+        );
+
+        let access_list = tx
+            .transaction
+            .unsigned
+            .access_list()
+            .into_iter()
+            .map(|x| (x.address, x.storage_keys))
+            .collect::<Vec<_>>();
+
+        let (exit, res) = match tx.transaction.unsigned.action() {
+            TransactionAction::Call(addr) => executor.transact_call(
+                tx.sender,
+                *addr,
+                *tx.transaction.unsigned.value(),
+                tx.transaction.unsigned.data().to_vec(),
+                gas_limit,
+                access_list,
+            ),
+            TransactionAction::Create => executor.transact_create(
+                tx.sender,
+                *tx.transaction.unsigned.value(),
+                tx.transaction.unsigned.data().to_vec(),
+                gas_limit,
+                access_list,
+            ),
+        };
+
+        let remained_gas = executor.gas();
+        let used_gas = executor.used_gas();
+
+        // The changes are collected since they may borrow the backend otherwise.
+        let changes = exit.is_succeed().then(|| {
+            let (values, logs) = executor.into_state().deconstruct();
+            let values = values
+                .into_iter()
+                .map(|apply| match apply {
+                    Apply::Modify {
+                        address,
+                        basic,
+                        code,
+                        storage,
+                        reset_storage,
+                    } => Apply::Modify {
+                        address,
+                        basic,
+                        code,
+                        storage: storage.into_iter().collect(),
+                        reset_storage,
+                    },
+                    Apply::Delete { address } => Apply::Delete { address },
+                })
+                .collect();
+            (values, logs.into_iter().collect())
+        });
+
+        (exit, res, remained_gas, used_gas, changes)
+    }
+
     /// The `exec()` function is run in `tokio::task::block_in_place()` and all
     /// the read or write operations are in the scope of exec function. The
     /// thread context is not switched during exec function.
@@ -402,10 +619,29 @@ impl AxonExecutor {
         });
     }
 
+    fn execution_order<'a>(
+        txs: &'a [SignedTransaction],
+        hardforks: &H256,
+    ) -> Vec<(usize, &'a SignedTransaction)> {
+        let mut order = txs.iter().enumerate().collect::<Vec<_>>();
+        if is_hardfork_enabled(hardforks, HardforkName::Antlia) {
+            // The sort is stable, so the relative order is kept in each group.
+            order.sort_by_key(|(_, tx)| {
+                !is_call_system_script(tx.transaction.unsigned.action()).unwrap_or(false)
+            });
+        }
+        order
+    }
+
+    /// The gas refunds follow EIP-3529 of London, which caps the refund at a
+    /// fifth of the gas used and removes the refund of `SELFDESTRUCT`. The
+    /// London rules apply since the genesis of Axon, so they are not gated by a
+    /// hardfork.
     fn config(&self) -> Config {
         let mut evm_config = Config::london();
+        let hardforks = self.hardforks();
         let create_contract_limit = {
-            if enable_hardfork(HardforkName::Andromeda) {
+            if is_hardfork_enabled(&hardforks, HardforkName::Andromeda) {
                 let handle = MetadataHandle::new(CURRENT_METADATA_ROOT.with(|r| *r.borrow()));
                 let consensus_config = handle.get_consensus_config().unwrap();
                 Some(consensus_config.max_contract_limit as usize)
@@ -415,6 +651,10 @@ impl AxonExecutor {
             }
         };
         evm_config.create_contract_limit = create_contract_limit;
+        evm_config.stack_limit = self.limits.stack_limit as usize;
+        evm_config.call_stack_limit = self.limits.call_stack_limit as usize;
+        evm_config.memory_limit = self.limits.memory_limit as usize;
+        disable_opcodes(&mut evm_config, &DISABLED_OPCODES.load(), &hardforks);
         evm_config
     }
 
@@ -439,7 +679,7 @@ impl AxonExecutor {
 
             // Execute a transaction, if system contract dispatch return None, means the
             // transaction called EVM
-            let mut r = system_contract_dispatch(adapter, tx)
+            let mut r = system_contract_dispatch(adapter, tx, &self.hardforks())
                 .unwrap_or_else(|| Self::evm_exec(adapter, &config, &precompiles, tx));
 
             r.logs = adapter.take_logs();
@@ -472,34 +712,86 @@ impl AxonExecutor {
         // commit changes by all txs included in this block only once
         let new_state_root = adapter.commit();
 
-        let receipt_root = if encode_receipts.is_empty() {
-            RLP_NULL
-        } else {
-            TrieMerkle::from_receipts(&encode_receipts)
-                .root_hash()
-                .unwrap_or_else(|err| {
-                    panic!("failed to calculate trie root hash for receipts since {err}")
-                })
-        };
-
         ExecResp {
-            state_root: new_state_root,
-            receipt_root,
-            gas_used: gas,
-            tx_resp: res,
+            state_root:   new_state_root,
+            receipt_root: receipts_root(&encode_receipts),
+            gas_used:     gas,
+            tx_resp:      res,
         }
     }
 }
 
+/// Calculate the receipts root of a block in the same way as the block
+/// execution, so that the `receipts_root` in the header can be verified
+/// independently. A receipt is encoded with the type of its transaction, so
+/// the transactions of the block are required as well.
+pub fn compute_receipts_root(txs: &[SignedTransaction], receipts: &[Receipt]) -> MerkleRoot {
+    receipts_root(&encode_receipts(txs, receipts))
+}
+
+/// Encode the receipts of a block as the leaves of its receipts trie, which
+/// are the typed receipt envelopes of the transactions.
+pub fn encode_receipts(txs: &[SignedTransaction], receipts: &[Receipt]) -> Vec<Bytes> {
+    txs.iter()
+        .zip(receipts.iter())
+        .map(|(tx, receipt)| {
+            let resp = TxResp {
+                exit_reason: receipt.ret.clone(),
+                gas_used: receipt.used_gas.low_u64(),
+                logs: receipt.logs.clone(),
+                ..Default::default()
+            };
+            tx.encode_receipt(&resp, receipt.logs_bloom)
+        })
+        .collect()
+}
+
+fn receipts_root(encode_receipts: &[Bytes]) -> MerkleRoot {
+    if encode_receipts.is_empty() {
+        return RLP_NULL;
+    }
+
+    TrieMerkle::from_receipts(encode_receipts)
+        .root_hash()
+        .unwrap_or_else(|err| panic!("failed to calculate trie root hash for receipts since {err}"))
+}
+
 pub fn is_transaction_call(action: &TransactionAction, addr: &H160) -> bool {
     action == &TransactionAction::Call(*addr)
 }
 
 pub fn enable_hardfork(name: HardforkName) -> bool {
-    let latest_hardfork_info = &**HARDFORK_INFO.load();
+    is_hardfork_enabled(&HARDFORK_INFO.load(), name)
+}
+
+pub fn set_disabled_opcodes(disabled: Vec<DisabledOpcodes>) {
+    DISABLED_OPCODES.store(Arc::new(disabled));
+}
+
+/// Turn off the opcodes disabled by the hardforks enabled in the flags.
+pub(crate) fn disable_opcodes(config: &mut Config, disabled: &[DisabledOpcodes], flags: &H256) {
+    for opcode in disabled
+        .iter()
+        .filter(|d| flags & &d.hardfork == d.hardfork)
+        .flat_map(|d| d.opcodes.iter())
+    {
+        match opcode {
+            DisableableOpcode::DelegateCall => config.has_delegate_call = false,
+            DisableableOpcode::Create2 => config.has_create2 = false,
+            DisableableOpcode::Revert => config.has_revert = false,
+            DisableableOpcode::ChainId => config.has_chain_id = false,
+            DisableableOpcode::SelfBalance => config.has_self_balance = false,
+            DisableableOpcode::ExtCodeHash => config.has_ext_code_hash = false,
+            DisableableOpcode::BaseFee => config.has_base_fee = false,
+        }
+    }
+}
+
+/// Whether the hardfork is enabled in the flags of the hardfork info.
+pub(crate) fn is_hardfork_enabled(flags: &H256, name: HardforkName) -> bool {
     let enable_flag = H256::from_low_u64_be((name as u64).to_be());
 
-    latest_hardfork_info & &enable_flag == enable_flag
+    flags & &enable_flag == enable_flag
 }
 
 #[cfg(test)]
@@ -1,7 +1,7 @@
 use evm::executor::stack::{PrecompileFailure, PrecompileOutput};
 use evm::{Context, ExitError, ExitSucceed};
 
-use protocol::{ckb_blake2b_256, types::H160};
+use protocol::{hash::ckb_blake2b, types::H160};
 
 use crate::err;
 use crate::precompiles::{axon_precompile_address, PrecompileContract};
@@ -29,7 +29,7 @@ impl PrecompileContract for CkbBlake2b {
         Ok((
             PrecompileOutput {
                 exit_status: ExitSucceed::Returned,
-                output:      ckb_blake2b_256(input).to_vec(),
+                output:      ckb_blake2b(input).to_vec(),
             },
             gas,
         ))
@@ -5,7 +5,7 @@ use evm::{Context, ExitError, ExitSucceed};
 use protocol::types::H160;
 
 use crate::err;
-use crate::precompiles::{eip_precompile_address, read_point, PrecompileContract};
+use crate::precompiles::{eip_precompile_address, read_point, GasSchedule, PrecompileContract};
 
 #[derive(Default)]
 pub struct EcAdd;
@@ -50,6 +50,6 @@ impl PrecompileContract for EcAdd {
     }
 
     fn gas_cost(_input: &[u8]) -> u64 {
-        Self::MIN_GAS
+        GasSchedule::active().ec_add
     }
 }
@@ -5,7 +5,9 @@ use evm::{Context, ExitError, ExitSucceed};
 use protocol::types::H160;
 
 use crate::err;
-use crate::precompiles::{eip_precompile_address, read_fr, read_point, PrecompileContract};
+use crate::precompiles::{
+    eip_precompile_address, read_fr, read_point, GasSchedule, PrecompileContract,
+};
 
 #[derive(Default)]
 pub struct EcMul;
@@ -50,6 +52,6 @@ impl PrecompileContract for EcMul {
     }
 
     fn gas_cost(_input: &[u8]) -> u64 {
-        Self::MIN_GAS
+        GasSchedule::active().ec_mul
     }
 }
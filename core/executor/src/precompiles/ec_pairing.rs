@@ -7,7 +7,7 @@ use evm::{Context, ExitError, ExitSucceed};
 use protocol::types::{H160, U256};
 
 use crate::err;
-use crate::precompiles::{eip_precompile_address, PrecompileContract};
+use crate::precompiles::{eip_precompile_address, GasSchedule, PrecompileContract};
 
 #[derive(Default)]
 pub struct EcPairing;
@@ -31,7 +31,7 @@ impl PrecompileContract for EcPairing {
                     exit_status: ExitSucceed::Returned,
                     output:      res.to_vec(),
                 },
-                Self::MIN_GAS,
+                GasSchedule::active().ec_pairing_base,
             ));
         }
 
@@ -94,15 +94,19 @@ impl PrecompileContract for EcPairing {
     }
 
     fn gas_cost(input: &[u8]) -> u64 {
-        let elements = (input.len() / Self::GROUP_ARGS_LEN) as u64;
-        elements * Self::GAS_PER_PAIRING + Self::MIN_GAS
+        Self::gas_cost_with(input, &GasSchedule::active())
     }
 }
 
 impl EcPairing {
-    const GAS_PER_PAIRING: u64 = 34_000;
+    pub(crate) const GAS_PER_PAIRING: u64 = 34_000;
     const GROUP_ARGS_LEN: usize = 192;
 
+    pub(crate) fn gas_cost_with(input: &[u8], schedule: &GasSchedule) -> u64 {
+        let elements = (input.len() / Self::GROUP_ARGS_LEN) as u64;
+        elements * schedule.ec_pairing_point + schedule.ec_pairing_base
+    }
+
     fn index_range(i: usize, offset: usize) -> Range<usize> {
         let start = i * Self::GROUP_ARGS_LEN + offset;
         start..(start + 32)
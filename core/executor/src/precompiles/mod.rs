@@ -1,5 +1,5 @@
 mod blake2_f;
-mod call_ckb_vm;
+pub(crate) mod call_ckb_vm;
 mod ckb_blake2b;
 mod ec_add;
 mod ec_mul;
@@ -18,18 +18,25 @@ mod sha256;
 mod tests;
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
+use arc_swap::ArcSwap;
 use bn::{AffineG1, Fq, Fr, Group, G1};
 use evm::executor::stack::{PrecompileFailure, PrecompileFn, PrecompileOutput};
 use evm::{Context, ExitError};
 
-use protocol::types::H160;
+use protocol::types::{ModExpPricing, PrecompileGasOverride, H160, H256};
 
 use crate::precompiles::{
     blake2_f::Blake2F, call_ckb_vm::CallCkbVM, ckb_blake2b::CkbBlake2b, ec_add::EcAdd,
     ec_mul::EcMul, ec_pairing::EcPairing, ecrecover::EcRecover, identity::Identity, modexp::ModExp,
     ripemd160::Ripemd160, sha256::Sha256,
 };
+use crate::HARDFORK_INFO;
+
+lazy_static::lazy_static! {
+    static ref GAS_OVERRIDES: ArcSwap<Vec<PrecompileGasOverride>> = ArcSwap::from_pointee(Vec::new());
+}
 
 #[macro_export]
 macro_rules! err {
@@ -78,6 +85,64 @@ trait PrecompileContract {
     fn gas_cost(input: &[u8]) -> u64;
 }
 
+/// Set the gas costs of the precompiles repriced by the hardforks.
+pub fn set_precompile_gas_overrides(overrides: Vec<PrecompileGasOverride>) {
+    GAS_OVERRIDES.store(Arc::new(overrides));
+}
+
+/// The gas costs of the precompiles which can be repriced by a hardfork.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct GasSchedule {
+    pub modexp_pricing:   ModExpPricing,
+    pub modexp_min:       u64,
+    pub ec_add:           u64,
+    pub ec_mul:           u64,
+    pub ec_pairing_base:  u64,
+    pub ec_pairing_point: u64,
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        GasSchedule {
+            modexp_pricing:   ModExpPricing::default(),
+            modexp_min:       ModExp::MIN_GAS,
+            ec_add:           EcAdd::MIN_GAS,
+            ec_mul:           EcMul::MIN_GAS,
+            ec_pairing_base:  EcPairing::MIN_GAS,
+            ec_pairing_point: EcPairing::GAS_PER_PAIRING,
+        }
+    }
+}
+
+impl GasSchedule {
+    /// The schedule of the block being executed.
+    pub(crate) fn active() -> Self {
+        Self::with_hardforks(&HARDFORK_INFO.load())
+    }
+
+    /// The schedule with the overrides of the hardforks enabled in the flags.
+    pub(crate) fn with_hardforks(flags: &H256) -> Self {
+        Self::with_overrides(&GAS_OVERRIDES.load(), flags)
+    }
+
+    pub(crate) fn with_overrides(overrides: &[PrecompileGasOverride], flags: &H256) -> Self {
+        let mut schedule = GasSchedule::default();
+        for o in overrides.iter() {
+            if flags & &o.hardfork != o.hardfork {
+                continue;
+            }
+
+            schedule.modexp_pricing = o.modexp_pricing.unwrap_or(schedule.modexp_pricing);
+            schedule.modexp_min = o.modexp_min.unwrap_or(schedule.modexp_min);
+            schedule.ec_add = o.ec_add.unwrap_or(schedule.ec_add);
+            schedule.ec_mul = o.ec_mul.unwrap_or(schedule.ec_mul);
+            schedule.ec_pairing_base = o.ec_pairing_base.unwrap_or(schedule.ec_pairing_base);
+            schedule.ec_pairing_point = o.ec_pairing_point.unwrap_or(schedule.ec_pairing_point);
+        }
+        schedule
+    }
+}
+
 const fn eip_precompile_address(addr: u8) -> H160 {
     H160([
         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
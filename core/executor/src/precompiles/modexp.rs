@@ -6,10 +6,10 @@ use evm::{Context, ExitError, ExitSucceed};
 use rug::ops::Pow;
 use rug::{integer::Order, Integer};
 
-use protocol::types::H160;
+use protocol::types::{ModExpPricing, H160};
 
 use crate::err;
-use crate::precompiles::{eip_precompile_address, PrecompileContract};
+use crate::precompiles::{eip_precompile_address, GasSchedule, PrecompileContract};
 
 #[derive(Default)]
 pub struct ModExp;
@@ -78,12 +78,20 @@ impl PrecompileContract for ModExp {
     }
 
     fn gas_cost(input: &[u8]) -> u64 {
+        Self::gas_cost_with(input, &GasSchedule::active())
+    }
+}
+
+impl ModExp {
+    pub(crate) fn gas_cost_with(input: &[u8], schedule: &GasSchedule) -> u64 {
         let base_size = get_data(input, 0, 32);
         let modulo_size = get_data(input, 64, 32);
 
+        let min_gas = schedule.modexp_min;
+
         // multiplication_complexity always zero
         if base_size == 0 && modulo_size == 0 {
-            return Self::MIN_GAS;
+            return min_gas;
         }
 
         let exponent_size = get_data(input, 32, 32);
@@ -104,13 +112,18 @@ impl PrecompileContract for ModExp {
             )
         };
 
-        let multiplication_complexity = multiplication_complexity(base_size, modulo_size);
-
         let iterator_count = iterator_count(exponent_size, exponent);
 
-        let dynamic_gas = multiplication_complexity * iterator_count / 3u64;
+        let dynamic_gas = match schedule.modexp_pricing {
+            ModExpPricing::Eip198 => {
+                eip198_multiplication_complexity(base_size, modulo_size) * iterator_count / 20u64
+            }
+            ModExpPricing::Eip2565 => {
+                multiplication_complexity(base_size, modulo_size) * iterator_count / 3u64
+            }
+        };
         dynamic_gas
-            .max(Integer::from(Self::MIN_GAS))
+            .max(Integer::from(min_gas))
             .saturating_as::<u64>()
     }
 }
@@ -191,6 +204,19 @@ fn multiplication_complexity(b_size: Integer, m_size: Integer) -> Integer {
     a.pow(2)
 }
 
+fn eip198_multiplication_complexity(b_size: Integer, m_size: Integer) -> Integer {
+    let x = b_size.max(m_size);
+    if x <= 64 {
+        x.pow(2)
+    } else if x <= 1024 {
+        let a: Integer = x.clone().pow(2) / 4;
+        a + 96 * x - 3072
+    } else {
+        let a: Integer = x.clone().pow(2) / 16;
+        a + 480 * x - 199_680
+    }
+}
+
 fn iterator_count(e_size: Integer, exponent: Integer) -> u64 {
     let iter_count = if e_size <= 32 && exponent == Integer::ZERO {
         0
@@ -1,11 +1,17 @@
 use evm::Context;
 use sha2::Digest;
 
-use protocol::{ckb_blake2b_256, codec::hex_decode, rand::random, types::U256};
+use common_config_parser::types::spec::HardforkName;
+use protocol::{
+    codec::hex_decode,
+    hash::ckb_blake2b,
+    rand::random,
+    types::{ModExpPricing, PrecompileGasOverride, H256, U256},
+};
 
 use crate::precompiles::{
-    Blake2F, CkbBlake2b, EcAdd, EcMul, EcPairing, EcRecover, Identity, ModExp, PrecompileContract,
-    Ripemd160, Sha256,
+    set_precompile_gas_overrides, Blake2F, CkbBlake2b, EcAdd, EcMul, EcPairing, EcRecover,
+    GasSchedule, Identity, ModExp, PrecompileContract, Ripemd160, Sha256,
 };
 
 macro_rules! test_precompile {
@@ -52,7 +58,7 @@ fn test_sha256() {
 #[test]
 fn test_ckb_blake2b() {
     let input = rand_bytes(100);
-    let output = ckb_blake2b_256(&input);
+    let output = ckb_blake2b(&input);
 
     test_precompile!(CkbBlake2b, &input, output, 108);
 }
@@ -193,6 +199,64 @@ fn test_ec_pair() {
     test_precompile!(EcPairing, input, output, 79000);
 }
 
+#[test]
+fn test_ec_pair_repriced_by_hardfork() {
+    set_precompile_gas_overrides(vec![PrecompileGasOverride {
+        hardfork: HardforkName::Andromeda.flag(),
+        ec_pairing_base: Some(100_000),
+        ec_pairing_point: Some(80_000),
+        ..Default::default()
+    }]);
+    let before = H256::zero();
+    let after = HardforkName::Andromeda.flag();
+
+    let input = &hex_decode("089142debb13c461f61523586a60732d8b69c5b38a3380a74da7b2961d867dbf2d5fc7bbc013c16d7945f190b232eacc25da675c0eb093fe6b9f1b4b4e107b3629f2c1dbcc614745f242077001ec9edd475acdab9ab435770d456bd22bbd2abf268683f9b1be0bde4508e2e25e51f6b44da3546e87524337d506fd03c4ff7ce01851abe58ef4e08916bec8034ca62c04cd08340ab6cc525e61706340926221651b71422869c92e49465200ca19033a8aa425f955be3d8329c4475503e45c00e1").unwrap();
+    let gas_at =
+        |flags: &H256| EcPairing::gas_cost_with(input, &GasSchedule::with_hardforks(flags));
+    assert_eq!(gas_at(&before), 79_000);
+    assert_eq!(gas_at(&after), 180_000);
+
+    // The hardfork is not activated at the executing block.
+    let (_, gas) = EcPairing::exec_fn(input, Some(79_000), &mock_context(), false).unwrap();
+    assert_eq!(gas, 79_000);
+
+    set_precompile_gas_overrides(Vec::new());
+}
+
+/// The MODEXP is priced by EIP-198 since the genesis and by EIP-2565 since the
+/// hardfork.
+#[test]
+fn test_modexp_pricing_by_hardfork() {
+    let overrides = vec![
+        PrecompileGasOverride {
+            hardfork: HardforkName::None.flag(),
+            modexp_pricing: Some(ModExpPricing::Eip198),
+            ..Default::default()
+        },
+        PrecompileGasOverride {
+            hardfork: HardforkName::Andromeda.flag(),
+            modexp_pricing: Some(ModExpPricing::Eip2565),
+            ..Default::default()
+        },
+    ];
+    let input = &hex_decode(
+        "0000000000000000000000000000000000000000000000000000000000000001\
+			0000000000000000000000000000000000000000000000000000000000000020\
+			0000000000000000000000000000000000000000000000000000000000000020\
+			03\
+			fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e\
+			fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+    )
+    .unwrap();
+    let gas_at = |flags: &H256| {
+        ModExp::gas_cost_with(input, &GasSchedule::with_overrides(&overrides, flags))
+    };
+    // 32 ^ 2 * 255 / 20
+    assert_eq!(gas_at(&H256::zero()), 13_056);
+    // (32 / 8) ^ 2 * 255 / 3
+    assert_eq!(gas_at(&HardforkName::Andromeda.flag()), 1_360);
+}
+
 #[test]
 fn test_blake2f() {
     let input = &hex_decode("0000000048c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001").unwrap();
@@ -2,8 +2,9 @@ mod abi;
 mod store;
 
 pub use abi::ckb_light_client_abi;
+pub use store::CkbLightClientStore;
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use ethers::abi::AbiDecode;
 
@@ -12,13 +13,19 @@ use protocol::trie::Trie as _;
 use protocol::types::{SignedTransaction, TxResp, H160, H256};
 use protocol::ProtocolResult;
 
-use crate::system_contract::ckb_light_client::store::CkbLightClientStore;
 use crate::system_contract::utils::{succeed_resp, update_states};
 use crate::system_contract::{system_contract_address, SystemContract};
 use crate::{exec_try, system_contract_struct, CURRENT_HEADER_CELL_ROOT};
 
 pub const CKB_LIGHT_CLIENT_CONTRACT_ADDRESS: H160 = system_contract_address(0x2);
 static ALLOW_READ: AtomicBool = AtomicBool::new(false);
+static HEADER_RETENTION: AtomicU64 = AtomicU64::new(0);
+
+/// Keep the headers of the recent `blocks` CKB blocks only, the older ones are
+/// pruned on update. The cells are kept. 0 means no pruning.
+pub fn set_header_retention(blocks: u64) {
+    HEADER_RETENTION.store(blocks, Ordering::Relaxed);
+}
 
 system_contract_struct!(CkbLightClientContract);
 
@@ -52,7 +59,7 @@ impl<Adapter: ExecutorAdapter + ApplyBackend> SystemContract<Adapter>
             }
             ckb_light_client_abi::CkbLightClientContractCalls::Update(data) => {
                 exec_try!(
-                    store.update(data),
+                    store.update(data, HEADER_RETENTION.load(Ordering::Relaxed)),
                     gas_limit,
                     "[ckb light client] update error:"
                 );
@@ -10,6 +10,8 @@ use crate::system_contract::{
 };
 use crate::{adapter::RocksTrieDB, MPTTrie, CURRENT_HEADER_CELL_ROOT};
 
+const HEADER_NUMBER_INDEX_PREFIX: &[u8] = b"header_number";
+
 /// The CKB light client store does not follow the storage layout of EVM smart
 /// contract. It use MPT called HeaderCell MPT with the following layout:
 /// | key                   | value                    |
@@ -18,6 +20,11 @@ use crate::{adapter::RocksTrieDB, MPTTrie, CURRENT_HEADER_CELL_ROOT};
 /// | ...                   | ...                      |
 /// | CellOutpoint.encode() | `CellInfo.encode()`      |
 /// | ...                   | ...                      |
+/// | "header_number" + BE  | CKB Header Hash          |
+/// | ...                   | ...                      |
+///
+/// The header number index is saved only with a header retention, see
+/// `set_header_retention`.
 ///
 /// All these data are stored in a the `c10` column family of RocksDB, and the
 /// root of the HeaderCell MPT is stored in the storage MPT of the CKB light
@@ -71,17 +78,50 @@ impl CkbLightClientStore {
         Ok(CkbLightClientStore { trie })
     }
 
-    pub fn update(&mut self, data: ckb_light_client_abi::UpdateCall) -> ProtocolResult<()> {
+    /// Save the headers. If the retention is not 0, the header `retention`
+    /// blocks before a saved one is pruned, which is found by the index of the
+    /// header numbers saved along with the headers.
+    pub fn update(
+        &mut self,
+        data: ckb_light_client_abi::UpdateCall,
+        retention: u64,
+    ) -> ProtocolResult<()> {
         for header in data.headers {
             self.save_header(&header)?;
+
+            if retention != 0 {
+                self.insert_number_index(header.number, &header.block_hash)?;
+                if let Some(number) = header.number.checked_sub(retention) {
+                    self.prune_header(number)?;
+                }
+            }
         }
 
         self.commit()
     }
 
+    /// Remove the headers from the highest one, the numbers of them must be
+    /// consecutive and descending.
     pub fn rollback(&mut self, data: ckb_light_client_abi::RollbackCall) -> ProtocolResult<()> {
+        let mut last = None;
         for block_hash in data.block_hashes {
+            let number = match self.get_header(&block_hash)? {
+                Some(header) => header.number,
+                None => {
+                    let content = format!("header {} not found", hex_encode(block_hash));
+                    return Err(SystemScriptError::RemoveHeader(content).into());
+                }
+            };
+
+            if let Some(last) = last {
+                if number.checked_add(1) != Some(last) {
+                    return Err(SystemScriptError::RollbackGap { last, number }.into());
+                }
+            }
+
             self.remove_header(&block_hash)?;
+            self.remove_number_index(number, &block_hash)?;
+            last = Some(number);
         }
 
         self.commit()
@@ -126,6 +166,39 @@ impl CkbLightClientStore {
             .map_err(Into::into)
     }
 
+    fn insert_number_index(&mut self, number: u64, block_hash: &[u8]) -> ProtocolResult<()> {
+        self.trie
+            .insert(number_index_key(number), block_hash.to_vec())
+            .map_err(|e| SystemScriptError::InsertHeader(e.to_string()).into())
+    }
+
+    fn remove_number_index(&mut self, number: u64, block_hash: &[u8]) -> ProtocolResult<()> {
+        let key = number_index_key(number);
+        match self.trie.get(&key) {
+            Ok(Some(hash)) if hash == block_hash => {
+                self.trie
+                    .remove(&key)
+                    .map_err(|e| SystemScriptError::RemoveHeader(e.to_string()))?;
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+            Err(e) => Err(SystemScriptError::GetHeader(e.to_string()).into()),
+        }
+    }
+
+    /// Remove the header of the number and its index, the header saved
+    /// without the index is kept.
+    fn prune_header(&mut self, number: u64) -> ProtocolResult<()> {
+        let block_hash = match self.trie.get(&number_index_key(number)) {
+            Ok(Some(hash)) => hash,
+            Ok(None) => return Ok(()),
+            Err(e) => return Err(SystemScriptError::GetHeader(e.to_string()).into()),
+        };
+
+        self.remove_header(&block_hash)?;
+        self.remove_number_index(number, &block_hash)
+    }
+
     pub fn commit(&mut self) -> ProtocolResult<()> {
         match self.trie.commit() {
             Ok(new_root) => {
@@ -136,3 +209,7 @@ impl CkbLightClientStore {
         }
     }
 }
+
+fn number_index_key(number: u64) -> Vec<u8> {
+    [HEADER_NUMBER_INDEX_PREFIX, &number.to_be_bytes()].concat()
+}
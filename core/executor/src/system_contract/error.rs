@@ -3,7 +3,10 @@ use std::io;
 use ethers::abi::AbiError;
 use thiserror::Error;
 
-use protocol::{types::H160, ProtocolError, ProtocolErrorKind};
+use protocol::{
+    types::{H160, H256},
+    ProtocolError, ProtocolErrorKind,
+};
 
 #[derive(Error, Debug)]
 pub enum SystemScriptError {
@@ -40,12 +43,24 @@ pub enum SystemScriptError {
     #[error("Decode cell failed: {0}")]
     DecodeCell(rlp::DecoderError),
 
+    #[error("Cell data size {size} exceeds the limit {limit}")]
+    CellDataTooLarge { size: usize, limit: usize },
+
+    #[error("Input cell {tx_hash:?}:{index} not found")]
+    InputCellNotFound { tx_hash: H256, index: u32 },
+
+    #[error("Output cell {tx_hash:?}:{index} is duplicated")]
+    DuplicateOutputCell { tx_hash: H256, index: u32 },
+
     #[error("Insert header error: {0}")]
     InsertHeader(String),
 
     #[error("Remove header error: {0}")]
     RemoveHeader(String),
 
+    #[error("Rollback header {number} after {last}, expect the consecutive descending numbers")]
+    RollbackGap { last: u64, number: u64 },
+
     #[error("Get header error: {0}")]
     GetHeader(String),
 
@@ -2,23 +2,37 @@ mod abi;
 mod store;
 
 pub use abi::image_cell_abi;
-pub use store::{CellInfo, CellKey};
+pub use store::{CellInfo, CellKey, ImageCellStore};
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+use common_config_parser::types::spec::HardforkName;
 use ethers::abi::AbiDecode;
 
 use protocol::traits::{ApplyBackend, ExecutorAdapter};
 use protocol::types::{SignedTransaction, TxResp, H160, H256};
 use protocol::ProtocolResult;
 
-use crate::system_contract::image_cell::store::ImageCellStore;
 use crate::system_contract::utils::{succeed_resp, update_states};
 use crate::system_contract::{system_contract_address, SystemContract};
-use crate::{exec_try, system_contract_struct, MPTTrie, CURRENT_HEADER_CELL_ROOT};
+use crate::{
+    exec_try, is_hardfork_enabled, system_contract_struct, MPTTrie, CURRENT_HEADER_CELL_ROOT,
+    HARDFORK_INFO,
+};
 
 pub const IMAGE_CELL_CONTRACT_ADDRESS: H160 = system_contract_address(0x3);
 static ALLOW_READ: AtomicBool = AtomicBool::new(false);
+static MAX_CELL_DATA_SIZE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Set the max size of the data of an image cell in bytes. An update with a
+/// larger cell is rejected, 0 sets no limit.
+pub fn set_max_cell_data_size(size: u64) {
+    let size = match size {
+        0 => usize::MAX,
+        s => usize::try_from(s).unwrap_or(usize::MAX),
+    };
+    MAX_CELL_DATA_SIZE.store(size, Ordering::Relaxed);
+}
 
 system_contract_struct!(ImageCellContract);
 
@@ -28,6 +42,18 @@ impl<Adapter: ExecutorAdapter + ApplyBackend> SystemContract<Adapter>
     const ADDRESS: H160 = IMAGE_CELL_CONTRACT_ADDRESS;
 
     fn exec_(&self, adapter: &mut Adapter, tx: &SignedTransaction) -> TxResp {
+        self.exec_with_hardforks(adapter, tx, &HARDFORK_INFO.load())
+    }
+}
+
+impl<Adapter: ExecutorAdapter + ApplyBackend> ImageCellContract<Adapter> {
+    /// Execute the call with the hardforks enabled in the flags.
+    pub fn exec_with_hardforks(
+        &self,
+        adapter: &mut Adapter,
+        tx: &SignedTransaction,
+        hardforks: &H256,
+    ) -> TxResp {
         let sender = tx.sender;
         let tx = &tx.transaction.unsigned;
         let tx_data = tx.data();
@@ -51,7 +77,13 @@ impl<Adapter: ExecutorAdapter + ApplyBackend> SystemContract<Adapter>
                 ALLOW_READ.store(data.allow_read, Ordering::Relaxed);
             }
             image_cell_abi::ImageCellContractCalls::Update(data) => {
-                exec_try!(store.update(data), gas_limit, "[image cell] update error:");
+                // The lenient mode is kept for replaying the blocks before the
+                // Apus hardfork.
+                exec_try!(
+                    store.update(data, is_hardfork_enabled(hardforks, HardforkName::Apus)),
+                    gas_limit,
+                    "[image cell] update error:"
+                );
             }
             image_cell_abi::ImageCellContractCalls::Rollback(data) => {
                 exec_try!(
@@ -62,7 +94,7 @@ impl<Adapter: ExecutorAdapter + ApplyBackend> SystemContract<Adapter>
             }
         }
 
-        update_states(adapter, sender, Self::ADDRESS);
+        update_states(adapter, sender, IMAGE_CELL_CONTRACT_ADDRESS);
         succeed_resp(gas_limit)
     }
 }
@@ -1,11 +1,18 @@
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{atomic::Ordering, Arc};
 
 use ckb_types::{bytes::Bytes, core::cell::CellMeta, packed, prelude::*};
 use rlp::{RlpDecodable, RlpEncodable};
 
-use protocol::{ckb_blake2b_256, codec::hex_encode, trie::Trie as _, types::H256, ProtocolResult};
+use protocol::{
+    codec::hex_encode,
+    hash::ckb_blake2b,
+    trie::Trie as _,
+    types::{Hasher, H256},
+    ProtocolResult,
+};
 
-use crate::system_contract::image_cell::{image_cell_abi, MPTTrie};
+use crate::system_contract::image_cell::{image_cell_abi, MPTTrie, MAX_CELL_DATA_SIZE};
 use crate::system_contract::HEADER_CELL_DB;
 use crate::{
     adapter::RocksTrieDB, system_contract::error::SystemScriptError, CURRENT_HEADER_CELL_ROOT,
@@ -78,10 +85,29 @@ impl ImageCellStore {
         Ok(ImageCellStore { trie })
     }
 
-    pub fn update(&mut self, data: image_cell_abi::UpdateCall) -> ProtocolResult<()> {
+    /// Save the output cells and mark the input cells consumed. In the strict
+    /// mode, an input pointing at a missing cell fails the whole update,
+    /// otherwise it is skipped.
+    ///
+    /// The outputs of a block are saved before its inputs are consumed, so a
+    /// cell created and consumed in the same call ends up consumed. The same
+    /// out point appearing twice in the outputs of a call fails the update.
+    pub fn update(&mut self, data: image_cell_abi::UpdateCall, strict: bool) -> ProtocolResult<()> {
+        let mut outputs = HashSet::new();
+        for cell in data.blocks.iter().flat_map(|block| block.tx_outputs.iter()) {
+            let out_point = &cell.out_point;
+            if !outputs.insert((out_point.tx_hash, out_point.index)) {
+                return Err(SystemScriptError::DuplicateOutputCell {
+                    tx_hash: H256(out_point.tx_hash),
+                    index:   out_point.index,
+                }
+                .into());
+            }
+        }
+
         for block in data.blocks {
             self.save_cells(block.tx_outputs, block.block_number)?;
-            self.mark_cells_consumed(block.tx_inputs, block.block_number)?;
+            self.mark_cells_consumed(block.tx_inputs, block.block_number, strict)?;
         }
 
         self.commit()
@@ -100,13 +126,24 @@ impl ImageCellStore {
         &mut self,
         inputs: Vec<image_cell_abi::OutPoint>,
         new_block_number: u64,
+        strict: bool,
     ) -> ProtocolResult<()> {
         for input in inputs {
             let key = CellKey::new(input.tx_hash, input.index);
 
-            if let Some(ref mut cell) = self.get_cell(&key)? {
-                cell.consumed_number = Some(new_block_number);
-                self.insert_cell(&key, cell)?;
+            match self.get_cell(&key)? {
+                Some(ref mut cell) => {
+                    cell.consumed_number = Some(new_block_number);
+                    self.insert_cell(&key, cell)?;
+                }
+                None if strict => {
+                    return Err(SystemScriptError::InputCellNotFound {
+                        tx_hash: key.tx_hash,
+                        index:   key.index,
+                    }
+                    .into())
+                }
+                None => (),
             }
         }
         Ok(())
@@ -148,11 +185,38 @@ impl ImageCellStore {
         ))
     }
 
+    /// Iterate over the cells with the hashes of their keys, see
+    /// `CellKey::hash`. The trie is keyed by the hashes, so the out points are
+    /// not recoverable and the cells come in no particular order. The headers
+    /// sharing the trie are skipped.
+    pub fn cells(&self) -> impl Iterator<Item = (H256, CellInfo)> + '_ {
+        self.trie.iter().filter_map(|(key, value)| {
+            rlp::decode::<CellInfo>(&value)
+                .ok()
+                .map(|cell| (H256::from_slice(&key), cell))
+        })
+    }
+
+    pub fn live_cells(&self) -> impl Iterator<Item = (H256, CellInfo)> + '_ {
+        self.cells()
+            .filter(|(_, cell)| cell.consumed_number.is_none())
+    }
+
     pub fn save_cells(
         &mut self,
         outputs: Vec<image_cell_abi::CellInfo>,
         created_number: u64,
     ) -> ProtocolResult<()> {
+        // Check all the cells ahead, so that no cell is saved on rejection.
+        let limit = MAX_CELL_DATA_SIZE.load(Ordering::Relaxed);
+        if let Some(cell) = outputs.iter().find(|cell| cell.data.0.len() > limit) {
+            return Err(SystemScriptError::CellDataTooLarge {
+                size: cell.data.0.len(),
+                limit,
+            }
+            .into());
+        }
+
         for cell in outputs {
             let lock = cell.output.lock;
             let lock = packed::Script::new_builder()
@@ -263,6 +327,11 @@ impl CellKey {
         Ok(CellKey { tx_hash, index })
     }
 
+    /// The key of the cell in the trie.
+    pub fn hash(&self) -> H256 {
+        Hasher::digest(self.encode())
+    }
+
     pub fn encode(&self) -> Bytes {
         let mut ret = Vec::with_capacity(Self::ENCODED_LEN);
         ret.extend_from_slice(&self.tx_hash.0);
@@ -294,7 +363,7 @@ impl CellInfo {
 
 fn cell_data_hash(data: &Bytes) -> packed::Byte32 {
     if !data.is_empty() {
-        return ckb_blake2b_256(data).pack();
+        return ckb_blake2b(data).pack();
     }
 
     packed::Byte32::zero()
@@ -1,4 +1,6 @@
-use protocol::types::{CkbRelatedInfo, ConsensusConfig, HardforkInfo, Metadata, H160, H256};
+use protocol::types::{
+    CkbRelatedInfo, ConsensusConfig, ExecutorRules, HardforkInfo, Metadata, H160, H256,
+};
 use protocol::ProtocolResult;
 
 use std::sync::Arc;
@@ -30,10 +32,29 @@ impl MetadataHandle {
         store.get_metadata(epoch)
     }
 
+    /// Return the epoch which the given block belongs to, or `None` if the
+    /// block is beyond the last recorded epoch.
+    pub fn epoch_of_block(&self, block_number: u64) -> ProtocolResult<Option<u64>> {
+        if block_number == 0 {
+            return Ok(Some(0));
+        }
+
+        let segment = MetadataStore::new(self.root)?.get_epoch_segment()?;
+        if block_number > segment.last_endpoint() {
+            return Ok(None);
+        }
+
+        segment.get_epoch_number(block_number).map(Some)
+    }
+
     pub fn get_metadata_by_epoch(&self, epoch: u64) -> ProtocolResult<Metadata> {
         MetadataStore::new(self.root)?.get_metadata(epoch)
     }
 
+    pub fn slashing_report(&self, epoch: u64) -> ProtocolResult<Vec<H160>> {
+        MetadataStore::new(self.root)?.slashing_report(epoch)
+    }
+
     pub fn is_last_block_in_current_epoch(&self, block_number: u64) -> ProtocolResult<bool> {
         let store = MetadataStore::new(self.root)?;
         let segment = store.get_epoch_segment()?;
@@ -54,9 +75,13 @@ impl MetadataHandle {
         MetadataStore::new(self.root)?.hardfork_infos()
     }
 
-    pub fn init_hardfork(&self, block_number: u64) -> ProtocolResult<()> {
+    pub fn executor_rules(&self) -> ProtocolResult<ExecutorRules> {
+        MetadataStore::new(self.root)?.executor_rules()
+    }
+
+    pub fn init_hardfork(&self, block_number: u64, timestamp: u64) -> ProtocolResult<()> {
         let hardfork = MetadataStore::new(self.root)?
-            .hardfork_info(block_number)
+            .hardfork_info(block_number, timestamp)
             .unwrap();
 
         HARDFORK_INFO.swap(Arc::new(hardfork));
@@ -5,9 +5,10 @@ mod store;
 
 pub use abi::metadata_abi;
 pub use handle::MetadataHandle;
-pub use store::{encode_consensus_config, MetadataStore};
+pub use store::{encode_consensus_config, genesis_metadata_root, MetadataStore};
 
-use std::{num::NonZeroUsize, sync::Arc};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
 
 use arc_swap::ArcSwap;
 use ethers::abi::AbiDecode;
@@ -34,6 +35,8 @@ lazy_static::lazy_static! {
     static ref CKB_RELATED_INFO_KEY: H256 = Hasher::digest("ckb_related_info");
     pub static ref CONSENSUS_CONFIG: H256 = Hasher::digest("consensus_config");
     pub static ref HARDFORK_KEY: H256 = Hasher::digest("hardfork");
+    pub static ref EXECUTOR_RULES_KEY: H256 = Hasher::digest("executor_rules");
+    pub static ref SLASHING_REPORT_KEY: H256 = Hasher::digest("slashing_report");
     pub static ref HARDFORK_INFO: ArcSwap<H256> = ArcSwap::new(Arc::new(H256::zero()));
     static ref METADATA_CACHE: RwLock<LruCache<Epoch, Metadata>> =  RwLock::new(LruCache::new(METADATA_CACHE_SIZE));
 }
@@ -118,13 +121,19 @@ impl<Adapter: ExecutorAdapter + ApplyBackend> SystemContract<Adapter>
 
         if let Some(t) = adapter.get_ctx().extra_data.get(0) {
             if let Ok(data) = HardforkInfoInner::decode(&t.inner) {
-                store
-                    .set_hardfork_info(data.block_number, data.flags)
-                    .expect("set new hardfork info fail")
+                let mut hardfork_info = store.hardfork_infos().unwrap();
+                match hardfork_info.push(data) {
+                    Ok(()) => store
+                        .set_hardfork_infos(&hardfork_info)
+                        .expect("set new hardfork info fail"),
+                    Err(e) => log::warn!("[metadata] ignore the proposed hardfork: {}", e),
+                }
             }
         }
 
-        let hardfork = store.hardfork_info(block_number.as_u64()).unwrap();
+        let hardfork = store
+            .hardfork_info(block_number.as_u64(), adapter.block_timestamp().as_u64())
+            .unwrap();
 
         HARDFORK_INFO.swap(Arc::new(hardfork));
 
@@ -132,11 +141,53 @@ impl<Adapter: ExecutorAdapter + ApplyBackend> SystemContract<Adapter>
             panic!("Update propose count at {:?} failed: {:?}", block_number, e)
         }
 
+        report_slashing(&mut store, block_number.as_u64());
+
+        if let Err(e) = store.init_next_propose_counter(block_number.as_u64()) {
+            panic!(
+                "Init next propose counter at {:?} failed: {:?}",
+                block_number, e
+            )
+        }
+
         let changes = generate_mpt_root_changes(adapter, Self::ADDRESS);
         adapter.apply(changes, vec![], false);
     }
 }
 
+fn report_slashing(store: &mut MetadataStore, block_number: u64) {
+    let segment = match store.get_epoch_segment() {
+        Ok(segment) => segment,
+        Err(_) => return,
+    };
+    if !segment.is_last_block_in_epoch(block_number) {
+        return;
+    }
+
+    let threshold = match store.executor_rules() {
+        Ok(rules) => match rules.slashing_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        },
+        Err(e) => panic!("Get executor rules at {:?} failed: {:?}", block_number, e),
+    };
+
+    let report = segment
+        .get_epoch_number(block_number)
+        .and_then(|epoch| Ok((epoch, store.report_slashing(epoch, threshold)?)));
+    match report {
+        Ok((epoch, validators)) if !validators.is_empty() => {
+            log::warn!(
+                "[metadata] slashing report of epoch {}: {:?}",
+                epoch,
+                validators
+            )
+        }
+        Ok(_) => (),
+        Err(e) => panic!("Report slashing at {:?} failed: {:?}", block_number, e),
+    }
+}
+
 pub fn check_ckb_related_info_exist(root: H256) -> bool {
     MetadataHandle::new(root).get_ckb_related_info().is_ok()
 }
@@ -14,7 +14,8 @@ impl EpochSegment {
         EpochSegment { seg: vec![0] }
     }
 
-    /// Epoch segment requires the pushed endpoint is incremental.
+    /// Epoch segment requires the pushed endpoint is strictly greater than the
+    /// last one, otherwise the epoch lookups are broken.
     pub fn append_endpoint(&mut self, endpoint: u64) -> ProtocolResult<()> {
         if endpoint <= *self.seg.last().unwrap() {
             return Err(SystemScriptError::InvalidEpochEnd(endpoint).into());
@@ -43,7 +44,8 @@ impl EpochSegment {
         self.seg.len() == 1
     }
 
-    pub fn last_block_number(&self) -> u64 {
+    /// The last block of the latest epoch, or 0 if there is no epoch.
+    pub fn last_endpoint(&self) -> u64 {
         *self.seg.last().unwrap()
     }
 
@@ -97,6 +99,28 @@ mod tests {
         test_codec(&epochs);
     }
 
+    #[test]
+    fn test_append_endpoint() {
+        let mut epochs = EpochSegment::new();
+        assert!(epochs.is_empty());
+        assert_eq!(epochs.last_endpoint(), 0);
+        assert!(epochs.append_endpoint(0).is_err());
+
+        epochs.append_endpoint(100).unwrap();
+        epochs.append_endpoint(200).unwrap();
+        assert_eq!(epochs.last_endpoint(), 200);
+
+        // A decreasing or repeated endpoint is rejected and changes nothing.
+        assert!(epochs.append_endpoint(150).is_err());
+        assert!(epochs.append_endpoint(200).is_err());
+        assert_eq!(epochs.last_endpoint(), 200);
+        assert_eq!(epochs.get_latest_epoch_number(), 1);
+
+        epochs.append_endpoint(201).unwrap();
+        assert_eq!(epochs.last_endpoint(), 201);
+        assert_eq!(epochs.get_epoch_number(201).unwrap(), 2);
+    }
+
     fn init_epoch_segment() -> EpochSegment {
         let mut epochs = EpochSegment::new();
         epochs.append_endpoint(100).unwrap();
@@ -2,16 +2,16 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use common_config_parser::types::spec::HardforkName;
-use protocol::trie::Trie as _;
+use protocol::trie::{MemoryDB, Trie as _};
 use protocol::types::{
-    CkbRelatedInfo, ConsensusConfig, ConsensusConfigV0, HardforkInfo, HardforkInfoInner, Metadata,
-    MetadataInner, H160, H256,
+    CkbRelatedInfo, ConsensusConfig, ConsensusConfigV0, ExecutorRules, HardforkInfo,
+    HardforkInfoInner, Hasher, Metadata, MetadataInner, ProposeCount, ValidatorExtend, H160, H256,
 };
 use protocol::{codec::ProtocolCodec, ProtocolResult};
 
 use crate::system_contract::metadata::{
     segment::EpochSegment, CKB_RELATED_INFO_KEY, CONSENSUS_CONFIG, EPOCH_SEGMENT_KEY,
-    HARDFORK_INFO, HARDFORK_KEY,
+    EXECUTOR_RULES_KEY, HARDFORK_INFO, HARDFORK_KEY, SLASHING_REPORT_KEY,
 };
 use crate::system_contract::{error::SystemScriptError, METADATA_DB};
 use crate::{adapter::RocksTrieDB, MPTTrie, CURRENT_METADATA_ROOT};
@@ -23,6 +23,8 @@ use crate::{adapter::RocksTrieDB, MPTTrie, CURRENT_METADATA_ROOT};
 /// | EPOCH_SEGMENT_KEY    | `EpochSegment.encode()`              |
 /// | CKB_RELATED_INFO_KEY | `CkbRelatedInfo.encode()`            |
 /// | HARDFORK_KEY         | `HardforkInfo.encode()`              |
+/// | EXECUTOR_RULES_KEY   | `ExecutorRules.encode()`             |
+/// | slashing_report_key  | `rlp::encode_list(validators)`       |
 /// | epoch_0.be_bytes()   | `Metadata.encode()`                  |
 /// | epoch_1.be_bytes()   | `Metadata.encode()`                  |
 /// | CONSENSUS_CONFIG     | `version + ConsensesConfig.encode()` |
@@ -98,19 +100,13 @@ impl MetadataStore {
             return Err(SystemScriptError::PastEpoch.into());
         }
 
-        if (metadata.version.start != epoch_segment.last_block_number() + 1) && metadata.epoch != 0
+        if (metadata.version.start != epoch_segment.last_endpoint() + 1) && metadata.epoch != 0
         {
             return Err(SystemScriptError::MetadataVersionDiscontinuity.into());
         }
 
-        // Build propose counter
-        let map = metadata
-            .verifier_list
-            .iter()
-            .map(|v| (v.address, 0u64))
-            .collect::<BTreeMap<_, _>>();
         let mut metadata = metadata.clone();
-        metadata.propose_counter = map.into_iter().map(Into::into).collect();
+        metadata.propose_counter = zeroed_propose_counter(&metadata.verifier_list);
 
         epoch_segment.append_endpoint(metadata.version.end)?;
 
@@ -157,6 +153,101 @@ impl MetadataStore {
         Ok(())
     }
 
+    /// At the last block of an epoch, reset the propose counter of the next
+    /// epoch to zero for each validator in its verifier list. The counts of
+    /// the finished epoch are never carried over. It is a no-op if the block
+    /// is not the end of an epoch, the next epoch has not been appended yet or
+    /// its counter is already zeroed by `append_metadata`.
+    pub fn init_next_propose_counter(&mut self, block_number: u64) -> ProtocolResult<()> {
+        let epoch_segment = self.get_epoch_segment()?;
+        if block_number == 0
+            || epoch_segment.is_empty()
+            || !epoch_segment.is_last_block_in_epoch(block_number)
+        {
+            return Ok(());
+        }
+
+        let next_epoch = self.get_epoch_by_block_number(block_number)? + 1;
+        if next_epoch > epoch_segment.get_latest_epoch_number() {
+            return Ok(());
+        }
+
+        let mut metadata = self.get_metadata_inner(next_epoch)?;
+        let propose_counter = zeroed_propose_counter(&metadata.verifier_list);
+        if metadata.propose_counter == propose_counter {
+            return Ok(());
+        }
+        metadata.propose_counter = propose_counter;
+
+        self.trie.insert(
+            metadata.epoch.to_be_bytes().to_vec(),
+            metadata.encode()?.to_vec(),
+        )?;
+        let new_root = self.trie.commit()?;
+        CURRENT_METADATA_ROOT.with(|r| *r.borrow_mut() = new_root);
+
+        Ok(())
+    }
+
+    /// Save the slashing report of the epoch, which is the validators whose
+    /// faults in the epoch exceed the threshold. An empty report is not saved.
+    pub fn report_slashing(&mut self, epoch: u64, threshold: u64) -> ProtocolResult<Vec<H160>> {
+        let validators = self.faulty_validators(epoch, threshold)?;
+        if validators.is_empty() {
+            return Ok(validators);
+        }
+
+        self.trie.insert(
+            slashing_report_key(epoch).as_bytes().to_vec(),
+            rlp::encode_list::<H160, _>(&validators).to_vec(),
+        )?;
+        let new_root = self.trie.commit()?;
+        CURRENT_METADATA_ROOT.with(|r| *r.borrow_mut() = new_root);
+
+        Ok(validators)
+    }
+
+    /// The slashing report saved at the end of the epoch, which is empty if no
+    /// validator is reported.
+    pub fn slashing_report(&self, epoch: u64) -> ProtocolResult<Vec<H160>> {
+        match self.trie.get(slashing_report_key(epoch).as_bytes())? {
+            Some(data) => Ok(rlp::decode_list(&data)),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The validators whose faults in the epoch exceed the threshold. The
+    /// faults of a validator are the proposals it was expected to make by its
+    /// propose weight but did not, according to the propose counter.
+    pub fn faulty_validators(&self, epoch: u64, threshold: u64) -> ProtocolResult<Vec<H160>> {
+        let metadata = self.get_metadata_inner(epoch)?;
+        let blocks = metadata.version.end + 1 - metadata.version.start;
+        let total_weight = metadata
+            .verifier_list
+            .iter()
+            .map(|v| v.propose_weight as u64)
+            .sum::<u64>()
+            .max(1);
+
+        let report = metadata
+            .verifier_list
+            .iter()
+            .filter(|v| {
+                let expected = blocks * v.propose_weight as u64 / total_weight;
+                let proposed = metadata
+                    .propose_counter
+                    .iter()
+                    .find(|p| p.address == v.address)
+                    .map(|p| p.count)
+                    .unwrap_or_default();
+                expected.saturating_sub(proposed) > threshold
+            })
+            .map(|v| v.address)
+            .collect();
+
+        Ok(report)
+    }
+
     pub fn get_epoch_segment(&self) -> ProtocolResult<EpochSegment> {
         let raw = self.trie.get(EPOCH_SEGMENT_KEY.as_bytes())?.unwrap();
         EpochSegment::from_raw(raw.to_vec())
@@ -213,49 +304,24 @@ impl MetadataStore {
         self.get_epoch_segment()?.get_epoch_number(block_number)
     }
 
-    pub fn set_hardfork_info(&mut self, block_number: u64, info: H256) -> ProtocolResult<()> {
-        let current_info = {
-            match self.trie.get(HARDFORK_KEY.as_bytes())? {
-                Some(data) => {
-                    let mut hardfork_info = HardforkInfo::decode(data)?;
-
-                    hardfork_info.push(HardforkInfoInner {
-                        block_number,
-                        flags: info,
-                    });
-                    hardfork_info
-                }
-                None => HardforkInfo {
-                    inner: vec![HardforkInfoInner {
-                        block_number,
-                        flags: info,
-                    }],
-                },
-            }
-        };
+    pub fn set_hardfork_info(&mut self, info: HardforkInfoInner) -> ProtocolResult<()> {
+        let mut hardfork_info = self.hardfork_infos()?;
+        hardfork_info.push(info)?;
+        self.set_hardfork_infos(&hardfork_info)
+    }
 
-        self.trie.insert(
-            HARDFORK_KEY.as_bytes().to_vec(),
-            current_info.encode()?.to_vec(),
-        )?;
+    pub fn set_hardfork_infos(&mut self, info: &HardforkInfo) -> ProtocolResult<()> {
+        self.trie
+            .insert(HARDFORK_KEY.as_bytes().to_vec(), info.encode()?.to_vec())?;
         let new_root = self.trie.commit()?;
         CURRENT_METADATA_ROOT.with(|r| *r.borrow_mut() = new_root);
         Ok(())
     }
 
-    pub fn hardfork_info(&self, target_number: u64) -> ProtocolResult<H256> {
+    /// The hardforks activated at the block of the number and the timestamp.
+    pub fn hardfork_info(&self, target_number: u64, timestamp: u64) -> ProtocolResult<H256> {
         match self.trie.get(HARDFORK_KEY.as_bytes())? {
-            Some(data) => {
-                let hardfork_info = HardforkInfo::decode(data)?;
-
-                for k in hardfork_info.inner.iter().rev() {
-                    if k.block_number > target_number {
-                        continue;
-                    }
-                    return Ok(k.flags);
-                }
-                Ok(H256::zero())
-            }
+            Some(data) => Ok(HardforkInfo::decode(data)?.flags_at(target_number, timestamp)),
             None => Ok(H256::zero()),
         }
     }
@@ -266,6 +332,90 @@ impl MetadataStore {
             None => Ok(HardforkInfo::default()),
         }
     }
+
+    /// Store the executor rules at the genesis, the default ones are skipped.
+    pub fn set_executor_rules(&mut self, rules: &ExecutorRules) -> ProtocolResult<()> {
+        if *rules == ExecutorRules::default() {
+            return Ok(());
+        }
+
+        self.trie.insert(
+            EXECUTOR_RULES_KEY.as_bytes().to_vec(),
+            rules.encode()?.to_vec(),
+        )?;
+        let new_root = self.trie.commit()?;
+        CURRENT_METADATA_ROOT.with(|r| *r.borrow_mut() = new_root);
+        Ok(())
+    }
+
+    /// The executor rules of the chain, which are the defaults if none is
+    /// stored at the genesis.
+    pub fn executor_rules(&self) -> ProtocolResult<ExecutorRules> {
+        match self.trie.get(EXECUTOR_RULES_KEY.as_bytes())? {
+            Some(data) => ExecutorRules::decode(data),
+            None => Ok(ExecutorRules::default()),
+        }
+    }
+}
+
+/// Calculate the root of the Metadata MPT which the genesis should produce with
+/// the first two metadata, the hardfork info and the executor rules,
+/// independently of the store.
+pub fn genesis_metadata_root(
+    metadata_list: &[Metadata],
+    hardfork: &HardforkInfoInner,
+    rules: &ExecutorRules,
+) -> ProtocolResult<H256> {
+    let mut trie = MPTTrie::new(Arc::new(MemoryDB::new(false)));
+    let mut epoch_segment = EpochSegment::new();
+
+    for metadata in metadata_list.iter() {
+        let mut metadata = metadata.clone();
+        metadata.propose_counter = zeroed_propose_counter(&metadata.verifier_list);
+        epoch_segment.append_endpoint(metadata.version.end)?;
+
+        // No hardfork is activated before the genesis.
+        let (inner, config) = metadata.into_part();
+        trie.insert(inner.epoch.to_be_bytes().to_vec(), inner.encode()?.to_vec())?;
+        trie.insert(
+            CONSENSUS_CONFIG.as_bytes().to_vec(),
+            encode_consensus_config(H256::zero(), config)?,
+        )?;
+    }
+
+    trie.insert(
+        EPOCH_SEGMENT_KEY.as_bytes().to_vec(),
+        epoch_segment.as_bytes(),
+    )?;
+    let hardfork_info = HardforkInfo {
+        inner: vec![hardfork.clone()],
+    };
+    trie.insert(
+        HARDFORK_KEY.as_bytes().to_vec(),
+        hardfork_info.encode()?.to_vec(),
+    )?;
+    if *rules != ExecutorRules::default() {
+        trie.insert(
+            EXECUTOR_RULES_KEY.as_bytes().to_vec(),
+            rules.encode()?.to_vec(),
+        )?;
+    }
+
+    trie.commit()
+}
+
+fn slashing_report_key(epoch: u64) -> H256 {
+    Hasher::digest([SLASHING_REPORT_KEY.as_bytes(), &epoch.to_be_bytes()].concat())
+}
+
+fn zeroed_propose_counter(verifier_list: &[ValidatorExtend]) -> Vec<ProposeCount> {
+    verifier_list
+        .iter()
+        .map(|v| (v.address, 0u64))
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(Into::into)
+        .collect()
 }
 
 #[derive(Debug)]
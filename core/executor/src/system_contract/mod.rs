@@ -7,10 +7,13 @@ pub(crate) mod image_cell;
 pub mod metadata;
 
 pub use crate::system_contract::ckb_light_client::{
-    CkbLightClientContract, CKB_LIGHT_CLIENT_CONTRACT_ADDRESS,
+    ckb_light_client_abi, set_header_retention, CkbLightClientContract,
+    CKB_LIGHT_CLIENT_CONTRACT_ADDRESS,
 };
 use crate::system_contract::error::SystemScriptError;
-pub use crate::system_contract::image_cell::{ImageCellContract, IMAGE_CELL_CONTRACT_ADDRESS};
+pub use crate::system_contract::image_cell::{
+    set_max_cell_data_size, CellInfo, CellKey, ImageCellContract, IMAGE_CELL_CONTRACT_ADDRESS,
+};
 pub use crate::system_contract::metadata::{
     check_ckb_related_info_exist, MetadataContract, METADATA_CONTRACT_ADDRESS,
 };
@@ -24,20 +27,23 @@ use ckb_traits::{CellDataProvider, ExtensionProvider, HeaderProvider};
 use ckb_types::core::cell::{CellProvider, CellStatus};
 use ckb_types::core::{HeaderBuilder, HeaderView};
 use ckb_types::{packed, prelude::*};
+use ethers::abi::AbiDecode;
 use evm::backend::ApplyBackend;
 use parking_lot::RwLock;
 use rocksdb::DB;
 
 use protocol::traits::{CkbDataProvider, ExecutorAdapter};
 use protocol::types::{
-    Bytes, HardforkInfoInner, Hasher, Metadata, SignedTransaction, TransactionAction, TxResp, H160,
-    H256,
+    Bytes, ExecutorRules, HardforkInfoInner, Hasher, Metadata, SignedTransaction,
+    TransactionAction, TxResp, H160, H256,
 };
-use protocol::{ckb_blake2b_256, ProtocolResult};
+use protocol::{hash::ckb_blake2b, ProtocolResult};
 
 use crate::adapter::RocksTrieDB;
 use crate::system_contract::{
-    ckb_light_client::CkbHeaderReader, image_cell::ImageCellReader, metadata::MetadataStore,
+    ckb_light_client::CkbHeaderReader,
+    image_cell::{ImageCellReader, ImageCellStore},
+    metadata::MetadataStore,
     utils::generate_mpt_root_changes,
 };
 
@@ -131,9 +137,10 @@ pub fn init<Adapter: ExecutorAdapter + ApplyBackend>(
     adapter: &mut Adapter,
     metadata_list: &[Metadata],
     hardfork: HardforkInfoInner,
+    rules: &ExecutorRules,
 ) -> ProtocolResult<(H256, H256)> {
     let ret = init_system_contract_db(db, adapter);
-    init_metadata_and_hardfork(adapter, ret.0, metadata_list, hardfork)?;
+    init_metadata_and_hardfork(adapter, ret.0, metadata_list, hardfork, rules)?;
 
     Ok(ret)
 }
@@ -180,19 +187,22 @@ pub fn init_system_contract_db<Adapter: ExecutorAdapter + ApplyBackend>(
 }
 
 /// This method is used for insert the first two metadata, so the
-/// `metadata_list.len()` should be equal to 2.
+/// `metadata_list.len()` should be equal to 2. The hardfork info and the
+/// executor rules of the genesis are inserted as well.
 fn init_metadata_and_hardfork<Adapter: ExecutorAdapter + ApplyBackend>(
     adapter: &mut Adapter,
     metadata_root: H256,
     metadata_list: &[Metadata],
     hardfork: HardforkInfoInner,
+    rules: &ExecutorRules,
 ) -> ProtocolResult<()> {
     debug_assert!(metadata_list.len() == 2);
 
     let mut store = MetadataStore::new(metadata_root)?;
     store.append_metadata(&metadata_list[0])?;
     store.append_metadata(&metadata_list[1])?;
-    store.set_hardfork_info(hardfork.block_number, hardfork.flags)?;
+    store.set_hardfork_info(hardfork)?;
+    store.set_executor_rules(rules)?;
 
     let changes = generate_mpt_root_changes(adapter, METADATA_CONTRACT_ADDRESS);
     adapter.apply(changes, vec![], false);
@@ -217,6 +227,7 @@ pub fn after_block_hook<Adapter: ExecutorAdapter + ApplyBackend>(adapter: &mut A
 pub fn system_contract_dispatch<Adapter: ExecutorAdapter + ApplyBackend>(
     adapter: &mut Adapter,
     tx: &SignedTransaction,
+    hardforks: &H256,
 ) -> Option<TxResp> {
     if let Some(addr) = tx.get_to() {
         log::debug!("execute addr {:#x}", addr);
@@ -228,7 +239,7 @@ pub fn system_contract_dispatch<Adapter: ExecutorAdapter + ApplyBackend>(
         } else if addr == CKB_LIGHT_CLIENT_CONTRACT_ADDRESS {
             return Some(CkbLightClientContract::default().exec_(adapter, tx));
         } else if addr == IMAGE_CELL_CONTRACT_ADDRESS {
-            return Some(ImageCellContract::default().exec_(adapter, tx));
+            return Some(ImageCellContract::default().exec_with_hardforks(adapter, tx, hardforks));
         }
     }
 
@@ -268,7 +279,7 @@ impl CellDataProvider for DataProvider {
             if data.is_empty() {
                 packed::Byte32::zero()
             } else {
-                ckb_blake2b_256(data).pack()
+                ckb_blake2b(data).pack()
             }
         })
     }
@@ -316,12 +327,50 @@ impl DataProvider {
     pub fn new(root: H256) -> Self {
         DataProvider { root }
     }
+
+    /// Get the image cell of the out point, the consumed one is returned with
+    /// its consumed number.
+    pub fn get_cell_info(&self, tx_hash: H256, index: u32) -> ProtocolResult<Option<CellInfo>> {
+        ImageCellReader.get_cell(self.root, &CellKey::new(tx_hash.0, index))
+    }
+
+    /// Get the unconsumed image cells with the hashes of their keys, see
+    /// `CellKey::hash`.
+    pub fn live_cells(&self) -> ProtocolResult<Vec<(H256, CellInfo)>> {
+        Ok(ImageCellStore::new(self.root)?.live_cells().collect())
+    }
+
+    pub fn get_header_info(
+        &self,
+        block_hash: H256,
+    ) -> ProtocolResult<Option<ckb_light_client_abi::Header>> {
+        CkbHeaderReader.get_header_by_block_hash(self.root, &block_hash)
+    }
 }
 
 pub fn is_system_contract_address_format(addr: &H160) -> bool {
     addr.0[0..19] == SYSTEM_CONTRACT_ADDRESSES_PREFIX
 }
 
+/// Whether the data calls a state-mutating method of a system contract. The
+/// native token contract takes a direction byte and an address as its data,
+/// the others take their ABI calls which all mutate the state.
+pub fn is_system_contract_mutation(addr: &H160, data: &[u8]) -> bool {
+    match *addr {
+        NATIVE_TOKEN_CONTRACT_ADDRESS => data.len() >= 21 && data[0] <= 1,
+        METADATA_CONTRACT_ADDRESS => {
+            metadata::metadata_abi::MetadataContractCalls::decode(data).is_ok()
+        }
+        CKB_LIGHT_CLIENT_CONTRACT_ADDRESS => {
+            ckb_light_client_abi::CkbLightClientContractCalls::decode(data).is_ok()
+        }
+        IMAGE_CELL_CONTRACT_ADDRESS => {
+            image_cell::image_cell_abi::ImageCellContractCalls::decode(data).is_ok()
+        }
+        _ => false,
+    }
+}
+
 pub fn is_call_system_script(action: &TransactionAction) -> ProtocolResult<bool> {
     let call_addr = match action {
         TransactionAction::Call(addr) => addr,
@@ -356,6 +405,9 @@ mod tests {
         let action = TransactionAction::Call(addr);
         assert!(!is_call_system_script(&action).unwrap());
 
+        let action = TransactionAction::Call(H160::zero());
+        assert!(!is_call_system_script(&action).unwrap());
+
         let addr = NATIVE_TOKEN_CONTRACT_ADDRESS;
         let action = TransactionAction::Call(addr);
         assert!(is_call_system_script(&action).unwrap());
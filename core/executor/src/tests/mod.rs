@@ -1,25 +1,59 @@
+mod state_diff;
 mod system_script;
 
 use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use evm::backend::{MemoryAccount, MemoryVicinity};
-use evm::Config;
+use ethers::abi::AbiEncode;
+use evm::backend::{Apply, ApplyBackend, Backend, Basic, MemoryAccount, MemoryVicinity};
+use evm::{Config, Opcode};
 
 use protocol::types::{
-    Bytes, Eip1559Transaction, ExecutorContext, ExitReason, ExitSucceed, Public,
-    SignatureComponents, SignedTransaction, TransactionAction, UnsignedTransaction,
-    UnverifiedTransaction, H160, H256, U256,
+    Block, Bytes, DisableableOpcode, DisabledOpcodes, Eip1559Transaction, ExecutorContext,
+    ExecutorLimits, ExitError, ExitReason, ExitSucceed, Public, SignatureComponents,
+    SignedTransaction, Tracer, TransactionAction, TxTrace, UnsignedTransaction,
+    UnverifiedTransaction, H160, H256, MAX_BLOCK_GAS_LIMIT, RLP_NULL, U256,
+};
+use protocol::{
+    codec::hex_decode,
+    tokio,
+    traits::{Executor, ExecutorAdapter},
+    trie::MemoryDB,
 };
-use protocol::{codec::hex_decode, tokio, traits::Executor, trie::MemoryDB};
 
+use common_config_parser::types::spec::HardforkName;
 use core_db::MemoryAdapter;
 use core_storage::ImplStorage;
 
-use crate::AxonExecutorApplyAdapter;
+use crate::system_contract::image_cell::image_cell_abi;
+use crate::system_contract::{
+    system_contract_address, CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, HEADER_CELL_ROOT_KEY,
+    IMAGE_CELL_CONTRACT_ADDRESS,
+};
+use crate::{
+    code_address, compute_receipts_root, decode_revert_msg, disable_opcodes,
+    AxonExecutorApplyAdapter, TraceBudget,
+};
 use crate::{precompiles::build_precompile_set, AxonExecutor as EvmExecutor, AxonExecutor};
 
+// pragma solidity ^0.4.24;
+//
+// contract SimpleStorage {
+//     uint storedData;
+//
+//     function set(uint x) public {
+//         storedData = x;
+//     }
+//
+//     function get() view public returns (uint) {
+//         return storedData;
+//     }
+// }
+//
+// SIMPLESTORAGE_CREATE_CODE created from above solidity
+const SIMPLESTORAGE_CREATE_CODE: &str = "608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029";
+
 fn exec_adapter() -> AxonExecutorApplyAdapter<ImplStorage<MemoryAdapter>, MemoryDB> {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 20);
     let ctx = ExecutorContext {
@@ -72,6 +106,466 @@ fn gen_tx(sender: H160, addr: H160, value: u64, data: Vec<u8>) -> SignedTransact
     }
 }
 
+fn gen_storage_applies(round: u64) -> Vec<Apply<Vec<(H256, H256)>>> {
+    let mut applies = (1..=200u64)
+        .map(|i| Apply::Modify {
+            address:       H160::from_low_u64_be(i),
+            basic:         Basic {
+                balance: (i * round).into(),
+                nonce:   round.into(),
+            },
+            code:          None,
+            storage:       (0..16u64)
+                .map(|slot| {
+                    // Clear the odd slots in the second round.
+                    let value = if round > 1 && slot % 2 == 1 {
+                        H256::zero()
+                    } else {
+                        H256::from_low_u64_be(i * 100 + slot + round)
+                    };
+                    (H256::from_low_u64_be(slot), value)
+                })
+                .collect(),
+            reset_storage: round > 1 && i % 50 == 0,
+        })
+        .collect::<Vec<_>>();
+    if round > 1 {
+        applies.push(Apply::Delete {
+            address: H160::from_low_u64_be(7),
+        });
+    }
+    applies
+}
+
+#[test]
+fn test_parallel_trie_commit() {
+    let mut serial = exec_adapter();
+    let mut parallel = exec_adapter();
+    parallel.set_parallel_commit(true);
+
+    for round in 1..=2 {
+        serial.apply(gen_storage_applies(round), Vec::new(), false);
+        parallel.apply(gen_storage_applies(round), Vec::new(), false);
+        assert_eq!(serial.commit(), parallel.commit());
+    }
+
+    let address = H160::from_low_u64_be(3);
+    assert_eq!(
+        parallel.storage(address, H256::from_low_u64_be(2)),
+        H256::from_low_u64_be(304)
+    );
+    assert!(parallel
+        .storage(address, H256::from_low_u64_be(1))
+        .is_zero());
+    assert!(!parallel.exists(H160::from_low_u64_be(7)));
+}
+
+#[test]
+fn test_executor_limits() {
+    let adapter = exec_adapter();
+    // PUSH1 0x01 PUSH1 0x01 ADD
+    let code = hex_decode("6001600101").unwrap();
+
+    let r =
+        AxonExecutor::default().call(&adapter, u64::MAX, None, None, U256::zero(), code.clone());
+    assert!(r.exit_reason.is_succeed());
+
+    let limits = ExecutorLimits {
+        stack_limit: 1,
+        ..Default::default()
+    };
+    let r = AxonExecutor::new(limits).call(&adapter, u64::MAX, None, None, U256::zero(), code);
+    assert_eq!(r.exit_reason, ExitReason::Error(ExitError::StackOverflow));
+}
+
+#[test]
+fn test_call_eoa() {
+    let mut adapter = exec_adapter();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let eoa = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let call = |adapter: &AxonExecutorApplyAdapter<_, _>, value: u64| {
+        AxonExecutor::default().call(
+            adapter,
+            MAX_BLOCK_GAS_LIMIT,
+            Some(sender),
+            Some(eoa),
+            value.into(),
+            hex_decode("6d4ce63c").unwrap(),
+        )
+    };
+
+    let r = call(&adapter, 0);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+    assert!(r.ret.is_empty());
+
+    // The value is transferred as in Ethereum, which the sender can't afford.
+    let r = call(&adapter, 100);
+    assert_eq!(r.exit_reason, ExitReason::Error(ExitError::OutOfFund));
+
+    adapter.apply(
+        vec![Apply::Modify {
+            address:       sender,
+            basic:         Basic {
+                balance: 100u64.into(),
+                nonce:   U256::zero(),
+            },
+            code:          None,
+            storage:       Vec::new(),
+            reset_storage: false,
+        }],
+        Vec::new(),
+        false,
+    );
+    let r = call(&adapter, 100);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+    assert!(r.ret.is_empty());
+}
+
+#[test]
+fn test_call_system_contract() {
+    let adapter = exec_adapter();
+    let root = adapter.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY);
+    let update = image_cell_abi::UpdateCall {
+        blocks: vec![image_cell_abi::BlockUpdate {
+            block_number: 0x1,
+            tx_inputs:    vec![],
+            tx_outputs:   vec![],
+        }],
+    }
+    .encode();
+
+    let r = AxonExecutor::default().call(
+        &adapter,
+        MAX_BLOCK_GAS_LIMIT,
+        None,
+        Some(IMAGE_CELL_CONTRACT_ADDRESS),
+        U256::zero(),
+        update.clone(),
+    );
+    assert!(r.exit_reason.is_revert());
+    assert_eq!(r.gas_used, 0);
+    assert_eq!(
+        decode_revert_msg(&r.ret),
+        "execution reverted: system contract is not callable in a simulation"
+    );
+    assert_eq!(
+        adapter.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY),
+        root
+    );
+
+    // The calls which are not state-mutating methods run as usual.
+    for (to, data) in [
+        (IMAGE_CELL_CONTRACT_ADDRESS, vec![0xde, 0xad, 0xbe, 0xef]),
+        (system_contract_address(0xff), update),
+    ] {
+        let r = AxonExecutor::default().call(
+            &adapter,
+            MAX_BLOCK_GAS_LIMIT,
+            None,
+            Some(to),
+            U256::zero(),
+            data,
+        );
+        assert!(r.exit_reason.is_succeed());
+        assert!(r.ret.is_empty());
+    }
+}
+
+#[test]
+fn test_trace_access() {
+    let mut adapter = exec_adapter();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let (slot_a, slot_b) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+
+    // sstore(2, sload(1))
+    adapter.apply(
+        vec![Apply::Modify {
+            address:       contract,
+            basic:         Basic::default(),
+            code:          Some(hex_decode("60015460025500").unwrap()),
+            storage:       vec![(slot_a, H256::from_low_u64_be(0x2a))],
+            reset_storage: false,
+        }],
+        Vec::new(),
+        false,
+    );
+
+    let tx = gen_tx(sender, contract, 0, Vec::new());
+    let mut traced = AxonExecutor::default().trace_access(&mut adapter, &[tx]);
+    let (resp, accessed) = traced.pop().flatten().unwrap();
+    assert!(resp.exit_reason.is_succeed());
+    assert_eq!(
+        adapter.storage(contract, slot_b),
+        H256::from_low_u64_be(0x2a)
+    );
+
+    let slot_a = accessed.slot(&contract, &slot_a).unwrap();
+    assert!(slot_a.read && !slot_a.written);
+    assert!(accessed.slot(&contract, &slot_b).unwrap().written);
+    assert!(accessed.accounts[&contract].read);
+    assert!(accessed.accounts[&sender].written);
+}
+
+#[test]
+fn test_trace_txs() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract_a = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let contract_b = H160::from_str("0x2000000000000000000000000000000000000000").unwrap();
+    let block_adapter = || {
+        let mut adapter = exec_adapter();
+        // sstore(1, 1)
+        let applies = [contract_a, contract_b]
+            .into_iter()
+            .map(|address| Apply::Modify {
+                address,
+                basic: Basic::default(),
+                code: Some(hex_decode("600160015500").unwrap()),
+                storage: Vec::new(),
+                reset_storage: false,
+            })
+            .collect();
+        adapter.apply(applies, Vec::new(), false);
+        adapter
+    };
+    let txs = vec![
+        gen_tx(sender, contract_a, 0, Vec::new()),
+        gen_tx(sender, contract_b, 0, Vec::new()),
+    ];
+
+    let budget = TraceBudget {
+        steps: usize::MAX,
+        bytes: usize::MAX,
+    };
+    let traces = AxonExecutor::default()
+        .trace_txs(&mut block_adapter(), &txs, Tracer::Call, budget)
+        .unwrap();
+    let callees = traces
+        .iter()
+        .map(|trace| match trace {
+            Some(TxTrace::Call(frame)) => {
+                assert_eq!(frame.call_type, "CALL");
+                assert!(frame.error.is_none());
+                frame.to
+            }
+            _ => panic!("unexpected trace {:?}", trace),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(callees, vec![Some(contract_a), Some(contract_b)]);
+
+    let traces = AxonExecutor::default()
+        .trace_txs(&mut block_adapter(), &txs, Tracer::Struct, budget)
+        .unwrap();
+    assert_eq!(traces.len(), 2);
+    for trace in traces.iter() {
+        match trace {
+            Some(TxTrace::Struct(trace)) => {
+                assert!(!trace.failed);
+                let ops = trace
+                    .struct_logs
+                    .iter()
+                    .map(|log| log.op)
+                    .collect::<Vec<_>>();
+                assert_eq!(ops, vec![0x60, 0x60, 0x55, 0x00]);
+                assert!(trace.struct_logs.iter().all(|log| log.depth == 1));
+            }
+            _ => panic!("unexpected trace {:?}", trace),
+        }
+    }
+}
+
+#[test]
+fn test_trace_budget() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let loop_adapter = || {
+        let mut adapter = exec_adapter();
+        // for (i = 100; i != 0; i--) {}
+        adapter.apply(
+            vec![Apply::Modify {
+                address:       contract,
+                basic:         Basic::default(),
+                code:          Some(hex_decode("60645b600190038060025700").unwrap()),
+                storage:       Vec::new(),
+                reset_storage: false,
+            }],
+            Vec::new(),
+            false,
+        );
+        adapter
+    };
+    let txs = vec![
+        gen_tx(sender, contract, 0, Vec::new()),
+        gen_tx(sender, contract, 0, Vec::new()),
+    ];
+
+    // The steps are shared by the block, so the second transaction has none left.
+    let budget = TraceBudget {
+        steps: 10,
+        bytes: usize::MAX,
+    };
+    let traces = AxonExecutor::default()
+        .trace_txs(&mut loop_adapter(), &txs, Tracer::Struct, budget)
+        .unwrap();
+    let steps = traces
+        .iter()
+        .map(|trace| match trace {
+            Some(TxTrace::Struct(trace)) => {
+                assert!(!trace.failed);
+                assert!(trace.truncated);
+                trace.struct_logs.len()
+            }
+            trace => panic!("unexpected trace {:?}", trace),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(steps, vec![10, 0]);
+
+    // The tracing is aborted once the bytes of the block are exceeded.
+    let budget = TraceBudget {
+        steps: usize::MAX,
+        bytes: 10_000,
+    };
+    for tracer in [Tracer::Struct, Tracer::Call] {
+        let traces = AxonExecutor::default().trace_txs(&mut loop_adapter(), &txs, tracer, budget);
+        assert_eq!(traces.is_none(), tracer == Tracer::Struct);
+    }
+    let budget = TraceBudget {
+        steps: usize::MAX,
+        bytes: 300,
+    };
+    assert!(AxonExecutor::default()
+        .trace_txs(&mut loop_adapter(), &txs, Tracer::Call, budget)
+        .is_none());
+}
+
+#[test]
+fn test_call_gas_refund() {
+    let mut adapter = exec_adapter();
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let slot = H256::from_low_u64_be(1);
+
+    // sstore(1, calldataload(0))
+    adapter.apply(
+        vec![Apply::Modify {
+            address:       contract,
+            basic:         Basic::default(),
+            code:          Some(hex_decode("60003560015500").unwrap()),
+            storage:       vec![(slot, H256::from_low_u64_be(0x2a))],
+            reset_storage: false,
+        }],
+        Vec::new(),
+        false,
+    );
+
+    let call = |value: u64| {
+        AxonExecutor::default().call(
+            &adapter,
+            MAX_BLOCK_GAS_LIMIT,
+            None,
+            Some(contract),
+            U256::zero(),
+            H256::from_low_u64_be(value).0.to_vec(),
+        )
+    };
+
+    // Overwriting the slot refunds nothing, while clearing it does.
+    let r = call(1);
+    assert!(r.exit_reason.is_succeed());
+    assert_eq!(r.gas_refund(MAX_BLOCK_GAS_LIMIT), 0);
+    assert_eq!(MAX_BLOCK_GAS_LIMIT - r.remain_gas, r.gas_used);
+
+    let r = call(0);
+    assert!(r.exit_reason.is_succeed());
+    let refund = r.gas_refund(MAX_BLOCK_GAS_LIMIT);
+    assert!(refund > 0);
+    assert_eq!(MAX_BLOCK_GAS_LIMIT - r.remain_gas, r.gas_used + refund);
+}
+
+#[test]
+fn test_gas_refund_cap() {
+    let mut adapter = exec_adapter();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let clear = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let destruct = H160::from_str("0x2000000000000000000000000000000000000000").unwrap();
+
+    adapter.apply(
+        vec![
+            // sstore(0, 0) sstore(1, 0)
+            Apply::Modify {
+                address:       clear,
+                basic:         Basic::default(),
+                code:          Some(hex_decode("6000600055600060015500").unwrap()),
+                storage:       vec![
+                    (H256::from_low_u64_be(0), H256::from_low_u64_be(1)),
+                    (H256::from_low_u64_be(1), H256::from_low_u64_be(1)),
+                ],
+                reset_storage: false,
+            },
+            // selfdestruct(caller())
+            Apply::Modify {
+                address:       destruct,
+                basic:         Basic::default(),
+                code:          Some(hex_decode("33ff").unwrap()),
+                storage:       Vec::new(),
+                reset_storage: false,
+            },
+        ],
+        Vec::new(),
+        false,
+    );
+    let config = AxonExecutor::default().config();
+    let precompiles = build_precompile_set();
+
+    // Clearing two slots refunds more than a fifth of the gas, so the refund is
+    // capped.
+    let tx = gen_tx(sender, clear, 0, Vec::new());
+    let gas_limit = tx.transaction.unsigned.gas_limit().as_u64();
+    let r = EvmExecutor::evm_exec(&mut adapter, &config, &precompiles, &tx);
+    assert!(r.exit_reason.is_succeed());
+    let consumed = gas_limit - r.remain_gas;
+    assert!(2 * config.refund_sstore_clears as u64 > consumed / 5);
+    assert_eq!(r.gas_refund(gas_limit), consumed / 5);
+    assert_eq!(adapter.storage(clear, H256::zero()), H256::zero());
+
+    // Self destruct refunds nothing.
+    let tx = gen_tx(sender, destruct, 0, Vec::new());
+    let r = EvmExecutor::evm_exec(&mut adapter, &config, &precompiles, &tx);
+    assert!(r.exit_reason.is_succeed());
+    assert_eq!(r.gas_refund(gas_limit), 0);
+}
+
+#[test]
+fn test_compute_receipts_root() {
+    assert_eq!(compute_receipts_root(&[], &[]), RLP_NULL);
+
+    let mut adapter = exec_adapter();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let mut create = gen_tx(
+        sender,
+        H160::zero(),
+        0,
+        hex_decode(SIMPLESTORAGE_CREATE_CODE).unwrap(),
+    );
+    create
+        .transaction
+        .unsigned
+        .set_action(TransactionAction::Create);
+    // SimpleStorage.set(42)
+    let set = gen_tx(
+        sender,
+        code_address(&sender, &U256::zero()).into(),
+        0,
+        hex_decode("60fe47b1000000000000000000000000000000000000000000000000000000000000002a")
+            .unwrap(),
+    );
+    let txs = vec![create, set];
+
+    let resp = AxonExecutor::default().test_exec(&mut adapter, &txs, &[]);
+    assert_ne!(resp.receipt_root, RLP_NULL);
+    let (receipts, _) = Block::default().generate_receipts_and_logs(&txs, &resp);
+    assert_eq!(compute_receipts_root(&txs, &receipts), resp.receipt_root);
+}
+
 #[test]
 fn test_ackermann31() {
     let mut state = BTreeMap::new();
@@ -109,6 +603,78 @@ fn test_ackermann31() {
     assert_eq!(r.remain_gas, 68719455392);
 }
 
+#[test]
+fn test_create_and_call_zero_address() {
+    let mut adapter = exec_adapter();
+    let config = Config::london();
+    let precompiles = build_precompile_set();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let create_code = hex_decode(SIMPLESTORAGE_CREATE_CODE).unwrap();
+
+    // Calling the zero address with the creation code as input is a normal call,
+    // which deploys nothing.
+    let tx = gen_tx(sender, H160::zero(), 0, create_code.clone());
+    let r = EvmExecutor::evm_exec(&mut adapter, &config, &precompiles, &tx);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+    assert!(r.code_address.is_none());
+    assert!(adapter.code(H160::zero()).is_empty());
+    assert!(adapter
+        .code(code_address(&sender, &U256::zero()).into())
+        .is_empty());
+
+    // Only `TransactionAction::Create` deploys the contract.
+    let mut tx = gen_tx(sender, H160::zero(), 0, create_code);
+    tx.transaction
+        .unsigned
+        .set_action(TransactionAction::Create);
+    let r = EvmExecutor::evm_exec(&mut adapter, &config, &precompiles, &tx);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+    let contract = code_address(&sender, &U256::one());
+    assert_eq!(r.code_address, Some(contract));
+    assert!(!adapter.code(contract.into()).is_empty());
+    assert!(adapter.code(H160::zero()).is_empty());
+}
+
+#[test]
+fn test_disabled_opcodes() {
+    let mut adapter = exec_adapter();
+    let precompiles = build_precompile_set();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let andromeda = H256::from_low_u64_be((HardforkName::Andromeda as u64).to_be());
+    let disabled = vec![DisabledOpcodes {
+        hardfork: andromeda,
+        opcodes:  vec![DisableableOpcode::ChainId],
+    }];
+
+    // The runtime code returns the chain ID.
+    // CHAINID PUSH1 0x00 MSTORE PUSH1 0x20 PUSH1 0x00 RETURN
+    let create_code = hex_decode("6009600c60003960096000f34660005260206000f3").unwrap();
+    let mut tx = gen_tx(sender, H160::zero(), 0, create_code);
+    tx.transaction
+        .unsigned
+        .set_action(TransactionAction::Create);
+    let r = EvmExecutor::evm_exec(&mut adapter, &Config::london(), &precompiles, &tx);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+    let contract = code_address(&sender, &U256::zero()).into();
+
+    let tx = gen_tx(sender, contract, 0, vec![]);
+    let mut call = |flags: H256| {
+        let mut config = Config::london();
+        disable_opcodes(&mut config, &disabled, &flags);
+        EvmExecutor::evm_exec(&mut adapter, &config, &precompiles, &tx)
+    };
+
+    // The opcode is available before the hardfork.
+    let r = call(H256::zero());
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+
+    let r = call(andromeda);
+    assert_eq!(
+        r.exit_reason,
+        ExitReason::Error(ExitError::InvalidCode(Opcode::CHAINID))
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_simplestorage() {
     let mut state = BTreeMap::new();
@@ -126,27 +692,11 @@ async fn test_simplestorage() {
     let config = Config::london();
     let precompiles = build_precompile_set();
 
-    // pragma solidity ^0.4.24;
-    //
-    // contract SimpleStorage {
-    //     uint storedData;
-    //
-    //     function set(uint x) public {
-    //         storedData = x;
-    //     }
-    //
-    //     function get() view public returns (uint) {
-    //         return storedData;
-    //     }
-    // }
-    //
-    // simplestorage_create_code created from above solidity
-    let simplestorage_create_code = "608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029";
     let mut tx = gen_tx(
         H160::from_str("0xf000000000000000000000000000000000000000").unwrap(),
         H160::from_str("0x1000000000000000000000000000000000000000").unwrap(),
         0,
-        hex_decode(simplestorage_create_code).unwrap(),
+        hex_decode(SIMPLESTORAGE_CREATE_CODE).unwrap(),
     );
     tx.transaction
         .unsigned
@@ -185,7 +735,7 @@ async fn test_simplestorage() {
     assert_eq!(r.remain_gas, 68719455672);
 
     // let's call SimpleStorage.get() by call
-    let r = AxonExecutor.call(
+    let r = AxonExecutor::default().call(
         &adapter,
         u64::MAX,
         None,
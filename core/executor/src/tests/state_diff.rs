@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use protocol::codec::ProtocolCodec;
+use protocol::trie::{MemoryDB, Trie as _};
+use protocol::types::{Account, Hasher, MerkleRoot, H160, H256, NIL_DATA, RLP_NULL, U256};
+
+use crate::{AccountChange, AccountDiff, MPTTrie, StateDiff};
+
+fn insert_account(
+    trie: &mut MPTTrie<MemoryDB>,
+    address: H160,
+    balance: u64,
+    storage_root: MerkleRoot,
+) {
+    let account = Account {
+        nonce: U256::zero(),
+        balance: balance.into(),
+        storage_root,
+        code_hash: NIL_DATA,
+    };
+    trie.insert(
+        address.as_bytes().to_vec(),
+        account.encode().unwrap().to_vec(),
+    )
+    .unwrap();
+}
+
+fn storage_root(db: &Arc<MemoryDB>, slots: &[(H256, H256)]) -> MerkleRoot {
+    let mut trie = MPTTrie::new(Arc::clone(db));
+    for (k, v) in slots.iter() {
+        trie.insert(
+            k.as_bytes().to_vec(),
+            U256::from_big_endian(v.as_bytes())
+                .encode()
+                .unwrap()
+                .to_vec(),
+        )
+        .unwrap();
+    }
+    trie.commit().unwrap()
+}
+
+#[test]
+fn test_apply_and_diff_state() {
+    let db = Arc::new(MemoryDB::new(false));
+    let (addr_a, addr_b, addr_c, addr_d) = (
+        H160::from_low_u64_be(1),
+        H160::from_low_u64_be(2),
+        H160::from_low_u64_be(3),
+        H160::from_low_u64_be(4),
+    );
+    let (slot_1, slot_2) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+
+    let genesis_root = {
+        let mut trie = MPTTrie::new(Arc::clone(&db));
+        insert_account(&mut trie, addr_a, 100, RLP_NULL);
+        insert_account(&mut trie, addr_b, 200, RLP_NULL);
+        insert_account(&mut trie, addr_c, 300, RLP_NULL);
+        trie.commit().unwrap()
+    };
+
+    let mut change_a = AccountChange {
+        balance: 150u64.into(),
+        code_hash: NIL_DATA,
+        ..Default::default()
+    };
+    change_a.set_storage(slot_1, H256::from_low_u64_be(0xaa));
+    let mut change_d = AccountChange {
+        balance: 400u64.into(),
+        code_hash: Hasher::digest([0x60, 0x00]),
+        ..Default::default()
+    };
+    change_d
+        .set_storage(slot_1, H256::from_low_u64_be(0xbb))
+        .set_storage(slot_2, H256::from_low_u64_be(0xcc));
+
+    let mut diff = StateDiff::default();
+    diff.insert(&addr_a, AccountDiff::Modified(change_a))
+        .insert(&addr_c, AccountDiff::Deleted)
+        .insert(&addr_d, AccountDiff::Created(change_d));
+
+    let new_root = diff.apply(Arc::clone(&db), genesis_root).unwrap();
+
+    let expect_root = {
+        let mut trie = MPTTrie::new(Arc::clone(&db));
+        let root_a = storage_root(&db, &[(slot_1, H256::from_low_u64_be(0xaa))]);
+        insert_account(&mut trie, addr_a, 150, root_a);
+        insert_account(&mut trie, addr_b, 200, RLP_NULL);
+        let root_d = storage_root(&db, &[
+            (slot_1, H256::from_low_u64_be(0xbb)),
+            (slot_2, H256::from_low_u64_be(0xcc)),
+        ]);
+        let account_d = Account {
+            nonce:        U256::zero(),
+            balance:      400u64.into(),
+            storage_root: root_d,
+            code_hash:    Hasher::digest([0x60, 0x00]),
+        };
+        trie.insert(
+            addr_d.as_bytes().to_vec(),
+            account_d.encode().unwrap().to_vec(),
+        )
+        .unwrap();
+        trie.commit().unwrap()
+    };
+    assert_eq!(new_root, expect_root);
+
+    // Diffing the two states back recovers the applied changes.
+    let recovered = StateDiff::diff(Arc::clone(&db), genesis_root, new_root).unwrap();
+    assert_eq!(recovered, diff);
+    assert_eq!(recovered.get(&addr_c), Some(&AccountDiff::Deleted));
+    assert!(recovered.get(&addr_b).is_none());
+
+    // A slot removal is recorded as a zero value.
+    let mut change_a = AccountChange {
+        balance: 150u64.into(),
+        code_hash: NIL_DATA,
+        ..Default::default()
+    };
+    change_a.set_storage(slot_1, H256::zero());
+    let mut clear = StateDiff::default();
+    clear.insert(&addr_a, AccountDiff::Modified(change_a));
+    let cleared_root = clear.apply(Arc::clone(&db), new_root).unwrap();
+    assert_eq!(
+        StateDiff::diff(Arc::clone(&db), new_root, cleared_root).unwrap(),
+        clear
+    );
+
+    assert!(StateDiff::diff(db, new_root, new_root).unwrap().is_empty());
+}
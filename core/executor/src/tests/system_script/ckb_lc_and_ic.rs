@@ -3,5 +3,12 @@ use super::{ckb_light_client, image_cell};
 #[test]
 fn test_ckb_light_client_and_image_cell() {
     ckb_light_client::test_write_functions();
+    ckb_light_client::test_batch_rollback();
+    ckb_light_client::test_header_retention();
     image_cell::test_write_functions();
+    image_cell::test_system_contract_first();
+    image_cell::test_cell_data_size_limit();
+    image_cell::test_strict_cell_inputs();
+    image_cell::test_live_cells();
+    image_cell::test_create_and_consume_in_same_call();
 }
@@ -7,15 +7,21 @@ use core_db::RocksAdapter;
 use protocol::types::{Backend, MemoryBackend, TxResp, H160, H256, U256};
 
 use crate::system_contract::ckb_light_client::{
-    ckb_light_client_abi, CkbHeaderReader, CkbLightClientContract,
+    ckb_light_client_abi, CkbHeaderReader, CkbLightClientContract, CkbLightClientStore,
+};
+use crate::system_contract::image_cell::{
+    image_cell_abi, CellKey, ImageCellReader, ImageCellStore,
 };
 use crate::system_contract::{
-    init_system_contract_db, SystemContract, CKB_LIGHT_CLIENT_CONTRACT_ADDRESS,
+    init_system_contract_db, DataProvider, SystemContract, CKB_LIGHT_CLIENT_CONTRACT_ADDRESS,
     HEADER_CELL_ROOT_KEY, IMAGE_CELL_CONTRACT_ADDRESS,
 };
 use crate::tests::{gen_tx, gen_vicinity};
+use crate::CURRENT_HEADER_CELL_ROOT;
 
 static ROCKSDB_PATH: &str = "./free-space/system-contract/ckb-light-client";
+static ROLLBACK_ROCKSDB_PATH: &str = "./free-space/system-contract/ckb-light-client-rollback";
+static RETENTION_ROCKSDB_PATH: &str = "./free-space/system-contract/ckb-light-client-retention";
 
 pub fn test_write_functions() {
     let vicinity = gen_vicinity();
@@ -37,6 +43,142 @@ pub fn test_write_functions() {
     test_set_state(&mut backend, &executor);
 }
 
+pub fn test_batch_rollback() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+    let executor = CkbLightClientContract::default();
+    let inner_db = RocksAdapter::new(ROLLBACK_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let (_, h_root) = init_system_contract_db(inner_db, &mut backend);
+    CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow_mut() = h_root);
+
+    let headers = (1..=3u8)
+        .map(|i| ckb_light_client_abi::Header {
+            number: i.into(),
+            block_hash: [i; 32],
+            ..prepare_header_1()
+        })
+        .collect::<Vec<_>>();
+    let data = ckb_light_client_abi::UpdateCall {
+        headers: headers.clone(),
+    };
+    let r = exec(&mut backend, &executor, data.encode());
+    assert!(r.exit_reason.is_succeed());
+
+    // The headers out of order or with a gap are rejected as a whole.
+    for block_hashes in [vec![[1u8; 32], [2u8; 32]], vec![[3u8; 32], [1u8; 32]]] {
+        let data = ckb_light_client_abi::RollbackCall { block_hashes };
+        let r = exec(&mut backend, &executor, data.encode());
+        assert!(r.exit_reason.is_revert());
+    }
+
+    let root = backend.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY);
+    for header in headers.iter() {
+        assert!(CkbHeaderReader
+            .get_header_by_block_hash(root, &H256::from_slice(&header.block_hash))
+            .unwrap()
+            .is_some());
+    }
+
+    let data = ckb_light_client_abi::RollbackCall {
+        block_hashes: vec![[3u8; 32], [2u8; 32], [1u8; 32]],
+    };
+    let r = exec(&mut backend, &executor, data.encode());
+    assert!(r.exit_reason.is_succeed());
+
+    let root = backend.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY);
+    for header in headers.iter() {
+        assert!(CkbHeaderReader
+            .get_header_by_block_hash(root, &H256::from_slice(&header.block_hash))
+            .unwrap()
+            .is_none());
+    }
+}
+
+pub fn test_header_retention() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+    let executor = CkbLightClientContract::default();
+    let inner_db = RocksAdapter::new(RETENTION_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let (_, h_root) = init_system_contract_db(inner_db, &mut backend);
+    CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow_mut() = h_root);
+
+    // A cell saved before the headers is not pruned with them.
+    let cell = image_cell_abi::CellInfo {
+        out_point: image_cell_abi::OutPoint {
+            tx_hash: [7u8; 32],
+            index:   0x0,
+        },
+        ..Default::default()
+    };
+    ImageCellStore::new(h_root)
+        .unwrap()
+        .update(
+            image_cell_abi::UpdateCall {
+                blocks: vec![image_cell_abi::BlockUpdate {
+                    block_number: 0x1,
+                    tx_inputs:    vec![],
+                    tx_outputs:   vec![cell],
+                }],
+            },
+            false,
+        )
+        .unwrap();
+
+    let headers = (1..=5u8)
+        .map(|i| ckb_light_client_abi::Header {
+            number: i.into(),
+            block_hash: [i; 32],
+            ..prepare_header_1()
+        })
+        .collect::<Vec<_>>();
+    // Keep the headers of the recent 3 blocks.
+    for header in headers.iter() {
+        let root = CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow());
+        let data = ckb_light_client_abi::UpdateCall {
+            headers: vec![header.clone()],
+        };
+        CkbLightClientStore::new(root)
+            .unwrap()
+            .update(data, 3)
+            .unwrap();
+    }
+
+    let root = CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow());
+    let get_header = |block_hash: [u8; 32]| {
+        CkbHeaderReader
+            .get_header_by_block_hash(root, &H256(block_hash))
+            .unwrap()
+    };
+    assert!(get_header([1u8; 32]).is_none());
+    assert!(get_header([2u8; 32]).is_none());
+    for header in headers[2..].iter() {
+        assert_eq!(get_header(header.block_hash).as_ref(), Some(header));
+    }
+    assert!(ImageCellReader
+        .get_cell(root, &CellKey::new([7u8; 32], 0x0))
+        .unwrap()
+        .is_some());
+
+    // The headers in the window can be rolled back.
+    let data = ckb_light_client_abi::RollbackCall {
+        block_hashes: vec![[5u8; 32], [4u8; 32]],
+    };
+    let r = exec(&mut backend, &executor, data.encode());
+    assert!(r.exit_reason.is_succeed());
+
+    let root = backend.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY);
+    let header_3 = CkbHeaderReader
+        .get_header_by_block_hash(root, &H256([3u8; 32]))
+        .unwrap();
+    assert_eq!(header_3.as_ref(), Some(&headers[2]));
+}
+
 fn prepare_header_1() -> ckb_light_client_abi::Header {
     ckb_light_client_abi::Header::default()
 }
@@ -103,6 +245,11 @@ fn test_update_second<'a>(
         .unwrap();
 
     assert_eq!(queried_header, header);
+
+    let header_info = DataProvider::new(root)
+        .get_header_info(H256(header.block_hash))
+        .unwrap();
+    assert_eq!(header_info, Some(header));
 }
 
 fn test_roll_back_first<'a>(
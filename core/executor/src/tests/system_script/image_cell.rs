@@ -1,23 +1,32 @@
 use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use ckb_types::{bytes::Bytes, packed, prelude::*};
 use ethers::abi::AbiEncode;
 
+use common_config_parser::types::spec::HardforkName;
 use core_db::RocksAdapter;
-use protocol::types::{Backend, MemoryBackend, TxResp, H160, U256};
+use protocol::traits::Executor;
+use protocol::types::{Backend, ExitError, ExitReason, MemoryBackend, TxResp, H160, H256, U256};
 
+use crate::precompiles::call_ckb_vm::{CallCkbVmPayload, CellDep};
 use crate::system_contract::image_cell::{
-    image_cell_abi, CellInfo, CellKey, ImageCellContract, ImageCellReader,
+    image_cell_abi, CellInfo, CellKey, ImageCellContract, ImageCellReader, ImageCellStore,
 };
 use crate::system_contract::{
-    init_system_contract_db, SystemContract, CKB_LIGHT_CLIENT_CONTRACT_ADDRESS,
-    HEADER_CELL_ROOT_KEY, IMAGE_CELL_CONTRACT_ADDRESS,
+    init_system_contract_db, set_max_cell_data_size, DataProvider, SystemContract,
+    CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, HEADER_CELL_ROOT_KEY, IMAGE_CELL_CONTRACT_ADDRESS,
 };
-use crate::tests::{gen_tx, gen_vicinity};
-use crate::{CURRENT_HEADER_CELL_ROOT, CURRENT_METADATA_ROOT};
+use crate::tests::{exec_adapter, gen_tx, gen_vicinity};
+use crate::{AxonExecutor, CURRENT_HEADER_CELL_ROOT, CURRENT_METADATA_ROOT};
 
 static ROCKSDB_PATH: &str = "./free-space/system-contract/image-cell";
+static ORDER_ROCKSDB_PATH: &str = "./free-space/system-contract/image-cell-order";
+static LIMIT_ROCKSDB_PATH: &str = "./free-space/system-contract/image-cell-limit";
+static STRICT_ROCKSDB_PATH: &str = "./free-space/system-contract/image-cell-strict";
+static LIVE_ROCKSDB_PATH: &str = "./free-space/system-contract/image-cell-live";
+static SAME_CALL_ROCKSDB_PATH: &str = "./free-space/system-contract/image-cell-same-call";
 
 pub fn test_write_functions() {
     let vicinity = gen_vicinity();
@@ -41,6 +50,271 @@ pub fn test_write_functions() {
     test_set_state(&mut backend, &executor);
 }
 
+/// An interop transaction which loads a program from an image cell is placed
+/// before the image cell update creating the cell in the same block.
+pub fn test_system_contract_first() {
+    let inner_db = RocksAdapter::new(ORDER_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    // The address of the `CallCkbVM` precompile contract.
+    let call_ckb_vm = H160::from_low_u64_be(0x0104);
+
+    let update = image_cell_abi::UpdateCall {
+        blocks: vec![image_cell_abi::BlockUpdate {
+            block_number: 0x1,
+            tx_inputs:    vec![],
+            tx_outputs:   prepare_outputs(),
+        }],
+    };
+    let payload = CallCkbVmPayload {
+        cell:   CellDep {
+            out_point: image_cell_abi::OutPoint {
+                tx_hash: [7u8; 32],
+                index:   0x0,
+            },
+            dep_type:  0,
+        },
+        inputs: vec![],
+    };
+    let txs = vec![
+        gen_tx(sender, call_ckb_vm, 0, payload.encode()),
+        gen_tx(sender, IMAGE_CELL_CONTRACT_ADDRESS, 0, update.encode()),
+    ];
+
+    let exec = |hardforks: u64| {
+        let mut adapter = exec_adapter();
+        init_system_contract_db(Arc::clone(&inner_db), &mut adapter);
+        AxonExecutor::default()
+            .with_hardforks(H256::from_low_u64_be(hardforks.to_be()))
+            .exec(&mut adapter, &txs, &[])
+    };
+
+    // Before the Antlia hardfork, the transactions are executed in the block
+    // order and the interop transaction can not find the cell.
+    let resp = exec(HardforkName::None as u64);
+    assert!(resp.tx_resp[1].exit_reason.is_succeed());
+    assert!(vm_error_contains(
+        &resp.tx_resp[0].exit_reason,
+        "Cannot get program"
+    ));
+
+    // Since the Antlia hardfork, the image cell update is executed first, so
+    // the program is loaded from the cell. The cell data is not a valid program,
+    // which fails the VM call.
+    let resp = exec(HardforkName::Antlia as u64);
+    assert_eq!(resp.tx_resp.len(), 2);
+    assert!(resp.tx_resp[1].exit_reason.is_succeed());
+    assert!(vm_error_contains(
+        &resp.tx_resp[0].exit_reason,
+        "CKB VM call failed"
+    ));
+}
+
+pub fn test_cell_data_size_limit() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+    let executor = ImageCellContract::default();
+    let inner_db = RocksAdapter::new(LIMIT_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let (_, h_root) = init_system_contract_db(inner_db, &mut backend);
+    CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow_mut() = h_root);
+
+    let cell = prepare_outputs().remove(0);
+    let limit = cell.data.0.len();
+    set_max_cell_data_size(limit as u64);
+
+    let update = |tx_outputs| image_cell_abi::UpdateCall {
+        blocks: vec![image_cell_abi::BlockUpdate {
+            block_number: 0x1,
+            tx_inputs: vec![],
+            tx_outputs,
+        }],
+    };
+
+    // The second cell is over the limit, so none of the cells is saved.
+    let mut large = cell.clone();
+    large.out_point.index = 0x1;
+    large.data = vec![0u8; limit + 1].into();
+    let r = exec(
+        &mut backend,
+        &executor,
+        update(vec![cell.clone(), large]).encode(),
+    );
+    assert!(r.exit_reason.is_revert());
+
+    let root = CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow());
+    assert_eq!(root, h_root);
+    let cell_key = CellKey::new([7u8; 32], 0x0);
+    assert!(ImageCellReader.get_cell(root, &cell_key).unwrap().is_none());
+
+    // The cell at the limit is accepted.
+    let r = exec(&mut backend, &executor, update(vec![cell]).encode());
+    assert!(r.exit_reason.is_succeed());
+
+    let root = backend.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY);
+    let get_cell = ImageCellReader.get_cell(root, &cell_key).unwrap().unwrap();
+    check_cell(&get_cell, 0x1, None);
+
+    set_max_cell_data_size(0);
+}
+
+pub fn test_strict_cell_inputs() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+    let executor = ImageCellContract::default();
+    let inner_db = RocksAdapter::new(STRICT_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let (_, h_root) = init_system_contract_db(inner_db, &mut backend);
+    CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow_mut() = h_root);
+
+    // The input points at a cell which is never saved.
+    let update = image_cell_abi::UpdateCall {
+        blocks: vec![image_cell_abi::BlockUpdate {
+            block_number: 0x1,
+            tx_inputs:    vec![image_cell_abi::OutPoint {
+                tx_hash: [8u8; 32],
+                index:   0x1,
+            }],
+            tx_outputs:   prepare_outputs(),
+        }],
+    };
+
+    let addr = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let tx = gen_tx(addr, IMAGE_CELL_CONTRACT_ADDRESS, 1000, update.encode());
+
+    // Since the Apus hardfork, the input fails the whole update.
+    let r = executor.exec_with_hardforks(&mut backend, &tx, &HardforkName::Apus.flag());
+    assert!(r.exit_reason.is_revert());
+    assert_eq!(CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow()), h_root);
+
+    // Before the hardfork, the input is skipped and the outputs are saved.
+    let r = executor.exec_with_hardforks(&mut backend, &tx, &H256::zero());
+    assert!(r.exit_reason.is_succeed());
+
+    let root = backend.storage(CKB_LIGHT_CLIENT_CONTRACT_ADDRESS, *HEADER_CELL_ROOT_KEY);
+    let get_cell = ImageCellReader
+        .get_cell(root, &CellKey::new([7u8; 32], 0x0))
+        .unwrap()
+        .unwrap();
+    check_cell(&get_cell, 0x1, None);
+    assert!(ImageCellReader
+        .get_cell(root, &CellKey::new([8u8; 32], 0x1))
+        .unwrap()
+        .is_none());
+}
+
+pub fn test_live_cells() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+    let inner_db = RocksAdapter::new(LIVE_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let (_, h_root) = init_system_contract_db(inner_db, &mut backend);
+
+    let outputs = (0..3)
+        .map(|index| {
+            let mut cell = prepare_outputs().remove(0);
+            cell.out_point.index = index;
+            cell
+        })
+        .collect();
+    let consumed = image_cell_abi::OutPoint {
+        tx_hash: [7u8; 32],
+        index:   0x1,
+    };
+    let update = image_cell_abi::UpdateCall {
+        blocks: vec![
+            image_cell_abi::BlockUpdate {
+                block_number: 0x1,
+                tx_inputs:    vec![],
+                tx_outputs:   outputs,
+            },
+            image_cell_abi::BlockUpdate {
+                block_number: 0x2,
+                tx_inputs:    vec![consumed],
+                tx_outputs:   vec![],
+            },
+        ],
+    };
+
+    let mut store = ImageCellStore::new(h_root).unwrap();
+    store.update(update, true).unwrap();
+    assert_eq!(store.cells().count(), 3);
+
+    let mut live_cells = store
+        .live_cells()
+        .map(|(key, cell)| {
+            check_cell(&cell, 0x1, None);
+            key
+        })
+        .collect::<Vec<_>>();
+    live_cells.sort();
+    let mut expected = vec![
+        CellKey::new([7u8; 32], 0x0).hash(),
+        CellKey::new([7u8; 32], 0x2).hash(),
+    ];
+    expected.sort();
+    assert_eq!(live_cells, expected);
+}
+
+pub fn test_create_and_consume_in_same_call() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+    let inner_db = RocksAdapter::new(SAME_CALL_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    let (_, h_root) = init_system_contract_db(inner_db, &mut backend);
+
+    let cell = prepare_outputs().remove(0);
+    let cell_key = CellKey::new(cell.out_point.tx_hash, cell.out_point.index);
+
+    // The duplicated output fails the update even in different blocks.
+    let update = image_cell_abi::UpdateCall {
+        blocks: vec![
+            image_cell_abi::BlockUpdate {
+                block_number: 0x1,
+                tx_inputs:    vec![],
+                tx_outputs:   vec![cell.clone()],
+            },
+            image_cell_abi::BlockUpdate {
+                block_number: 0x2,
+                tx_inputs:    vec![],
+                tx_outputs:   vec![cell.clone()],
+            },
+        ],
+    };
+    let err = ImageCellStore::new(h_root)
+        .unwrap()
+        .update(update, true)
+        .unwrap_err();
+    assert!(err.to_string().contains("is duplicated"));
+    assert_eq!(CURRENT_HEADER_CELL_ROOT.with(|r| *r.borrow()), h_root);
+
+    // The cell created and consumed in the same block ends up consumed.
+    let update = image_cell_abi::UpdateCall {
+        blocks: vec![image_cell_abi::BlockUpdate {
+            block_number: 0x1,
+            tx_inputs:    vec![cell.out_point.clone()],
+            tx_outputs:   vec![cell],
+        }],
+    };
+    let mut store = ImageCellStore::new(h_root).unwrap();
+    store.update(update, true).unwrap();
+    let get_cell = store.get_cell(&cell_key).unwrap().unwrap();
+    check_cell(&get_cell, 0x1, Some(0x1));
+    assert_eq!(get_cell.consumed_number, Some(0x1));
+    assert_eq!(store.live_cells().count(), 0);
+}
+
+fn vm_error_contains(reason: &ExitReason, msg: &str) -> bool {
+    matches!(reason, ExitReason::Error(ExitError::Other(e)) if e.contains(msg))
+}
+
 fn test_update_first<'a>(
     backend: &mut MemoryBackend<'a>,
     executor: &ImageCellContract<MemoryBackend<'a>>,
@@ -88,6 +362,18 @@ fn test_update_second<'a>(
     let cell_key = CellKey::new([7u8; 32], 0x0);
     let get_cell = ImageCellReader.get_cell(root, &cell_key).unwrap().unwrap();
     check_cell(&get_cell, 0x1, Some(0x2));
+
+    // The consumed cell is readable by the out point.
+    let provider = DataProvider::new(root);
+    let cell_info = provider
+        .get_cell_info(H256([7u8; 32]), 0x0)
+        .unwrap()
+        .unwrap();
+    check_cell(&cell_info, 0x1, Some(0x2));
+    assert!(provider
+        .get_cell_info(H256([7u8; 32]), 0x1)
+        .unwrap()
+        .is_none());
 }
 
 fn test_rollback_first<'a>(
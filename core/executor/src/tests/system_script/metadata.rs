@@ -3,14 +3,16 @@ use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 use ethers::abi::AbiEncode;
 
 use core_db::RocksAdapter;
-use protocol::types::{CkbRelatedInfo, MemoryBackend, SignedTransaction, H160, H256, U256};
+use protocol::types::{
+    CkbRelatedInfo, MemoryBackend, ProposeCount, SignedTransaction, H160, H256, U256,
+};
 
 use crate::{
     system_contract::{
         init_system_contract_db,
         metadata::{
             metadata_abi::{self, ConsensusConfig, Metadata, MetadataVersion, ValidatorExtend},
-            MetadataContract, MetadataStore,
+            MetadataContract, MetadataHandle, MetadataStore,
         },
         SystemContract, METADATA_CONTRACT_ADDRESS, METADATA_DB,
     },
@@ -20,6 +22,9 @@ use crate::{
 
 static ROCKSDB_PATH: &str = "./free-space/system-contract/metadata";
 static CKB_INFO_ROCKSDB_PATH: &str = "./free-space/system-contract/ckb_info";
+static EPOCH_ROCKSDB_PATH: &str = "./free-space/system-contract/epoch";
+static SHORT_EPOCH_ROCKSDB_PATH: &str = "./free-space/system-contract/short-epoch";
+static SLASHING_ROCKSDB_PATH: &str = "./free-space/system-contract/slashing";
 
 #[test]
 fn test_write_functions() {
@@ -250,3 +255,131 @@ fn test_set_ckb_related_info() {
         assert_ne!(new_metadata_root, old_metadata_root);
     });
 }
+
+#[test]
+fn test_propose_counter_reset_at_epoch_boundary() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+    let inner_db = RocksAdapter::new(EPOCH_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    init_system_contract_db(inner_db, &mut backend);
+
+    let validator_a = prepare_validator().address;
+    let validator_b = H160::from_str("0xe000000000000000000000000000000000000000").unwrap();
+
+    let mut store = MetadataStore::new(H256::zero()).unwrap();
+    store.append_metadata(&prepare_metadata().into()).unwrap();
+
+    let mut next = prepare_metadata();
+    next.epoch = 1;
+    next.version.start = 101;
+    next.version.end = 200;
+    next.verifier_list[0].address = validator_b;
+    store.append_metadata(&next.into()).unwrap();
+
+    // The blocks of epoch 0 up to the boundary, as the after block hook runs.
+    for number in 1..=100 {
+        store.update_propose_count(number, &validator_a).unwrap();
+        store.init_next_propose_counter(number).unwrap();
+    }
+    assert_eq!(store.get_metadata(1).unwrap().propose_counter, vec![
+        ProposeCount::from((validator_b, 0))
+    ]);
+
+    // The first block of epoch 1 counts from zero.
+    store.update_propose_count(101, &validator_b).unwrap();
+    store.init_next_propose_counter(101).unwrap();
+
+    assert_eq!(store.get_metadata(0).unwrap().propose_counter, vec![
+        ProposeCount::from((validator_a, 100))
+    ]);
+    assert_eq!(store.get_metadata(1).unwrap().propose_counter, vec![
+        ProposeCount::from((validator_b, 1))
+    ]);
+
+    // The next epoch has not been appended yet.
+    store.init_next_propose_counter(200).unwrap();
+}
+
+#[test]
+fn test_metadata_by_block() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+    let inner_db = RocksAdapter::new(SHORT_EPOCH_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    init_system_contract_db(inner_db, &mut backend);
+
+    // Each epoch lasts 10 blocks.
+    let mut store = MetadataStore::new(H256::zero()).unwrap();
+    let mut first = prepare_metadata();
+    first.version.end = 10;
+    store.append_metadata(&first.into()).unwrap();
+
+    let mut next = prepare_metadata();
+    next.epoch = 1;
+    next.version.start = 11;
+    next.version.end = 20;
+    next.verifier_list[0].address =
+        H160::from_str("0xe000000000000000000000000000000000000000").unwrap();
+    store.append_metadata(&next.into()).unwrap();
+
+    let handle = MetadataHandle::new(CURRENT_METADATA_ROOT.with(|r| *r.borrow()));
+    assert_eq!(handle.epoch_of_block(0).unwrap(), Some(0));
+    assert_eq!(handle.epoch_of_block(10).unwrap(), Some(0));
+    assert_eq!(handle.epoch_of_block(11).unwrap(), Some(1));
+    // The block is in an epoch which has not been appended yet.
+    assert_eq!(handle.epoch_of_block(21).unwrap(), None);
+
+    let epoch_0 = handle.get_metadata_by_epoch(0).unwrap();
+    let epoch_1 = handle.get_metadata_by_epoch(1).unwrap();
+    assert_eq!(handle.get_metadata_by_block_number(5).unwrap(), epoch_0);
+    assert_eq!(handle.get_metadata_by_block_number(15).unwrap(), epoch_1);
+    assert_ne!(epoch_0.verifier_list, epoch_1.verifier_list);
+}
+
+#[test]
+fn test_slashing_report() {
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+    let inner_db = RocksAdapter::new(SLASHING_ROCKSDB_PATH, Default::default())
+        .unwrap()
+        .inner_db();
+    init_system_contract_db(inner_db, &mut backend);
+
+    // Each validator is expected to propose 50 of the 100 blocks.
+    let validator_a = prepare_validator().address;
+    let validator_b = H160::from_str("0xe000000000000000000000000000000000000000").unwrap();
+    let mut metadata = prepare_metadata();
+    let mut validator = prepare_validator();
+    validator.address = validator_b;
+    metadata.verifier_list.push(validator);
+
+    let mut store = MetadataStore::new(H256::zero()).unwrap();
+    store.append_metadata(&metadata.into()).unwrap();
+
+    for number in 1..=100 {
+        let proposer = if number <= 95 {
+            validator_a
+        } else {
+            validator_b
+        };
+        store.update_propose_count(number, &proposer).unwrap();
+    }
+
+    // The validator b missed 45 proposals and the validator a missed none.
+    assert_eq!(store.faulty_validators(0, 10).unwrap(), vec![validator_b]);
+    assert_eq!(store.faulty_validators(0, 45).unwrap(), vec![]);
+
+    // An empty report leaves the state unchanged.
+    let root = store.trie.commit().unwrap();
+    assert_eq!(store.report_slashing(0, 45).unwrap(), vec![]);
+    assert_eq!(store.trie.commit().unwrap(), root);
+    assert_eq!(store.slashing_report(0).unwrap(), vec![]);
+
+    // The report is saved under the epoch.
+    assert_eq!(store.report_slashing(0, 10).unwrap(), vec![validator_b]);
+    assert_eq!(store.slashing_report(0).unwrap(), vec![validator_b]);
+    assert_eq!(store.slashing_report(1).unwrap(), vec![]);
+}
@@ -0,0 +1,321 @@
+use std::{cell::RefCell, rc::Rc};
+
+use evm::tracing::{Event, EventListener};
+use evm::CreateScheme;
+
+use protocol::types::{
+    CallFrame, Hex, SignedTransaction, StructLog, StructTrace, TransactionAction, TxResp, H160,
+    U256,
+};
+
+// The approximate serialized sizes of a struct log without its stack, of an
+// item of the stack and of a call frame without its input and output.
+const STRUCT_LOG_BYTES: usize = 48;
+const STACK_ITEM_BYTES: usize = 69;
+const CALL_FRAME_BYTES: usize = 256;
+
+/// The budget of the traces of a block, which is shared by all of its
+/// transactions.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceBudget {
+    /// The max steps of the struct logs, the ones beyond it are truncated.
+    pub steps: usize,
+    /// The max approximate serialized size of the traces in bytes, the tracing
+    /// is aborted once it is exceeded.
+    pub bytes: usize,
+}
+
+/// A listener which records the tree of the calls made by the transactions of
+/// a block one by one.
+pub struct CallTracer {
+    // The calls which have not exited yet, the innermost one is the last.
+    frames:   Vec<CallFrame>,
+    root:     Option<CallFrame>,
+    // The bytes left in the budget, the calls entered after it is exceeded are
+    // skipped.
+    bytes:    usize,
+    skipped:  usize,
+    exceeded: bool,
+}
+
+impl CallTracer {
+    pub fn new(bytes: usize) -> Self {
+        CallTracer {
+            frames: Vec::new(),
+            root: None,
+            bytes,
+            skipped: 0,
+            exceeded: false,
+        }
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// Take the trace of the last transaction. The outermost call is built
+    /// from the transaction and its response, and the inner calls are the
+    /// recorded ones.
+    pub fn take_trace(&mut self, tx: &SignedTransaction, resp: &TxResp) -> CallFrame {
+        while !self.frames.is_empty() {
+            self.exit(None, Vec::new());
+        }
+        self.skipped = 0;
+
+        let unsigned = &tx.transaction.unsigned;
+        let (call_type, to) = match unsigned.action() {
+            TransactionAction::Call(addr) => ("CALL", Some(*addr)),
+            TransactionAction::Create => ("CREATE", resp.code_address.map(H160::from)),
+        };
+        self.charge(CALL_FRAME_BYTES + 2 * (unsigned.data().len() + resp.ret.len()));
+
+        CallFrame {
+            call_type: call_type.to_string(),
+            from: tx.sender,
+            to,
+            value: *unsigned.value(),
+            gas: unsigned.gas_limit().as_u64(),
+            gas_used: resp.gas_used,
+            input: Hex::encode(unsigned.data()),
+            output: Hex::encode(&resp.ret),
+            error: (!resp.exit_reason.is_succeed()).then(|| format!("{:?}", resp.exit_reason)),
+            calls: self.root.take().map(|root| root.calls).unwrap_or_default(),
+        }
+    }
+
+    fn charge(&mut self, bytes: usize) -> bool {
+        if self.exceeded || bytes > self.bytes {
+            self.exceeded = true;
+            return false;
+        }
+        self.bytes -= bytes;
+        true
+    }
+
+    fn enter(&mut self, frame: CallFrame, input_len: usize) {
+        if self.skipped > 0 || !self.charge(CALL_FRAME_BYTES + 2 * input_len) {
+            self.skipped += 1;
+            return;
+        }
+        self.frames.push(frame);
+    }
+
+    fn exit(&mut self, error: Option<String>, output: Vec<u8>) {
+        if self.skipped > 0 {
+            self.skipped -= 1;
+            return;
+        }
+        let mut frame = match self.frames.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        frame.error = error;
+        if self.charge(2 * output.len()) {
+            frame.output = Hex::encode(output);
+        }
+
+        match self.frames.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl EventListener for CallTracer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Call {
+                code_address,
+                transfer,
+                input,
+                target_gas,
+                is_static,
+                context,
+            } => {
+                let call_type = if is_static {
+                    "STATICCALL"
+                } else if code_address != context.address {
+                    "DELEGATECALL"
+                } else {
+                    "CALL"
+                };
+                self.enter(
+                    new_frame(
+                        call_type,
+                        context.caller,
+                        Some(context.address),
+                        transfer
+                            .as_ref()
+                            .map(|t| t.value)
+                            .unwrap_or(context.apparent_value),
+                        target_gas.unwrap_or_default(),
+                        input,
+                    ),
+                    input.len(),
+                );
+            }
+            Event::Create {
+                caller,
+                address,
+                scheme,
+                value,
+                init_code,
+                target_gas,
+            } => {
+                let call_type = match scheme {
+                    CreateScheme::Create2 { .. } => "CREATE2",
+                    _ => "CREATE",
+                };
+                self.enter(
+                    new_frame(
+                        call_type,
+                        caller,
+                        Some(address),
+                        value,
+                        target_gas.unwrap_or_default(),
+                        init_code,
+                    ),
+                    init_code.len(),
+                );
+            }
+            Event::Suicide {
+                address,
+                target,
+                balance,
+            } => {
+                self.enter(
+                    new_frame("SELFDESTRUCT", address, Some(target), balance, 0, &[]),
+                    0,
+                );
+                self.exit(None, Vec::new());
+            }
+            Event::Exit {
+                reason,
+                return_value,
+            } => {
+                let error = (!reason.is_succeed()).then(|| format!("{:?}", reason));
+                self.exit(error, return_value.to_vec());
+            }
+            _ => (),
+        }
+    }
+}
+
+fn new_frame(
+    call_type: &str,
+    from: H160,
+    to: Option<H160>,
+    value: U256,
+    gas: u64,
+    input: &[u8],
+) -> CallFrame {
+    CallFrame {
+        call_type: call_type.to_string(),
+        from,
+        to,
+        value,
+        gas,
+        gas_used: 0,
+        input: Hex::encode(input),
+        output: Hex::empty(),
+        error: None,
+        calls: Vec::new(),
+    }
+}
+
+struct StructLogs {
+    logs:      Vec<StructLog>,
+    depth:     u64,
+    // The budget left for the rest of the block.
+    budget:    TraceBudget,
+    truncated: bool,
+    exceeded:  bool,
+}
+
+/// A listener which records each opcode executed by the transactions of a
+/// block one by one. It listens to the calls for the depth and to the steps of
+/// the runtime for the opcodes, so the clones of it share the records.
+#[derive(Clone)]
+pub struct StructLogger(Rc<RefCell<StructLogs>>);
+
+impl StructLogger {
+    /// The steps beyond the budget are not recorded, the execution goes on and
+    /// the trace is marked as truncated.
+    pub fn new(budget: TraceBudget) -> Self {
+        StructLogger(Rc::new(RefCell::new(StructLogs {
+            logs: Vec::new(),
+            depth: 0,
+            budget,
+            truncated: false,
+            exceeded: false,
+        })))
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.0.borrow().exceeded
+    }
+
+    /// Take the trace of the last transaction.
+    pub fn take_trace(&self, resp: &TxResp) -> StructTrace {
+        let mut inner = self.0.borrow_mut();
+        inner.depth = 0;
+        match inner.budget.bytes.checked_sub(2 * resp.ret.len()) {
+            Some(bytes) => inner.budget.bytes = bytes,
+            None => inner.exceeded = true,
+        }
+        StructTrace {
+            gas:          resp.gas_used,
+            failed:       !resp.exit_reason.is_succeed(),
+            return_value: Hex::encode(&resp.ret),
+            struct_logs:  std::mem::take(&mut inner.logs),
+            truncated:    std::mem::take(&mut inner.truncated),
+        }
+    }
+}
+
+impl EventListener for StructLogger {
+    fn event(&mut self, event: Event) {
+        let mut inner = self.0.borrow_mut();
+        match event {
+            Event::Call { .. } | Event::Create { .. } => inner.depth += 1,
+            Event::Exit { .. } => inner.depth = inner.depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+}
+
+impl evm_runtime::tracing::EventListener for StructLogger {
+    fn event(&mut self, event: evm_runtime::tracing::Event) {
+        if let evm_runtime::tracing::Event::Step {
+            opcode,
+            position,
+            stack,
+            ..
+        } = event
+        {
+            let mut inner = self.0.borrow_mut();
+            if inner.exceeded {
+                return;
+            }
+            if inner.budget.steps == 0 {
+                inner.truncated = true;
+                return;
+            }
+            let bytes = STRUCT_LOG_BYTES + STACK_ITEM_BYTES * stack.data().len();
+            if bytes > inner.budget.bytes {
+                inner.exceeded = true;
+                return;
+            }
+            inner.budget.steps -= 1;
+            inner.budget.bytes -= bytes;
+
+            let log = StructLog {
+                pc:    position.as_ref().map(|pc| *pc as u64).unwrap_or_default(),
+                op:    opcode.0,
+                depth: inner.depth,
+                stack: stack.data().clone(),
+            };
+            inner.logs.push(log);
+        }
+    }
+}
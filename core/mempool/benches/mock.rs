@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use dashmap::DashMap;
 
@@ -10,9 +11,8 @@ use common_crypto::{
 use protocol::rand::{random, rngs::OsRng};
 use protocol::traits::{Context, MemPool, MemPoolAdapter};
 use protocol::types::{
-    public_to_address, recover_intact_pub_key, Bytes, Eip1559Transaction, Hash, PackedTxHashes,
-    Public, SignedTransaction, TransactionAction, UnsignedTransaction, UnverifiedTransaction, H160,
-    H256, U256,
+    public_to_address, Bytes, Eip1559Transaction, Hash, PackedTxHashes, Public, SignedTransaction,
+    TransactionAction, UnsignedTransaction, UnverifiedTransaction, H160, H256, U256,
 };
 use protocol::{async_trait, tokio, ProtocolResult};
 
@@ -57,6 +57,19 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         Ok(vec)
     }
 
+    async fn pull_pending_tx_hashes(
+        &self,
+        _ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<Hash>> {
+        Ok(self
+            .network_txs
+            .iter()
+            .take(limit)
+            .map(|kv| *kv.key())
+            .collect())
+    }
+
     async fn broadcast_tx(
         &self,
         _ctx: Context,
@@ -203,17 +216,9 @@ fn check_hash(tx: &SignedTransaction) -> ProtocolResult<()> {
 }
 
 pub fn check_sig(stx: &SignedTransaction) -> ProtocolResult<()> {
-    Secp256k1Recoverable::verify_signature(
-        stx.transaction.signature_hash(true).as_bytes(),
-        stx.transaction
-            .signature
-            .as_ref()
-            .unwrap()
-            .as_bytes()
-            .as_ref(),
-        recover_intact_pub_key(&stx.public.unwrap()).as_bytes(),
-    )
-    .map_err(|err| AdapterError::VerifySignature(err.to_string()))?;
+    if stx.transaction.recover_signer()? != stx.sender {
+        return Err(AdapterError::VerifySignature("sender mismatch".to_string()).into());
+    }
     Ok(())
 }
 
@@ -277,7 +282,7 @@ pub async fn exec_package(
     tx_num_limit: u64,
 ) -> PackedTxHashes {
     mempool
-        .package(Context::new(), cycle_limit, tx_num_limit)
+        .package(Context::new(), cycle_limit, tx_num_limit, Duration::MAX)
         .await
         .unwrap()
 }
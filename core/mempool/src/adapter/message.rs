@@ -1,4 +1,6 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::future::{try_join_all, TryFutureExt};
 use rlp_derive::{RlpDecodable, RlpEncodable};
@@ -6,7 +8,7 @@ use rlp_derive::{RlpDecodable, RlpEncodable};
 use common_apm::Instant;
 use protocol::{
     async_trait,
-    constants::endpoints::RPC_RESP_PULL_TXS,
+    constants::endpoints::{RPC_RESP_PULL_PENDING_TX_HASHES, RPC_RESP_PULL_TXS},
     tokio,
     traits::{Context, MemPool, MessageHandler, Priority, Rpc, TrustFeedback},
     types::{BatchSignedTxs, Hash, SignedTransaction},
@@ -14,8 +16,18 @@ use protocol::{
 
 use crate::context::TxContext;
 
+/// The max number of the pending transaction hashes responded to a peer.
+const MAX_PENDING_TX_HASHES: u64 = 10_000;
+/// The startup sync is given up after the attempts fail.
+const MAX_STARTUP_SYNC_ATTEMPTS: u32 = 5;
+/// The backoff after the first failed startup sync, it doubles after each one.
+const STARTUP_SYNC_BACKOFF: Duration = Duration::from_secs(1);
+
 pub struct NewTxsHandler<M> {
-    mem_pool: Arc<M>,
+    mem_pool:     Arc<M>,
+    // Sync the pending transactions from the first peer gossiping to us.
+    startup_sync: Arc<AtomicBool>,
+    failed_syncs: Arc<AtomicU32>,
 }
 
 impl<M> NewTxsHandler<M>
@@ -23,7 +35,11 @@ where
     M: MemPool,
 {
     pub fn new(mem_pool: Arc<M>) -> Self {
-        NewTxsHandler { mem_pool }
+        NewTxsHandler {
+            mem_pool,
+            startup_sync: Arc::new(AtomicBool::new(true)),
+            failed_syncs: Arc::new(AtomicU32::new(0)),
+        }
     }
 }
 
@@ -37,6 +53,36 @@ where
     async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
         let ctx = ctx.mark_network_origin_new_txs();
 
+        if self.startup_sync.swap(false, Ordering::AcqRel) {
+            let mem_pool = Arc::clone(&self.mem_pool);
+            let startup_sync = Arc::clone(&self.startup_sync);
+            let failed_syncs = Arc::clone(&self.failed_syncs);
+            let ctx = ctx.clone();
+
+            tokio::spawn(async move {
+                match mem_pool.sync_from_peer(ctx).await {
+                    Ok(n) => log::info!("[core_mempool] sync {} txs from peer", n),
+                    Err(e) => {
+                        let failed = failed_syncs.fetch_add(1, Ordering::AcqRel) + 1;
+                        if failed >= MAX_STARTUP_SYNC_ATTEMPTS {
+                            log::warn!("[core_mempool] sync txs from peer {}, give up", e);
+                            return;
+                        }
+
+                        // The next gossip retries the sync after the backoff.
+                        let backoff = STARTUP_SYNC_BACKOFF * 2u32.pow(failed - 1);
+                        log::warn!(
+                            "[core_mempool] sync txs from peer {}, retry in {:?}",
+                            e,
+                            backoff
+                        );
+                        tokio::time::sleep(backoff).await;
+                        startup_sync.store(true, Ordering::Release);
+                    }
+                }
+            });
+        }
+
         let insert_stx = |stx: SignedTransaction| -> _ {
             let mem_pool = Arc::clone(&self.mem_pool);
             let ctx = ctx.clone();
@@ -127,3 +173,63 @@ where
         TrustFeedback::Neutral
     }
 }
+
+#[derive(Clone, Debug, RlpEncodable, RlpDecodable)]
+pub struct MsgPullPendingTxHashes {
+    pub limit: u64,
+}
+
+#[derive(Clone, Debug, RlpEncodable, RlpDecodable)]
+pub struct MsgPendingTxHashes {
+    pub hashes: Vec<Hash>,
+}
+
+pub struct PullPendingTxHashesHandler<N, M> {
+    network:  Arc<N>,
+    mem_pool: Arc<M>,
+}
+
+impl<N, M> PullPendingTxHashesHandler<N, M>
+where
+    N: Rpc + 'static,
+    M: MemPool + 'static,
+{
+    pub fn new(network: Arc<N>, mem_pool: Arc<M>) -> Self {
+        PullPendingTxHashesHandler { network, mem_pool }
+    }
+}
+
+#[async_trait]
+impl<N, M> MessageHandler for PullPendingTxHashesHandler<N, M>
+where
+    N: Rpc + 'static,
+    M: MemPool + 'static,
+{
+    type Message = MsgPullPendingTxHashes;
+
+    async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
+        let limit = msg.limit.min(MAX_PENDING_TX_HASHES) as usize;
+        let ret = self
+            .mem_pool
+            .get_pending_txs(ctx.clone(), limit)
+            .await
+            .map(|txs| MsgPendingTxHashes {
+                hashes: txs.iter().map(|tx| tx.transaction.hash).collect(),
+            });
+
+        if let Err(e) = self
+            .network
+            .response::<MsgPendingTxHashes>(
+                ctx,
+                RPC_RESP_PULL_PENDING_TX_HASHES,
+                ret,
+                Priority::High,
+            )
+            .await
+        {
+            log::warn!("[core_mempool] push pending tx hashes {}", e);
+        }
+
+        TrustFeedback::Neutral
+    }
+}
@@ -17,12 +17,12 @@ use protocol::traits::{
 };
 use protocol::types::{
     recover_intact_pub_key, Backend, BatchSignedTxs, CellDepWithPubKey, Hash, MerkleRoot,
-    SignedTransaction, H160, U256,
+    SignedTransaction, UnsignedTransaction, H160, U256,
 };
 use protocol::{
     async_trait,
     codec::ProtocolCodec,
-    constants::endpoints::{END_GOSSIP_NEW_TXS, RPC_PULL_TXS},
+    constants::endpoints::{END_GOSSIP_NEW_TXS, RPC_PULL_PENDING_TX_HASHES, RPC_PULL_TXS},
     tokio, trie, Display, ProtocolError, ProtocolErrorKind, ProtocolResult,
 };
 
@@ -33,17 +33,21 @@ use core_executor::{
 };
 use core_interoperation::InteroperationImpl;
 
-use crate::adapter::message::MsgPullTxs;
+use crate::adapter::message::{MsgPendingTxHashes, MsgPullPendingTxHashes, MsgPullTxs};
 use crate::context::TxContext;
 use crate::MemPoolError;
 
-struct IntervalTxsBroadcaster;
+/// Gossip the new transactions in batches. The cached transactions are
+/// broadcasted once their number reaches `tx_size` or their encoded size
+/// reaches `tx_bytes`, or at every interval otherwise.
+pub(crate) struct IntervalTxsBroadcaster;
 
 impl IntervalTxsBroadcaster {
     pub async fn broadcast<G>(
         stx_rx: UnboundedReceiver<(Option<usize>, SignedTransaction)>,
         interval_ms: u64,
         tx_size: usize,
+        tx_bytes: usize,
         gossip: G,
         err_tx: UnboundedSender<ProtocolError>,
     ) where
@@ -51,6 +55,7 @@ impl IntervalTxsBroadcaster {
     {
         let mut stx_rx = stx_rx;
         let mut txs_cache: HashMap<_, Vec<SignedTransaction>> = HashMap::with_capacity(10);
+        let (mut len, mut bytes) = (0usize, 0usize);
         let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
@@ -58,21 +63,21 @@ impl IntervalTxsBroadcaster {
             tokio::select! {
                 opt_stx = stx_rx.next() => {
                     if let Some((origin, stx)) = opt_stx {
+                        len += 1;
+                        bytes += rlp::encode(&stx).len();
                         txs_cache.entry(origin).or_default().push(stx);
 
-                        let len: usize = {
-                            txs_cache.values().map(|v| v.len()).sum()
-                        };
-
-                        if len == tx_size {
-                            Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await
+                        if len >= tx_size || bytes >= tx_bytes {
+                            Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await;
+                            (len, bytes) = (0, 0);
                         }
                     } else {
                         debug!("mempool: default mempool adapter dropped")
                     }
                 },
                 _ = interval.tick() => {
-                        Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await
+                        Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await;
+                        (len, bytes) = (0, 0);
                 },
                 else => {
                     break
@@ -125,6 +130,9 @@ pub struct DefaultMemPoolAdapter<C, N, S, DB, I> {
     max_tx_size: AtomicUsize,
     chain_id:    u64,
 
+    check_fee_cap:           bool,
+    max_access_list_entries: usize,
+
     stx_tx: UnboundedSender<(Option<usize>, SignedTransaction)>,
     err_rx: Mutex<UnboundedReceiver<ProtocolError>>,
 
@@ -148,7 +156,10 @@ where
         gas_limit: u64,
         max_tx_size: usize,
         broadcast_txs_size: usize,
+        broadcast_txs_bytes: usize,
         broadcast_txs_interval: u64,
+        check_fee_cap: bool,
+        max_access_list_entries: usize,
     ) -> Self {
         let (stx_tx, stx_rx) = unbounded();
         let (err_tx, err_rx) = unbounded();
@@ -157,6 +168,7 @@ where
             stx_rx,
             broadcast_txs_interval,
             broadcast_txs_size,
+            broadcast_txs_bytes,
             network.clone(),
             err_tx,
         ));
@@ -171,6 +183,9 @@ where
             max_tx_size: AtomicUsize::new(max_tx_size),
             chain_id,
 
+            check_fee_cap,
+            max_access_list_entries,
+
             stx_tx,
             err_rx: Mutex::new(err_rx),
 
@@ -218,18 +233,9 @@ where
         Ok(())
     }
 
-    fn verify_tx_size(&self, ctx: Context, stx: &SignedTransaction) -> ProtocolResult<()> {
+    fn verify_tx_size(&self, stx: &SignedTransaction) -> ProtocolResult<()> {
         let fixed_bytes = stx.transaction.encode()?;
         if fixed_bytes.len() > self.max_tx_size.load(Ordering::Acquire) {
-            if ctx.is_network_origin_txs() {
-                self.network.report(
-                    ctx,
-                    TrustFeedback::Bad(format!(
-                        "Mempool exceed size limit of tx {:#x}",
-                        stx.transaction.hash
-                    )),
-                );
-            }
             return Err(MemPoolError::ExceedSizeLimit {
                 tx_hash:     stx.transaction.hash,
                 max_tx_size: self.max_tx_size.load(Ordering::Acquire),
@@ -250,18 +256,9 @@ where
         Ok(())
     }
 
-    fn verify_gas_limit(&self, ctx: Context, stx: &SignedTransaction) -> ProtocolResult<()> {
+    fn verify_gas_limit(&self, stx: &SignedTransaction) -> ProtocolResult<()> {
         let gas_limit_tx = stx.transaction.unsigned.gas_limit();
         if gas_limit_tx > &U256::from(self.gas_limit.load(Ordering::Acquire)) {
-            if ctx.is_network_origin_txs() {
-                self.network.report(
-                    ctx,
-                    TrustFeedback::Bad(format!(
-                        "Mempool exceed cycle limit of tx {:#x}",
-                        stx.transaction.hash
-                    )),
-                );
-            }
             return Err(MemPoolError::ExceedGasLimit {
                 tx_hash:          stx.transaction.hash,
                 gas_limit_tx:     gas_limit_tx.as_u64(),
@@ -282,15 +279,24 @@ where
                 signature.as_bytes().as_ref(),
                 recover_intact_pub_key(&stx.public.unwrap()).as_bytes(),
             )
-            .map_err(|err| AdapterError::VerifySignature(err.to_string()))?;
+            .map_err(|e| {
+                self.invalid_signature(ctx, stx, AdapterError::VerifySignature(e.to_string()))
+            })?;
 
             return Ok(());
         }
 
-        let root = self.executor_backend(ctx).await?.get_image_cell_root();
-
-        // Verify interoperation signature call CKB-VM mode
-        let r = rlp::decode::<CellDepWithPubKey>(&signature.r[1..]).map_err(AdapterError::Rlp)?;
+        let root = self
+            .executor_backend(ctx.clone())
+            .await?
+            .get_image_cell_root();
+
+        // Verify interoperation signature call CKB-VM mode. The signature only
+        // references the cell of the verifying script, whose image keeps the CKB
+        // block number it's created at but no header, so there is no CKB header
+        // timestamp to check against the one of the Axon block here.
+        let r = rlp::decode::<CellDepWithPubKey>(&signature.r[1..])
+            .map_err(|e| self.invalid_signature(ctx.clone(), stx, AdapterError::Rlp(e)))?;
         InteroperationImpl::call_ckb_vm(
             Default::default(),
             &DataProvider::new(root),
@@ -298,11 +304,33 @@ where
             &[r.pub_key, signature.s],
             u64::MAX,
         )
-        .map_err(|e| AdapterError::VerifySignature(e.to_string()))?;
+        .map_err(|e| {
+            self.invalid_signature(ctx, stx, AdapterError::VerifySignature(e.to_string()))
+        })?;
 
         Ok(())
     }
 
+    /// A transaction with an invalid signature can never be valid, so the peer
+    /// which gossips it is penalized.
+    fn invalid_signature(
+        &self,
+        ctx: Context,
+        stx: &SignedTransaction,
+        err: AdapterError,
+    ) -> ProtocolError {
+        if ctx.is_network_origin_txs() {
+            self.network.report(
+                ctx,
+                TrustFeedback::Bad(format!(
+                    "Mempool invalid signature of tx {:#x}",
+                    stx.transaction.hash
+                )),
+            );
+        }
+        err.into()
+    }
+
     async fn executor_backend(
         &self,
         ctx: Context,
@@ -346,6 +374,28 @@ where
         Ok(resp_msg.inner())
     }
 
+    async fn pull_pending_tx_hashes(
+        &self,
+        ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<Hash>> {
+        let pull_msg = MsgPullPendingTxHashes {
+            limit: limit as u64,
+        };
+
+        let resp_msg = self
+            .network
+            .call::<MsgPullPendingTxHashes, MsgPendingTxHashes>(
+                ctx,
+                RPC_PULL_PENDING_TX_HASHES,
+                pull_msg,
+                Priority::Normal,
+            )
+            .await?;
+
+        Ok(resp_msg.hashes)
+    }
+
     async fn broadcast_tx(
         &self,
         _ctx: Context,
@@ -423,17 +473,30 @@ where
 
     async fn check_transaction(&self, ctx: Context, stx: &SignedTransaction) -> ProtocolResult<()> {
         if stx.transaction.signature.is_none() {
-            return Err(AdapterError::VerifySignature("missing signature".to_string()).into());
+            return Err(self.invalid_signature(
+                ctx,
+                stx,
+                AdapterError::VerifySignature("missing signature".to_string()),
+            ));
         }
 
         if stx.public.is_none() {
-            return Err(AdapterError::VerifySignature("missing public key".to_string()).into());
+            return Err(self.invalid_signature(
+                ctx,
+                stx,
+                AdapterError::VerifySignature("missing public key".to_string()),
+            ));
         }
 
+        // Only a transaction invalid by the protocol penalizes the peer, but not
+        // one rejected by the local policy, such as the gas price, fee cap and
+        // access list limits, nor a failure of the storage.
         self.verify_chain_id(ctx.clone(), stx)?;
-        self.verify_tx_size(ctx.clone(), stx)?;
+        self.verify_tx_size(stx)?;
         self.verify_gas_price(stx)?;
-        self.verify_gas_limit(ctx.clone(), stx)?;
+        verify_fee_cap(stx, self.check_fee_cap)?;
+        verify_access_list(stx, self.max_access_list_entries)?;
+        self.verify_gas_limit(stx)?;
         self.verify_signature(ctx, stx).await?;
 
         Ok(())
@@ -493,6 +556,57 @@ where
     }
 }
 
+/// An EIP-1559 transaction whose max priority fee per gas is above its max fee
+/// per gas can never be executed. The check is skipped unless `check_fee_cap`
+/// is enabled.
+pub(crate) fn verify_fee_cap(stx: &SignedTransaction, check_fee_cap: bool) -> ProtocolResult<()> {
+    if !check_fee_cap {
+        return Ok(());
+    }
+
+    if let UnsignedTransaction::Eip1559(tx) = &stx.transaction.unsigned {
+        if tx.max_priority_fee_per_gas > tx.gas_price {
+            return Err(MemPoolError::TipAboveFeeCap {
+                tx_hash:                  stx.transaction.hash,
+                max_fee_per_gas:          tx.gas_price,
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// A large access list is cheap to send but inflates the size and the
+/// intrinsic gas of a transaction, so the number of its addresses and storage
+/// keys is limited.
+pub(crate) fn verify_access_list(
+    stx: &SignedTransaction,
+    max_entries: usize,
+) -> ProtocolResult<()> {
+    let access_list = match &stx.transaction.unsigned {
+        UnsignedTransaction::Legacy(_) => return Ok(()),
+        UnsignedTransaction::Eip2930(tx) => &tx.access_list,
+        UnsignedTransaction::Eip1559(tx) => &tx.access_list,
+    };
+
+    let entries = access_list
+        .iter()
+        .map(|item| 1 + item.storage_keys.len())
+        .sum::<usize>();
+    if entries > max_entries {
+        return Err(MemPoolError::AccessListTooLarge {
+            tx_hash: stx.transaction.hash,
+            entries,
+            max_entries,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Display)]
 pub enum AdapterError {
     #[display(fmt = "adapter: interval broadcaster drop")]
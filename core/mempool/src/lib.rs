@@ -5,12 +5,17 @@ mod pool;
 mod tests;
 mod tx_wrapper;
 
-pub use adapter::message::{MsgPullTxs, NewTxsHandler, PullTxsHandler};
+pub use adapter::message::{
+    MsgPendingTxHashes, MsgPullPendingTxHashes, MsgPullTxs, NewTxsHandler,
+    PullPendingTxHashesHandler, PullTxsHandler,
+};
 pub use adapter::{AdapterError, DefaultMemPoolAdapter};
 
 use std::collections::HashSet;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::future::try_join_all;
 
@@ -23,11 +28,18 @@ use protocol::{async_trait, tokio, Display, ProtocolError, ProtocolErrorKind, Pr
 use core_executor::is_call_system_script;
 use core_network::NetworkContext;
 
-use crate::{context::TxContext, pool::PriorityPool};
+use crate::{context::TxContext, pool::PriorityPool, tx_wrapper::SenderLimits};
 
 pub struct MemPoolImpl<Adapter> {
-    pool:    PriorityPool,
-    adapter: Arc<Adapter>,
+    pool:         Arc<PriorityPool>,
+    adapter:      Arc<Adapter>,
+    // The max number of the transactions re-validated after a flush.
+    revalidate:   usize,
+    revalidating: Arc<AtomicBool>,
+    sync_limit:   usize,
+    tx_ttl:       Duration,
+
+    read_your_writes: bool,
 }
 
 impl<Adapter> MemPoolImpl<Adapter>
@@ -41,8 +53,14 @@ where
         initial_txs: Vec<SignedTransaction>,
     ) -> Self {
         let mempool = MemPoolImpl {
-            pool:    PriorityPool::new(pool_size, timeout_gap).await,
-            adapter: Arc::new(adapter),
+            pool:         Arc::new(PriorityPool::new(pool_size, timeout_gap).await),
+            adapter:      Arc::new(adapter),
+            revalidate:   0,
+            revalidating: Arc::new(AtomicBool::new(false)),
+            sync_limit:   0,
+            tx_ttl:       Duration::ZERO,
+
+            read_your_writes: false,
         };
 
         for tx in initial_txs.into_iter() {
@@ -54,6 +72,71 @@ where
         mempool
     }
 
+    /// Re-validate at most `max_txs` pending transactions against the new
+    /// state in the background after each flush, and remove the ones which
+    /// become invalid. It is disabled if the max is 0.
+    pub fn revalidate_after_flush(mut self, max_txs: usize) -> Self {
+        self.revalidate = max_txs;
+        self
+    }
+
+    /// Pull at most `limit` pending transactions from a peer after startup,
+    /// it is disabled if the limit is 0.
+    pub fn sync_on_startup(mut self, limit: usize) -> Self {
+        self.sync_limit = limit;
+        self
+    }
+
+    /// A transaction replaces the pending one with the same sender and nonce
+    /// only if it bumps the fees by at least `percent` percent.
+    pub fn price_bump(self, percent: u64) -> Self {
+        self.pool.set_price_bump(percent);
+        self
+    }
+
+    /// Limit the numbers of the pending and the queued transactions of a
+    /// sender, see `SenderLimits`.
+    pub fn sender_limits(self, max_pending: usize, max_queued: usize) -> Self {
+        self.pool.set_sender_limits(SenderLimits {
+            max_pending,
+            max_queued,
+        });
+        self
+    }
+
+    /// Evict the transactions which have stayed in the pool for `ttl` on
+    /// `evict_expired`, it is disabled if the ttl is 0.
+    pub fn tx_ttl(mut self, ttl: Duration) -> Self {
+        self.tx_ttl = ttl;
+        self
+    }
+
+    /// Make the pending nonce observe the transactions inserted right before
+    /// it, instead of the ones moved to the pending queues in the background.
+    pub fn read_your_writes(mut self, enable: bool) -> Self {
+        self.read_your_writes = enable;
+        self
+    }
+
+    /// Remove the transactions which have stayed in the pool longer than the
+    /// ttl, returning their hashes. It is expected to be called on a timer.
+    pub fn evict_expired(&self) -> Vec<Hash> {
+        self.evict_expired_at(std::time::Instant::now())
+    }
+
+    fn evict_expired_at(&self, now: std::time::Instant) -> Vec<Hash> {
+        if self.tx_ttl.is_zero() {
+            return Vec::new();
+        }
+
+        let hashes = self.pool.expired(now, self.tx_ttl);
+        if !hashes.is_empty() {
+            log::info!("[mempool]: evict {} expired txs", hashes.len());
+            self.pool.remove(&hashes);
+        }
+        hashes
+    }
+
     pub fn len(&self) -> usize {
         self.pool.len()
     }
@@ -104,8 +187,11 @@ where
         if self.pool.contains(tx_hash) {
             return Ok(());
         } else {
-            let check_nonce = self.adapter.check_authorization(ctx.clone(), &tx).await?;
+            // An invalid transaction is dropped before being inserted or broadcasted,
+            // the check penalizes the peer which gossips it if it's invalid by the
+            // protocol.
             self.adapter.check_transaction(ctx.clone(), &tx).await?;
+            let check_nonce = self.adapter.check_authorization(ctx.clone(), &tx).await?;
             self.adapter
                 .check_storage_exist(ctx.clone(), tx_hash)
                 .await?;
@@ -172,6 +258,36 @@ where
         Ok(res)
     }
 
+    /// Remove the transactions whose nonce is below the account nonce or
+    /// whose cost exceeds the account balance after the state changes. At
+    /// most `max_txs` transactions are checked.
+    async fn revalidate(
+        pool: Arc<PriorityPool>,
+        adapter: Arc<Adapter>,
+        ctx: Context,
+        max_txs: usize,
+    ) {
+        let mut invalid_hashes = Vec::new();
+        for tx in pool.txs().into_iter().take(max_txs) {
+            if let Err(e) = adapter.check_authorization(ctx.clone(), &tx).await {
+                log::debug!(
+                    "[mempool]: remove invalid tx {:?} {:?}",
+                    tx.transaction.hash,
+                    e
+                );
+                invalid_hashes.push(tx.transaction.hash);
+            }
+        }
+
+        if !invalid_hashes.is_empty() {
+            log::info!(
+                "[mempool]: remove {} txs invalid after flush",
+                invalid_hashes.len()
+            );
+            pool.remove(&invalid_hashes);
+        }
+    }
+
     pub fn get_tx_cache(&self) -> &PriorityPool {
         &self.pool
     }
@@ -202,13 +318,16 @@ where
         _ctx: Context,
         gas_limit: U256,
         tx_num_limit: u64,
+        time_budget: Duration,
     ) -> ProtocolResult<PackedTxHashes> {
         log::info!(
             "[core_mempool]: {:?} txs in map while package",
             self.pool.len(),
         );
         let inst = Instant::now();
-        let txs = self.pool.package(gas_limit, tx_num_limit as usize);
+        let txs = self
+            .pool
+            .package(gas_limit, tx_num_limit as usize, time_budget);
 
         common_apm::metrics::mempool::MEMPOOL_PACKAGE_SIZE_VEC_STATIC
             .package
@@ -221,7 +340,7 @@ where
 
     async fn flush(
         &self,
-        _ctx: Context,
+        ctx: Context,
         tx_hashes: &[Hash],
         current_number: BlockNumber,
     ) -> ProtocolResult<()> {
@@ -231,6 +350,20 @@ where
         );
         self.adapter.clear_nonce_cache();
         self.pool.flush(tx_hashes, current_number);
+
+        // The re-validation runs off the commit path, and a flush doesn't start
+        // another one while the last one is running.
+        if self.revalidate > 0 && !self.revalidating.swap(true, Ordering::AcqRel) {
+            let pool = Arc::clone(&self.pool);
+            let adapter = Arc::clone(&self.adapter);
+            let revalidating = Arc::clone(&self.revalidating);
+            let max_txs = self.revalidate;
+
+            tokio::spawn(async move {
+                Self::revalidate(pool, adapter, ctx, max_txs).await;
+                revalidating.store(false, Ordering::Release);
+            });
+        }
         Ok(())
     }
 
@@ -325,9 +458,59 @@ where
         _ctx: Context,
         address: H160,
     ) -> ProtocolResult<(usize, Option<BlockNumber>)> {
+        if self.read_your_writes {
+            self.pool.flush_incoming();
+        }
         Ok(self.pool.get_tx_count_by_address(address))
     }
 
+    async fn get_pending_txs(
+        &self,
+        _ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        Ok(self.pool.pending_snapshot(limit))
+    }
+
+    fn pending_by_sender(&self, sender: H160) -> Vec<SignedTransaction> {
+        self.pool.pending_by_sender(sender)
+    }
+
+    async fn sync_from_peer(&self, ctx: Context) -> ProtocolResult<usize> {
+        if self.sync_limit == 0 {
+            return Ok(0);
+        }
+
+        let mut hashes = self
+            .adapter
+            .pull_pending_tx_hashes(ctx.clone(), self.sync_limit)
+            .await?;
+        hashes.truncate(self.sync_limit);
+        let unknown = self.show_unknown_txs(&hashes).await;
+        if unknown.is_empty() {
+            return Ok(0);
+        }
+
+        let mut requested = unknown.iter().copied().collect::<HashSet<_>>();
+        let txs = self.adapter.pull_txs(ctx.clone(), None, unknown).await?;
+        let mut admitted = 0;
+        for tx in txs {
+            // Only the requested transactions are admitted, and each of them is
+            // checked as a new one.
+            if !requested.remove(&tx.transaction.hash) {
+                continue;
+            }
+
+            let tx_hash = tx.transaction.hash;
+            match self.insert(ctx.clone(), tx).await {
+                Ok(()) => admitted += 1,
+                Err(e) => log::debug!("[mempool]: sync tx {:?} failed {:?}", tx_hash, e),
+            }
+        }
+
+        Ok(admitted)
+    }
+
     fn get_tx_from_mem(&self, _ctx: Context, tx_hash: &Hash) -> Option<SignedTransaction> {
         self.pool.get_by_hash(tx_hash)
     }
@@ -375,6 +558,18 @@ pub enum MemPoolError {
     #[display(fmt = "Invalid gas price {:?}", _0)]
     InvalidGasPrice(U256),
 
+    #[display(
+        fmt = "Tx: {:?} max priority fee per gas {} is above max fee per gas {}",
+        tx_hash,
+        max_priority_fee_per_gas,
+        max_fee_per_gas
+    )]
+    TipAboveFeeCap {
+        tx_hash:                  Hash,
+        max_fee_per_gas:          U256,
+        max_priority_fee_per_gas: U256,
+    },
+
     #[display(
         fmt = "Tx: {:?} exceeds size limit, now: {}, limit: {} Bytes",
         tx_hash,
@@ -399,6 +594,18 @@ pub enum MemPoolError {
         gas_limit_tx:     u64,
     },
 
+    #[display(
+        fmt = "Tx: {:?} access list has {} entries, limit: {}",
+        tx_hash,
+        entries,
+        max_entries
+    )]
+    AccessListTooLarge {
+        tx_hash:     Hash,
+        entries:     usize,
+        max_entries: usize,
+    },
+
     #[display(fmt = "Tx nonce {} is invalid current nonce {}", tx_nonce, current)]
     InvalidNonce { current: u64, tx_nonce: u64 },
 
@@ -411,6 +618,12 @@ pub enum MemPoolError {
     #[display(fmt = "Tx: {:?} exists in pool", _0)]
     Dup(Hash),
 
+    #[display(fmt = "Tx: {:?} replacement transaction underpriced", _0)]
+    ReplacementUnderpriced(Hash),
+
+    #[display(fmt = "Sender {:?} has too many transactions in pool", _0)]
+    TooManyTransactionsForSender(H160),
+
     #[display(fmt = "Pull txs, require: {}, response: {}", require, response)]
     EnsureBreak { require: usize, response: usize },
 
@@ -1,21 +1,29 @@
 use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet};
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicU64, AtomicUsize, Ordering},
     Arc,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossbeam_queue::ArrayQueue;
 use dashmap::DashMap;
 use parking_lot::{Mutex, RwLock};
 
+use common_config_parser::types::{
+    DEFAULT_MAX_PENDING_PER_SENDER, DEFAULT_MAX_QUEUED_PER_SENDER, DEFAULT_PRICE_BUMP,
+};
+
 use protocol::tokio::{self, time::sleep};
 use protocol::types::{BlockNumber, Bytes, Hash, PackedTxHashes, SignedTransaction, H160, U256};
 use protocol::{ProtocolResult, MEMPOOL_REFRESH_TIMEOUT};
 
-use crate::tx_wrapper::{PendingQueue, TxPtr, TxWrapper};
+use core_executor::is_call_system_script;
+
+use crate::tx_wrapper::{Admission, PendingQueue, SenderLimits, TxPtr, TxWrapper};
 use crate::MemPoolError;
 
+const PACKAGE_CHECK_INTERVAL: usize = 64;
+
 pub struct PriorityPool {
     sys_tx_bucket:          BuiltInContractTxBucket,
     // The transaction data in this queue should be consistent with the real queue.
@@ -29,12 +37,15 @@ pub struct PriorityPool {
     // Record all transactions in the transaction pool
     tx_map:                 DashMap<Hash, TxPtr>,
     stock_len:              AtomicUsize,
-    // Record the height at which the transaction first enters the transaction pool
-    // There must be a record for each block height, keeping timeout_config
+    // Record the height of the first flush after the transaction enters the
+    // transaction pool, there is a record for each flushed block height.
     pub(crate) timeout_gap: Mutex<BTreeMap<BlockNumber, HashSet<Hash>>>,
     // When a transaction is not submitted for more than timeout_config blocks
-    // in the transaction pool, the transaction will be discarded.
+    // since its admission, the transaction will be discarded.
     timeout_config:         u64,
+    // The min percentage to bump the fees by to replace a pending transaction.
+    price_bump:             Arc<AtomicU64>,
+    sender_limits:          Arc<RwLock<SenderLimits>>,
 
     flush_lock: Arc<RwLock<()>>,
 }
@@ -50,6 +61,11 @@ impl PriorityPool {
             stock_len: AtomicUsize::new(0),
             timeout_gap: Mutex::new(BTreeMap::new()),
             timeout_config,
+            price_bump: Arc::new(AtomicU64::new(DEFAULT_PRICE_BUMP)),
+            sender_limits: Arc::new(RwLock::new(SenderLimits {
+                max_pending: DEFAULT_MAX_PENDING_PER_SENDER,
+                max_queued:  DEFAULT_MAX_QUEUED_PER_SENDER,
+            })),
             flush_lock: Arc::new(RwLock::new(())),
         };
 
@@ -57,6 +73,8 @@ impl PriorityPool {
         let real_queue = Arc::clone(&pool.real_queue);
         let pending_queues = Arc::clone(&pool.pending_queue);
         let flush_lock = Arc::clone(&pool.flush_lock);
+        let price_bump = Arc::clone(&pool.price_bump);
+        let sender_limits = Arc::clone(&pool.sender_limits);
 
         tokio::spawn(async move {
             loop {
@@ -64,17 +82,22 @@ impl PriorityPool {
                     let _flushing = flush_lock.read();
                     let mut q = real_queue.lock();
                     let txs = pop_all_item(Arc::clone(&co_queue));
+                    let price_bump = price_bump.load(Ordering::Acquire);
+                    let limits = *sender_limits.read();
                     for (tx, nonce_diff) in txs {
                         let mut pending_queue = pending_queues.entry(tx.sender()).or_default();
 
-                        // drop this tx
-                        if pending_queue.len() > 64 {
-                            tx.set_dropped();
-                            continue;
+                        match pending_queue.admission(tx.nonce(), nonce_diff, limits) {
+                            Admission::Accept => (),
+                            Admission::Evict(evicted) => evicted.set_dropped(),
+                            Admission::Reject => {
+                                tx.set_dropped();
+                                continue;
+                            }
                         }
 
                         // replace with real queue tx
-                        if pending_queue.insert(Arc::clone(&tx), nonce_diff) {
+                        if pending_queue.insert(Arc::clone(&tx), nonce_diff, price_bump) {
                             q.push(tx);
                         }
 
@@ -98,6 +121,39 @@ impl PriorityPool {
         (0usize, number)
     }
 
+    /// Move the inserted transactions to the pending queues without waiting
+    /// for the background flush.
+    pub fn flush_incoming(&self) {
+        let _flushing = self.flush_lock.write();
+
+        if !self.co_queue.is_empty() {
+            self.flush_to_pending_queue()
+        }
+    }
+
+    /// Take the transactions of a sender in ascending nonce order, including
+    /// the ones not ready to be packaged due to a nonce gap.
+    pub fn pending_by_sender(&self, sender: H160) -> Vec<SignedTransaction> {
+        let _flushing = self.flush_lock.write();
+
+        if !self.co_queue.is_empty() {
+            self.flush_to_pending_queue()
+        }
+
+        self.pending_queue
+            .get(&sender)
+            .map(|queue| queue.txs())
+            .unwrap_or_default()
+    }
+
+    pub fn set_price_bump(&self, price_bump: u64) {
+        self.price_bump.store(price_bump, Ordering::Release);
+    }
+
+    pub fn set_sender_limits(&self, limits: SenderLimits) {
+        *self.sender_limits.write() = limits;
+    }
+
     pub fn insert_system_script_tx(&self, stx: SignedTransaction) -> ProtocolResult<()> {
         let _flushing = self.flush_lock.read();
         if self.sys_tx_bucket.insert(stx) {
@@ -112,6 +168,8 @@ impl PriorityPool {
         check_limit: bool,
         check_nonce: U256,
     ) -> ProtocolResult<()> {
+        self.check_replacement(&stx)?;
+
         if let Err(n) = self
             .stock_len
             .fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
@@ -125,6 +183,13 @@ impl PriorityPool {
             return Err(MemPoolError::ReachLimit(n).into());
         }
 
+        // Check the limits of the sender after reserving the room in the pool, so
+        // that no transaction is evicted for a rejected one.
+        if let Err(e) = self.check_sender_limits(&stx, check_nonce) {
+            self.stock_len.fetch_sub(1, Ordering::AcqRel);
+            return Err(e);
+        }
+
         // This lock is necessary to avoid mismatch error triggered by the concurrent
         // operation of tx insertion and flush.
         let _flushing = self.flush_lock.read();
@@ -150,7 +215,58 @@ impl PriorityPool {
         Ok(())
     }
 
-    pub fn package(&self, _gas_limit: U256, limit: usize) -> PackedTxHashes {
+    /// A transaction with the same sender and nonce as a pending one should
+    /// bump its fees enough to replace it. The transactions which have not
+    /// been moved into the pending queue are checked when they are moved.
+    fn check_replacement(&self, stx: &SignedTransaction) -> ProtocolResult<()> {
+        let queue = match self.pending_queue.get(&stx.sender) {
+            Some(queue) => queue,
+            None => return Ok(()),
+        };
+
+        let new = TxWrapper::from(stx.clone());
+        match queue.get(new.nonce()) {
+            Some(old)
+                if old.hash() != new.hash()
+                    && !new.can_replace(old, self.price_bump.load(Ordering::Acquire)) =>
+            {
+                Err(MemPoolError::ReplacementUnderpriced(new.hash()).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject a transaction over the limits of its sender, or evict a queued
+    /// one of a higher nonce for it. Like the replacement, the transactions
+    /// which have not been moved into the pending queue are checked when they
+    /// are moved.
+    fn check_sender_limits(&self, stx: &SignedTransaction, nonce_diff: U256) -> ProtocolResult<()> {
+        let queue = match self.pending_queue.get(&stx.sender) {
+            Some(queue) => queue,
+            None => return Ok(()),
+        };
+
+        let nonce = stx.transaction.unsigned.nonce();
+        match queue.admission(nonce, nonce_diff, *self.sender_limits.read()) {
+            Admission::Accept => Ok(()),
+            Admission::Evict(evicted) => {
+                evicted.set_dropped();
+                Ok(())
+            }
+            Admission::Reject => Err(MemPoolError::TooManyTransactionsForSender(stx.sender).into()),
+        }
+    }
+
+    /// Package the transactions in order. The selection stops once the time
+    /// budget runs out and the transactions selected so far are returned. The
+    /// deadline is checked every `PACKAGE_CHECK_INTERVAL` transactions so that
+    /// the packaging always makes progress.
+    ///
+    /// The transactions of a sender are selected in consecutive nonces from
+    /// its account nonce, so the ones after a nonce gap, or after one beyond
+    /// the gas limit, are skipped.
+    pub fn package(&self, gas_limit: U256, limit: usize, time_budget: Duration) -> PackedTxHashes {
+        let deadline = Instant::now().checked_add(time_budget);
         let _flushing = self.flush_lock.read();
 
         let mut hashes = self.sys_tx_bucket.package();
@@ -164,15 +280,9 @@ impl PriorityPool {
         q.sort_unstable();
 
         hashes.extend(
-            q.iter()
-                .filter_map(|ptr| {
-                    if ptr.is_dropped() {
-                        None
-                    } else {
-                        Some(ptr.hash())
-                    }
-                })
-                .take(limit),
+            self.select(&q, gas_limit, limit, deadline)
+                .into_iter()
+                .map(|ptr| ptr.hash()),
         );
 
         PackedTxHashes {
@@ -181,20 +291,113 @@ impl PriorityPool {
         }
     }
 
+    /// Take a snapshot of the pending transactions in the packaging order, the
+    /// transactions are selected as `package` does without the gas limit. The
+    /// insertions are blocked while taking the snapshot so that it is
+    /// consistent.
+    pub fn pending_snapshot(&self, limit: usize) -> Vec<SignedTransaction> {
+        let _flushing = self.flush_lock.write();
+
+        let mut txs = self.sys_tx_bucket.package_txs();
+        txs.truncate(limit);
+
+        if !self.co_queue.is_empty() {
+            self.flush_to_pending_queue()
+        }
+        let mut q = self.real_queue.lock();
+
+        q.sort_unstable();
+
+        let limit = limit - txs.len();
+        txs.extend(
+            self.select(&q, U256::MAX, limit, None)
+                .into_iter()
+                .map(|ptr| ptr.raw_tx()),
+        );
+        txs
+    }
+
+    /// Select the transactions of the sorted queue as `package` does, see it
+    /// for the rules.
+    fn select<'a>(
+        &self,
+        q: &'a [TxPtr],
+        gas_limit: U256,
+        limit: usize,
+        deadline: Option<Instant>,
+    ) -> Vec<&'a TxPtr> {
+        let mut selected = Vec::new();
+        let mut next_nonces: HashMap<H160, U256> = HashMap::new();
+        // The transactions ahead of the next nonce of their senders.
+        let mut waiting: HashMap<H160, BTreeMap<U256, &TxPtr>> = HashMap::new();
+        let mut gas_used = U256::zero();
+
+        for (idx, ptr) in q.iter().filter(|ptr| !ptr.is_dropped()).enumerate() {
+            if selected.len() >= limit {
+                break;
+            }
+            if idx != 0
+                && idx % PACKAGE_CHECK_INTERVAL == 0
+                && deadline.map(|d| Instant::now() >= d).unwrap_or(false)
+            {
+                log::warn!(
+                    "[mempool]: package stops after {} txs due to the time budget",
+                    selected.len()
+                );
+                break;
+            }
+
+            let sender = ptr.sender();
+            let next = next_nonces.entry(sender).or_insert_with(|| {
+                self.pending_queue
+                    .get(&sender)
+                    .map(|queue| queue.tip_nonce())
+                    .unwrap_or_default()
+            });
+            let nonce = *ptr.nonce();
+            if nonce > *next {
+                waiting.entry(sender).or_default().insert(nonce, ptr);
+            }
+            if nonce != *next {
+                continue;
+            }
+
+            let mut candidate = Some(ptr);
+            while let Some(tx) = candidate {
+                let tx_gas = *tx.gas_limit();
+                if selected.len() >= limit || gas_used.saturating_add(tx_gas) > gas_limit {
+                    break;
+                }
+
+                gas_used += tx_gas;
+                selected.push(tx);
+                *next += U256::one();
+                candidate = waiting.get_mut(&sender).and_then(|w| w.remove(&*next));
+            }
+        }
+
+        selected
+    }
+
     fn flush_to_pending_queue(&self) {
         let mut q = self.real_queue.lock();
         let txs = pop_all_item(Arc::clone(&self.co_queue));
+        let price_bump = self.price_bump.load(Ordering::Acquire);
+        let limits = *self.sender_limits.read();
         for (tx, nonce_diff) in txs {
             let mut pending_queue = self.pending_queue.entry(tx.sender()).or_default();
 
-            // drop this tx
-            if pending_queue.len() > 64 {
-                tx.set_dropped();
-                continue;
+            match pending_queue.admission(tx.nonce(), nonce_diff, limits) {
+                Admission::Accept => (),
+                Admission::Evict(evicted) => evicted.set_dropped(),
+                Admission::Reject => {
+                    tx.set_dropped();
+                    continue;
+                }
             }
 
             // replace with real queue tx
-            if pending_queue.insert(Arc::clone(&tx), nonce_diff) {
+            if pending_queue.insert(Arc::clone(&tx), nonce_diff, price_bump) {
                 q.push(tx);
             }
 
@@ -233,6 +436,57 @@ impl PriorityPool {
         }
     }
 
+    /// Take all the transactions except the system script ones. The system
+    /// script transactions restored from the storage on startup are kept in
+    /// the map as well, so they are filtered out here.
+    pub fn txs(&self) -> Vec<SignedTransaction> {
+        let _flushing = self.flush_lock.read();
+        self.tx_map
+            .iter()
+            .filter(|kv| !kv.value().is_dropped())
+            .map(|kv| kv.value().raw_tx())
+            .filter(|tx| {
+                !is_call_system_script(tx.transaction.unsigned.action()).unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Take the transactions which have stayed in the pool for at least `ttl`
+    /// at `now`, except the system script ones.
+    pub fn expired(&self, now: Instant, ttl: Duration) -> Vec<Hash> {
+        let _flushing = self.flush_lock.read();
+        self.tx_map
+            .iter()
+            .filter(|kv| !kv.value().is_dropped() && kv.value().is_expired(now, ttl))
+            .map(|kv| *kv.key())
+            .collect()
+    }
+
+    /// Remove the given transactions which have become invalid.
+    pub fn remove(&self, hashes: &[Hash]) {
+        let _flushing = self.flush_lock.write();
+        self.flush_to_pending_queue();
+
+        let mut reduce_len = 0;
+        for hash in hashes.iter() {
+            if let Some((_, ptr)) = self.tx_map.remove(hash) {
+                ptr.set_dropped();
+                reduce_len += 1;
+            }
+        }
+
+        if reduce_len == 0 {
+            return;
+        }
+
+        self.stock_len.fetch_sub(reduce_len, Ordering::AcqRel);
+        self.real_queue.lock().retain(|ptr| !ptr.is_dropped());
+        self.pending_queue.retain(|_, v| {
+            v.clear_droped();
+            !v.need_remove()
+        })
+    }
+
     pub fn flush(&self, hashes: &[Hash], number: BlockNumber) {
         let _flushing = self.flush_lock.write();
         self.flush_to_pending_queue();
@@ -272,18 +526,25 @@ impl PriorityPool {
             }
         }
 
-        let timeout = if number >= self.timeout_config {
-            timeout_gap
-                .remove(&number.saturating_sub(self.timeout_config))
-                .unwrap_or_default()
-        } else {
-            HashSet::new()
+        // Sweep all the heights up to `number - timeout_config`, so that a skipped
+        // height does not keep its transactions forever.
+        let timeout: HashSet<Hash> = match number.checked_sub(self.timeout_config) {
+            Some(expired) => {
+                let live = timeout_gap.split_off(&expired.saturating_add(1));
+                std::mem::replace(&mut *timeout_gap, live)
+                    .into_values()
+                    .flatten()
+                    .collect()
+            }
+            None => HashSet::new(),
         };
 
-        let mut retain_keys = Vec::with_capacity(self.len() / 4);
+        let mut admitted = Vec::with_capacity(self.len() / 4);
         self.tx_map.retain(|hash, v| {
-            if !v.is_dropped() && (timeout.is_empty() || !timeout.contains(&v.hash())) {
-                retain_keys.push(*hash);
+            if !v.is_dropped() && !timeout.contains(hash) {
+                if v.admit() {
+                    admitted.push(*hash);
+                }
                 return true;
             }
 
@@ -292,7 +553,7 @@ impl PriorityPool {
             false
         });
 
-        timeout_gap.entry(number).or_default().extend(retain_keys);
+        timeout_gap.entry(number).or_default().extend(admitted);
 
         q.retain(|ptr| !ptr.is_dropped());
 
@@ -350,6 +611,13 @@ impl BuiltInContractTxBucket {
             .collect()
     }
 
+    pub fn package_txs(&self) -> Vec<SignedTransaction> {
+        self.tx_buckets
+            .iter()
+            .map(|kv| kv.value().values().next().cloned().unwrap())
+            .collect()
+    }
+
     pub fn get_tx_by_hash(&self, hash: &Hash) -> Option<SignedTransaction> {
         if let Some(data) = self.hash_data_map.get(hash) {
             if let Some(tx_map) = self.tx_buckets.get(data.value()) {
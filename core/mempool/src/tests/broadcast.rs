@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use futures::channel::mpsc::unbounded;
+use parking_lot::Mutex;
+
+use protocol::traits::{Gossip, MessageCodec, Priority};
+use protocol::types::BatchSignedTxs;
+
+use crate::adapter::IntervalTxsBroadcaster;
+
+use super::*;
+
+/// A gossip which records the number of transactions in each message.
+#[derive(Clone, Default)]
+struct MockGossip {
+    batches: Arc<Mutex<Vec<usize>>>,
+}
+
+#[async_trait]
+impl Gossip for MockGossip {
+    async fn broadcast<M>(&self, _: Context, _: &str, _: M, _: Priority) -> ProtocolResult<()>
+    where
+        M: MessageCodec,
+    {
+        unimplemented!()
+    }
+
+    async fn gossip<M>(
+        &self,
+        _: Context,
+        _: Option<usize>,
+        _: &str,
+        mut msg: M,
+        _: Priority,
+    ) -> ProtocolResult<()>
+    where
+        M: MessageCodec,
+    {
+        let batch = BatchSignedTxs::decode_msg(msg.encode_msg()?)?;
+        self.batches.lock().push(batch.inner().len());
+        Ok(())
+    }
+
+    async fn multicast<'a, M, P>(
+        &self,
+        _: Context,
+        _: &str,
+        _: P,
+        _: M,
+        _: Priority,
+    ) -> ProtocolResult<()>
+    where
+        M: MessageCodec,
+        P: AsRef<[Bytes]> + Send + 'a,
+    {
+        unimplemented!()
+    }
+}
+
+async fn broadcast_batches(
+    txs: Vec<SignedTransaction>,
+    tx_size: usize,
+    tx_bytes: usize,
+) -> Vec<usize> {
+    let gossip = MockGossip::default();
+    let (stx_tx, stx_rx) = unbounded();
+    let (err_tx, _err_rx) = unbounded();
+    tokio::spawn(IntervalTxsBroadcaster::broadcast(
+        stx_rx,
+        100,
+        tx_size,
+        tx_bytes,
+        gossip.clone(),
+        err_tx,
+    ));
+
+    for tx in txs.into_iter() {
+        stx_tx.unbounded_send((None, tx)).unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let batches = gossip.batches.lock();
+    batches.clone()
+}
+
+#[tokio::test]
+async fn test_broadcast_in_batches() {
+    let txs = default_mock_txs(25);
+
+    // The txs are batched by number.
+    let batches = broadcast_batches(txs.clone(), 10, usize::MAX).await;
+    assert_eq!(batches.iter().sum::<usize>(), 25);
+    assert!(batches.len() >= 3 && batches.len() < 25);
+    assert!(batches.iter().all(|n| *n <= 10));
+
+    // The txs are batched by encoded size.
+    let tx_bytes = txs.iter().map(|tx| rlp::encode(tx).len()).min().unwrap() * 3;
+    let batches = broadcast_batches(txs, usize::MAX, tx_bytes).await;
+    assert_eq!(batches.iter().sum::<usize>(), 25);
+    assert!(batches.len() < 25);
+    assert!(batches.iter().all(|n| *n <= 3));
+}
@@ -107,7 +107,7 @@ async fn test_package_multi_types() {
     assert_eq!(mempool.get_tx_cache().system_script_queue_len(), 5);
 
     let package_txs = mempool
-        .package(Context::new(), 1000000000u64.into(), 10000)
+        .package(Context::new(), 1000000000u64.into(), 10000, NO_TIME_BUDGET)
         .await
         .unwrap();
     assert_eq!(
@@ -124,6 +124,143 @@ async fn test_package_multi_types() {
     assert_eq!(mempool.len(), 0);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_flush_removes_invalid_txs() {
+    let mempool = Arc::new(default_mempool().await.revalidate_after_flush(usize::MAX));
+
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let poor_txs = (0..3)
+        .map(|i| mock_signed_tx(&priv_key, &pub_key, TIMEOUT, i, true))
+        .collect::<Vec<_>>();
+    let mut txs = default_mock_txs(5);
+    txs.extend_from_slice(&poor_txs);
+    concurrent_insert(txs, Arc::clone(&mempool)).await;
+    assert_eq!(mempool.len(), 8);
+
+    // A committed block drains the balance of the sender.
+    mempool
+        .get_adapter()
+        .balances
+        .insert(poor_txs[0].sender, U256::zero());
+    exec_flush(vec![], Arc::clone(&mempool)).await;
+
+    // The re-validation runs in the background.
+    for _ in 0..100 {
+        if mempool.len() == 5 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(mempool.len(), 5);
+    for tx in poor_txs.iter() {
+        assert!(!mempool.contains(Context::new(), &tx.transaction.hash).await);
+    }
+    let package_txs = exec_package(Arc::clone(&mempool), CYCLE_LIMIT.into(), TX_NUM_LIMIT).await;
+    assert_eq!(package_txs.hashes.len(), 5);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_flush_without_revalidation() {
+    let mempool = Arc::new(default_mempool().await);
+
+    let txs = default_mock_txs(3);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+    mempool
+        .get_adapter()
+        .balances
+        .insert(txs[0].sender, U256::zero());
+    exec_flush(vec![], Arc::clone(&mempool)).await;
+
+    // The re-validation is disabled by default.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(mempool.len(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pending_txs_snapshot() {
+    let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await);
+
+    let mut txs = mock_sys_txs(3);
+    txs.extend(default_mock_txs(100));
+    concurrent_insert(txs, Arc::clone(&mempool)).await;
+
+    let snapshot = mempool.get_pending_txs(Context::new(), 1000).await.unwrap();
+    let package_txs = exec_package(Arc::clone(&mempool), CYCLE_LIMIT.into(), 1000).await;
+    assert_eq!(
+        snapshot
+            .iter()
+            .map(|tx| tx.transaction.hash)
+            .collect::<Vec<_>>(),
+        package_txs.hashes
+    );
+
+    // The snapshot is capped by the limit.
+    let snapshot = mempool.get_pending_txs(Context::new(), 10).await.unwrap();
+    assert_eq!(
+        snapshot
+            .iter()
+            .map(|tx| tx.transaction.hash)
+            .collect::<Vec<_>>(),
+        package_txs.hashes[..10]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_pending_by_sender() {
+    let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await);
+
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let txs: Vec<SignedTransaction> = (0..3)
+        .map(|i| mock_signed_tx(&priv_key, &pub_key, 0, i as u64, true))
+        .collect();
+    let sender = txs[0].sender;
+
+    let pool = mempool.get_tx_cache();
+    for i in [2, 0, 1] {
+        pool.insert(txs[i].clone(), false, i.into()).unwrap();
+    }
+    // A transaction of another sender is not taken.
+    pool.insert(default_mock_txs(1).remove(0), false, 0.into())
+        .unwrap();
+
+    assert_eq!(
+        mempool
+            .pending_by_sender(sender)
+            .iter()
+            .map(|tx| tx.transaction.hash)
+            .collect::<Vec<_>>(),
+        txs.iter().map(|tx| tx.transaction.hash).collect::<Vec<_>>()
+    );
+    assert!(mempool.pending_by_sender(H160::random()).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_package_time_budget() {
+    let mempool = Arc::new(new_mempool(4096, 0, 0, 0).await);
+    concurrent_insert(default_mock_txs(2000), Arc::clone(&mempool)).await;
+    let full = exec_package(Arc::clone(&mempool), CYCLE_LIMIT.into(), TX_NUM_LIMIT).await;
+    assert_eq!(full.hashes.len(), 2000);
+
+    let inst = std::time::Instant::now();
+    let partial = mempool
+        .package(
+            Context::new(),
+            CYCLE_LIMIT.into(),
+            TX_NUM_LIMIT,
+            Duration::ZERO,
+        )
+        .await
+        .unwrap();
+    assert!(inst.elapsed() < Duration::from_secs(1));
+
+    // The selection is a non-empty prefix of the full packaging.
+    assert!(!partial.hashes.is_empty());
+    assert!(partial.hashes.len() < full.hashes.len());
+    assert_eq!(partial.hashes[..], full.hashes[..partial.hashes.len()]);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_flush() {
     let mempool = Arc::new(default_mempool().await);
@@ -211,7 +348,7 @@ async fn test_repeat_insertion_with_timout_gap() {
 
     pool.flush(&[], 20);
 
-    let list = pool.package(1000.into(), 3);
+    let list = pool.package(1000.into(), 3, NO_TIME_BUDGET);
 
     assert_eq!(
         list.hashes,
@@ -222,6 +359,73 @@ async fn test_repeat_insertion_with_timout_gap() {
     )
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tx_block_lifetime() {
+    // The block lifetime of the test mempool is 20.
+    let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await);
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let tx = mock_signed_tx(&priv_key, &pub_key, 0, 0, true);
+    let hash = tx.transaction.hash;
+
+    // The tx is admitted at height 10.
+    let pool = mempool.get_tx_cache();
+    pool.flush(&[], 10);
+    pool.insert(tx, false, 0.into()).unwrap();
+
+    // The tx stays until the height passes 10 + 20, even if some heights are
+    // not flushed.
+    for number in [11, 15, 30] {
+        pool.flush(&[], number);
+        assert!(pool.contains(&hash));
+    }
+
+    pool.flush(&[], 31);
+    assert!(!pool.contains(&hash));
+    assert_eq!(pool.len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_evict_expired_txs() {
+    let ttl = Duration::from_millis(100);
+    let mempool = new_mempool(1024, 0, 0, 0).await.tx_ttl(ttl);
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let tx = mock_signed_tx(&priv_key, &pub_key, 0, 0, true);
+    let hash = tx.transaction.hash;
+
+    let pool = mempool.get_tx_cache();
+    pool.insert(tx, false, 0.into()).unwrap();
+
+    let now = std::time::Instant::now();
+    assert!(mempool.evict_expired_at(now).is_empty());
+    assert!(pool.contains(&hash));
+
+    assert_eq!(mempool.evict_expired_at(now + ttl), vec![hash]);
+    assert!(!pool.contains(&hash));
+    assert_eq!(pool.len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_read_your_writes() {
+    let mempool = new_mempool(1024, 0, 0, 0).await.read_your_writes(true);
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let tx = mock_signed_tx(&priv_key, &pub_key, 0, 0, true);
+    let (sender, hash) = (tx.sender, tx.transaction.hash);
+
+    // Read right after the insertion, before the background flush.
+    mempool.get_tx_cache().insert(tx, false, 0.into()).unwrap();
+    let (count, _) = mempool
+        .get_tx_count_by_address(Context::new(), sender)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    let pending = mempool.get_pending_txs(Context::new(), 10).await.unwrap();
+    assert!(pending.iter().any(|tx| tx.transaction.hash == hash));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_nonce_insert() {
     let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await);
@@ -265,7 +469,7 @@ async fn test_nonce_insert() {
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     assert_eq!(3, pool.real_queue_len());
 
-    let list = pool.package(1000.into(), 2);
+    let list = pool.package(1000.into(), 2, NO_TIME_BUDGET);
 
     assert_eq!(
         list.hashes,
@@ -283,7 +487,7 @@ async fn test_nonce_insert() {
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     assert_eq!(2, pool.real_queue_len());
 
-    let list = pool.package(1000.into(), 2);
+    let list = pool.package(1000.into(), 2, NO_TIME_BUDGET);
 
     assert_eq!(
         list.hashes,
@@ -306,7 +510,7 @@ async fn test_nonce_insert() {
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     assert_eq!(2, pool.real_queue_len());
 
-    let list = pool.package(1000.into(), 2);
+    let list = pool.package(1000.into(), 2, NO_TIME_BUDGET);
     assert_eq!(list.hashes, vec![replace_tx.transaction.hash]);
 
     pool.flush(&list.hashes, 3);
@@ -314,6 +518,308 @@ async fn test_nonce_insert() {
     assert_eq!(0, pool.real_queue_len());
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_package_with_nonce_gap() {
+    let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await);
+    let pool = mempool.get_tx_cache();
+
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let txs: Vec<SignedTransaction> = [0, 1, 3]
+        .iter()
+        .map(|i| mock_signed_tx(&priv_key, &pub_key, 0, *i, true))
+        .collect();
+    let hashes =
+        |txs: &[SignedTransaction]| txs.iter().map(|tx| tx.transaction.hash).collect::<Vec<_>>();
+
+    for tx in txs.iter() {
+        let nonce = *tx.transaction.unsigned.nonce();
+        pool.insert(tx.clone(), false, nonce).unwrap();
+    }
+
+    // The nonce 3 waits for the gap of nonce 2.
+    let list = pool.package(1000.into(), 10, NO_TIME_BUDGET);
+    assert_eq!(list.hashes, hashes(&txs[0..2]));
+    let snapshot = mempool.get_pending_txs(Context::new(), 10).await.unwrap();
+    assert_eq!(hashes(&snapshot), hashes(&txs[0..2]));
+
+    // The gas limit of each tx is 1.
+    let list = pool.package(1.into(), 10, NO_TIME_BUDGET);
+    assert_eq!(list.hashes, hashes(&txs[0..1]));
+    let list = pool.package(1000.into(), 1, NO_TIME_BUDGET);
+    assert_eq!(list.hashes, hashes(&txs[0..1]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_replace_by_fee() {
+    let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await);
+    let pool = mempool.get_tx_cache();
+
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let fee_tx = |max_fee_per_gas: u64, max_priority_fee_per_gas: u64, id: u64| {
+        let mut tx = mock_signed_tx(&priv_key, &pub_key, 0, 0, true);
+        if let UnsignedTransaction::Eip1559(ref mut p) = tx.transaction.unsigned {
+            p.gas_price = max_fee_per_gas.into();
+            p.max_priority_fee_per_gas = max_priority_fee_per_gas.into();
+        }
+        tx.transaction.hash = H256::from_low_u64_le(id);
+        tx
+    };
+    let pending_hashes = |sender: H160| {
+        mempool
+            .pending_by_sender(sender)
+            .iter()
+            .map(|tx| tx.transaction.hash)
+            .collect::<Vec<_>>()
+    };
+
+    let old = fee_tx(100, 100, 1);
+    pool.insert(old.clone(), false, 0.into()).unwrap();
+    assert_eq!(pending_hashes(old.sender), vec![old.transaction.hash]);
+
+    // Both fees should be bumped by at least 10%.
+    for (max_fee, max_priority_fee) in [(109, 109), (130, 109), (109, 130)] {
+        let err = pool
+            .insert(fee_tx(max_fee, max_priority_fee, 2), false, 0.into())
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("replacement transaction underpriced"));
+    }
+    assert_eq!(pending_hashes(old.sender), vec![old.transaction.hash]);
+
+    // The exact bump is accepted.
+    let new = fee_tx(110, 110, 3);
+    pool.insert(new.clone(), false, 0.into()).unwrap();
+    assert_eq!(pending_hashes(old.sender), vec![new.transaction.hash]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sender_limits() {
+    let mempool = Arc::new(new_mempool(1024, 0, 0, 0).await.sender_limits(2, 2));
+    let pool = mempool.get_tx_cache();
+
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let txs: Vec<SignedTransaction> = (0..8)
+        .map(|i| mock_signed_tx(&priv_key, &pub_key, 0, i as u64, true))
+        .collect();
+    let sender = txs[0].sender;
+    // The account nonce is 0. Taking the transactions of the sender moves the
+    // inserted ones into the pending queue.
+    let insert = |i: usize| pool.insert(txs[i].clone(), false, i.into());
+    let nonces = || {
+        mempool
+            .pending_by_sender(sender)
+            .iter()
+            .map(|tx| tx.transaction.unsigned.nonce().as_u64())
+            .collect::<Vec<_>>()
+    };
+    let is_rejected = |res: ProtocolResult<()>| {
+        res.unwrap_err()
+            .to_string()
+            .contains("too many transactions")
+    };
+
+    insert(0).unwrap();
+    insert(1).unwrap();
+    assert_eq!(nonces(), vec![0, 1]);
+
+    // The pending transactions are full.
+    assert!(is_rejected(insert(2)));
+    assert_eq!(nonces(), vec![0, 1]);
+
+    insert(5).unwrap();
+    assert_eq!(nonces(), vec![0, 1, 5]);
+    insert(6).unwrap();
+    assert_eq!(nonces(), vec![0, 1, 5, 6]);
+
+    // The queued transactions are full, the one of the highest nonce is evicted.
+    insert(4).unwrap();
+    assert_eq!(nonces(), vec![0, 1, 4, 5]);
+
+    // No queued transaction of a higher nonce to evict.
+    assert!(is_rejected(insert(7)));
+    assert_eq!(nonces(), vec![0, 1, 4, 5]);
+}
+
+fn mock_fee_tx(max_fee_per_gas: u64, max_priority_fee_per_gas: u64) -> SignedTransaction {
+    let mut raw = mock_transaction(0, false);
+    raw.gas_price = max_fee_per_gas.into();
+    raw.max_priority_fee_per_gas = max_priority_fee_per_gas.into();
+    sign_mock_tx(raw)
+}
+
+fn mock_access_list_tx(addresses: usize, keys_per_address: usize) -> SignedTransaction {
+    let mut raw = mock_transaction(0, false);
+    raw.access_list = (0..addresses)
+        .map(|_| AccessListItem {
+            address:      H160::random(),
+            storage_keys: (0..keys_per_address).map(|_| H256::random()).collect(),
+        })
+        .collect();
+    sign_mock_tx(raw)
+}
+
+fn sign_mock_tx(raw: Eip1559Transaction) -> SignedTransaction {
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = Public::from_slice(&priv_key.pub_key().to_uncompressed_bytes()[1..65]);
+
+    let mut tx = UnverifiedTransaction {
+        unsigned:  UnsignedTransaction::Eip1559(raw),
+        signature: None,
+        chain_id:  Some(random::<u64>()),
+        hash:      Default::default(),
+    };
+    let signature = Secp256k1Recoverable::sign_message(
+        tx.signature_hash(true).as_bytes(),
+        &priv_key.to_bytes(),
+    )
+    .unwrap()
+    .to_bytes();
+    tx.signature = Some(signature.into());
+
+    SignedTransaction {
+        transaction: tx.calc_hash(),
+        sender:      public_to_address(&pub_key),
+        public:      Some(pub_key),
+    }
+}
+
+#[tokio::test]
+async fn test_tip_above_fee_cap() {
+    let mempool = default_mempool().await;
+
+    // tip == cap
+    let tx = mock_fee_tx(2, 2);
+    mempool.insert(Context::new(), tx).await.unwrap();
+
+    // tip < cap
+    let tx = mock_fee_tx(2, 1);
+    mempool.insert(Context::new(), tx).await.unwrap();
+
+    // tip > cap
+    let tx = mock_fee_tx(1, 2);
+    let expect = MemPoolError::TipAboveFeeCap {
+        tx_hash:                  tx.transaction.hash,
+        max_fee_per_gas:          1.into(),
+        max_priority_fee_per_gas: 2.into(),
+    };
+    let err = mempool.insert(Context::new(), tx).await.unwrap_err();
+    assert!(err.to_string().contains(&expect.to_string()));
+    assert_eq!(mempool.get_tx_cache().len(), 2);
+}
+
+#[tokio::test]
+async fn test_tip_above_fee_cap_unchecked() {
+    let mut adapter = HashMemPoolAdapter::new();
+    adapter.check_fee_cap = false;
+    let mempool = MemPoolImpl::new(POOL_SIZE, 20, adapter, vec![]).await;
+
+    for (cap, tip) in [(2, 2), (2, 1), (1, 2)] {
+        let tx = mock_fee_tx(cap, tip);
+        mempool.insert(Context::new(), tx).await.unwrap();
+    }
+    assert_eq!(mempool.get_tx_cache().len(), 3);
+}
+
+#[tokio::test]
+async fn test_access_list_limit() {
+    let mempool = default_mempool().await;
+
+    // A reasonable access list.
+    let tx = mock_access_list_tx(2, 3);
+    mempool.insert(Context::new(), tx).await.unwrap();
+
+    // Each address and storage key is an entry, this one is at the limit.
+    let tx = mock_access_list_tx(MAX_ACCESS_LIST_ENTRIES / 4, 3);
+    mempool.insert(Context::new(), tx).await.unwrap();
+
+    let tx = mock_access_list_tx(MAX_ACCESS_LIST_ENTRIES + 1, 0);
+    let expect = MemPoolError::AccessListTooLarge {
+        tx_hash:     tx.transaction.hash,
+        entries:     MAX_ACCESS_LIST_ENTRIES + 1,
+        max_entries: MAX_ACCESS_LIST_ENTRIES,
+    };
+    let err = mempool.insert(Context::new(), tx).await.unwrap_err();
+    assert!(err.to_string().contains(&expect.to_string()));
+    assert_eq!(mempool.get_tx_cache().len(), 2);
+}
+
+#[tokio::test]
+async fn test_drop_invalid_gossiped_tx() {
+    let mempool = default_mempool().await;
+    let ctx = Context::new().mark_network_origin_new_txs();
+
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let tx = mock_signed_tx(&priv_key, &priv_key.pub_key(), TIMEOUT, 0, false);
+    assert!(mempool.insert(ctx, tx).await.is_err());
+
+    // The tx is neither inserted nor broadcasted, and the peer is penalized.
+    let adapter = mempool.get_adapter();
+    assert!(mempool.is_empty());
+    assert!(adapter.network_txs.is_empty());
+    assert_eq!(adapter.bad_reports.load(Ordering::SeqCst), 1);
+
+    // A tx from the rpc is not reported.
+    let tx = mock_signed_tx(&priv_key, &priv_key.pub_key(), TIMEOUT, 1, false);
+    assert!(mempool.insert(Context::new(), tx).await.is_err());
+    assert_eq!(adapter.bad_reports.load(Ordering::SeqCst), 1);
+
+    // A tx rejected by the local policy is not reported.
+    let ctx = Context::new().mark_network_origin_new_txs();
+    assert!(mempool
+        .insert(ctx.clone(), mock_fee_tx(1, 2))
+        .await
+        .is_err());
+    assert!(mempool
+        .insert(ctx, mock_access_list_tx(MAX_ACCESS_LIST_ENTRIES + 1, 0))
+        .await
+        .is_err());
+    assert!(mempool.is_empty());
+    assert_eq!(adapter.bad_reports.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_sync_from_peer() {
+    // The sync is disabled by default.
+    let mempool = default_mempool().await;
+    for tx in default_mock_txs(10) {
+        mempool
+            .get_adapter()
+            .network_txs
+            .insert(tx.transaction.hash, tx);
+    }
+    assert_eq!(mempool.sync_from_peer(Context::new()).await.unwrap(), 0);
+    assert!(mempool.is_empty());
+
+    // The number of the pulled txs is bounded.
+    let mempool = default_mempool().await.sync_on_startup(5);
+    for tx in default_mock_txs(10) {
+        mempool
+            .get_adapter()
+            .network_txs
+            .insert(tx.transaction.hash, tx);
+    }
+    assert_eq!(mempool.sync_from_peer(Context::new()).await.unwrap(), 5);
+    assert_eq!(mempool.len(), 5);
+
+    // Each pulled tx is validated before admitted.
+    let mempool = default_mempool().await.sync_on_startup(100);
+    for tx in mock_txs(10, 2, TIMEOUT) {
+        mempool
+            .get_adapter()
+            .network_txs
+            .insert(tx.transaction.hash, tx);
+    }
+    assert_eq!(mempool.sync_from_peer(Context::new()).await.unwrap(), 10);
+    assert_eq!(mempool.len(), 10);
+
+    // The known txs are not pulled again.
+    assert_eq!(mempool.sync_from_peer(Context::new()).await.unwrap(), 0);
+}
+
 macro_rules! ensure_order_txs {
     ($in_pool: expr, $out_pool: expr, $pool_size: expr) => {
         let mempool = &Arc::new(new_mempool($pool_size, 0, 0, 0).await);
@@ -1,6 +1,9 @@
+mod broadcast;
 mod mempool;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use dashmap::DashMap;
 
@@ -12,13 +15,15 @@ use core_executor::system_contract::system_contract_address;
 use protocol::rand::{random, rngs::OsRng};
 use protocol::traits::{Context, MemPool, MemPoolAdapter};
 use protocol::types::{
-    public_to_address, recover_intact_pub_key, Bytes, Eip1559Transaction, Hash, PackedTxHashes,
-    Public, SignedTransaction, TransactionAction, UnsignedTransaction, UnverifiedTransaction, H160,
-    H256, U256,
+    public_to_address, AccessListItem, Bytes, Eip1559Transaction, Hash, PackedTxHashes, Public,
+    SignedTransaction, TransactionAction, UnsignedTransaction, UnverifiedTransaction, H160, H256,
+    U256,
 };
 use protocol::{async_trait, tokio, ProtocolResult};
 
-use crate::{adapter::AdapterError, check_dup_order_hashes, MemPoolError, MemPoolImpl};
+use crate::adapter::{verify_access_list, verify_fee_cap, AdapterError};
+use crate::context::TxContext;
+use crate::{check_dup_order_hashes, MemPoolError, MemPoolImpl};
 
 const CYCLE_LIMIT: u64 = 1_000_000;
 const TX_NUM_LIMIT: u64 = 10_000;
@@ -27,18 +32,35 @@ const POOL_SIZE: usize = 100_000;
 const MAX_TX_SIZE: u64 = 1024; // 1KB
 const TIMEOUT: u64 = 1000;
 const TIMEOUT_GAP: u64 = 100;
+const MAX_ACCESS_LIST_ENTRIES: usize = 64;
 const NATIVE_TOKEN_ISSUE_ADDRESS: H160 = system_contract_address(0x0);
+const NO_TIME_BUDGET: Duration = Duration::MAX;
 
 pub struct HashMemPoolAdapter {
-    network_txs: DashMap<Hash, SignedTransaction>,
+    network_txs:   DashMap<Hash, SignedTransaction>,
+    bad_reports:   AtomicUsize,
+    /// The balances of the senders, a sender absent from it affords any tx.
+    balances:      DashMap<H160, U256>,
+    /// The `check_fee_cap` of the mempool config, enabled by default.
+    check_fee_cap: bool,
 }
 
 impl HashMemPoolAdapter {
     fn new() -> HashMemPoolAdapter {
         HashMemPoolAdapter {
-            network_txs: DashMap::new(),
+            network_txs:   DashMap::new(),
+            bad_reports:   AtomicUsize::new(0),
+            balances:      DashMap::new(),
+            check_fee_cap: true,
         }
     }
+
+    fn report_bad(&self, ctx: &Context, res: ProtocolResult<()>) -> ProtocolResult<()> {
+        if res.is_err() && ctx.is_network_origin_txs() {
+            self.bad_reports.fetch_add(1, Ordering::SeqCst);
+        }
+        res
+    }
 }
 
 #[async_trait]
@@ -58,6 +80,19 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         Ok(vec)
     }
 
+    async fn pull_pending_tx_hashes(
+        &self,
+        _ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<Hash>> {
+        Ok(self
+            .network_txs
+            .iter()
+            .take(limit)
+            .map(|kv| *kv.key())
+            .collect())
+    }
+
     async fn broadcast_tx(
         &self,
         _ctx: Context,
@@ -71,14 +106,28 @@ impl MemPoolAdapter for HashMemPoolAdapter {
     async fn check_authorization(
         &self,
         _ctx: Context,
-        _tx: &SignedTransaction,
+        tx: &SignedTransaction,
     ) -> ProtocolResult<U256> {
+        if let Some(balance) = self.balances.get(&tx.sender) {
+            if *balance < tx.transaction.unsigned.may_cost() {
+                return Err(MemPoolError::ExceedBalance {
+                    tx_hash:         tx.transaction.hash,
+                    account_balance: *balance,
+                    tx_gas_limit:    *tx.transaction.unsigned.gas_limit(),
+                }
+                .into());
+            }
+        }
+
         Ok(U256::zero())
     }
 
-    async fn check_transaction(&self, _ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()> {
-        check_hash(tx)?;
-        check_sig(tx)
+    async fn check_transaction(&self, ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()> {
+        // Only a wrong hash or signature penalizes the peer.
+        self.report_bad(&ctx, check_hash(tx))?;
+        verify_fee_cap(tx, self.check_fee_cap)?;
+        verify_access_list(tx, MAX_ACCESS_LIST_ENTRIES)?;
+        self.report_bad(&ctx, check_sig(tx))
     }
 
     async fn check_storage_exist(&self, _ctx: Context, _tx_hash: &Hash) -> ProtocolResult<()> {
@@ -158,17 +207,9 @@ fn check_hash(tx: &SignedTransaction) -> ProtocolResult<()> {
 }
 
 fn check_sig(stx: &SignedTransaction) -> ProtocolResult<()> {
-    Secp256k1Recoverable::verify_signature(
-        stx.transaction.signature_hash(true).as_bytes(),
-        stx.transaction
-            .signature
-            .as_ref()
-            .unwrap()
-            .as_bytes()
-            .as_ref(),
-        recover_intact_pub_key(&stx.public.unwrap()).as_bytes(),
-    )
-    .map_err(|err| AdapterError::VerifySignature(err.to_string()))?;
+    if stx.transaction.recover_signer()? != stx.sender {
+        return Err(AdapterError::VerifySignature("sender mismatch".to_string()).into());
+    }
     Ok(())
 }
 
@@ -226,7 +267,7 @@ async fn exec_package(
     tx_num_limit: u64,
 ) -> PackedTxHashes {
     mempool
-        .package(Context::new(), cycle_limit, tx_num_limit)
+        .package(Context::new(), cycle_limit, tx_num_limit, NO_TIME_BUDGET)
         .await
         .unwrap()
 }
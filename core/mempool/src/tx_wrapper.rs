@@ -3,6 +3,7 @@ use std::collections::{btree_map::Entry, BTreeMap};
 use std::ops::Bound::{Included, Unbounded};
 use std::sync::atomic::{AtomicU8, Ordering as AtomicOrdering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use protocol::types::{Hash, SignedTransaction, H160, U256};
 
@@ -12,16 +13,19 @@ pub type TxPtr = Arc<TxWrapper>;
 pub struct TxWrapper {
     // 0x00 init
     // 0x01 package
+    // 0x02 admit
     // 0x10 drop
-    state: AtomicU8,
-    tx:    SignedTransaction,
+    state:       AtomicU8,
+    tx:          SignedTransaction,
+    inserted_at: Instant,
 }
 
 impl From<SignedTransaction> for TxWrapper {
     fn from(stx: SignedTransaction) -> Self {
         TxWrapper {
-            tx:    stx,
-            state: AtomicU8::new(0),
+            tx:          stx,
+            state:       AtomicU8::new(0),
+            inserted_at: Instant::now(),
         }
     }
 }
@@ -66,6 +70,27 @@ impl TxWrapper {
         self.tx.transaction.unsigned.gas_price()
     }
 
+    pub fn gas_limit(&self) -> &U256 {
+        self.tx.transaction.unsigned.gas_limit()
+    }
+
+    pub fn max_priority_fee_per_gas(&self) -> &U256 {
+        self.tx.transaction.unsigned.max_priority_fee_per_gas()
+    }
+
+    /// Whether the tx raises both the gas price and the max priority fee per
+    /// gas of the old one by at least `price_bump` percent.
+    pub fn can_replace(&self, old: &TxWrapper, price_bump: u64) -> bool {
+        let bumped = |price: U256| price * (100 + price_bump) / 100;
+        self.gas_price() >= bumped(old.gas_price())
+            && *self.max_priority_fee_per_gas() >= bumped(*old.max_priority_fee_per_gas())
+    }
+
+    /// Whether the tx has stayed in the pool for at least `ttl` at `now`.
+    pub fn is_expired(&self, now: Instant, ttl: Duration) -> bool {
+        now.saturating_duration_since(self.inserted_at) >= ttl
+    }
+
     pub fn raw_tx(&self) -> SignedTransaction {
         self.tx.clone()
     }
@@ -78,6 +103,12 @@ impl TxWrapper {
         self.state.fetch_or(0x10, AtomicOrdering::AcqRel);
     }
 
+    /// Mark the tx as admitted at a block height, returns false if it has
+    /// been admitted before.
+    pub fn admit(&self) -> bool {
+        self.state.fetch_or(0x02, AtomicOrdering::AcqRel) & 0x02 == 0
+    }
+
     fn set_package(&self) {
         self.state.fetch_or(0x01, AtomicOrdering::AcqRel);
     }
@@ -87,6 +118,22 @@ impl TxWrapper {
     }
 }
 
+/// The limits of the transactions of a sender in the pool. A pending
+/// transaction continues the consecutive nonces from the account nonce, and a
+/// queued one is after a nonce gap.
+#[derive(Clone, Copy, Debug)]
+pub struct SenderLimits {
+    pub max_pending: usize,
+    pub max_queued:  usize,
+}
+
+pub enum Admission {
+    Accept,
+    /// Accept the transaction and evict the queued one of the highest nonce.
+    Evict(TxPtr),
+    Reject,
+}
+
 #[derive(Default)]
 pub struct PendingQueue {
     queue:             BTreeMap<U256, TxPtr>,
@@ -97,7 +144,7 @@ pub struct PendingQueue {
 }
 
 impl PendingQueue {
-    pub fn insert(&mut self, tx: TxPtr, nonce_diff: U256) -> bool {
+    pub fn insert(&mut self, tx: TxPtr, nonce_diff: U256, price_bump: u64) -> bool {
         let nonce = *tx.nonce();
         let current_tip = nonce - nonce_diff;
         if self.current_tip_nonce > nonce {
@@ -111,7 +158,7 @@ impl PendingQueue {
         }
         match self.queue.entry(nonce) {
             Entry::Occupied(mut o) => {
-                if o.get().gas_price() < tx.gas_price() {
+                if tx.can_replace(o.get(), price_bump) {
                     let old = o.insert(Arc::clone(&tx));
                     old.set_dropped();
                     // replace with package list tx
@@ -131,6 +178,42 @@ impl PendingQueue {
         false
     }
 
+    /// Check whether a transaction of the nonce fits the limits of the sender.
+    /// A queued transaction over the limit evicts the queued one of a higher
+    /// nonce if there is one.
+    pub fn admission(&self, nonce: &U256, nonce_diff: U256, limits: SenderLimits) -> Admission {
+        // A replacement doesn't take more room.
+        if self.get(nonce).is_some() {
+            return Admission::Accept;
+        }
+
+        let tip = self.current_tip_nonce.max(*nonce - nonce_diff);
+        let (mut next, mut pending) = (tip, 0);
+        let mut queued = Vec::new();
+        for (k, v) in self.queue.range((Included(tip), Unbounded)) {
+            if v.is_dropped() {
+                continue;
+            }
+            if *k == next {
+                next = next + 1;
+                pending += 1;
+            } else {
+                queued.push(v);
+            }
+        }
+
+        if *nonce == next && pending >= limits.max_pending {
+            return Admission::Reject;
+        }
+        if *nonce > next && queued.len() >= limits.max_queued {
+            return match queued.last() {
+                Some(highest) if highest.nonce() > nonce => Admission::Evict(Arc::clone(highest)),
+                _ => Admission::Reject,
+            };
+        }
+        Admission::Accept
+    }
+
     pub fn try_search_package_list(&mut self, list: &mut Vec<TxPtr>) {
         let mut current = self.pop_tip_nonce;
         for (k, v) in self.queue.range((Included(current), Unbounded)) {
@@ -163,6 +246,24 @@ impl PendingQueue {
         self.current_tip_nonce = self.pop_tip_nonce;
     }
 
+    /// The transactions which have not been dropped, in ascending nonce order.
+    pub fn txs(&self) -> Vec<SignedTransaction> {
+        self.queue
+            .values()
+            .filter(|tx| !tx.is_dropped())
+            .map(|tx| tx.raw_tx())
+            .collect()
+    }
+
+    /// The account nonce of the sender known by the queue.
+    pub fn tip_nonce(&self) -> U256 {
+        self.current_tip_nonce
+    }
+
+    pub fn get(&self, nonce: &U256) -> Option<&TxPtr> {
+        self.queue.get(nonce).filter(|tx| !tx.is_dropped())
+    }
+
     pub fn count(&self) -> usize {
         self.queue.values().filter(|tx| !tx.is_dropped()).count()
     }
@@ -170,8 +271,4 @@ impl PendingQueue {
     pub fn need_remove(&self) -> bool {
         self.need_remove
     }
-
-    pub fn len(&self) -> usize {
-        self.queue.len()
-    }
 }
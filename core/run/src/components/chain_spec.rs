@@ -1,12 +1,23 @@
 use common_config_parser::types::spec::ChainSpec;
 
 use protocol::types::{
-    Block, Eip1559Transaction, RichBlock, TransactionAction, UnsignedTransaction, BASE_FEE_PER_GAS,
+    Block, Eip1559Transaction, Metadata, RichBlock, TransactionAction, UnsignedTransaction,
+    BASE_FEE_PER_GAS,
 };
 
+use crate::MainError;
+
 pub(crate) trait ChainSpecExt {
     //! Generate the genesis block.
     fn generate_genesis_block(&self) -> RichBlock;
+
+    /// The metadata of the first two epochs seeded in the genesis, the second
+    /// one is the same as the first one except the epoch and the version.
+    fn genesis_metadata_list(&self) -> [Metadata; 2];
+
+    /// Check the version ranges of the genesis metadata list, see
+    /// `check_metadata_versions`.
+    fn check_metadata_versions(&self) -> Result<(), MainError>;
 }
 
 impl ChainSpecExt for ChainSpec {
@@ -19,6 +30,59 @@ impl ChainSpecExt for ChainSpec {
 
         RichBlock { block, txs }
     }
+
+    fn genesis_metadata_list(&self) -> [Metadata; 2] {
+        let metadata_0 = self.genesis_metadata();
+        let metadata_1 = {
+            let mut tmp = metadata_0.clone();
+            tmp.epoch = metadata_0.epoch + 1;
+            tmp.version.start = metadata_0.version.end + 1;
+            tmp.version.end = tmp.version.start + metadata_0.version.end - 1;
+            tmp
+        };
+        [metadata_0, metadata_1]
+    }
+
+    fn check_metadata_versions(&self) -> Result<(), MainError> {
+        check_metadata_versions(&self.genesis_metadata_list())
+    }
+}
+
+/// The version range of each epoch must not be empty, and the one of an epoch
+/// must start right after the end of the previous one.
+pub(crate) fn check_metadata_versions(metadata_list: &[Metadata]) -> Result<(), MainError> {
+    for metadata in metadata_list.iter() {
+        if metadata.version.start > metadata.version.end {
+            return Err(MainError::ChainSpec(format!(
+                "the version range [{}, {}] of epoch {} is empty",
+                metadata.version.start, metadata.version.end, metadata.epoch
+            )));
+        }
+    }
+
+    for pair in metadata_list.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        let reason = if next.version.start <= prev.version.end {
+            "overlaps"
+        } else if next.version.start - prev.version.end > 1 {
+            "leaves a gap after"
+        } else {
+            continue;
+        };
+
+        return Err(MainError::ChainSpec(format!(
+            "the version range [{}, {}] of epoch {} {} the version range [{}, {}] of epoch {}",
+            next.version.start,
+            next.version.end,
+            next.epoch,
+            reason,
+            prev.version.start,
+            prev.version.end,
+            prev.epoch
+        )));
+    }
+
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,112 @@
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc};
+
+use serde_derive::{Deserialize, Serialize};
+
+use core_executor::{
+    system_contract::{METADATA_CONTRACT_ADDRESS, METADATA_ROOT_KEY},
+    AccountChange, AccountDiff, MPTTrie, RocksTrieDB, StateDiff,
+};
+use protocol::{
+    trie::MemoryDB,
+    types::{Hex, H256, RLP_NULL, U256},
+    ProtocolResult,
+};
+
+use crate::{components::storage::DatabaseGroup, MainError};
+
+const METADATA_TRIE_CACHE_SIZE: usize = 10;
+
+/// An account in the genesis state, the storage is keyed by the hashed slot
+/// key as in the storage trie.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DumpAccount {
+    pub(crate) nonce:     U256,
+    pub(crate) balance:   U256,
+    pub(crate) code_hash: H256,
+    pub(crate) storage:   BTreeMap<H256, H256>,
+}
+
+/// A dump of the genesis state in a stable JSON schema for auditing.
+///
+/// The accounts are keyed by the hashed address and the metadata entries are
+/// keyed by the hashed key, since the preimages of the trie keys are not
+/// stored. The maps are ordered by the keys so that the same state always
+/// produces the same dump.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct GenesisDump {
+    pub(crate) state_root:    H256,
+    pub(crate) accounts:      BTreeMap<H256, DumpAccount>,
+    pub(crate) metadata_root: H256,
+    pub(crate) metadata:      BTreeMap<H256, Hex>,
+}
+
+impl GenesisDump {
+    /// Rebuild the state trie from the dumped accounts and return its root.
+    pub(crate) fn compute_state_root(&self) -> ProtocolResult<H256> {
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|(key, account)| {
+                let change = AccountChange {
+                    nonce:     account.nonce,
+                    balance:   account.balance,
+                    code_hash: account.code_hash,
+                    storage:   account.storage.clone(),
+                };
+                (*key, AccountDiff::Created(change))
+            })
+            .collect();
+
+        StateDiff { accounts }.apply(Arc::new(MemoryDB::new(false)), RLP_NULL)
+    }
+
+    pub(crate) fn write<P: AsRef<Path>>(&self, path: P) -> ProtocolResult<()> {
+        let raw = serde_json::to_vec_pretty(self).map_err(MainError::JSONParse)?;
+        fs::write(path, raw).map_err(|e| MainError::Io(e).into())
+    }
+}
+
+/// Walk the state trie of the given root and the metadata MPT whose root is
+/// saved in the metadata contract account.
+pub(crate) fn export_genesis_state(
+    db_group: &DatabaseGroup,
+    root: H256,
+) -> ProtocolResult<GenesisDump> {
+    let state = StateDiff::diff(db_group.trie_db(), RLP_NULL, root)?;
+    let accounts = state
+        .accounts
+        .into_iter()
+        .filter_map(|(key, diff)| match diff {
+            AccountDiff::Created(change) => Some((key, DumpAccount {
+                nonce:     change.nonce,
+                balance:   change.balance,
+                code_hash: change.code_hash,
+                storage:   change.storage,
+            })),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let metadata_root = accounts
+        .get(&StateDiff::account_key(&METADATA_CONTRACT_ADDRESS))
+        .and_then(|account| {
+            account
+                .storage
+                .get(&StateDiff::slot_key(&METADATA_ROOT_KEY))
+                .copied()
+        })
+        .ok_or_else(|| MainError::Other("the metadata root is missing".to_string()))?;
+
+    let trie_db = RocksTrieDB::new_metadata(db_group.inner_db(), METADATA_TRIE_CACHE_SIZE);
+    let metadata = MPTTrie::from_root(metadata_root, Arc::new(trie_db))?
+        .iter()
+        .map(|(key, value)| (H256::from_slice(&key), Hex::encode(value)))
+        .collect();
+
+    Ok(GenesisDump {
+        state_root: root,
+        accounts,
+        metadata_root,
+        metadata,
+    })
+}
@@ -1,6 +1,8 @@
 pub(crate) mod chain_spec;
 pub(crate) mod extensions;
+pub(crate) mod genesis_dump;
 pub(crate) mod network;
+pub(crate) mod snapshot;
 pub(crate) mod storage;
 pub(crate) mod system;
 
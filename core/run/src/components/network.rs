@@ -8,15 +8,16 @@ use core_consensus::message::{
 };
 use core_consensus::OverlordSynchronization;
 use core_db::RocksAdapter;
-use core_mempool::{NewTxsHandler, PullTxsHandler};
+use core_mempool::{NewTxsHandler, PullPendingTxHashesHandler, PullTxsHandler};
 use core_network::{KeyProvider, NetworkService, PeerId, PeerIdExt};
 use core_storage::ImplStorage;
 use protocol::{
     constants::endpoints::{
         BROADCAST_HEIGHT, END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_NEW_TXS, END_GOSSIP_SIGNED_CHOKE,
-        END_GOSSIP_SIGNED_PROPOSAL, END_GOSSIP_SIGNED_VOTE, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
-        RPC_RESP_PULL_TXS_SYNC, RPC_RESP_SYNC_PULL_BLOCK, RPC_RESP_SYNC_PULL_PROOF,
-        RPC_RESP_SYNC_PULL_TXS, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
+        END_GOSSIP_SIGNED_PROPOSAL, END_GOSSIP_SIGNED_VOTE, RPC_PULL_PENDING_TX_HASHES,
+        RPC_PULL_TXS, RPC_RESP_PULL_PENDING_TX_HASHES, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
+        RPC_RESP_SYNC_PULL_BLOCK, RPC_RESP_SYNC_PULL_PROOF, RPC_RESP_SYNC_PULL_TXS,
+        RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
     },
     traits::{Consensus, Context, MemPool, Network, SynchronizationAdapter},
     types::ValidatorExtend,
@@ -76,6 +77,11 @@ where
             RPC_PULL_TXS,
             PullTxsHandler::new(Arc::new(self.handle()), Arc::clone(mempool)),
         )?;
+        // register pull pending tx hashes for the startup sync of other node
+        self.register_endpoint_handler(
+            RPC_PULL_PENDING_TX_HASHES,
+            PullPendingTxHashesHandler::new(Arc::new(self.handle()), Arc::clone(mempool)),
+        )?;
         Ok(())
     }
 
@@ -138,6 +144,7 @@ where
     fn register_rpc(&mut self) -> ProtocolResult<()> {
         self.register_rpc_response(RPC_RESP_PULL_TXS)?;
         self.register_rpc_response(RPC_RESP_PULL_TXS_SYNC)?;
+        self.register_rpc_response(RPC_RESP_PULL_PENDING_TX_HASHES)?;
         self.register_rpc_response(RPC_RESP_SYNC_PULL_BLOCK)?;
         self.register_rpc_response(RPC_RESP_SYNC_PULL_PROOF)?;
         self.register_rpc_response(RPC_RESP_SYNC_PULL_TXS)?;
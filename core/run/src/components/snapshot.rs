@@ -0,0 +1,348 @@
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc};
+
+use rlp::{Rlp, RlpStream};
+
+use core_executor::{
+    AccountChange, AccountDiff, AxonExecutorReadOnlyAdapter, MPTTrie, RocksTrieDB, StateDiff,
+};
+use protocol::{
+    traits::{Context, ReadOnlyStorage, Storage},
+    types::{
+        Block, BlockNumber, Bytes, Hash, Hasher, MerkleRoot, Proof, Proposal, Receipt,
+        SignedTransaction, H256, NIL_DATA, RLP_NULL, U256,
+    },
+    ProtocolResult,
+};
+
+use crate::{components::storage::DatabaseGroup, MainError};
+
+// The cache sizes of the tries of the system contracts while they are copied.
+const SYSTEM_TRIE_CACHE_SIZE: usize = 200;
+
+/// A full snapshot of the world state at a block, which contains all the
+/// accounts with their storage and code, the tries of the system contracts,
+/// and the block itself with its transactions, receipts and proof.
+///
+/// The accounts are keyed by the hashed address as in the state trie, so that
+/// the trie can be rebuilt without knowing the addresses. The metadata and
+/// header cell tries of the system contracts are stored apart from the state
+/// trie, so their pairs of the key hash and the value are kept as well.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct StateSnapshot {
+    pub(crate) number:       BlockNumber,
+    pub(crate) state_root:   MerkleRoot,
+    pub(crate) state:        StateDiff,
+    pub(crate) codes:        BTreeMap<Hash, Bytes>,
+    pub(crate) metadata:     Vec<(Bytes, Bytes)>,
+    pub(crate) header_cells: Vec<(Bytes, Bytes)>,
+    pub(crate) block:        Block,
+    pub(crate) txs:          Vec<SignedTransaction>,
+    pub(crate) receipts:     Vec<Receipt>,
+    pub(crate) proof:        Proof,
+}
+
+impl StateSnapshot {
+    /// Export the state at the given block, or at the latest block if it is
+    /// `None`.
+    pub(crate) async fn export(
+        db_group: &DatabaseGroup,
+        number: Option<BlockNumber>,
+    ) -> ProtocolResult<Self> {
+        let storage = db_group.storage();
+        let block = match number {
+            Some(n) => storage
+                .get_block(Context::new(), n)
+                .await?
+                .ok_or_else(|| MainError::Other(format!("block {} doesn't exist", n)))?,
+            None => storage.get_latest_block(Context::new()).await?,
+        };
+        let header = &block.header;
+
+        let state = StateDiff::diff(db_group.trie_db(), RLP_NULL, header.state_root)?;
+        let mut codes = BTreeMap::new();
+        for diff in state.accounts.values() {
+            let code_hash = match diff {
+                AccountDiff::Created(change) if change.code_hash != NIL_DATA => change.code_hash,
+                _ => continue,
+            };
+
+            if !codes.contains_key(&code_hash) {
+                let code = storage
+                    .get_code_by_hash(Context::new(), &code_hash)
+                    .await?
+                    .ok_or_else(|| MainError::Other(format!("code {:?} is missing", code_hash)))?;
+                codes.insert(code_hash, code);
+            }
+        }
+
+        let backend = AxonExecutorReadOnlyAdapter::from_root(
+            header.state_root,
+            db_group.trie_db(),
+            Arc::clone(&storage),
+            Proposal::new_without_state_root(header).into(),
+        )?;
+        let metadata = trie_pairs(
+            RocksTrieDB::new_metadata(db_group.inner_db(), SYSTEM_TRIE_CACHE_SIZE),
+            backend.get_metadata_root(),
+        )?;
+        let header_cells = trie_pairs(
+            RocksTrieDB::new_ckb_light_client(db_group.inner_db(), SYSTEM_TRIE_CACHE_SIZE),
+            backend.get_image_cell_root(),
+        )?;
+
+        let txs = storage
+            .get_transactions(Context::new(), header.number, &block.tx_hashes)
+            .await?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                MainError::Other(format!("txs of block {} are missing", header.number))
+            })?;
+        let receipts = storage
+            .get_receipts(Context::new(), header.number, &block.tx_hashes)
+            .await?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| {
+                MainError::Other(format!("receipts of block {} are missing", header.number))
+            })?;
+        // The proof of a block is carried by the next one.
+        let proof = match storage
+            .get_block_header(Context::new(), header.number + 1)
+            .await?
+        {
+            Some(next) => next.proof,
+            None => storage.get_latest_proof(Context::new()).await?,
+        };
+
+        Ok(StateSnapshot {
+            number: header.number,
+            state_root: header.state_root,
+            state,
+            codes,
+            metadata,
+            header_cells,
+            txs,
+            receipts,
+            proof,
+            block,
+        })
+    }
+
+    /// Rebuild the state trie and the tries of the system contracts, save the
+    /// codes and the block as the latest one, then verify the roots.
+    pub(crate) async fn import(&self, db_group: &DatabaseGroup) -> ProtocolResult<()> {
+        if self.block.header.number != self.number
+            || self.block.header.state_root != self.state_root
+        {
+            return Err(MainError::Other(format!(
+                "the snapshot of block {} mismatches the block {}",
+                self.number, self.block.header.number
+            ))
+            .into());
+        }
+
+        let state_root = self.state.apply(db_group.trie_db(), RLP_NULL)?;
+        if state_root != self.state_root {
+            return Err(MainError::Other(format!(
+                "the state root {:?} of snapshot mismatches the one {:?} of block {}",
+                state_root, self.state_root, self.number
+            ))
+            .into());
+        }
+
+        // The address of an account is not recoverable from the state trie, so the
+        // code is only saved by its hash, as the execution reads it.
+        let storage = db_group.storage();
+        for code_hash in self.state.accounts.values().filter_map(|diff| match diff {
+            AccountDiff::Created(change) if change.code_hash != NIL_DATA => Some(change.code_hash),
+            _ => None,
+        }) {
+            let code = self
+                .codes
+                .get(&code_hash)
+                .ok_or_else(|| MainError::Other(format!("code {:?} is missing", code_hash)))?;
+            storage.insert_code_by_hash(code_hash, code.clone())?;
+        }
+
+        let backend = AxonExecutorReadOnlyAdapter::from_root(
+            state_root,
+            db_group.trie_db(),
+            Arc::clone(&storage),
+            Proposal::new_without_state_root(&self.block.header).into(),
+        )?;
+        for (name, db, pairs, root) in [
+            (
+                "metadata",
+                RocksTrieDB::new_metadata(db_group.inner_db(), SYSTEM_TRIE_CACHE_SIZE),
+                &self.metadata,
+                backend.get_metadata_root(),
+            ),
+            (
+                "header cell",
+                RocksTrieDB::new_ckb_light_client(db_group.inner_db(), SYSTEM_TRIE_CACHE_SIZE),
+                &self.header_cells,
+                backend.get_image_cell_root(),
+            ),
+        ] {
+            let rebuilt = rebuild_trie(db, pairs)?;
+            if rebuilt != root {
+                return Err(MainError::Other(format!(
+                    "the {} root {:?} of snapshot mismatches the one {:?} of block {}",
+                    name, rebuilt, root, self.number
+                ))
+                .into());
+            }
+        }
+
+        storage
+            .insert_transactions(Context::new(), self.number, self.txs.clone())
+            .await?;
+        storage
+            .insert_receipts(Context::new(), self.number, self.receipts.clone())
+            .await?;
+        storage
+            .update_latest_proof(Context::new(), self.proof.clone())
+            .await?;
+        storage
+            .insert_block(Context::new(), self.block.clone())
+            .await?;
+        storage.reindex_from(self.number).await
+    }
+
+    pub(crate) fn write<P: AsRef<Path>>(&self, path: P) -> ProtocolResult<()> {
+        fs::write(path, self.encode()).map_err(|e| MainError::Io(e).into())
+    }
+
+    pub(crate) fn read<P: AsRef<Path>>(path: P) -> ProtocolResult<Self> {
+        let raw = fs::read(path).map_err(MainError::Io)?;
+        Self::decode(&raw).map_err(|e| MainError::Other(format!("decode snapshot {:?}", e)).into())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(10);
+        stream.append(&self.number).append(&self.state_root);
+
+        stream.begin_list(self.state.accounts.len());
+        for (key, diff) in self.state.accounts.iter() {
+            let change = match diff {
+                AccountDiff::Created(change) | AccountDiff::Modified(change) => change,
+                AccountDiff::Deleted => unreachable!("snapshot contains no deleted account"),
+            };
+
+            stream
+                .begin_list(5)
+                .append(key)
+                .append(&change.nonce)
+                .append(&change.balance)
+                .append(&change.code_hash);
+            stream.begin_list(change.storage.len());
+            for (slot, value) in change.storage.iter() {
+                stream.begin_list(2).append(slot).append(value);
+            }
+        }
+
+        stream.begin_list(self.codes.len());
+        for code in self.codes.values() {
+            stream.append(&code.to_vec());
+        }
+
+        for pairs in [&self.metadata, &self.header_cells] {
+            stream.begin_list(pairs.len());
+            for (key, value) in pairs.iter() {
+                stream
+                    .begin_list(2)
+                    .append(&key.to_vec())
+                    .append(&value.to_vec());
+            }
+        }
+
+        stream
+            .append(&self.block)
+            .append_list(&self.txs)
+            .append_list(&self.receipts)
+            .append(&self.proof);
+
+        stream.out().to_vec()
+    }
+
+    fn decode(raw: &[u8]) -> Result<Self, rlp::DecoderError> {
+        let rlp = Rlp::new(raw);
+        let mut accounts = BTreeMap::new();
+        for account in rlp.at(2)?.iter() {
+            let mut storage = BTreeMap::new();
+            for item in account.at(4)?.iter() {
+                storage.insert(item.val_at::<H256>(0)?, item.val_at::<H256>(1)?);
+            }
+
+            let change = AccountChange {
+                nonce: account.val_at::<U256>(1)?,
+                balance: account.val_at::<U256>(2)?,
+                code_hash: account.val_at(3)?,
+                storage,
+            };
+            accounts.insert(account.val_at::<H256>(0)?, AccountDiff::Created(change));
+        }
+
+        let codes = rlp
+            .at(3)?
+            .iter()
+            .map(|code| {
+                let code = Bytes::from(code.as_val::<Vec<u8>>()?);
+                Ok((Hasher::digest(&code), code))
+            })
+            .collect::<Result<_, rlp::DecoderError>>()?;
+
+        let pairs = |index: usize| {
+            rlp.at(index)?
+                .iter()
+                .map(|pair| {
+                    Ok((
+                        Bytes::from(pair.val_at::<Vec<u8>>(0)?),
+                        Bytes::from(pair.val_at::<Vec<u8>>(1)?),
+                    ))
+                })
+                .collect::<Result<Vec<_>, rlp::DecoderError>>()
+        };
+
+        Ok(StateSnapshot {
+            number: rlp.val_at(0)?,
+            state_root: rlp.val_at(1)?,
+            state: StateDiff { accounts },
+            codes,
+            metadata: pairs(4)?,
+            header_cells: pairs(5)?,
+            block: rlp.val_at(6)?,
+            txs: rlp.list_at(7)?,
+            receipts: rlp.list_at(8)?,
+            proof: rlp.val_at(9)?,
+        })
+    }
+}
+
+/// The pairs of the key hash and the value of the trie, which is empty if the
+/// root is zero.
+fn trie_pairs(db: RocksTrieDB, root: H256) -> ProtocolResult<Vec<(Bytes, Bytes)>> {
+    if root.is_zero() {
+        return Ok(Vec::new());
+    }
+
+    Ok(MPTTrie::from_root(root, Arc::new(db))?
+        .iter()
+        .map(|(key, value)| (Bytes::from(key), Bytes::from(value)))
+        .collect())
+}
+
+/// Rebuild the trie from the pairs of the key hash and the value, and return
+/// its root, which is zero if there is no pair.
+fn rebuild_trie(db: RocksTrieDB, pairs: &[(Bytes, Bytes)]) -> ProtocolResult<H256> {
+    if pairs.is_empty() {
+        return Ok(H256::zero());
+    }
+
+    let mut trie = MPTTrie::new(Arc::new(db));
+    for (key, value) in pairs.iter() {
+        trie.insert_hashed(key.to_vec(), value.to_vec())?;
+    }
+    trie.commit()
+}
@@ -13,7 +13,10 @@ use protocol::{
     ProtocolResult,
 };
 
+use crate::MainError;
+
 pub(crate) struct DatabaseGroup {
+    adapter:  Arc<RocksAdapter>,
     storage:  Arc<ImplStorage<RocksAdapter>>,
     trie_db:  Arc<RocksTrieDB>,
     inner_db: Arc<RocksDB>,
@@ -25,17 +28,30 @@ impl DatabaseGroup {
         rocksdb_path: P,
         is_first_run: bool,
         triedb_cache_size: usize,
-    ) -> ProtocolResult<Self> {
+    ) -> Result<Self, MainError> {
+        let path = rocksdb_path.as_ref().display().to_string();
         let adapter_inner = if is_first_run {
             RocksAdapter::new(rocksdb_path, config.clone())
         } else {
             RocksAdapter::open(rocksdb_path, config.clone())
-        }?;
+        }
+        .map_err(|e| MainError::Database {
+            path,
+            reason: e.to_string(),
+        })?;
         let adapter = Arc::new(adapter_inner);
         let inner_db = adapter.inner_db();
         let trie_db = Arc::new(RocksTrieDB::new_evm(adapter.inner_db(), triedb_cache_size));
-        let storage = Arc::new(ImplStorage::new(adapter, config.cache_size));
+        let storage = Arc::new(
+            ImplStorage::with_code_cache_size(
+                Arc::clone(&adapter),
+                config.cache_size,
+                config.code_cache_size,
+            )
+            .tx_compression(config.compress_tx_data),
+        );
         Ok(Self {
+            adapter,
             storage,
             trie_db,
             inner_db,
@@ -53,6 +69,10 @@ impl DatabaseGroup {
     pub(crate) fn inner_db(&self) -> Arc<RocksDB> {
         Arc::clone(&self.inner_db)
     }
+
+    pub(crate) fn compact(&self) -> ProtocolResult<()> {
+        self.adapter.compact()
+    }
 }
 
 #[async_trait]
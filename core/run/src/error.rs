@@ -20,6 +20,18 @@ pub enum MainError {
     #[display(fmt = "{:?}", _0)]
     JSONParse(serde_json::error::Error),
 
+    #[display(fmt = "Failed to open the database at {} since {}", path, reason)]
+    #[from(ignore)]
+    Database { path: String, reason: String },
+
+    #[display(fmt = "Invalid chain spec: {}", _0)]
+    #[from(ignore)]
+    ChainSpec(String),
+
+    #[display(fmt = "Genesis execution failed: {}", _0)]
+    #[from(ignore)]
+    Genesis(String),
+
     #[display(fmt = "other error {:?}", _0)]
     Other(String),
 }
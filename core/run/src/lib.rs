@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
 
 use common_apm::metrics::mempool::{MEMPOOL_CO_QUEUE_LEN, MEMPOOL_LEN_GAUGE};
 use common_config_parser::types::spec::{ChainSpec, InitialAccount};
@@ -14,10 +14,10 @@ use protocol::traits::{
     Context, Executor, Gossip, MemPool, Network, NodeInfo, PeerTrust, ReadOnlyStorage, Rpc, Storage,
 };
 use protocol::types::{
-    Block, Bloom, BloomInput, ExecResp, HardforkInfoInner, Header, Metadata, Proposal, RichBlock,
-    SignedTransaction, Validator, ValidatorExtend, H256,
+    Block, Bloom, BloomInput, ExecResp, ExecutorRules, HardforkInfoInner, Header, Metadata,
+    Proposal, RichBlock, SignedTransaction, Validator, ValidatorExtend, H256,
 };
-use protocol::{lazy::CHAIN_ID, trie::DB as TrieDB, ProtocolResult};
+use protocol::{lazy::CHAIN_ID, trie::DB as TrieDB, ProtocolError, ProtocolResult};
 
 use core_api::{jsonrpc::run_jsonrpc_server, DefaultAPIAdapter};
 use core_consensus::status::{CurrentStatus, StatusAgent};
@@ -43,13 +43,16 @@ mod tests;
 use components::{
     chain_spec::ChainSpecExt as _,
     extensions::ExtensionConfig as _,
+    genesis_dump::export_genesis_state,
     network::NetworkServiceExt as _,
+    snapshot::StateSnapshot,
     storage::{DatabaseGroup, StorageExt as _, TrieExt as _},
 };
 pub use error::MainError;
 use key_provider::KeyP;
 
 pub fn init(config: Config, spec: ChainSpec) -> ProtocolResult<()> {
+    spec.check_metadata_versions()?;
     let genesis = spec.generate_genesis_block();
 
     let path_rocksdb = config.data_path_for_rocksdb();
@@ -83,6 +86,7 @@ pub fn run<K: KeyProvider>(
     config: Config,
     key_provider: Option<K>,
     stop_opt: Option<StopOpt>,
+    reindex: bool,
 ) -> ProtocolResult<()> {
     let path_rocksdb = config.data_path_for_rocksdb();
     if !path_rocksdb.exists() {
@@ -105,6 +109,11 @@ pub fn run<K: KeyProvider>(
             false,
             config.executor.triedb_cache_size,
         )?;
+        let storage = db_group.storage();
+        if reindex || !storage.is_indexed().await? {
+            log::info!("Rebuild the indexes.");
+            storage.reindex().await?;
+        }
         log::info!("Start all services.");
         start(version, config, key_provider, &db_group, stop_opt).await
     })?;
@@ -210,10 +219,15 @@ async fn start<K: KeyProvider>(
     .get_metadata_root();
 
     let metadata_handle = MetadataHandle::new(metadata_root);
-    metadata_handle.init_hardfork(current_block.header.number)?;
+    metadata_handle.init_hardfork(current_block.header.number, current_block.header.timestamp)?;
 
     let metadata = metadata_handle.get_metadata_by_block_number(current_block.header.number)?;
     let validators: Vec<Validator> = metadata.verifier_list.iter().map(Into::into).collect();
+    let executor_rules = metadata_handle.executor_rules()?;
+    core_executor::set_precompile_gas_overrides(executor_rules.precompile_gas.clone());
+    core_executor::set_disabled_opcodes(executor_rules.disabled_opcodes.clone());
+    system_contract::set_header_retention(executor_rules.header_retention);
+    system_contract::set_max_cell_data_size(executor_rules.max_cell_data_size);
 
     // Set args in mempool
     mempool.set_args(
@@ -232,7 +246,10 @@ async fn start<K: KeyProvider>(
         Arc::clone(&storage),
         Arc::clone(&trie_db),
         Arc::clone(&crypto),
-    )?;
+        config.mempool.package_time_ratio,
+        config.executor.parallel_trie_commit,
+    )?
+    .executor_limits(executor_rules.limits);
     let consensus_adapter = Arc::new(consensus_adapter);
     let status_agent = get_status_agent(&storage, &current_block, &metadata).await?;
 
@@ -286,12 +303,17 @@ async fn start<K: KeyProvider>(
     tokio::spawn(network_service.run());
 
     // Run API
-    let api_adapter = Arc::new(DefaultAPIAdapter::new(
-        Arc::clone(&mempool),
-        Arc::clone(&storage),
-        Arc::clone(&trie_db),
-        Arc::new(network_handle),
-    ));
+    let api_adapter = Arc::new(
+        DefaultAPIAdapter::new(
+            Arc::clone(&mempool),
+            Arc::clone(&storage),
+            Arc::clone(&trie_db),
+            Arc::new(network_handle),
+            executor_rules.limits,
+        )
+        .max_trace_steps(config.web3.max_trace_steps)
+        .max_trace_bytes(config.web3.max_trace_response_bytes),
+    );
     let _handles = run_jsonrpc_server(version, config, api_adapter).await?;
 
     // Run sync
@@ -348,7 +370,10 @@ where
         current_header.gas_limit.as_u64(),
         config.pool_size as usize,
         config.broadcast_txs_size,
+        config.broadcast_txs_bytes,
         config.broadcast_txs_interval,
+        config.check_fee_cap,
+        config.max_access_list_entries,
     );
     let mempool = Arc::new(
         MemPoolImpl::new(
@@ -357,9 +382,27 @@ where
             mempool_adapter,
             signed_txs.to_owned(),
         )
-        .await,
+        .await
+        .revalidate_after_flush(config.revalidate_after_commit)
+        .sync_on_startup(config.startup_sync_txs)
+        .price_bump(config.price_bump)
+        .sender_limits(config.max_pending_per_sender, config.max_queued_per_sender)
+        .tx_ttl(Duration::from_secs(config.tx_ttl))
+        .read_your_writes(config.read_your_writes),
     );
 
+    if config.tx_ttl != 0 {
+        // Sweep the expired transactions at most every minute.
+        let interval = Duration::from_secs(config.tx_ttl.min(60));
+        let evict_mempool = Arc::clone(&mempool);
+        tokio::spawn(async move {
+            loop {
+                sleep(interval).await;
+                evict_mempool.evict_expired();
+            }
+        });
+    }
+
     // Clone the mempool and spawn a thread to monitor the mempool length.
     let monitor_mempool = Arc::clone(&mempool);
     tokio::spawn(async move {
@@ -452,22 +495,22 @@ async fn execute_genesis(
     mut partial_genesis: RichBlock,
     spec: &ChainSpec,
     db_group: &DatabaseGroup,
-) -> ProtocolResult<RichBlock> {
-    let metadata_0 = spec.params.clone();
-    let metadata_1 = {
-        let mut tmp = metadata_0.clone();
-        tmp.epoch = metadata_0.epoch + 1;
-        tmp.version.start = metadata_0.version.end + 1;
-        tmp.version.end = tmp.version.start + metadata_0.version.end - 1;
-        tmp
-    };
+) -> Result<RichBlock, MainError> {
+    let metadata_list = spec.genesis_metadata_list();
+    let hardfork = spec.genesis.generate_hardfork_info();
+    let rules = ExecutorRules::from(spec.executor.clone());
+    let metadata_root =
+        system_contract::metadata::genesis_metadata_root(&metadata_list, &hardfork, &rules)
+            .map_err(|e| MainError::Genesis(e.to_string()))?;
 
     let resp = execute_genesis_transactions(
         &partial_genesis,
         db_group,
         &spec.accounts,
-        &[metadata_0, metadata_1],
-        spec.genesis.generate_hardfork_info(),
+        &metadata_list,
+        hardfork,
+        &rules,
+        metadata_root,
     )?;
 
     partial_genesis.block.header.state_root = resp.state_root;
@@ -488,7 +531,8 @@ async fn execute_genesis(
     db_group
         .storage()
         .save_block(&partial_genesis, &resp)
-        .await?;
+        .await
+        .map_err(|e| MainError::Genesis(e.to_string()))?;
 
     Ok(partial_genesis)
 }
@@ -499,30 +543,52 @@ fn execute_genesis_transactions(
     accounts: &[InitialAccount],
     metadata_list: &[Metadata],
     hardfork: HardforkInfoInner,
-) -> ProtocolResult<ExecResp> {
+    rules: &ExecutorRules,
+    expected_metadata_root: H256,
+) -> Result<ExecResp, MainError> {
+    let genesis_err = |e: ProtocolError| MainError::Genesis(e.to_string());
     let state_root = MPTTrie::new(db_group.trie_db())
         .insert_accounts(accounts)
-        .expect("insert accounts")
-        .commit()?;
+        .and_then(|mut trie| trie.commit())
+        .map_err(genesis_err)?;
     let mut backend = AxonExecutorApplyAdapter::from_root(
         state_root,
         db_group.trie_db(),
         db_group.storage(),
         Proposal::new_without_state_root(&rich.block.header).into(),
-    )?;
-
-    system_contract::init(db_group.inner_db(), &mut backend, metadata_list, hardfork)?;
+    )
+    .map_err(genesis_err)?;
+
+    system_contract::init(
+        db_group.inner_db(),
+        &mut backend,
+        metadata_list,
+        hardfork,
+        rules,
+    )
+    .map_err(genesis_err)?;
+
+    let metadata_root = backend.get_metadata_root();
+    if metadata_root != expected_metadata_root {
+        return Err(MainError::Genesis(format!(
+            "the genesis metadata root {:?} mismatches the expected one {:?}",
+            metadata_root, expected_metadata_root
+        )));
+    }
 
-    let resp = AxonExecutor.exec(&mut backend, &rich.txs, &[]);
+    let resp = AxonExecutor::new(rules.limits).exec(&mut backend, &rich.txs, &[]);
 
-    resp.tx_resp.iter().enumerate().for_each(|(i, r)| {
-        if !r.exit_reason.is_succeed() {
-            panic!(
-                "The {}th tx in genesis execute failed, reason {:?}",
-                i, r.exit_reason
-            );
-        }
-    });
+    if let Some((i, r)) = resp
+        .tx_resp
+        .iter()
+        .enumerate()
+        .find(|(_, r)| !r.exit_reason.is_succeed())
+    {
+        return Err(MainError::Genesis(format!(
+            "the {}th tx in genesis execute failed, reason {:?}",
+            i, r.exit_reason
+        )));
+    }
 
     Ok(resp)
 }
@@ -580,13 +646,17 @@ pub fn set_hardfork_info(
         .get_metadata_root();
 
         let metadata_handle = MetadataHandle::new(metadata_root);
-        metadata_handle.init_hardfork(current_block.header.number)?;
+        metadata_handle.init_hardfork(current_block.header.number, current_block.header.timestamp)?;
         if let Some(proposed_info) = hardfork_info {
-            if current_block.header.number >= proposed_info.block_number {
+            if proposed_info.is_activated(current_block.header.number, current_block.header.timestamp)
+            {
                 return Err::<(), protocol::ProtocolError>(
                     MainError::Other(format!(
-                        "Hardfork start block number {} less than current number {}",
-                        proposed_info.block_number, current_block.header.number
+                        "Hardfork start block number {} or timestamp {} less than current number {} or timestamp {}",
+                        proposed_info.block_number,
+                        proposed_info.timestamp,
+                        current_block.header.number,
+                        current_block.header.timestamp
                     ))
                     .into(),
                 );
@@ -615,3 +685,202 @@ pub fn set_hardfork_info(
 
     Ok(())
 }
+
+/// Export the state at the given block, or at the latest block if it is `None`,
+/// into a snapshot file.
+pub fn export_snapshot<P: AsRef<Path>>(
+    config: Config,
+    number: Option<u64>,
+    out: P,
+) -> ProtocolResult<()> {
+    let path_rocksdb = config.data_path_for_rocksdb();
+    if !path_rocksdb.exists() {
+        let msg = format!(
+            "Data directory {} doesn't exist, please initialize it before run.",
+            path_rocksdb.display()
+        );
+        return Err(MainError::Other(msg).into());
+    }
+
+    let rt = RuntimeBuilder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("new tokio runtime");
+
+    rt.block_on(async move {
+        log::info!("Load databases.");
+        let db_group = DatabaseGroup::new(
+            &config.rocksdb,
+            path_rocksdb,
+            false,
+            config.executor.triedb_cache_size,
+        )?;
+
+        let snapshot = StateSnapshot::export(&db_group, number).await?;
+        log::info!(
+            "Export the state of block {} with {} accounts.",
+            snapshot.number,
+            snapshot.state.accounts.len()
+        );
+        snapshot.write(out)
+    })
+}
+
+/// Import a snapshot file into a new data directory, the roots of the state and
+/// the system contracts are verified against the ones of the exported block.
+pub fn import_snapshot<P: AsRef<Path>>(config: Config, input: P) -> ProtocolResult<()> {
+    let path_rocksdb = config.data_path_for_rocksdb();
+    if path_rocksdb.exists() {
+        let msg = format!("Data directory {} already exists.", path_rocksdb.display());
+        return Err(MainError::Other(msg).into());
+    }
+
+    let snapshot = StateSnapshot::read(input)?;
+
+    let rt = RuntimeBuilder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("new tokio runtime");
+
+    rt.block_on(async move {
+        log::info!("Load databases.");
+        let db_group = DatabaseGroup::new(
+            &config.rocksdb,
+            path_rocksdb,
+            true,
+            config.executor.triedb_cache_size,
+        )?;
+
+        snapshot.import(&db_group).await?;
+        log::info!(
+            "Import the state of block {} with state root {:?}.",
+            snapshot.number,
+            snapshot.state_root
+        );
+        Ok(())
+    })
+}
+
+/// Dump the genesis state, including the accounts and the metadata, into a
+/// JSON file.
+pub fn dump_genesis<P: AsRef<Path>>(config: Config, out: P) -> ProtocolResult<()> {
+    let path_rocksdb = config.data_path_for_rocksdb();
+    if !path_rocksdb.exists() {
+        let msg = format!(
+            "Data directory {} doesn't exist, please initialize it before run.",
+            path_rocksdb.display()
+        );
+        return Err(MainError::Other(msg).into());
+    }
+
+    let rt = RuntimeBuilder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("new tokio runtime");
+
+    rt.block_on(async move {
+        log::info!("Load databases.");
+        let db_group = DatabaseGroup::new(
+            &config.rocksdb,
+            path_rocksdb,
+            false,
+            config.executor.triedb_cache_size,
+        )?;
+
+        let genesis = db_group
+            .storage()
+            .get_block_header(Context::new(), 0)
+            .await?
+            .ok_or_else(|| MainError::Other("genesis block doesn't exist".to_string()))?;
+        let dump = export_genesis_state(&db_group, genesis.state_root)?;
+        log::info!(
+            "Dump the genesis state with {} accounts and {} metadata entries.",
+            dump.accounts.len(),
+            dump.metadata.len()
+        );
+        dump.write(out)
+    })
+}
+
+/// Remove the trie nodes unreachable from the states of the latest
+/// `keep_states` blocks if it is given, then compact the databases. The node
+/// must be stopped since the databases are opened exclusively.
+pub fn compact_db(config: Config, keep_states: Option<u64>) -> ProtocolResult<()> {
+    let path_rocksdb = config.data_path_for_rocksdb();
+    if !path_rocksdb.exists() {
+        let msg = format!(
+            "Data directory {} doesn't exist, please initialize it before run.",
+            path_rocksdb.display()
+        );
+        return Err(MainError::Other(msg).into());
+    }
+    if keep_states == Some(0) {
+        return Err(MainError::Other("at least one state should be kept".to_string()).into());
+    }
+
+    let size_before = dir_size(&path_rocksdb).map_err(MainError::Io)?;
+
+    let rt = RuntimeBuilder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("new tokio runtime");
+
+    rt.block_on(async {
+        log::info!("Load databases.");
+        let db_group = DatabaseGroup::new(
+            &config.rocksdb,
+            &path_rocksdb,
+            false,
+            config.executor.triedb_cache_size,
+        )?;
+
+        if let Some(keep) = keep_states {
+            let storage = db_group.storage();
+            let latest = storage
+                .get_latest_block_header(Context::new())
+                .await?
+                .number;
+
+            let mut roots = Vec::new();
+            for number in latest.saturating_sub(keep - 1)..=latest {
+                let header = storage
+                    .get_block_header(Context::new(), number)
+                    .await?
+                    .ok_or_else(|| MainError::Other(format!("block {} doesn't exist", number)))?;
+                roots.push(header.state_root);
+            }
+
+            let removed = db_group.trie_db().gc(&roots).map_err(MainError::Io)?;
+            log::info!(
+                "Remove {} trie nodes unreachable from the states of the latest {} blocks.",
+                removed,
+                roots.len()
+            );
+        }
+
+        log::info!("Compact databases.");
+        db_group.compact()
+    })?;
+
+    let size_after = dir_size(&path_rocksdb).map_err(MainError::Io)?;
+    log::info!(
+        "Reclaim {} bytes, the databases take {} bytes now.",
+        size_before.saturating_sub(size_after),
+        size_after
+    );
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        size += if meta.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            meta.len()
+        };
+    }
+    Ok(size)
+}
@@ -1,11 +1,12 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     convert::AsRef,
     env::{current_dir, set_current_dir},
     fs, io,
     path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
+    time::Duration,
 };
 
 use clap::{builder::TypedValueParser as _, Command};
@@ -13,26 +14,36 @@ use hasher::HasherKeccak;
 
 use common_config_parser::types::{
     spec::{ChainSpec, ChainSpecValueParser, HardforkName},
-    Config, ConfigValueParser,
+    Config, ConfigRocksDB, ConfigValueParser, DBCompression,
 };
+use core_api::jsonrpc::web3_types::BlockId;
 use core_executor::{
     system_contract::metadata::{
-        encode_consensus_config, segment::EpochSegment, CONSENSUS_CONFIG, EPOCH_SEGMENT_KEY,
-        HARDFORK_KEY,
+        encode_consensus_config, genesis_metadata_root, segment::EpochSegment, CONSENSUS_CONFIG,
+        EPOCH_SEGMENT_KEY, HARDFORK_KEY,
     },
     AxonExecutorApplyAdapter, MetadataHandle,
 };
 use protocol::{
     codec::{hex_decode, ProtocolCodec as _},
     tokio,
+    traits::{Context, ReadOnlyStorage as _, Storage as _},
     trie::{MemoryDB, PatriciaTrie, Trie as _},
     types::{
-        Bloom, BloomInput, HardforkInfo, HardforkInfoInner, Hasher, Header, Metadata, Proposal,
-        H256, RLP_EMPTY_LIST, RLP_NULL,
+        Block, Bloom, BloomInput, ExecutorRules, HardforkInfo, HardforkInfoInner, Hasher, Header,
+        Metadata, MetadataVersion, Proposal, H256, RLP_EMPTY_LIST, RLP_NULL,
     },
 };
 
-use crate::{components::chain_spec::ChainSpecExt as _, execute_genesis, DatabaseGroup};
+use crate::{
+    components::{
+        chain_spec::{check_metadata_versions, ChainSpecExt as _},
+        genesis_dump::GenesisDump,
+        snapshot::StateSnapshot,
+    },
+    execute_genesis, execute_genesis_transactions, export_genesis_state, start, DatabaseGroup,
+    MainError, SecioKeyPair, StopOpt,
+};
 
 const DEV_CONFIG_DIR: &str = "../../devtools/chain";
 
@@ -74,12 +85,210 @@ fn decode_type_id() {
     assert!(hex_decode(type_id_str).is_ok());
 }
 
+#[test]
+fn metadata_version_ranges() {
+    let spec_path = PathBuf::from_str(DEV_CONFIG_DIR)
+        .expect("read dev config dir")
+        .join("specs/multi_nodes/chain-spec.toml");
+    let spec: ChainSpec = ChainSpecValueParser
+        .parse_ref(&Command::new("dummy-command"), None, spec_path.as_os_str())
+        .expect("parse chain-spec file");
+    assert!(spec.check_metadata_versions().is_ok());
+
+    let metadata = |epoch: u64, start: u64, end: u64| Metadata {
+        epoch,
+        version: MetadataVersion::new(start, end),
+        ..Default::default()
+    };
+
+    let overlapping = [metadata(0, 1, 100), metadata(1, 100, 200)];
+    let err = check_metadata_versions(&overlapping)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("epoch 1 overlaps"), "{}", err);
+
+    let gapped = [metadata(0, 1, 100), metadata(1, 102, 200)];
+    let err = check_metadata_versions(&gapped).unwrap_err().to_string();
+    assert!(err.contains("epoch 1 leaves a gap after"), "{}", err);
+
+    let empty = [metadata(0, 100, 1)];
+    assert!(check_metadata_versions(&empty).is_err());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn genesis_data_for_dev_chain() {
     for case in TESTCASES.iter() {
         println!("======Test case {:?}======", case.chain_name);
         check_genesis_data(case).await;
     }
+
+    // The genesis data is global, so the checks run one by one.
+    println!("======Test genesis with reordered verifiers======");
+    check_genesis_with_reordered_verifiers().await;
+
+    println!("======Test genesis with wrong metadata root======");
+    check_genesis_with_wrong_metadata_root();
+
+    println!("======Test genesis with executor rules======");
+    check_genesis_with_executor_rules().await;
+}
+
+async fn check_genesis_with_reordered_verifiers() {
+    let spec_path = PathBuf::from_str(DEV_CONFIG_DIR)
+        .expect("read dev config dir")
+        .join("specs/multi_nodes/chain-spec.toml");
+    let mut spec: ChainSpec = ChainSpecValueParser
+        .parse_ref(&Command::new("dummy-command"), None, spec_path.as_os_str())
+        .expect("parse chain-spec file");
+    spec.genesis.sort_verifier_list = true;
+    let mut reordered = spec.clone();
+    reordered.params.verifier_list.reverse();
+    assert_ne!(spec.params.verifier_list, reordered.params.verifier_list);
+
+    let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+    let mut state_roots = Vec::new();
+    for (i, spec) in [spec, reordered].iter().enumerate() {
+        let db_group = DatabaseGroup::new(
+            &ConfigRocksDB::default(),
+            tmp_dir.path().join(i.to_string()),
+            true,
+            100,
+        )
+        .expect("initialize databases");
+        let genesis = execute_genesis(spec.generate_genesis_block(), spec, &db_group)
+            .await
+            .expect("complete genesis block");
+        state_roots.push(genesis.block.header.state_root);
+    }
+
+    assert_eq!(state_roots[0], state_roots[1]);
+}
+
+/// The executor rules in the chain spec are stored in the genesis state, so the
+/// chains with different rules have different genesis.
+async fn check_genesis_with_executor_rules() {
+    let spec_path = PathBuf::from_str(DEV_CONFIG_DIR)
+        .expect("read dev config dir")
+        .join("specs/single_node/chain-spec.toml");
+    let spec: ChainSpec = ChainSpecValueParser
+        .parse_ref(&Command::new("dummy-command"), None, spec_path.as_os_str())
+        .expect("parse chain-spec file");
+    let mut with_rules = spec.clone();
+    with_rules.executor.limits.call_stack_limit = 256;
+
+    let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+    let mut headers = Vec::new();
+    for (i, spec) in [spec, with_rules.clone()].iter().enumerate() {
+        let db_group = DatabaseGroup::new(
+            &ConfigRocksDB::default(),
+            tmp_dir.path().join(i.to_string()),
+            true,
+            100,
+        )
+        .expect("initialize databases");
+        let genesis = execute_genesis(spec.generate_genesis_block(), spec, &db_group)
+            .await
+            .expect("complete genesis block");
+        let metadata_root = AxonExecutorApplyAdapter::from_root(
+            genesis.block.header.state_root,
+            db_group.trie_db(),
+            db_group.storage(),
+            Proposal::new_without_state_root(&genesis.block.header).into(),
+        )
+        .expect("read genesis state")
+        .get_metadata_root();
+        let rules = MetadataHandle::new(metadata_root)
+            .executor_rules()
+            .expect("read executor rules");
+        assert_eq!(rules, ExecutorRules::from(spec.executor.clone()));
+        headers.push(genesis.block.header);
+    }
+
+    assert_ne!(headers[0].state_root, headers[1].state_root);
+}
+
+/// The genesis aborts if the metadata root mismatches the one derived from the
+/// chain spec.
+fn check_genesis_with_wrong_metadata_root() {
+    let spec_path = PathBuf::from_str(DEV_CONFIG_DIR)
+        .expect("read dev config dir")
+        .join("specs/multi_nodes/chain-spec.toml");
+    let spec: ChainSpec = ChainSpecValueParser
+        .parse_ref(&Command::new("dummy-command"), None, spec_path.as_os_str())
+        .expect("parse chain-spec file");
+    let mut wrong = spec.clone();
+    wrong.params.consensus_config.gas_limit += 1;
+
+    let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+    let db_group = DatabaseGroup::new(&ConfigRocksDB::default(), tmp_dir.path(), true, 100)
+        .expect("initialize databases");
+    let hardfork = spec.genesis.generate_hardfork_info();
+    let rules = ExecutorRules::default();
+    let wrong_root = genesis_metadata_root(&wrong.genesis_metadata_list(), &hardfork, &rules)
+        .expect("calculate metadata root");
+
+    let res = execute_genesis_transactions(
+        &spec.generate_genesis_block(),
+        &db_group,
+        &spec.accounts,
+        &spec.genesis_metadata_list(),
+        hardfork,
+        &rules,
+        wrong_root,
+    );
+    let err = res.unwrap_err();
+    assert!(matches!(err, MainError::Genesis(_)));
+    assert!(err.to_string().contains("genesis metadata root"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn database_with_compression() {
+    let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+    let config = ConfigRocksDB {
+        compression: HashMap::from([
+            ("Block".to_string(), DBCompression::Zstd),
+            ("EvmState".to_string(), DBCompression::Lz4),
+        ]),
+        ..Default::default()
+    };
+    let db_group = DatabaseGroup::new(&config, tmp_dir.path().join("block"), true, 100)
+        .expect("initialize databases");
+
+    let block = Block::default();
+    db_group
+        .storage()
+        .insert_block(Context::new(), block.clone())
+        .await
+        .expect("insert block");
+    let stored = db_group
+        .storage()
+        .get_block(Context::new(), block.header.number)
+        .await
+        .expect("read block");
+    assert_eq!(stored, Some(block));
+
+    let config = ConfigRocksDB {
+        compression: HashMap::from([("Unknown".to_string(), DBCompression::Zstd)]),
+        ..Default::default()
+    };
+    assert!(DatabaseGroup::new(&config, tmp_dir.path().join("unknown"), true, 100).is_err());
+}
+
+#[test]
+fn database_with_unwritable_path() {
+    let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+    let file = tmp_dir.path().join("file");
+    std::fs::write(&file, b"").expect("create file");
+
+    // A path under a regular file can't be created even by the root user.
+    let path = file.join("db");
+    let err = match DatabaseGroup::new(&ConfigRocksDB::default(), &path, true, 100) {
+        Ok(_) => panic!("open the database under a regular file"),
+        Err(err) => err,
+    };
+    assert!(
+        matches!(err, MainError::Database { path: ref p, .. } if *p == path.display().to_string())
+    );
 }
 
 async fn check_genesis_data<'a>(case: &TestCase<'a>) {
@@ -132,6 +341,9 @@ async fn check_genesis_data<'a>(case: &TestCase<'a>) {
         genesis.block.header.hash(),
     );
 
+    println!("checking earliest block");
+    check_earliest_block(case, &db_group).await;
+
     println!("checking state root");
     check_hashes_via_str(
         case.chain_name,
@@ -181,6 +393,12 @@ async fn check_genesis_data<'a>(case: &TestCase<'a>) {
     println!("checking state");
     check_state(&chain_spec, &genesis.block.header, &db_group);
 
+    println!("checking state snapshot");
+    check_state_snapshot(&config, &genesis.block.header, &db_group, tmp_dir_path).await;
+
+    println!("checking genesis dump");
+    check_genesis_dump(&genesis.block.header, &db_group);
+
     set_current_dir(current_dir).expect("change back to original work directory");
 }
 
@@ -193,7 +411,7 @@ fn check_state(spec: &ChainSpec, genesis_header: &Header, db_group: &DatabaseGro
     )
     .unwrap();
 
-    let metadata_0 = spec.params.clone();
+    let metadata_0 = spec.genesis_metadata();
     let metadata_1 = {
         let mut tmp = metadata_0.clone();
         tmp.epoch = metadata_0.epoch + 1;
@@ -212,6 +430,174 @@ fn check_state(spec: &ChainSpec, genesis_header: &Header, db_group: &DatabaseGro
     assert_metadata(metadata_1, handle.get_metadata_by_epoch(1).unwrap());
 }
 
+/// The `earliest` tag of the block RPCs resolves to the genesis block.
+async fn check_earliest_block<'a>(case: &TestCase<'a>, db_group: &DatabaseGroup) {
+    let id: BlockId = serde_json::from_str("\"earliest\"").expect("parse block id");
+    let number = Option::<u64>::from(id).expect("earliest block number");
+    let block = db_group
+        .storage()
+        .get_block(Context::new(), number)
+        .await
+        .expect("read earliest block")
+        .expect("earliest block exists");
+
+    assert_eq!(block.header.number, 0);
+    check_hashes_via_str(
+        case.chain_name,
+        "earliest block hash",
+        case.input_genesis_hash,
+        block.hash(),
+    );
+}
+
+async fn check_state_snapshot(
+    config: &Config,
+    genesis_header: &Header,
+    db_group: &DatabaseGroup,
+    dir: &Path,
+) {
+    let snapshot = StateSnapshot::export(db_group, Some(0))
+        .await
+        .expect("export snapshot");
+    assert_eq!(snapshot.number, 0);
+    assert_eq!(snapshot.state_root, genesis_header.state_root);
+
+    let snapshot_path = dir.join("snap.bin");
+    snapshot.write(&snapshot_path).expect("write snapshot");
+    let imported = StateSnapshot::read(&snapshot_path).expect("read snapshot");
+    assert_eq!(imported, snapshot);
+
+    // Import into a new database, the rebuilt roots are verified.
+    let new_db_group = DatabaseGroup::new(
+        &config.rocksdb,
+        dir.join("snapshot"),
+        true,
+        config.executor.triedb_cache_size,
+    )
+    .expect("initialize databases");
+    imported
+        .import(&new_db_group)
+        .await
+        .expect("import snapshot");
+    assert_eq!(
+        new_db_group
+            .storage()
+            .get_latest_block_header(Context::new())
+            .await
+            .unwrap(),
+        *genesis_header
+    );
+
+    let backend = AxonExecutorApplyAdapter::from_root(
+        genesis_header.state_root,
+        new_db_group.trie_db(),
+        new_db_group.storage(),
+        Proposal::new_without_state_root(genesis_header).into(),
+    )
+    .unwrap();
+    assert_eq!(
+        backend.get_metadata_root(),
+        AxonExecutorApplyAdapter::from_root(
+            genesis_header.state_root,
+            db_group.trie_db(),
+            db_group.storage(),
+            Proposal::new_without_state_root(genesis_header).into(),
+        )
+        .unwrap()
+        .get_metadata_root()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn start_from_state_snapshot() {
+    let dev_config_dir = PathBuf::from_str(DEV_CONFIG_DIR).expect("read dev config dir");
+    let tmp_dir = tempfile::tempdir().expect("create temporary directory");
+    let command = Command::new("dummy-command");
+    let mut config: Config = ConfigValueParser
+        .parse_ref(
+            &command,
+            None,
+            dev_config_dir.join("config.toml").as_os_str(),
+        )
+        .expect("parse config file");
+    let chain_spec: ChainSpec = ChainSpecValueParser
+        .parse_ref(
+            &command,
+            None,
+            dev_config_dir
+                .join("specs/single_node/chain-spec.toml")
+                .as_os_str(),
+        )
+        .expect("parse chain-spec file");
+
+    let db_group = DatabaseGroup::new(
+        &config.rocksdb,
+        tmp_dir.path().join("origin"),
+        true,
+        config.executor.triedb_cache_size,
+    )
+    .expect("initialize databases");
+    let genesis = execute_genesis(chain_spec.generate_genesis_block(), &chain_spec, &db_group)
+        .await
+        .expect("complete genesis block");
+    let snapshot = StateSnapshot::export(&db_group, None)
+        .await
+        .expect("export snapshot");
+
+    config.data_path = tmp_dir.path().join("imported");
+    config.rpc.http_listening_address = None;
+    config.rpc.ws_listening_address = None;
+    config.network.listening_address = "/ip4/127.0.0.1/tcp/0".parse().unwrap();
+    config.jaeger = None;
+    config.prometheus = None;
+    let imported = DatabaseGroup::new(
+        &config.rocksdb,
+        config.data_path_for_rocksdb(),
+        true,
+        config.executor.triedb_cache_size,
+    )
+    .expect("initialize databases");
+    snapshot.import(&imported).await.expect("import snapshot");
+    assert!(imported.storage().is_indexed().await.unwrap());
+
+    // The node starts from the imported data only, and mines the next block.
+    tokio::time::timeout(
+        Duration::from_secs(60),
+        start(
+            String::new(),
+            config,
+            None::<SecioKeyPair>,
+            &imported,
+            Some(StopOpt::MineNBlocks(1)),
+        ),
+    )
+    .await
+    .expect("mine a block in time")
+    .expect("start from the snapshot");
+
+    let latest = imported
+        .storage()
+        .get_latest_block_header(Context::new())
+        .await
+        .unwrap();
+    assert_eq!(latest.number, 1);
+    assert_eq!(latest.prev_hash, genesis.block.header.hash());
+}
+
+fn check_genesis_dump(genesis_header: &Header, db_group: &DatabaseGroup) {
+    let dump = export_genesis_state(db_group, genesis_header.state_root).expect("export genesis");
+    assert_eq!(dump.state_root, genesis_header.state_root);
+    assert!(!dump.metadata.is_empty());
+
+    let json = serde_json::to_string(&dump).expect("serialize genesis dump");
+    let decoded: GenesisDump = serde_json::from_str(&json).expect("deserialize genesis dump");
+    assert_eq!(decoded, dump);
+    assert_eq!(
+        decoded.compute_state_root().expect("compute state root"),
+        genesis_header.state_root
+    );
+}
+
 fn check_hashes_via_str(chain: &str, name: &str, expected_str: &str, actual: H256) {
     let expected = H256::from_str(expected_str)
         .unwrap_or_else(|err| panic!("failed to parse hash {name} of chain {chain} since {err}"));
@@ -314,8 +700,9 @@ fn generate_memory_mpt_root(metadata_0: Metadata, metadata_1: Metadata) -> Vec<u
         .unwrap();
 
     let info = HardforkInfoInner {
-        flags:        H256::from_low_u64_be(HardforkName::all().to_be()),
+        flags:        H256::from_low_u64_be(HardforkName::implicit().to_be()),
         block_number: 0,
+        timestamp:    0,
     };
     let hardfork = HardforkInfo { inner: vec![info] }
         .encode()
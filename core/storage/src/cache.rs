@@ -11,20 +11,73 @@ pub struct StorageCache {
     pub block_numbers: Mutex<LruCache<Hash, u64>>,
     pub headers:       Mutex<LruCache<u64, Header>>,
     pub transactions:  Mutex<LruCache<Hash, SignedTransaction>>,
-    pub codes:         Mutex<LruCache<Hash, Bytes>>,
+    pub codes:         Mutex<CodeCache>,
     pub receipts:      Mutex<LruCache<Hash, Receipt>>,
 }
 
 impl StorageCache {
-    pub fn new(size: usize) -> Self {
+    pub fn new(size: usize, code_cache_size: usize) -> Self {
         let size = NonZeroUsize::new(size).unwrap();
         StorageCache {
             blocks:        Mutex::new(LruCache::new(size)),
             block_numbers: Mutex::new(LruCache::new(size)),
             headers:       Mutex::new(LruCache::new(size)),
             transactions:  Mutex::new(LruCache::new(size)),
-            codes:         Mutex::new(LruCache::new(size)),
+            codes:         Mutex::new(CodeCache::new(code_cache_size)),
             receipts:      Mutex::new(LruCache::new(size)),
         }
     }
 }
+
+/// The LRU cache of the contract codes which is bounded by the total bytes of
+/// the cached codes. The contracts with identical bytecode share one entry. A
+/// code is immutable for its hash, so the cache never needs invalidation.
+#[derive(Debug)]
+pub struct CodeCache {
+    inner:    LruCache<Hash, Bytes>,
+    size:     usize,
+    capacity: usize,
+}
+
+impl CodeCache {
+    pub fn new(capacity: usize) -> Self {
+        CodeCache {
+            inner: LruCache::unbounded(),
+            size: 0,
+            capacity,
+        }
+    }
+
+    pub fn get(&mut self, hash: &Hash) -> Option<&Bytes> {
+        self.inner.get(hash)
+    }
+
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.inner.contains(hash)
+    }
+
+    pub fn put(&mut self, hash: Hash, code: Bytes) -> Option<Bytes> {
+        if code.len() > self.capacity {
+            return None;
+        }
+
+        self.size += code.len();
+        let old = self.inner.put(hash, code);
+        if let Some(old) = old.as_ref() {
+            self.size -= old.len();
+        }
+
+        while self.size > self.capacity {
+            match self.inner.pop_lru() {
+                Some((_, evicted)) => self.size -= evicted.len(),
+                None => break,
+            }
+        }
+
+        old
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
@@ -0,0 +1,49 @@
+use snap::raw::{Decoder as SnapDecoder, Encoder as SnapEncoder};
+
+use protocol::codec::ProtocolCodec;
+use protocol::types::{BufMut, Bytes, BytesMut, DBBytes, SignedTransaction};
+use protocol::ProtocolResult;
+
+use crate::StorageError;
+
+/// The encoding of a signed transaction is a RLP string whose first byte is
+/// never less than `0x80`, so a leading zero byte marks the value as
+/// snappy compressed without breaking the transactions stored before.
+const COMPRESS_FLAG: u8 = 0x00;
+
+/// Encode the signed transaction for storage. The transaction is compressed
+/// only if it is enabled and the compressed value is smaller. The transaction
+/// hash is never affected since it is calculated over the uncompressed
+/// canonical encoding.
+pub fn encode_stx(stx: &SignedTransaction, compress: bool) -> ProtocolResult<DBBytes> {
+    let raw = stx.encode()?;
+    if !compress {
+        return Ok(DBBytes(raw));
+    }
+
+    let compressed = SnapEncoder::new()
+        .compress_vec(&raw)
+        .map_err(|e| StorageError::Compress(e.to_string()))?;
+    if compressed.len() + 1 >= raw.len() {
+        return Ok(DBBytes(raw));
+    }
+
+    let mut ret = BytesMut::with_capacity(compressed.len() + 1);
+    ret.put_u8(COMPRESS_FLAG);
+    ret.put(compressed.as_slice());
+    Ok(DBBytes(ret.freeze()))
+}
+
+/// Decode a signed transaction which is stored either compressed or not.
+pub fn decode_stx<B: AsRef<[u8]>>(bytes: B) -> ProtocolResult<SignedTransaction> {
+    let bytes = bytes.as_ref();
+    match bytes.first() {
+        Some(&COMPRESS_FLAG) => {
+            let raw = SnapDecoder::new()
+                .decompress_vec(&bytes[1..])
+                .map_err(|e| StorageError::Compress(e.to_string()))?;
+            SignedTransaction::decode(Bytes::from(raw))
+        }
+        _ => SignedTransaction::decode(bytes),
+    }
+}
@@ -1,4 +1,5 @@
 mod cache;
+mod compress;
 mod hash_key;
 mod schema;
 #[cfg(test)]
@@ -21,21 +22,28 @@ use protocol::traits::{
 };
 use protocol::types::{
     Block, BlockNumber, Bytes, DBBytes, HardforkInfoInner, Hash, Hasher, Header, Proof, Receipt,
-    SignedTransaction, H256,
+    SignedTransaction, H160, H256,
 };
 use protocol::{
     async_trait, tokio, Display, From, ProtocolError, ProtocolErrorKind, ProtocolResult,
 };
 
 use crate::cache::StorageCache;
+use crate::compress::{decode_stx, encode_stx};
 use crate::hash_key::{BlockKey, CommonHashKey, CommonPrefix};
 use crate::schema::{
-    BlockHashNumberSchema, BlockHeaderSchema, BlockSchema, EvmCodeAddressSchema, EvmCodeSchema,
-    HardforkSchema, LatestBlockSchema, LatestProofSchema, ReceiptBytesSchema, ReceiptSchema,
-    TransactionBytesSchema, TransactionSchema, TxHashNumberSchema,
+    BlockHashNumberSchema, BlockHeaderSchema, BlockSchema, DeployedContractsSchema,
+    EvmCodeAddressSchema, EvmCodeSchema, HardforkSchema, IndexVersionSchema, LatestBlockSchema,
+    LatestProofSchema, ReceiptBytesSchema, ReceiptSchema, TransactionBytesSchema,
+    TxHashNumberSchema,
 };
 
 const BATCH_VALUE_DECODE_NUMBER: usize = 1000;
+const DEFAULT_CODE_CACHE_SIZE: usize = 16 * 1024 * 1024; // 16MB
+/// The version of the indexes, which is bumped once an index is added so that
+/// the existing databases are reindexed. The version 1 adds the deployed
+/// contracts index.
+const INDEX_VERSION: u64 = 1;
 
 lazy_static::lazy_static! {
     pub static ref LATEST_BLOCK_KEY: Hash = Hasher::digest(Bytes::from("latest_hash"));
@@ -43,6 +51,7 @@ lazy_static::lazy_static! {
     pub static ref OVERLORD_WAL_KEY: Hash = Hasher::digest(Bytes::from("overlord_wal"));
     pub static ref MONITOR_CKB_NUMBER_KEY: Hash = Hasher::digest(Bytes::from("monitor_ckb_number"));
     pub static ref HAEDFORK_PROPOSAL: Hash = Hasher::digest(Bytes::from("hardfork_proposal"));
+    pub static ref INDEX_VERSION_KEY: Hash = Hasher::digest(Bytes::from("index_version"));
 }
 
 macro_rules! get_cache {
@@ -93,18 +102,38 @@ pub struct ImplStorage<Adapter> {
     cache:        Arc<StorageCache>,
     latest_block: ArcSwap<Option<Block>>,
     latest_proof: ArcSwap<Option<Proof>>,
+    compress_txs: bool,
 }
 
 impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
     pub fn new(adapter: Arc<Adapter>, cache_size: usize) -> Self {
+        Self::with_code_cache_size(adapter, cache_size, DEFAULT_CODE_CACHE_SIZE)
+    }
+
+    /// Create a storage whose contract code cache is bounded by
+    /// `code_cache_size` bytes.
+    pub fn with_code_cache_size(
+        adapter: Arc<Adapter>,
+        cache_size: usize,
+        code_cache_size: usize,
+    ) -> Self {
         Self {
             adapter,
-            cache: Arc::new(StorageCache::new(cache_size)),
+            cache: Arc::new(StorageCache::new(cache_size, code_cache_size)),
             latest_block: ArcSwap::new(Arc::new(None)),
             latest_proof: ArcSwap::new(Arc::new(None)),
+            compress_txs: false,
         }
     }
 
+    /// Compress the data of the transactions written afterwards. The
+    /// transactions are decompressed transparently when they are read, no
+    /// matter whether the compression is enabled.
+    pub fn tx_compression(mut self, enable: bool) -> Self {
+        self.compress_txs = enable;
+        self
+    }
+
     async fn get_block_number_by_hash(&self, hash: &Hash) -> ProtocolResult<Option<u64>> {
         get_cache!(self, hash, block_numbers);
         let ret = self.adapter.get::<BlockHashNumberSchema>(*hash)?;
@@ -128,17 +157,19 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
             .unzip();
 
         let (keys, batch_stxs): (Vec<_>, Vec<_>) = stxs
-            .into_iter()
+            .iter()
             .map(|item| {
-                (
+                Ok((
                     CommonHashKey::new(block_number, item.transaction.hash),
-                    StorageBatchModify::Insert(item),
-                )
+                    StorageBatchModify::Insert(encode_stx(item, self.compress_txs)?),
+                ))
             })
+            .collect::<ProtocolResult<Vec<_>>>()?
+            .into_iter()
             .unzip();
 
         self.adapter
-            .batch_modify::<TransactionSchema>(keys, batch_stxs)?;
+            .batch_modify::<TransactionBytesSchema>(keys, batch_stxs)?;
 
         self.adapter
             .batch_modify::<TxHashNumberSchema>(hashes, heights)?;
@@ -146,15 +177,122 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
         Ok(())
     }
 
-    async fn batch_insert_receipts(
+    /// Insert the code by its hash only, without the index of the address of
+    /// it.
+    pub fn insert_code_by_hash(&self, code_hash: Hash, code: Bytes) -> ProtocolResult<()> {
+        self.adapter.insert::<EvmCodeSchema>(code_hash, code)
+    }
+
+    /// Rebuild the indexes of the block hashes, the transaction locations and
+    /// the deployed contracts by scanning all the blocks.
+    pub async fn reindex(&self) -> ProtocolResult<()> {
+        self.reindex_from(0).await
+    }
+
+    /// Rebuild the indexes by scanning the blocks since the given number, the
+    /// ones before it are absent from a database imported from a snapshot.
+    pub async fn reindex_from(&self, start: BlockNumber) -> ProtocolResult<()> {
+        let latest = self.get_latest_block_header(Context::new()).await?.number;
+
+        for number in start..=latest {
+            let block = self
+                .get_block(Context::new(), number)
+                .await?
+                .ok_or_else(|| StorageError::GetNone(format!("block {}", number)))?;
+            self.index_block_hash(&block)?;
+
+            let receipts = self
+                .get_receipts(Context::new(), number, &block.tx_hashes)
+                .await?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            self.index_receipts(&receipts, number)?;
+        }
+
+        self.adapter
+            .insert::<IndexVersionSchema>(*INDEX_VERSION_KEY, INDEX_VERSION)
+    }
+
+    /// Whether the indexes of the current version are built and the ones of the
+    /// latest block are present. They are missing if the index column families
+    /// are dropped or created by an upgrade. The contracts of a deployer can't
+    /// be checked from the latest block, so the version tells whether they are
+    /// indexed.
+    pub async fn is_indexed(&self) -> ProtocolResult<bool> {
+        if self.adapter.get::<IndexVersionSchema>(*INDEX_VERSION_KEY)? != Some(INDEX_VERSION) {
+            return Ok(false);
+        }
+
+        let block = self.get_latest_block(Context::new()).await?;
+        if self.adapter.get::<BlockHashNumberSchema>(block.hash())? != Some(block.header.number) {
+            return Ok(false);
+        }
+
+        for hash in block.tx_hashes.iter() {
+            if self.adapter.get::<TxHashNumberSchema>(*hash)? != Some(block.header.number) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn index_block_hash(&self, block: &Block) -> ProtocolResult<()> {
+        self.adapter
+            .insert::<BlockHashNumberSchema>(block.hash(), block.header.number)?;
+        self.cache
+            .block_numbers
+            .lock()
+            .put(block.hash(), block.header.number);
+        Ok(())
+    }
+
+    /// Index the transactions of the receipts by the block number, and the
+    /// contracts created by them by their deployers.
+    fn index_receipts(
         &self,
-        receipts: Vec<Receipt>,
+        receipts: &[Receipt],
         block_number: BlockNumber,
     ) -> ProtocolResult<()> {
         let (hashes, heights) = receipts
             .iter()
             .map(|item| (item.tx_hash, StorageBatchModify::Insert(block_number)))
             .unzip();
+        self.adapter
+            .batch_modify::<TxHashNumberSchema>(hashes, heights)?;
+
+        let mut deployed: HashMap<H160, Vec<H160>> = HashMap::new();
+        for receipt in receipts.iter() {
+            if let Some(address) = receipt.code_address {
+                deployed
+                    .entry(receipt.sender)
+                    .or_default()
+                    .push(address.into());
+            }
+        }
+        for (deployer, contracts) in deployed.into_iter() {
+            let mut all = self
+                .adapter
+                .get::<DeployedContractsSchema>(deployer)?
+                .unwrap_or_default();
+            for contract in contracts.into_iter() {
+                if !all.contains(&contract) {
+                    all.push(contract);
+                }
+            }
+            self.adapter
+                .insert::<DeployedContractsSchema>(deployer, all)?;
+        }
+
+        Ok(())
+    }
+
+    async fn batch_insert_receipts(
+        &self,
+        receipts: Vec<Receipt>,
+        block_number: BlockNumber,
+    ) -> ProtocolResult<()> {
+        self.index_receipts(&receipts, block_number)?;
 
         let (keys, batch_stxs): (Vec<_>, Vec<_>) = receipts
             .into_iter()
@@ -169,9 +307,6 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
         self.adapter
             .batch_modify::<ReceiptSchema>(keys, batch_stxs)?;
 
-        self.adapter
-            .batch_modify::<TxHashNumberSchema>(hashes, heights)?;
-
         Ok(())
     }
 }
@@ -290,7 +425,7 @@ impl<Adapter: StorageAdapter> ReadOnlyStorage for ImplStorage<Adapter> {
             if found.len() <= BATCH_VALUE_DECODE_NUMBER {
                 found
                     .drain(..)
-                    .map(|(k, v): (Hash, DBBytes)| SignedTransaction::decode(v).map(|v| (k, v)))
+                    .map(|(k, v): (Hash, DBBytes)| decode_stx(v).map(|v| (k, v)))
                     .collect::<ProtocolResult<Vec<_>>>()?
                     .into_iter()
                     .collect::<HashMap<_, _>>()
@@ -303,7 +438,7 @@ impl<Adapter: StorageAdapter> ReadOnlyStorage for ImplStorage<Adapter> {
                         // FIXME: cancel decode
                         tokio::spawn(async move {
                             vals.into_iter()
-                                .map(|(k, v): (Hash, DBBytes)| <_>::decode(v).map(|v| (k, v)))
+                                .map(|(k, v): (Hash, DBBytes)| decode_stx(v).map(|v| (k, v)))
                                 .collect::<ProtocolResult<Vec<_>>>()
                         })
                     })
@@ -344,6 +479,17 @@ impl<Adapter: StorageAdapter> ReadOnlyStorage for ImplStorage<Adapter> {
         }
     }
 
+    async fn get_contracts_by_deployer(
+        &self,
+        _ctx: Context,
+        deployer: &H160,
+    ) -> ProtocolResult<Vec<H160>> {
+        Ok(self
+            .adapter
+            .get::<DeployedContractsSchema>(*deployer)?
+            .unwrap_or_default())
+    }
+
     async fn get_transaction_by_hash(
         &self,
         _ctx: Context,
@@ -352,13 +498,15 @@ impl<Adapter: StorageAdapter> ReadOnlyStorage for ImplStorage<Adapter> {
         get_cache!(self, hash, transactions);
 
         if let Some(block_height) = get!(self, *hash, TxHashNumberSchema)? {
-            get!(
+            let ret = get!(
                 self,
                 CommonHashKey::new(block_height, *hash),
-                TransactionSchema,
-                hash,
-                transactions
-            )
+                TransactionBytesSchema
+            )?
+            .map(decode_stx)
+            .transpose()?;
+            put_cache!(self, hash, ret, transactions);
+            Ok(ret)
         } else {
             Ok(None)
         }
@@ -496,9 +644,7 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
             BlockKey::new(block.header.number),
             block.header.clone(),
         )?;
-        self.adapter
-            .insert::<BlockHashNumberSchema>(block.hash(), block.header.number)?;
-        Ok(())
+        self.index_block_hash(&block)
     }
 
     async fn remove_block(&self, _ctx: Context, height: u64) -> ProtocolResult<()> {
@@ -532,7 +678,7 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         code_hash: Hash,
         code: Bytes,
     ) -> ProtocolResult<()> {
-        self.adapter.insert::<EvmCodeSchema>(code_hash, code)?;
+        self.insert_code_by_hash(code_hash, code)?;
         self.adapter
             .insert::<EvmCodeAddressSchema>(code_address, code_hash)
     }
@@ -580,6 +726,9 @@ pub enum StorageError {
 
     #[display(fmt = "decode batch value")]
     BatchDecode,
+
+    #[display(fmt = "compress transaction {:?}", _0)]
+    Compress(String),
 }
 
 impl Error for StorageError {}
@@ -1,6 +1,6 @@
 use protocol::traits::{StorageCategory, StorageSchema};
 use protocol::types::{
-    Block, Bytes, DBBytes, HardforkInfoInner, Hash, Header, Proof, Receipt, SignedTransaction,
+    Block, Bytes, DBBytes, HardforkInfoInner, Hash, Header, Proof, Receipt, SignedTransaction, H160,
 };
 
 use crate::hash_key::{BlockKey, CommonHashKey};
@@ -43,4 +43,6 @@ impl_storage_schema_for!(LatestProofSchema, Hash, Proof, Block);
 impl_storage_schema_for!(OverlordWalSchema, Hash, Bytes, Wal);
 impl_storage_schema_for!(EvmCodeSchema, Hash, Bytes, Code);
 impl_storage_schema_for!(EvmCodeAddressSchema, Hash, Hash, Code);
+impl_storage_schema_for!(DeployedContractsSchema, H160, Vec<H160>, DeployedContracts);
 impl_storage_schema_for!(HardforkSchema, Hash, HardforkInfoInner, Version);
+impl_storage_schema_for!(IndexVersionSchema, Hash, u64, Version);
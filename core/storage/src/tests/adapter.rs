@@ -1,7 +1,7 @@
 use core_db::{MemoryAdapter, RocksAdapter};
 use protocol::traits::{StorageAdapter, StorageBatchModify};
 
-use crate::{tests::mock_signed_tx, CommonHashKey, TransactionSchema};
+use crate::{schema::TransactionSchema, tests::mock_signed_tx, CommonHashKey};
 
 #[test]
 fn test_adapter_insert() {
@@ -12,6 +12,10 @@ use protocol::types::{
 use protocol::types::{Bytes, UnsignedTransaction};
 
 fn mock_signed_tx() -> SignedTransaction {
+    mock_signed_tx_with_data(Bytes::new())
+}
+
+fn mock_signed_tx_with_data(input: Bytes) -> SignedTransaction {
     let mut utx = UnverifiedTransaction {
         unsigned:  UnsignedTransaction::Eip1559(Eip1559Transaction {
             nonce:                    Default::default(),
@@ -20,7 +24,7 @@ fn mock_signed_tx() -> SignedTransaction {
             gas_limit:                Default::default(),
             action:                   TransactionAction::Create,
             value:                    Default::default(),
-            data:                     Bytes::new(),
+            data:                     input,
             access_list:              vec![],
         }),
         signature: Some(SignatureComponents {
@@ -1,12 +1,20 @@
 use std::sync::Arc;
 
-use protocol::traits::{Context, ReadOnlyStorage, Storage};
-use protocol::types::Hasher;
+use protocol::codec::ProtocolCodec;
+use protocol::traits::{Context, ReadOnlyStorage, Storage, StorageAdapter};
+use protocol::types::{Bytes, Hasher, H160};
 
 use core_db::MemoryAdapter;
 
-use crate::tests::{get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx};
-use crate::ImplStorage;
+use crate::schema::{
+    BlockHashNumberSchema, DeployedContractsSchema, EvmCodeSchema, TransactionBytesSchema,
+    TxHashNumberSchema,
+};
+use crate::tests::{
+    get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx,
+    mock_signed_tx_with_data,
+};
+use crate::{CommonHashKey, ImplStorage};
 
 macro_rules! exec {
     ($func: expr) => {
@@ -34,6 +42,35 @@ fn test_storage_block_insert() {
     assert_eq!(height, block.unwrap().header.number);
 }
 
+#[test]
+fn test_storage_block_number_by_hash() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter), 10);
+
+    let height = 100;
+    let block = mock_block(height, Hasher::digest(get_random_bytes(10)));
+    let block_hash = block.hash();
+    exec!(storage.insert_block(Context::new(), block));
+
+    // The number is cached once the block is inserted.
+    adapter.remove::<BlockHashNumberSchema>(block_hash).unwrap();
+    let number = exec!(ReadOnlyStorage::get_block_number_by_hash(
+        &storage,
+        Context::new(),
+        &block_hash
+    ));
+    assert_eq!(number, Some(height));
+
+    let unknown = Hasher::digest(get_random_bytes(10));
+    let number = exec!(ReadOnlyStorage::get_block_number_by_hash(
+        &storage,
+        Context::new(),
+        &unknown
+    ));
+    assert_eq!(number, None);
+    assert!(!storage.cache.block_numbers.lock().contains(&unknown));
+}
+
 #[test]
 fn test_storage_receipts_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
@@ -60,6 +97,89 @@ fn test_storage_receipts_insert() {
     }
 }
 
+#[test]
+fn test_storage_contracts_by_deployer() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
+    let (alice, bob) = (H160::random(), H160::random());
+
+    // Alice deploys a contract in each block and Bob deploys one in the second.
+    for height in 1..=2u64 {
+        let mut receipts = vec![mock_receipt(Hasher::digest(get_random_bytes(10)))];
+        receipts[0].sender = alice;
+        receipts[0].code_address = Some(Hasher::digest(height.to_be_bytes()));
+        if height == 2 {
+            let mut receipt = mock_receipt(Hasher::digest(get_random_bytes(10)));
+            receipt.sender = bob;
+            receipt.code_address = Some(Hasher::digest(b"bob"));
+            receipts.push(receipt);
+        }
+        exec!(storage.insert_receipts(Context::new(), height, receipts));
+    }
+
+    let contracts = exec!(storage.get_contracts_by_deployer(Context::new(), &alice));
+    assert_eq!(contracts, vec![
+        Hasher::digest(1u64.to_be_bytes()).into(),
+        Hasher::digest(2u64.to_be_bytes()).into()
+    ]);
+    let contracts = exec!(storage.get_contracts_by_deployer(Context::new(), &bob));
+    assert_eq!(contracts, vec![H160::from(Hasher::digest(b"bob"))]);
+    let contracts = exec!(storage.get_contracts_by_deployer(Context::new(), &H160::random()));
+    assert!(contracts.is_empty());
+}
+
+#[test]
+fn test_storage_reindex() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter), 10);
+    let deployer = H160::random();
+
+    let mut blocks = Vec::new();
+    for height in 0..3u64 {
+        let mut block = mock_block(height, Hasher::digest(get_random_bytes(10)));
+        let mut receipt = mock_receipt(Hasher::digest(get_random_bytes(10)));
+        receipt.sender = deployer;
+        receipt.code_address = Some(Hasher::digest(height.to_be_bytes()));
+        block.tx_hashes = vec![receipt.tx_hash];
+
+        exec!(storage.insert_block(Context::new(), block.clone()));
+        exec!(storage.insert_receipts(Context::new(), height, vec![receipt]));
+        blocks.push(block);
+    }
+
+    // The indexes built before the version are rebuilt.
+    assert!(!exec!(storage.is_indexed()));
+    exec!(storage.reindex());
+    assert!(exec!(storage.is_indexed()));
+
+    // Drop the indexes.
+    for block in blocks.iter() {
+        adapter
+            .remove::<BlockHashNumberSchema>(block.hash())
+            .unwrap();
+        adapter
+            .remove::<TxHashNumberSchema>(block.tx_hashes[0])
+            .unwrap();
+    }
+    adapter.remove::<DeployedContractsSchema>(deployer).unwrap();
+    let storage = ImplStorage::new(Arc::clone(&adapter), 10);
+    assert!(!exec!(storage.is_indexed()));
+
+    exec!(storage.reindex());
+    assert!(exec!(storage.is_indexed()));
+    for (height, block) in blocks.iter().enumerate() {
+        let number = exec!(ReadOnlyStorage::get_block_number_by_hash(
+            &storage,
+            Context::new(),
+            &block.hash()
+        ));
+        assert_eq!(number, Some(height as u64));
+        let receipt = exec!(storage.get_receipt_by_hash(Context::new(), &block.tx_hashes[0]));
+        assert_eq!(receipt.unwrap().tx_hash, block.tx_hashes[0]);
+    }
+    let contracts = exec!(storage.get_contracts_by_deployer(Context::new(), &deployer));
+    assert_eq!(contracts.len(), 3);
+}
+
 #[test]
 fn test_storage_transactions_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
@@ -85,6 +205,28 @@ fn test_storage_transactions_insert() {
     }
 }
 
+#[test]
+fn test_storage_transactions_compression() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter), 10).tx_compression(true);
+    let height = 2021;
+
+    let transaction = mock_signed_tx_with_data(Bytes::from(vec![0xab; 4096]));
+    let hash = transaction.transaction.hash;
+    exec!(storage.insert_transactions(Context::new(), height, vec![transaction.clone()]));
+
+    let stored = adapter
+        .get::<TransactionBytesSchema>(CommonHashKey::new(height, hash))
+        .unwrap()
+        .unwrap();
+    assert!(stored.0.len() < transaction.encode().unwrap().len());
+
+    let transactions = exec!(storage.get_transactions(Context::new(), height, &[hash]));
+    assert_eq!(transactions, vec![Some(transaction.clone())]);
+    let transaction_2 = exec!(storage.get_transaction_by_hash(Context::new(), &hash));
+    assert_eq!(transaction_2, Some(transaction));
+}
+
 #[test]
 fn test_storage_latest_proof_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()), 10);
@@ -114,3 +256,45 @@ fn test_storage_evm_code_insert() {
     let code_3 = exec!(storage.get_code_by_address(Context::new(), &address));
     assert_eq!(code, code_3.unwrap());
 }
+
+#[test]
+fn test_storage_evm_code_cache() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::with_code_cache_size(Arc::clone(&adapter), 10, 2048);
+
+    let code = get_random_bytes(1000);
+    let code_hash = Hasher::digest(&code);
+    let (address, clone_address) = (
+        Hasher::digest(get_random_bytes(10)),
+        Hasher::digest(get_random_bytes(10)),
+    );
+
+    exec!(storage.insert_code(Context::new(), address, code_hash, code.clone()));
+    exec!(storage.insert_code(Context::new(), clone_address, code_hash, code.clone()));
+
+    let code_2 = exec!(storage.get_code_by_address(Context::new(), &address));
+    assert_eq!(code, code_2.unwrap());
+    assert!(storage.cache.codes.lock().contains(&code_hash));
+
+    // The clone shares the code hash, so its code is read from the cache.
+    adapter.remove::<EvmCodeSchema>(code_hash).unwrap();
+    let code_3 = exec!(storage.get_code_by_address(Context::new(), &clone_address));
+    assert_eq!(code, code_3.unwrap());
+
+    // The cache is bounded by the total bytes of the codes.
+    let other_code = get_random_bytes(1500);
+    let other_hash = Hasher::digest(&other_code);
+    exec!(storage.insert_code(
+        Context::new(),
+        Hasher::digest(get_random_bytes(10)),
+        other_hash,
+        other_code.clone()
+    ));
+    assert_eq!(
+        exec!(storage.get_code_by_hash(Context::new(), &other_hash)),
+        Some(other_code)
+    );
+    let codes = storage.cache.codes.lock();
+    assert!(!codes.contains(&code_hash));
+    assert_eq!(codes.size(), 1500);
+}
@@ -3,9 +3,18 @@ use std::error::Error;
 use overlord::Codec;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
-use crate::types::{BlockVersion, Bytes, Proposal, BASE_FEE_PER_GAS};
+use crate::types::{
+    BlockVersion, Bytes, HardforkInfoInner, Proposal, BASE_FEE_PER_GAS, SUPPORTED_BLOCK_VERSIONS,
+};
 use crate::{codec::error::CodecError, lazy::CHAIN_ID, ProtocolError};
 
+lazy_static::lazy_static! {
+    static ref INVALID_BLOCK_VERSION: String = format!(
+        "Invalid block version, the supported versions are {:?}",
+        SUPPORTED_BLOCK_VERSIONS
+    );
+}
+
 impl Encodable for BlockVersion {
     fn rlp_append(&self, s: &mut RlpStream) {
         let ver: u8 = (*self).into();
@@ -17,13 +26,28 @@ impl Decodable for BlockVersion {
     fn decode(r: &Rlp) -> Result<Self, DecoderError> {
         let ver: u8 = r.val_at(0)?;
         ver.try_into()
-            .map_err(|_| DecoderError::Custom("Invalid block version"))
+            .map_err(|_| DecoderError::Custom(INVALID_BLOCK_VERSION.as_str()))
     }
 }
 
+/// The number of the fields of a V0 proposal.
+const PROPOSAL_V0_FIELDS: usize = 13;
+
+/// Check the number of the fields of a known block version. The fields
+/// appended by a newer version are rejected, unless the `lenient-decode`
+/// feature is enabled to skip them.
+fn check_fields(r: &Rlp, expected: usize) -> Result<(), DecoderError> {
+    let count = r.item_count()?;
+    if count < expected || (count > expected && !cfg!(feature = "lenient-decode")) {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+
+    Ok(())
+}
+
 impl Encodable for Proposal {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(13)
+        s.begin_list(PROPOSAL_V0_FIELDS)
             .append(&self.version)
             .append(&self.prev_hash)
             .append(&self.proposer)
@@ -42,22 +66,63 @@ impl Encodable for Proposal {
 
 impl Decodable for Proposal {
     fn decode(r: &Rlp) -> Result<Self, DecoderError> {
-        Ok(Proposal {
-            version:                  r.val_at(0)?,
-            prev_hash:                r.val_at(1)?,
-            proposer:                 r.val_at(2)?,
-            prev_state_root:          r.val_at(3)?,
-            transactions_root:        r.val_at(4)?,
-            signed_txs_hash:          r.val_at(5)?,
-            timestamp:                r.val_at(6)?,
-            number:                   r.val_at(7)?,
-            gas_limit:                r.val_at::<u64>(8)?.into(),
-            extra_data:               r.list_at(9)?,
-            base_fee_per_gas:         BASE_FEE_PER_GAS.into(),
-            proof:                    r.val_at(10)?,
-            chain_id:                 **CHAIN_ID.load(),
-            call_system_script_count: r.val_at(11)?,
-            tx_hashes:                r.list_at(12)?,
+        match r.val_at::<BlockVersion>(0)? {
+            BlockVersion::V0 => decode_proposal_v0(r),
+        }
+    }
+}
+
+fn decode_proposal_v0(r: &Rlp) -> Result<Proposal, DecoderError> {
+    check_fields(r, PROPOSAL_V0_FIELDS)?;
+
+    Ok(Proposal {
+        version:                  BlockVersion::V0,
+        prev_hash:                r.val_at(1)?,
+        proposer:                 r.val_at(2)?,
+        prev_state_root:          r.val_at(3)?,
+        transactions_root:        r.val_at(4)?,
+        signed_txs_hash:          r.val_at(5)?,
+        timestamp:                r.val_at(6)?,
+        number:                   r.val_at(7)?,
+        gas_limit:                r.val_at::<u64>(8)?.into(),
+        extra_data:               r.list_at(9)?,
+        base_fee_per_gas:         BASE_FEE_PER_GAS.into(),
+        proof:                    r.val_at(10)?,
+        chain_id:                 **CHAIN_ID.load(),
+        call_system_script_count: r.val_at(11)?,
+        tx_hashes:                r.list_at(12)?,
+    })
+}
+
+/// The timestamp of a hardfork is appended only if it is set, so that a
+/// hardfork scheduled by the block number is encoded as before.
+impl Encodable for HardforkInfoInner {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        if self.timestamp == 0 {
+            s.begin_list(2)
+                .append(&self.block_number)
+                .append(&self.flags);
+        } else {
+            s.begin_list(3)
+                .append(&self.block_number)
+                .append(&self.flags)
+                .append(&self.timestamp);
+        }
+    }
+}
+
+impl Decodable for HardforkInfoInner {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        let timestamp = match r.item_count()? {
+            2 => 0,
+            3 => r.val_at(2)?,
+            _ => return Err(DecoderError::RlpIncorrectListLen),
+        };
+
+        Ok(HardforkInfoInner {
+            block_number: r.val_at(0)?,
+            flags: r.val_at(1)?,
+            timestamp,
         })
     }
 }
@@ -77,7 +142,9 @@ impl Codec for Proposal {
 #[cfg(test)]
 mod tests {
     use crate::traits::MessageCodec;
-    use crate::types::{Block, Bytes, ExtraData, Header, Proof, H160, H256};
+    use crate::types::{
+        Block, Bytes, ExtraData, Header, Proof, H160, H256, SUPPORTED_BLOCK_VERSIONS,
+    };
     use rand::random;
 
     use super::*;
@@ -95,6 +162,70 @@ mod tests {
         assert_eq!(ver, decode);
     }
 
+    #[test]
+    fn test_proposal_version_dispatch() {
+        for ver in SUPPORTED_BLOCK_VERSIONS {
+            let proposal = Proposal {
+                version: BlockVersion::try_from(ver).unwrap(),
+                ..Default::default()
+            };
+            let decode: Proposal = rlp::decode(&rlp::encode(&proposal)).unwrap();
+            assert_eq!(decode.version, proposal.version);
+        }
+
+        // Replace the version with an unknown one.
+        let raw = rlp::encode(&Proposal::default());
+        let rlp = Rlp::new(&raw);
+        let mut s = RlpStream::new_list(PROPOSAL_V0_FIELDS);
+        s.begin_list(1).append(&1u8);
+        for item in rlp.iter().skip(1) {
+            s.append_raw(item.as_raw(), 1);
+        }
+        let err = rlp::decode::<Proposal>(&s.out()).unwrap_err();
+        assert_eq!(err, DecoderError::Custom(INVALID_BLOCK_VERSION.as_str()));
+    }
+
+    #[test]
+    fn test_proposal_unknown_fields() {
+        let proposal = Proposal::default();
+        let raw = rlp::encode(&proposal);
+        let mut s = RlpStream::new_list(PROPOSAL_V0_FIELDS + 1);
+        for item in Rlp::new(&raw).iter() {
+            s.append_raw(item.as_raw(), 1);
+        }
+        s.append(&"unknown");
+
+        let res = rlp::decode::<Proposal>(&s.out());
+        if cfg!(feature = "lenient-decode") {
+            assert_eq!(res.unwrap().version, proposal.version);
+        } else {
+            assert_eq!(res.unwrap_err(), DecoderError::RlpIncorrectListLen);
+        }
+    }
+
+    #[test]
+    fn test_hardfork_timestamp_codec() {
+        let flags = H256::random();
+        let mut s = RlpStream::new_list(2);
+        s.append(&7u64).append(&flags);
+        let legacy = s.out();
+
+        let decode: HardforkInfoInner = rlp::decode(&legacy).unwrap();
+        assert_eq!(decode, HardforkInfoInner {
+            block_number: 7,
+            flags,
+            timestamp: 0,
+        });
+        assert_eq!(rlp::encode(&decode), legacy);
+
+        let info = HardforkInfoInner {
+            timestamp: 1_000,
+            ..decode
+        };
+        let decode: HardforkInfoInner = rlp::decode(&rlp::encode(&info)).unwrap();
+        assert_eq!(decode, info);
+    }
+
     #[test]
     fn test_block_codec() {
         let block = Block::default();
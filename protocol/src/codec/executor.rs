@@ -1,6 +1,37 @@
 use rlp::{Decodable, DecoderError, Encodable, Prototype, Rlp, RlpStream};
 
-use crate::types::TxResp;
+use crate::types::{DisableableOpcode, ModExpPricing, TxResp};
+
+impl Encodable for DisableableOpcode {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.append(&(*self as u8));
+    }
+}
+
+impl Decodable for DisableableOpcode {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        let opcode: u8 = r.as_val()?;
+        opcode
+            .try_into()
+            .map_err(|_| DecoderError::Custom("Invalid disableable opcode"))
+    }
+}
+
+impl Encodable for ModExpPricing {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.append(&(*self as u8));
+    }
+}
+
+impl Decodable for ModExpPricing {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        match r.as_val::<u8>()? {
+            0 => Ok(ModExpPricing::Eip198),
+            1 => Ok(ModExpPricing::Eip2565),
+            _ => Err(DecoderError::Custom("Invalid modexp pricing")),
+        }
+    }
+}
 
 impl Encodable for TxResp {
     fn rlp_append(&self, s: &mut RlpStream) {
@@ -41,7 +72,9 @@ impl Decodable for TxResp {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::ExecutorContext;
+    use crate::types::{
+        DisabledOpcodes, ExecutorContext, ExecutorRules, PrecompileGasOverride, H256,
+    };
 
     #[test]
     fn test_exec_ctx_codec() {
@@ -51,4 +84,25 @@ mod tests {
         let decode: ExecutorContext = rlp::decode(bytes.as_ref()).unwrap();
         assert_eq!(exec_ctx, decode);
     }
+
+    #[test]
+    fn test_executor_rules_codec() {
+        let rules = ExecutorRules {
+            disabled_opcodes: vec![DisabledOpcodes {
+                hardfork: H256::from_low_u64_be(1u64.to_be()),
+                opcodes:  vec![DisableableOpcode::Create2, DisableableOpcode::BaseFee],
+            }],
+            precompile_gas: vec![PrecompileGasOverride {
+                modexp_pricing: Some(ModExpPricing::Eip198),
+                ec_add: Some(500),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let bytes = rlp::encode(&rules);
+        let decode: ExecutorRules = rlp::decode(bytes.as_ref()).unwrap();
+        assert_eq!(rules, decode);
+
+        assert!(rlp::decode::<DisableableOpcode>(&rlp::encode(&0xffu8)).is_err());
+    }
 }
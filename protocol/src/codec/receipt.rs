@@ -1,6 +1,9 @@
 use rlp::{Decodable, DecoderError, Encodable, Prototype, Rlp, RlpStream};
 
-use crate::types::Receipt;
+use crate::types::{Log, Receipt};
+
+/// The max number of topics of a log, which are emitted by `LOG0` to `LOG4`.
+pub const MAX_LOG_TOPICS: usize = 4;
 
 impl Encodable for Receipt {
     fn rlp_append(&self, s: &mut RlpStream) {
@@ -32,7 +35,7 @@ impl Decodable for Receipt {
                 state_root:   r.val_at(4)?,
                 used_gas:     r.val_at(5)?,
                 logs_bloom:   r.val_at(6)?,
-                logs:         r.list_at(7)?,
+                logs:         decode_logs(&r.at(7)?)?,
                 log_index:    r.val_at(8)?,
                 code_address: r.val_at(9)?,
                 sender:       r.val_at(10)?,
@@ -48,6 +51,15 @@ impl Decodable for Receipt {
     }
 }
 
+fn decode_logs(r: &Rlp) -> Result<Vec<Log>, DecoderError> {
+    let logs: Vec<Log> = r.as_list()?;
+    if logs.iter().any(|log| log.topics.len() > MAX_LOG_TOPICS) {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+
+    Ok(logs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,4 +72,29 @@ mod tests {
         let decode: Receipt = rlp::decode(bytes.as_ref()).unwrap();
         assert_eq!(receipt, decode);
     }
+
+    #[test]
+    fn test_receipt_with_too_many_topics() {
+        let log = |topics: usize| Log {
+            address: Default::default(),
+            topics:  vec![Default::default(); topics],
+            data:    vec![1, 2, 3],
+        };
+
+        let receipt = Receipt {
+            logs: vec![log(0), log(MAX_LOG_TOPICS)],
+            ..Default::default()
+        };
+        let decode: Receipt = rlp::decode(&rlp::encode(&receipt)).unwrap();
+        assert_eq!(receipt, decode);
+
+        let receipt = Receipt {
+            logs: vec![log(1), log(MAX_LOG_TOPICS + 1)],
+            ..Default::default()
+        };
+        assert_eq!(
+            rlp::decode::<Receipt>(&rlp::encode(&receipt)),
+            Err(DecoderError::RlpIncorrectListLen)
+        );
+    }
 }
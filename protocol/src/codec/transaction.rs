@@ -31,19 +31,24 @@ impl Encodable for SignatureComponents {
 
 impl SignatureComponents {
     fn rlp_decode(rlp: &Rlp, offset: usize, legacy_v: Option<u64>) -> Result<Self, DecoderError> {
-        let v: u8 = if let Some(n) = legacy_v {
-            SignatureComponents::extract_standard_v(n)
-                .ok_or(DecoderError::Custom("invalid legacy v in signature"))?
-        } else {
-            rlp.val_at(offset)?
-        };
-
         let signature_r_offset = offset + 1;
         let signature_s_offset = offset + 2;
         let signature_r_and_s_size =
             rlp.at(signature_r_offset)?.size() + rlp.at(signature_s_offset)?.size();
 
         let eth_tx_flag = signature_r_and_s_size <= 64;
+
+        // The v of an interoperation signature is not a recovery id, so it is kept
+        // as is.
+        let v: u8 = match legacy_v {
+            Some(n) => SignatureComponents::normalize_recovery_id(n, false)
+                .map_err(|_| DecoderError::Custom("invalid legacy v in signature"))?,
+            None if eth_tx_flag => {
+                SignatureComponents::normalize_recovery_id(rlp.val_at(offset)?, true)
+                    .map_err(|_| DecoderError::Custom("invalid y parity in signature"))?
+            }
+            None => rlp.val_at(offset)?,
+        };
         let (r, s) = match eth_tx_flag {
             true => {
                 let tmp_r: U256 = rlp.val_at(signature_r_offset)?;
@@ -370,7 +375,7 @@ mod tests {
     use common_crypto::secp256k1_recover;
 
     use crate::codec::hex_decode;
-    use crate::types::{Public, SignatureS, Witness, H160, U256};
+    use crate::types::{Public, SignatureS, TypesError, Witness, H160, U256};
 
     #[test]
     fn test_legacy_decode() {
@@ -545,6 +550,48 @@ mod tests {
         assert!(sig.is_eth_sig());
     }
 
+    #[test]
+    fn test_eip2930_tx_codec() {
+        // The signed EIP-2930 transaction in the tests of go-ethereum.
+        let raw = hex_decode("01f8630103018261a894b94f5374fce5edbc8e2a8697c15331677e6ebf0b0a825544c001a0c9519f4f2b30335884581971573fadf60c6204f59a911df35ee8a540456b2660a032f1e8e2c5dd761f9e4f88f41c8310aeaba26a8bfcdacfedfa12ec3862d37521").unwrap();
+        assert!(UnverifiedTransaction::check_type(&raw).is_ok());
+        let utx = UnverifiedTransaction::decode(&Rlp::new(&raw)).unwrap();
+        assert!(utx.check_hash().is_ok());
+        assert_eq!(utx.hash, Hasher::digest(&raw));
+        assert_eq!(utx.chain_id, Some(1));
+
+        let tx = match &utx.unsigned {
+            UnsignedTransaction::Eip2930(tx) => tx,
+            _ => panic!("expect an EIP-2930 transaction"),
+        };
+        assert_eq!(tx.nonce, U256::from(3u64));
+        assert_eq!(tx.gas_price, U256::one());
+        assert_eq!(tx.gas_limit, U256::from(25_000u64));
+        assert_eq!(tx.value, U256::from(10u64));
+        assert_eq!(tx.data.to_vec(), vec![0x55, 0x44]);
+        assert!(tx.access_list.is_empty());
+        assert_eq!(
+            utx.unsigned.to().unwrap(),
+            H160::from_slice(&hex_decode("b94f5374fce5edbc8e2a8697c15331677e6ebf0b").unwrap())
+        );
+
+        assert_eq!(utx.rlp_bytes().to_vec(), raw);
+        let stx = SignedTransaction::from_unverified(utx).unwrap();
+        let decode: SignedTransaction = rlp::decode(&stx.rlp_bytes()).unwrap();
+        assert_eq!(stx, decode);
+    }
+
+    #[test]
+    fn test_unsupported_tx_type() {
+        let mut raw = hex_decode("01f8630103018261a894b94f5374fce5edbc8e2a8697c15331677e6ebf0b0a825544c001a0c9519f4f2b30335884581971573fadf60c6204f59a911df35ee8a540456b2660a032f1e8e2c5dd761f9e4f88f41c8310aeaba26a8bfcdacfedfa12ec3862d37521").unwrap();
+        raw[0] = 0x03;
+        let err = UnverifiedTransaction::check_type(&raw).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&TypesError::UnsupportedTxType(0x03).to_string()));
+        assert!(UnverifiedTransaction::decode(&Rlp::new(&raw)).is_err());
+    }
+
     #[test]
     fn test_calc_tx_hash() {
         let raw = hex_decode("02f8690505030382520894a15da349978753d846eede580c7de8e590c1e5b8872386f26fc1000080c080a097d7a69ce423c2a5814daf71345b49698db5839e092f744e263983b56a992b87a02a5e12966dccbc8e3f6f21ffb528372c915c202381cfcbe3b8cf8ef8af273e99").unwrap();
@@ -556,6 +603,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_recover_signer() {
+        let raw = hex_decode("02f8670582010582012c82012c825208945cf83df52a32165a7f392168ac009b168c9e89150180c001a0a68aeb0db4d84cf16da5a6918becefd254654854cfc23f0112ef78154ce84db89f4b0af1cbf12f5bfaec81c3d4d495717d720b574a05092f6b436c2ab255cd35").unwrap();
+        let mut utx = UnverifiedTransaction::decode(&Rlp::new(&raw)).unwrap();
+        let signed = SignedTransaction::from_unverified(utx.clone()).unwrap();
+        assert_eq!(utx.recover_signer().unwrap(), signed.sender);
+
+        let mut sig = utx.signature.clone().unwrap();
+        sig.r = Bytes::from(vec![0u8; 32]);
+        utx.signature = Some(sig);
+        assert!(matches!(utx.recover_signer(), Err(TypesError::Crypto(_))));
+
+        utx.signature = None;
+        assert!(matches!(
+            utx.recover_signer(),
+            Err(TypesError::MissingSignature)
+        ));
+    }
+
     #[test]
     fn should_agree_with_vitalik() {
         let test_vector = |tx_data: &str, address: &'static str| {
@@ -613,6 +679,59 @@ mod tests {
         assert!(!utx.signature.as_ref().unwrap().is_eth_sig());
     }
 
+    #[test]
+    fn test_normalize_recovery_id() {
+        let raw = "f85f800182520894095e7baea6a6c7c4c2dfeb977efac326af552d870a80{}a048b55bfa915ac795c431978d8a6a992b628d557da5ff759b307d495a36649353a0efffd310ac743f371de3b9f7f9cb56c0b28ad43601b4ab949f53faa07bd2c804";
+        let sender =
+            H160::from_slice(&hex_decode("0f65fe9276bc9a24ae7083ae28e2660ef72df99e").unwrap());
+
+        // The same signature with the v of 27 and the EIP-155 form of chain 1 and 2.
+        for v in ["1b", "25", "27"] {
+            let bytes = hex_decode(&raw.replace("{}", v)).unwrap();
+            let tx = UnverifiedTransaction::decode(&Rlp::new(&bytes)).unwrap();
+
+            assert_eq!(tx.signature.as_ref().unwrap().standard_v, 0);
+            assert_eq!(
+                public_to_address(&tx.recover_public(false).unwrap()),
+                sender
+            );
+            // The raw v is re-encoded, so the hash matches the raw transaction.
+            assert_eq!(tx.rlp_bytes().to_vec(), bytes);
+            assert_eq!(tx.get_hash(), Hasher::digest(&bytes));
+            assert!(tx.calc_hash().check_hash().is_ok());
+        }
+
+        for (v, typed) in [(0, true), (27, false), (35, false), (0x248b, false)] {
+            assert_eq!(
+                SignatureComponents::normalize_recovery_id(v, typed).unwrap(),
+                0
+            );
+        }
+        for (v, typed) in [(1, true), (28, false), (36, false), (0x248c, false)] {
+            assert_eq!(
+                SignatureComponents::normalize_recovery_id(v, typed).unwrap(),
+                1
+            );
+        }
+        for (v, typed) in [
+            (2, true),
+            (27, true),
+            (37, true),
+            (0, false),
+            (1, false),
+            (2, false),
+            (29, false),
+            (34, false),
+        ] {
+            assert!(SignatureComponents::normalize_recovery_id(v, typed).is_err());
+        }
+
+        for v in ["80", "1d"] {
+            let bytes = hex_decode(&raw.replace("{}", v)).unwrap();
+            assert!(UnverifiedTransaction::decode(&Rlp::new(&bytes)).is_err());
+        }
+    }
+
     #[test]
     fn test_signature_s_codec() {
         let witness = Witness {
@@ -2,6 +2,8 @@ pub const END_GOSSIP_NEW_TXS: &str = "/gossip/mempool/new_txs";
 pub const RPC_PULL_TXS: &str = "/rpc_call/mempool/pull_txs";
 pub const RPC_RESP_PULL_TXS: &str = "/rpc_resp/mempool/pull_txs";
 pub const RPC_RESP_PULL_TXS_SYNC: &str = "/rpc_resp/mempool/pull_txs_sync";
+pub const RPC_PULL_PENDING_TX_HASHES: &str = "/rpc_call/mempool/pull_pending_tx_hashes";
+pub const RPC_RESP_PULL_PENDING_TX_HASHES: &str = "/rpc_resp/mempool/pull_pending_tx_hashes";
 
 pub const END_GOSSIP_SIGNED_PROPOSAL: &str = "/gossip/consensus/signed_proposal";
 pub const END_GOSSIP_SIGNED_VOTE: &str = "/gossip/consensus/signed_vote";
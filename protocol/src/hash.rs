@@ -0,0 +1,61 @@
+//! The hash functions used by Axon. The data of Axon itself, such as the
+//! transactions, blocks and tries, is hashed with keccak256 as Ethereum does.
+//! The data which is verified by CKB, such as the cell data and the CKB
+//! transactions, must be hashed with the blake2b of CKB instead, otherwise the
+//! verification fails silently.
+
+pub use common_hasher::keccak256;
+
+/// The blake2b-256 of CKB, which uses `ckb-default-hash` as personalization.
+pub fn ckb_blake2b<B: AsRef<[u8]>>(data: B) -> [u8; 32] {
+    ckb_hash::blake2b_256(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::hex_decode;
+    use crate::types::{
+        Bytes, Eip1559Transaction, SignatureComponents, TransactionAction, UnsignedTransaction,
+        UnverifiedTransaction, H160, H256, U256,
+    };
+
+    #[test]
+    fn test_ckb_blake2b() {
+        let empty_hash =
+            hex_decode("44f4c69744d5f8c55d642062949dcae49bc4e7ef43d388c5a12f42b5633d163e").unwrap();
+        assert_eq!(ckb_blake2b(b"").to_vec(), empty_hash);
+
+        let keccak_empty =
+            hex_decode("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470").unwrap();
+        assert_eq!(keccak256(b"").to_vec(), keccak_empty);
+    }
+
+    #[test]
+    fn test_tx_hash_is_keccak() {
+        let tx = UnverifiedTransaction {
+            unsigned:  UnsignedTransaction::Eip1559(Eip1559Transaction {
+                nonce:                    U256::one(),
+                max_priority_fee_per_gas: U256::one(),
+                gas_price:                U256::one(),
+                gas_limit:                U256::from(21000),
+                action:                   TransactionAction::Call(H160::random()),
+                value:                    U256::one(),
+                data:                     Bytes::new(),
+                access_list:              vec![],
+            }),
+            signature: Some(SignatureComponents {
+                standard_v: 0,
+                r:          Bytes::from(vec![1u8; 32]),
+                s:          Bytes::from(vec![2u8; 32]),
+            }),
+            chain_id:  Some(5),
+            hash:      H256::default(),
+        }
+        .calc_hash();
+
+        let raw = tx.unsigned.encode(tx.chain_id, tx.signature.clone());
+        assert_eq!(tx.hash, H256(keccak256(&raw)));
+        assert_ne!(tx.hash, H256(ckb_blake2b(&raw)));
+    }
+}
@@ -1,7 +1,7 @@
 use arc_swap::ArcSwap;
 use ckb_types::{packed, prelude::*};
 
-use crate::{ckb_blake2b_256, types::Hex};
+use crate::{hash::ckb_blake2b, types::Hex};
 
 lazy_static::lazy_static! {
     pub static ref CHAIN_ID: ArcSwap<u64> = ArcSwap::from_pointee(Default::default());
@@ -9,7 +9,7 @@ lazy_static::lazy_static! {
 
     pub static ref DUMMY_INPUT_OUT_POINT: packed::OutPoint
         = packed::OutPointBuilder::default()
-            .tx_hash(ckb_blake2b_256("DummyInputOutpointTxHash").pack())
+            .tx_hash(ckb_blake2b("DummyInputOutpointTxHash").pack())
             .index(0u32.pack())
             .build();
 }
@@ -1,5 +1,6 @@
 pub mod codec;
 pub mod constants;
+pub mod hash;
 pub mod lazy;
 pub mod traits;
 pub mod types;
@@ -7,10 +8,12 @@ pub mod types;
 use std::error::Error;
 
 pub use derive_more::{Constructor, Display, From};
-pub use {
-    async_trait::async_trait, ckb_hash::blake2b_256 as ckb_blake2b_256, rand, thiserror, tokio,
-    trie,
-};
+pub use {async_trait::async_trait, rand, thiserror, tokio, trie};
+
+#[deprecated(note = "use `protocol::hash::ckb_blake2b` instead")]
+pub fn ckb_blake2b_256<B: AsRef<[u8]>>(data: B) -> [u8; 32] {
+    hash::ckb_blake2b(data)
+}
 
 pub const MEMPOOL_REFRESH_TIMEOUT: u64 = 50;
 
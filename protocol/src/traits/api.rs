@@ -1,7 +1,7 @@
 use crate::types::{
-    Account, Block, BlockNumber, Bytes, CkbRelatedInfo, EthAccountProof, HardforkInfo,
-    HardforkInfoInner, Hash, Header, Metadata, Proposal, Receipt, SignedTransaction, TxResp, H160,
-    H256, U256,
+    AccessedState, Account, Block, BlockNumber, Bytes, CkbRelatedInfo, EthAccountProof,
+    HardforkInfo, HardforkInfoInner, Hash, Header, Metadata, Proposal, Receipt, SignedTransaction,
+    Tracer, TxResp, TxTrace, H160, H256, U256,
 };
 use crate::{async_trait, traits::Context, ProtocolResult};
 
@@ -15,6 +15,12 @@ pub trait APIAdapter: Send + Sync {
 
     async fn mempool_contains_tx(&self, ctx: Context, tx_hash: &Hash) -> bool;
 
+    async fn get_pending_txs(
+        &self,
+        ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<SignedTransaction>>;
+
     async fn get_block_by_number(
         &self,
         ctx: Context,
@@ -89,6 +95,13 @@ pub trait APIAdapter: Send + Sync {
 
     async fn get_code_by_hash(&self, ctx: Context, hash: &Hash) -> ProtocolResult<Option<Bytes>>;
 
+    /// The contracts created by the transactions sent from the deployer.
+    async fn get_contracts_by_deployer(
+        &self,
+        ctx: Context,
+        deployer: H160,
+    ) -> ProtocolResult<Vec<H160>>;
+
     async fn peer_count(&self, ctx: Context) -> ProtocolResult<U256>;
 
     async fn get_storage_at(
@@ -122,4 +135,24 @@ pub trait APIAdapter: Send + Sync {
         storage_position: Vec<U256>,
         state_root: Hash,
     ) -> ProtocolResult<EthAccountProof>;
+
+    /// Replay the block of the given receipt up to its transaction, and return
+    /// the accounts and storage slots accessed by the transaction. `None` is
+    /// returned for a system contract call which is not replayed.
+    async fn get_accessed_state(
+        &self,
+        ctx: Context,
+        receipt: &Receipt,
+    ) -> ProtocolResult<Option<AccessedState>>;
+
+    /// Replay the block against the state of its parent, and trace each of its
+    /// transactions in order with the tracer. `None` is returned for a system
+    /// contract call which is not replayed, and for the whole block once the
+    /// traces are beyond the budget.
+    async fn trace_block(
+        &self,
+        ctx: Context,
+        block: &Block,
+        tracer: Tracer,
+    ) -> ProtocolResult<Option<Vec<Option<TxTrace>>>>;
 }
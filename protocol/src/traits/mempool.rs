@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use crate::types::{BlockNumber, Hash, MerkleRoot, PackedTxHashes, SignedTransaction, H160, U256};
 use crate::{async_trait, traits::Context, ProtocolResult};
 
@@ -7,11 +9,14 @@ pub trait MemPool: Send + Sync {
 
     async fn contains(&self, ctx: Context, tx_hash: &Hash) -> bool;
 
+    /// Package the transactions for the next block. The selection stops
+    /// when `time_budget` runs out.
     async fn package(
         &self,
         ctx: Context,
         cycles_limit: U256,
         tx_num_limit: u64,
+        time_budget: Duration,
     ) -> ProtocolResult<PackedTxHashes>;
 
     async fn flush(
@@ -41,6 +46,21 @@ pub trait MemPool: Send + Sync {
         address: H160,
     ) -> ProtocolResult<(usize, Option<BlockNumber>)>;
 
+    /// Return at most `limit` pending transactions in the order they would be
+    /// packaged.
+    async fn get_pending_txs(
+        &self,
+        ctx: Context,
+        limit: usize,
+    ) -> ProtocolResult<Vec<SignedTransaction>>;
+
+    /// Return the transactions of a sender in the pool sorted by nonce.
+    fn pending_by_sender(&self, sender: H160) -> Vec<SignedTransaction>;
+
+    /// Pull the pending transactions of the peer of the context and admit
+    /// them, returns the number of the admitted transactions.
+    async fn sync_from_peer(&self, ctx: Context) -> ProtocolResult<usize>;
+
     fn get_tx_from_mem(&self, ctx: Context, tx_hash: &Hash) -> Option<SignedTransaction>;
     fn set_args(&self, context: Context, state_root: MerkleRoot, gas_limit: u64, max_tx_size: u64);
 }
@@ -54,6 +74,11 @@ pub trait MemPoolAdapter: Send + Sync {
         tx_hashes: Vec<Hash>,
     ) -> ProtocolResult<Vec<SignedTransaction>>;
 
+    /// Request at most `limit` hashes of the pending transactions from the
+    /// peer of the context.
+    async fn pull_pending_tx_hashes(&self, ctx: Context, limit: usize)
+        -> ProtocolResult<Vec<Hash>>;
+
     async fn broadcast_tx(
         &self,
         ctx: Context,
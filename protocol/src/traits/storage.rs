@@ -1,5 +1,5 @@
 use crate::types::{
-    Block, Bytes, HardforkInfoInner, Hash, Header, Proof, Receipt, SignedTransaction, H256,
+    Block, Bytes, HardforkInfoInner, Hash, Header, Proof, Receipt, SignedTransaction, H160, H256,
 };
 use crate::{async_trait, codec::ProtocolCodec, traits::Context, Display, ProtocolResult};
 
@@ -16,6 +16,7 @@ pub enum StorageCategory {
     MetadataState,
     CkbLightClientState,
     Version,
+    DeployedContracts,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -98,6 +99,13 @@ pub trait ReadOnlyStorage: Sync + Send {
         address: &H256,
     ) -> ProtocolResult<Option<Bytes>>;
 
+    /// The contracts created by the transactions sent from the deployer.
+    async fn get_contracts_by_deployer(
+        &self,
+        ctx: Context,
+        deployer: &H160,
+    ) -> ProtocolResult<Vec<H160>>;
+
     async fn get_receipt_by_hash(
         &self,
         ctx: Context,
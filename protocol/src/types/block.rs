@@ -21,6 +21,10 @@ pub const MAX_FEE_HISTORY: u64 = 1024;
 pub const MAX_RPC_GAS_CAP: u64 = 50_000_000;
 pub const BASE_FEE_PER_GAS: u64 = 0x539;
 
+/// The block versions known by this node, a block of any other version is
+/// rejected on decoding.
+pub const SUPPORTED_BLOCK_VERSIONS: [u8; 1] = [0];
+
 #[derive(Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq, Display)]
 pub enum BlockVersion {
     #[default]
@@ -281,6 +285,8 @@ pub struct Header {
     pub transactions_root:        MerkleRoot,
     pub signed_txs_hash:          Hash,
     pub receipts_root:            MerkleRoot,
+    /// The bloom of the RLP encoded list of the receipt blooms. Unlike the one
+    /// of Ethereum, it doesn't contain the addresses and topics of the logs.
     pub log_bloom:                Bloom,
     #[cfg_attr(feature = "hex-serialize", serde(serialize_with = "serialize_uint"))]
     pub timestamp:                u64,
@@ -389,6 +395,7 @@ mod tests {
     use crate::types::{
         primitive::default_max_contract_limit, Block, BlockVersion, ConsensusConfig, Header, Hex,
         Metadata, MetadataVersion, ProposeCount, RichBlock, ValidatorExtend, H160,
+        SUPPORTED_BLOCK_VERSIONS,
     };
     use std::{
         str::FromStr,
@@ -405,11 +412,20 @@ mod tests {
     #[test]
     fn test_invalid_version() {
         assert_eq!(BlockVersion::try_from(0).unwrap(), BlockVersion::V0);
+        for ver in SUPPORTED_BLOCK_VERSIONS {
+            assert_eq!(u8::from(BlockVersion::try_from(ver).unwrap()), ver);
+        }
 
         let ver = rand::random::<u8>();
         if ver != 0 {
             assert!(BlockVersion::try_from(ver).is_err());
         }
+
+        let err = BlockVersion::try_from(1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "InvalidBlockVersion 1, the supported versions are [0]"
+        );
     }
 
     #[test]
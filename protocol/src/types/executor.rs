@@ -2,6 +2,8 @@ pub use ethereum::{AccessList, AccessListItem, Account};
 pub use evm::{backend::Log, Config, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed};
 pub use hasher::HasherKeccak;
 
+use std::collections::BTreeMap;
+
 use rlp_derive::{RlpDecodable, RlpEncodable};
 use serde::{Deserialize, Serialize};
 
@@ -48,6 +50,122 @@ impl Default for TxResp {
     }
 }
 
+impl TxResp {
+    /// The gas refunded at the end of a call with the given gas limit, which is
+    /// the gas consumed by the execution minus the charged `gas_used`.
+    pub fn gas_refund(&self, gas_limit: u64) -> u64 {
+        gas_limit
+            .saturating_sub(self.remain_gas)
+            .saturating_sub(self.gas_used)
+    }
+}
+
+/// Whether a piece of state is read or written by a transaction.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateAccess {
+    pub read:    bool,
+    pub written: bool,
+}
+
+/// The accounts and storage slots accessed by a transaction, which is the
+/// state a witness of the transaction consists of. It is unrelated to the
+/// access list of the transaction.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct AccessedState {
+    pub accounts: BTreeMap<H160, StateAccess>,
+    pub storage:  BTreeMap<H160, BTreeMap<H256, StateAccess>>,
+}
+
+impl AccessedState {
+    pub fn read_account(&mut self, address: H160) {
+        self.accounts.entry(address).or_default().read = true;
+    }
+
+    pub fn write_account(&mut self, address: H160) {
+        self.accounts.entry(address).or_default().written = true;
+    }
+
+    pub fn read_slot(&mut self, address: H160, slot: H256) {
+        self.read_account(address);
+        self.slot_mut(address, slot).read = true;
+    }
+
+    pub fn write_slot(&mut self, address: H160, slot: H256) {
+        self.write_account(address);
+        self.slot_mut(address, slot).written = true;
+    }
+
+    pub fn slot(&self, address: &H160, slot: &H256) -> Option<StateAccess> {
+        self.storage.get(address)?.get(slot).copied()
+    }
+
+    fn slot_mut(&mut self, address: H160, slot: H256) -> &mut StateAccess {
+        self.storage
+            .entry(address)
+            .or_default()
+            .entry(slot)
+            .or_default()
+    }
+}
+
+/// The tracer to trace a transaction with, the struct logger records each
+/// executed opcode and the call tracer records the tree of the calls.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tracer {
+    #[default]
+    #[serde(rename = "structLogger")]
+    Struct,
+    #[serde(rename = "callTracer")]
+    Call,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum TxTrace {
+    Struct(StructTrace),
+    Call(CallFrame),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StructTrace {
+    pub gas:          u64,
+    pub failed:       bool,
+    pub return_value: Hex,
+    pub struct_logs:  Vec<StructLog>,
+    /// The struct logs stop at the max steps of a trace.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated:    bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct StructLog {
+    pub pc:    u64,
+    pub op:    u8,
+    pub depth: u64,
+    pub stack: Vec<H256>,
+}
+
+/// A call in the call tree of a transaction. The gas used is only known for
+/// the outermost call.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from:      H160,
+    pub to:        Option<H160>,
+    pub value:     U256,
+    pub gas:       u64,
+    pub gas_used:  u64,
+    pub input:     Hex,
+    pub output:    Hex,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error:     Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub calls:     Vec<CallFrame>,
+}
+
 #[derive(RlpEncodable, RlpDecodable, Default, Clone, Debug, PartialEq, Eq)]
 pub struct ExecutorContext {
     pub block_number:           U256,
@@ -93,6 +211,118 @@ impl From<&Header> for ExecutorContext {
     }
 }
 
+/// The rules of the executor set by the chain spec. They are stored in the
+/// Metadata MPT since the genesis, so all the nodes of a chain execute the
+/// blocks with the same rules. The defaults are not stored, which keeps the
+/// genesis of the chains without the rules.
+#[derive(RlpEncodable, RlpDecodable, Default, Clone, Debug, PartialEq, Eq)]
+pub struct ExecutorRules {
+    pub limits:             ExecutorLimits,
+    pub disabled_opcodes:   Vec<DisabledOpcodes>,
+    pub precompile_gas:     Vec<PrecompileGasOverride>,
+    /// The number of the recent CKB blocks whose headers are kept by the
+    /// light client, the older headers are pruned. 0 keeps all the headers.
+    pub header_retention:   u64,
+    /// The max size of the data of an image cell in bytes, an update with a
+    /// larger cell is rejected. 0 sets no limit.
+    pub max_cell_data_size: u64,
+    /// The max faults of a validator in an epoch, the validators missing more
+    /// proposals than it are reported at the end of the epoch. No report is
+    /// made if it is unset.
+    pub slashing_threshold: Option<u64>,
+}
+
+/// The resource limits of the EVM, the defaults are the ones of the Ethereum
+/// mainnet.
+#[derive(RlpEncodable, RlpDecodable, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct ExecutorLimits {
+    /// The max number of the items in the stack.
+    pub stack_limit:      u64,
+    /// The max depth of the nested calls and creates.
+    pub call_stack_limit: u64,
+    /// The max size of the memory in bytes.
+    pub memory_limit:     u64,
+}
+
+impl Default for ExecutorLimits {
+    fn default() -> Self {
+        ExecutorLimits {
+            stack_limit:      1024,
+            call_stack_limit: 1024,
+            memory_limit:     u64::MAX,
+        }
+    }
+}
+
+/// The gas costs of the precompiles since a hardfork is activated, the unset
+/// ones are kept. The overrides of the activated hardforks apply in order.
+#[derive(RlpEncodable, RlpDecodable, Default, Clone, Debug, PartialEq, Eq)]
+pub struct PrecompileGasOverride {
+    /// The flag of the hardfork as the one in `HardforkInfoInner`, the zero
+    /// flag applies since the genesis.
+    pub hardfork:         H256,
+    pub modexp_pricing:   Option<ModExpPricing>,
+    pub modexp_min:       Option<u64>,
+    pub ec_add:           Option<u64>,
+    pub ec_mul:           Option<u64>,
+    pub ec_pairing_base:  Option<u64>,
+    pub ec_pairing_point: Option<u64>,
+}
+
+/// The formula of the gas cost of the MODEXP precompile.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+#[repr(u8)]
+pub enum ModExpPricing {
+    /// The pricing of EIP-198.
+    Eip198 = 0,
+    /// The pricing of EIP-2565, which is the one since the genesis of Axon.
+    #[default]
+    Eip2565 = 1,
+}
+
+/// The opcodes disabled since a hardfork is activated, executing one of them
+/// fails with an invalid code error.
+#[derive(RlpEncodable, RlpDecodable, Clone, Debug, PartialEq, Eq)]
+pub struct DisabledOpcodes {
+    /// The flag of the hardfork as the one in `HardforkInfoInner`.
+    pub hardfork: H256,
+    pub opcodes:  Vec<DisableableOpcode>,
+}
+
+/// The opcodes which the EVM can turn off one by one. `SELFDESTRUCT` is not
+/// one of them since the EVM has no switch for it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+#[repr(u8)]
+pub enum DisableableOpcode {
+    DelegateCall = 0xf4,
+    Create2 = 0xf5,
+    Revert = 0xfd,
+    ChainId = 0x46,
+    SelfBalance = 0x47,
+    ExtCodeHash = 0x3f,
+    BaseFee = 0x48,
+}
+
+impl TryFrom<u8> for DisableableOpcode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0xf4 => Ok(DisableableOpcode::DelegateCall),
+            0xf5 => Ok(DisableableOpcode::Create2),
+            0xfd => Ok(DisableableOpcode::Revert),
+            0x46 => Ok(DisableableOpcode::ChainId),
+            0x47 => Ok(DisableableOpcode::SelfBalance),
+            0x3f => Ok(DisableableOpcode::ExtCodeHash),
+            0x48 => Ok(DisableableOpcode::BaseFee),
+            _ => Err(value),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct EthAccountProof {
@@ -128,6 +358,27 @@ where
     bloom
 }
 
+/// Build the bloom which the `logs_bloom` of any log matching the given filter
+/// contains. An address or a topic position with several alternatives is left
+/// out, since a matching log carries only one of them.
+pub fn address_topic_bloom(
+    address: Option<&[H160]>,
+    topics: &[Option<Vec<Option<H256>>>],
+) -> Bloom {
+    let mut bloom = Bloom::zero();
+
+    if let Some([address]) = address {
+        m3_2048(&mut bloom, address.as_bytes());
+    }
+
+    for topic in topics.iter().flatten() {
+        if let [Some(topic)] = topic.as_slice() {
+            m3_2048(&mut bloom, topic.as_bytes());
+        }
+    }
+    bloom
+}
+
 fn m3_2048(bloom: &mut Bloom, x: &[u8]) {
     let hash = Hasher::digest(x).0;
     for i in [0, 2, 4] {
@@ -135,3 +386,37 @@ fn m3_2048(bloom: &mut Bloom, x: &[u8]) {
         bloom.0[BLOOM_BYTE_LENGTH - 1 - bit / 8] |= 1 << (bit % 8);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_topic_bloom() {
+        let (address, other) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let (t0, t1) = (H256::from_low_u64_be(3), H256::from_low_u64_be(4));
+        let log = Log {
+            address,
+            topics: vec![t0, t1],
+            data: Vec::new(),
+        };
+        let log_bloom = logs_bloom([log].iter());
+
+        let query = address_topic_bloom(Some(&[address][..]), &[Some(vec![Some(t0)]), None]);
+        assert_ne!(query, Bloom::zero());
+        assert!(log_bloom.contains_bloom(&query));
+
+        let query = address_topic_bloom(None, &[Some(vec![Some(t0)]), Some(vec![Some(t1)])]);
+        assert!(log_bloom.contains_bloom(&query));
+
+        // The address and topic with several alternatives are left out.
+        let query = address_topic_bloom(Some(&[address, other][..]), &[Some(vec![
+            Some(t1),
+            Some(t0),
+        ])]);
+        assert_eq!(query, Bloom::zero());
+
+        let query = address_topic_bloom(Some(&[other][..]), &[]);
+        assert!(!log_bloom.contains_bloom(&query));
+    }
+}
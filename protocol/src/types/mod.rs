@@ -6,8 +6,10 @@ pub use bytes::{Buf, BufMut, Bytes, BytesMut};
 pub use ckb_client::*;
 pub use evm::{backend::*, ExitError, ExitRevert, ExitSucceed};
 pub use executor::{
-    logs_bloom, AccessList, AccessListItem, Account, Config, EthAccountProof, EthStorageProof,
-    ExecResp, ExecutorContext, ExitReason, HasherKeccak, TxResp,
+    address_topic_bloom, logs_bloom, AccessList, AccessListItem, AccessedState, Account, CallFrame,
+    Config, DisableableOpcode, DisabledOpcodes, EthAccountProof, EthStorageProof, ExecResp,
+    ExecutorContext, ExecutorLimits, ExecutorRules, ExitReason, HasherKeccak, ModExpPricing,
+    PrecompileGasOverride, StateAccess, StructLog, StructTrace, Tracer, TxResp, TxTrace,
 };
 pub use interoperation::*;
 pub use primitive::*;
@@ -82,11 +84,28 @@ pub enum TypesError {
     #[display(fmt = "Missing interoperation sender")]
     MissingInteroperationSender,
 
-    #[display(fmt = "InvalidBlockVersion {}", _0)]
+    #[display(
+        fmt = "InvalidBlockVersion {}, the supported versions are {:?}",
+        _0,
+        SUPPORTED_BLOCK_VERSIONS
+    )]
     InvalidBlockVersion(u8),
 
     #[display(fmt = "Decode interoperation signature R error {:?}", _0)]
     DecodeInteroperationSigR(rlp::DecoderError),
+
+    #[display(fmt = "Unsupported transaction type {:#x}", _0)]
+    UnsupportedTxType(u8),
+
+    #[display(
+        fmt = "Hardfork at block {} timestamp {} is activated before the last one",
+        block_number,
+        timestamp
+    )]
+    HardforkOutOfOrder {
+        block_number: u64,
+        timestamp:    u64,
+    },
 }
 
 impl Error for TypesError {}
@@ -14,8 +14,8 @@ use overlord::DurationConfig;
 use rlp_derive::{RlpDecodable, RlpEncodable};
 use serde::{de, ser, Deserialize, Serialize};
 
+use crate::hash::keccak256;
 use common_crypto::Secp256k1PublicKey;
-use common_hasher::keccak256;
 
 use crate::codec::{deserialize_address, hex_decode, hex_encode, serialize_uint};
 use crate::types::{BlockNumber, Bytes, BytesMut, TypesError};
@@ -565,27 +565,72 @@ pub struct HardforkInfo {
 }
 
 impl HardforkInfo {
-    pub fn push(&mut self, mut other: HardforkInfoInner) {
-        if let Some(i) = self.inner.last_mut() {
-            other.flags |= i.flags;
-            if other.flags == i.flags || other.block_number < i.block_number {
-                return;
+    /// Schedule the hardforks of the entry. The flags already scheduled by an
+    /// earlier entry are ignored, and an entry activated before the last one
+    /// of the same kind, by block number or by timestamp, is rejected.
+    pub fn push(&mut self, mut other: HardforkInfoInner) -> Result<(), TypesError> {
+        let by_timestamp = other.timestamp != 0;
+        if let Some(last) = self
+            .inner
+            .iter()
+            .rev()
+            .find(|i| (i.timestamp != 0) == by_timestamp)
+        {
+            if (by_timestamp && other.timestamp < last.timestamp)
+                || (!by_timestamp && other.block_number < last.block_number)
+            {
+                return Err(TypesError::HardforkOutOfOrder {
+                    block_number: other.block_number,
+                    timestamp:    other.timestamp,
+                });
             }
-            if i.block_number == other.block_number {
-                i.flags = other.flags;
-            } else {
-                self.inner.push(other);
-            }
-        } else {
-            self.inner.push(other);
         }
+
+        let scheduled = self.inner.iter().fold(H256::zero(), |acc, i| acc | i.flags);
+        other.flags = other.flags ^ (other.flags & scheduled);
+        if other.flags.is_zero() {
+            return Ok(());
+        }
+
+        match self
+            .inner
+            .iter_mut()
+            .find(|i| i.block_number == other.block_number && i.timestamp == other.timestamp)
+        {
+            Some(i) => i.flags |= other.flags,
+            None => self.inner.push(other),
+        }
+        Ok(())
+    }
+
+    /// The flags of all the hardforks activated at the block of the number and
+    /// the timestamp.
+    pub fn flags_at(&self, number: BlockNumber, timestamp: u64) -> H256 {
+        self.inner
+            .iter()
+            .filter(|i| i.is_activated(number, timestamp))
+            .fold(H256::zero(), |acc, i| acc | i.flags)
     }
 }
 
-#[derive(RlpEncodable, RlpDecodable, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct HardforkInfoInner {
     pub block_number: BlockNumber,
     pub flags:        H256,
+    /// The block timestamp since which the hardfork is activated, it is
+    /// preferred to the block number if it is not zero.
+    #[serde(default)]
+    pub timestamp:    u64,
+}
+
+impl HardforkInfoInner {
+    pub fn is_activated(&self, number: BlockNumber, timestamp: u64) -> bool {
+        if self.timestamp != 0 {
+            timestamp >= self.timestamp
+        } else {
+            number >= self.block_number
+        }
+    }
 }
 
 fn ensure_len(real: usize, expect: usize) -> ProtocolResult<()> {
@@ -728,7 +773,9 @@ mod tests {
                 a[0] = 0b1;
                 H256::from(a)
             },
-        });
+            timestamp:    0,
+        })
+        .unwrap();
         assert_eq!(a.inner.last().unwrap(), &HardforkInfoInner {
             block_number: 1,
             flags:        {
@@ -736,6 +783,7 @@ mod tests {
                 a[0] = 0b1;
                 H256::from(a)
             },
+            timestamp:    0,
         });
 
         // Multiple hardforks activated at the same height can be merged
@@ -746,7 +794,9 @@ mod tests {
                 a[0] = 0b10;
                 H256::from(a)
             },
-        });
+            timestamp:    0,
+        })
+        .unwrap();
         assert_eq!(a.inner.last().unwrap(), &HardforkInfoInner {
             block_number: 1,
             flags:        {
@@ -754,6 +804,7 @@ mod tests {
                 a[0] = 0b11;
                 H256::from(a)
             },
+            timestamp:    0,
         });
         assert_eq!(a.inner.len(), 1);
 
@@ -765,7 +816,9 @@ mod tests {
                 a[0] = 0b10;
                 H256::from(a)
             },
-        });
+            timestamp:    0,
+        })
+        .unwrap();
         assert_eq!(a.inner.last().unwrap(), &HardforkInfoInner {
             block_number: 1,
             flags:        {
@@ -773,6 +826,7 @@ mod tests {
                 a[0] = 0b11;
                 H256::from(a)
             },
+            timestamp:    0,
         });
         assert_eq!(a.inner.len(), 1);
 
@@ -784,34 +838,145 @@ mod tests {
                 a[0] = 0b1000;
                 H256::from(a)
             },
-        });
+            timestamp:    0,
+        })
+        .unwrap();
         assert_eq!(a.inner.last().unwrap(), &HardforkInfoInner {
             block_number: 30,
             flags:        {
                 let mut a = [0; 32];
-                a[0] = 0b1011;
+                a[0] = 0b1000;
                 H256::from(a)
             },
+            timestamp:    0,
         });
         assert_eq!(a.inner.len(), 2);
-
-        // The insertion height is lower than the known height and will be rejected
-        a.push(HardforkInfoInner {
-            block_number: 20,
-            flags:        {
-                let mut a = [0; 32];
-                a[0] = 0b0100;
-                H256::from(a)
-            },
+        assert_eq!(a.flags_at(29, 0), {
+            let mut a = [0; 32];
+            a[0] = 0b11;
+            H256::from(a)
         });
-        assert_eq!(a.inner.last().unwrap(), &HardforkInfoInner {
-            block_number: 30,
-            flags:        {
-                let mut a = [0; 32];
-                a[0] = 0b1011;
-                H256::from(a)
-            },
+        assert_eq!(a.flags_at(30, 0), {
+            let mut a = [0; 32];
+            a[0] = 0b1011;
+            H256::from(a)
         });
+
+        // The insertion height is lower than the known height and will be rejected
+        assert!(a
+            .push(HardforkInfoInner {
+                block_number: 20,
+                flags:        {
+                    let mut a = [0; 32];
+                    a[0] = 0b0100;
+                    H256::from(a)
+                },
+                timestamp:    0,
+            })
+            .is_err());
         assert_eq!(a.inner.len(), 2);
     }
+
+    #[test]
+    fn test_hardfork_activated_by_timestamp() {
+        let flags = |bits: u8| {
+            let mut a = [0; 32];
+            a[0] = bits;
+            H256::from(a)
+        };
+        let mut info = HardforkInfo::default();
+        info.push(HardforkInfoInner {
+            block_number: 0,
+            flags:        flags(0b1),
+            timestamp:    0,
+        })
+        .unwrap();
+        // The block number is ignored once the timestamp is set.
+        info.push(HardforkInfoInner {
+            block_number: 0,
+            flags:        flags(0b10),
+            timestamp:    1_000,
+        })
+        .unwrap();
+        assert_eq!(info.inner.len(), 2);
+
+        let headers = [(1, 900), (2, 999), (3, 1_000), (4, 1_100)];
+        let activated = headers
+            .iter()
+            .map(|(number, timestamp)| info.flags_at(*number, *timestamp))
+            .collect::<Vec<_>>();
+        assert_eq!(activated, vec![
+            flags(0b1),
+            flags(0b1),
+            flags(0b11),
+            flags(0b11)
+        ]);
+    }
+
+    #[test]
+    fn test_hardfork_mixed_activations() {
+        let flags = |bits: u8| {
+            let mut a = [0; 32];
+            a[0] = bits;
+            H256::from(a)
+        };
+        let mut info = HardforkInfo::default();
+        info.push(HardforkInfoInner {
+            block_number: 0,
+            flags:        flags(0b1),
+            timestamp:    0,
+        })
+        .unwrap();
+        info.push(HardforkInfoInner {
+            block_number: 0,
+            flags:        flags(0b10),
+            timestamp:    1_000,
+        })
+        .unwrap();
+        // A block number hardfork after a timestamp one does not carry its flag.
+        info.push(HardforkInfoInner {
+            block_number: 50,
+            flags:        flags(0b100),
+            timestamp:    0,
+        })
+        .unwrap();
+        // A timestamp hardfork after a block number one is not dropped.
+        info.push(HardforkInfoInner {
+            block_number: 0,
+            flags:        flags(0b1000),
+            timestamp:    2_000,
+        })
+        .unwrap();
+        assert_eq!(info.inner.len(), 4);
+
+        let headers = [(10, 500), (20, 1_000), (50, 1_500), (60, 2_000)];
+        let activated = headers
+            .iter()
+            .map(|(number, timestamp)| info.flags_at(*number, *timestamp))
+            .collect::<Vec<_>>();
+        assert_eq!(activated, vec![
+            flags(0b1),
+            flags(0b11),
+            flags(0b111),
+            flags(0b1111)
+        ]);
+        assert_eq!(info.flags_at(50, 500), flags(0b101));
+
+        // Each kind of hardfork is kept in order.
+        assert!(info
+            .push(HardforkInfoInner {
+                block_number: 40,
+                flags:        flags(0b10000),
+                timestamp:    0,
+            })
+            .is_err());
+        assert!(info
+            .push(HardforkInfoInner {
+                block_number: 0,
+                flags:        flags(0b10000),
+                timestamp:    1_500,
+            })
+            .is_err());
+        assert_eq!(info.inner.len(), 4);
+    }
 }
@@ -50,6 +50,12 @@ impl UnsignedTransaction {
         matches!(self, UnsignedTransaction::Eip1559(_))
     }
 
+    /// A contract creation is indicated solely by `TransactionAction::Create`.
+    /// A call to the zero address is a normal call rather than a creation.
+    pub fn is_create(&self) -> bool {
+        matches!(self.action(), TransactionAction::Create)
+    }
+
     pub fn data(&self) -> &[u8] {
         match self {
             UnsignedTransaction::Legacy(tx) => tx.data.as_ref(),
@@ -296,6 +302,17 @@ impl UnverifiedTransaction {
         Hasher::digest(&self.unsigned.encode(self.chain_id, self.signature.clone()))
     }
 
+    /// A typed transaction starts with a type byte less than `0x80` as
+    /// EIP-2718, and only the EIP-2930 and EIP-1559 ones are supported.
+    pub fn check_type(raw: &[u8]) -> ProtocolResult<()> {
+        match raw.first() {
+            Some(&ty) if ty < 0x80 && ty != 0x01 && ty != 0x02 => {
+                Err(TypesError::UnsupportedTxType(ty).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn check_hash(&self) -> ProtocolResult<()> {
         let calc_hash = self.get_hash();
         if self.hash != calc_hash {
@@ -322,6 +339,24 @@ impl UnverifiedTransaction {
         Hasher::digest(self.unsigned.encode(self.chain_id, None))
     }
 
+    /// Recover the sender from the Ethereum signature, the interoperation
+    /// signature carries its sender instead.
+    pub fn recover_signer(&self) -> Result<H160, TypesError> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or(TypesError::MissingSignature)?;
+        let public = secp256k1_recover(
+            self.signature_hash(true).as_bytes(),
+            signature.as_bytes().as_ref(),
+        )
+        .map_err(TypesError::Crypto)?;
+
+        Ok(public_to_address(&Public::from_slice(
+            &public.serialize_uncompressed()[1..65],
+        )))
+    }
+
     pub fn recover_public(&self, with_chain_id: bool) -> ProtocolResult<Public> {
         Ok(Public::from_slice(
             &secp256k1_recover(
@@ -353,7 +388,8 @@ impl From<Bytes> for SignatureComponents {
         SignatureComponents {
             r:          Bytes::from(bytes[0..32].to_vec()),
             s:          Bytes::from(bytes[32..64].to_vec()),
-            standard_v: bytes[64],
+            standard_v: SignatureComponents::normalize_recovery_id(bytes[64] as u64, false)
+                .unwrap_or(bytes[64]),
         }
     }
 }
@@ -382,12 +418,16 @@ impl SignatureComponents {
         (self.standard_v as u64) + chain_id.map(|i| i * 2 + 35).unwrap_or(27)
     }
 
-    pub fn extract_standard_v(v: u64) -> Option<u8> {
+    /// Normalize the `v` of a signature to the recovery id 0 or 1. A legacy
+    /// transaction carries 27/28 or the EIP-155 form `chain_id * 2 + 35 + id`,
+    /// which is re-encoded as is, while a typed transaction only carries the y
+    /// parity 0/1.
+    pub fn normalize_recovery_id(v: u64, typed: bool) -> ProtocolResult<u8> {
         match v {
-            v if v == 27 => Some(0),
-            v if v == 28 => Some(1),
-            v if v >= 35 => Some(((v - 1) % 2) as u8),
-            _ => None,
+            0 | 1 if typed => Ok(v as u8),
+            27 | 28 if !typed => Ok((v - 27) as u8),
+            v if !typed && v >= 35 => Ok(((v - 35) % 2) as u8),
+            _ => Err(TypesError::InvalidSignatureRType.into()),
         }
     }
 